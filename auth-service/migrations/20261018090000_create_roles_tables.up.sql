@@ -0,0 +1,41 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS permissions(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions(
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   permission TEXT NOT NULL REFERENCES permissions(name) ON DELETE CASCADE,
+   PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE IF NOT EXISTS user_roles(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   PRIMARY KEY (email, role)
+);
+
+INSERT INTO roles (name) VALUES ('user'), ('admin') ON CONFLICT DO NOTHING;
+
+INSERT INTO permissions (name)
+VALUES ('account:read'), ('account:write'), ('users:read'), ('users:write')
+ON CONFLICT DO NOTHING;
+
+INSERT INTO role_permissions (role, permission)
+VALUES
+   ('user', 'account:read'),
+   ('user', 'account:write'),
+   ('admin', 'account:read'),
+   ('admin', 'account:write'),
+   ('admin', 'users:read'),
+   ('admin', 'users:write')
+ON CONFLICT DO NOTHING;
+
+-- Accounts created before roles existed get the default role new users are given
+INSERT INTO user_roles (email, role)
+SELECT email, 'user' FROM users
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,11 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS audit_log(
+   id BIGSERIAL PRIMARY KEY,
+   actor TEXT,
+   event_type TEXT NOT NULL,
+   target TEXT,
+   details TEXT,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_target_idx ON audit_log (target, created_at);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active'
+    CONSTRAINT users_status_check
+    CHECK (status IN ('active', 'suspended', 'pending_verification', 'locked'));
+
+UPDATE users SET status = 'suspended' WHERE disabled;
+
+ALTER TABLE users DROP COLUMN IF EXISTS disabled;
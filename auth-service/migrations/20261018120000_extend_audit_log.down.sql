@@ -0,0 +1,9 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS audit_log_append_only ON audit_log;
+DROP FUNCTION IF EXISTS audit_log_reject_modification();
+DROP INDEX IF EXISTS audit_log_actor_idx;
+
+ALTER TABLE audit_log
+    DROP COLUMN IF EXISTS user_agent,
+    DROP COLUMN IF EXISTS ip,
+    DROP COLUMN IF EXISTS outcome;
@@ -0,0 +1,19 @@
+-- Add up migration script here
+ALTER TABLE audit_log
+    ADD COLUMN IF NOT EXISTS outcome TEXT NOT NULL DEFAULT 'success'
+        CONSTRAINT audit_log_outcome_check CHECK (outcome IN ('success', 'failure')),
+    ADD COLUMN IF NOT EXISTS ip TEXT,
+    ADD COLUMN IF NOT EXISTS user_agent TEXT;
+
+CREATE INDEX IF NOT EXISTS audit_log_actor_idx ON audit_log (actor, created_at);
+
+-- The audit log is append-only, rows can never be changed or removed
+CREATE OR REPLACE FUNCTION audit_log_reject_modification() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_reject_modification();
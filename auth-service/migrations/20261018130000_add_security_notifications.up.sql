@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS security_notifications BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE TABLE IF NOT EXISTS user_devices(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   fingerprint TEXT NOT NULL,
+   first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+   last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+   PRIMARY KEY (email, fingerprint)
+);
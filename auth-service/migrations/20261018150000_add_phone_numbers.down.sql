@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS phone_verifications;
+
+ALTER TABLE users DROP COLUMN IF EXISTS two_fa_channel;
+ALTER TABLE users DROP COLUMN IF EXISTS phone_number;
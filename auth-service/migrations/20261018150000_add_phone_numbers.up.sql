@@ -0,0 +1,12 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_number TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS two_fa_channel TEXT NOT NULL DEFAULT 'email'
+   CHECK (two_fa_channel IN ('email', 'sms'));
+
+CREATE TABLE IF NOT EXISTS phone_verifications(
+   email TEXT PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   phone_number TEXT NOT NULL,
+   code TEXT NOT NULL,
+   attempts INTEGER NOT NULL DEFAULT 0,
+   expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS token_generations;
+DROP TABLE IF EXISTS banned_tokens;
+DROP TABLE IF EXISTS two_fa_codes;
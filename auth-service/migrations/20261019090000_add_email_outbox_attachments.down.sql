@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE email_outbox DROP COLUMN IF EXISTS attachments;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- A JSON array of attachments with base64 encoded content
+ALTER TABLE email_outbox ADD COLUMN IF NOT EXISTS attachments TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,19 @@
+-- Add down migration script here
+CREATE OR REPLACE FUNCTION audit_log_reject_modification() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX IF EXISTS users_deletion_due_at_idx;
+DROP INDEX IF EXISTS users_restore_token_hash_idx;
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS restore_token_hash,
+    DROP COLUMN IF EXISTS deletion_due_at;
+
+UPDATE users SET status = 'active' WHERE status = 'pending_deletion';
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_status_check;
+ALTER TABLE users ADD CONSTRAINT users_status_check
+    CHECK (status IN ('active', 'suspended', 'pending_verification', 'locked'));
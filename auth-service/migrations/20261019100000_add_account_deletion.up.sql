@@ -0,0 +1,37 @@
+-- Add up migration script here
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_status_check;
+ALTER TABLE users ADD CONSTRAINT users_status_check
+    CHECK (status IN ('active', 'suspended', 'pending_verification', 'locked', 'pending_deletion'));
+
+-- Set while the user is pending deletion. Only the hash of the restore token is kept.
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS deletion_due_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS restore_token_hash TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_restore_token_hash_idx
+    ON users (restore_token_hash) WHERE restore_token_hash IS NOT NULL;
+CREATE INDEX IF NOT EXISTS users_deletion_due_at_idx
+    ON users (deletion_due_at) WHERE status = 'pending_deletion';
+
+-- The audit log stays append-only, except that a purged user's personal data
+-- can be erased from it: the actor and target can be replaced with '[erased]'
+-- and the ip and user agent cleared
+CREATE OR REPLACE FUNCTION audit_log_reject_modification() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'UPDATE'
+        AND NEW.id = OLD.id
+        AND NEW.event_type = OLD.event_type
+        AND NEW.outcome = OLD.outcome
+        AND NEW.details IS NOT DISTINCT FROM OLD.details
+        AND NEW.created_at = OLD.created_at
+        AND (NEW.actor IS NOT DISTINCT FROM OLD.actor OR NEW.actor = '[erased]')
+        AND (NEW.target IS NOT DISTINCT FROM OLD.target OR NEW.target = '[erased]')
+        AND (NEW.ip IS NOT DISTINCT FROM OLD.ip OR NEW.ip IS NULL)
+        AND (NEW.user_agent IS NOT DISTINCT FROM OLD.user_agent OR NEW.user_agent IS NULL)
+    THEN
+        RETURN NEW;
+    END IF;
+
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS token_generations_expires_at_idx;
+
+ALTER TABLE token_generations
+    DROP COLUMN IF EXISTS expires_at;
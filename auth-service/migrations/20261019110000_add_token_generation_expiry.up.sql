@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- Set once the user is purged, the generation is deleted when the tokens it
+-- revoked have expired
+ALTER TABLE token_generations
+    ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS token_generations_expires_at_idx
+    ON token_generations (expires_at) WHERE expires_at IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Emails that are of no use after a while, e.g. sign-in codes, are given up on
+-- instead of being sent late
+ALTER TABLE email_outbox ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
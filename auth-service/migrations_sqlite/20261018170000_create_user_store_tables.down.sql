@@ -0,0 +1,8 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS phone_verifications;
+DROP TABLE IF EXISTS user_devices;
+DROP TABLE IF EXISTS user_roles;
+DROP TABLE IF EXISTS role_permissions;
+DROP TABLE IF EXISTS permissions;
+DROP TABLE IF EXISTS roles;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,66 @@
+-- Add up migration script here
+-- The SQLite user store schema, matching the user store tables in ../migrations
+CREATE TABLE IF NOT EXISTS users(
+   email TEXT NOT NULL PRIMARY KEY,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   password_reset_required BOOLEAN NOT NULL DEFAULT FALSE,
+   status TEXT NOT NULL DEFAULT 'active'
+      CHECK (status IN ('active', 'suspended', 'pending_verification', 'locked')),
+   security_notifications BOOLEAN NOT NULL DEFAULT TRUE,
+   phone_number TEXT,
+   two_fa_channel TEXT NOT NULL DEFAULT 'email' CHECK (two_fa_channel IN ('email', 'sms'))
+);
+
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS permissions(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions(
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   permission TEXT NOT NULL REFERENCES permissions(name) ON DELETE CASCADE,
+   PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE IF NOT EXISTS user_roles(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   PRIMARY KEY (email, role)
+);
+
+INSERT INTO roles (name) VALUES ('user'), ('admin') ON CONFLICT DO NOTHING;
+
+INSERT INTO permissions (name)
+VALUES ('account:read'), ('account:write'), ('users:read'), ('users:write')
+ON CONFLICT DO NOTHING;
+
+INSERT INTO role_permissions (role, permission)
+VALUES
+   ('user', 'account:read'),
+   ('user', 'account:write'),
+   ('admin', 'account:read'),
+   ('admin', 'account:write'),
+   ('admin', 'users:read'),
+   ('admin', 'users:write')
+ON CONFLICT DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS user_devices(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   fingerprint TEXT NOT NULL,
+   first_seen_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+   last_seen_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+   PRIMARY KEY (email, fingerprint)
+);
+
+-- expires_at is in unix seconds
+CREATE TABLE IF NOT EXISTS phone_verifications(
+   email TEXT PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   phone_number TEXT NOT NULL,
+   code TEXT NOT NULL,
+   attempts INTEGER NOT NULL DEFAULT 0,
+   expires_at INTEGER NOT NULL
+);
@@ -0,0 +1,26 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS audit_log(
+   id INTEGER PRIMARY KEY AUTOINCREMENT,
+   actor TEXT,
+   event_type TEXT NOT NULL,
+   outcome TEXT NOT NULL DEFAULT 'success' CHECK (outcome IN ('success', 'failure')),
+   target TEXT,
+   ip TEXT,
+   user_agent TEXT,
+   details TEXT,
+   created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_actor_idx ON audit_log (actor, created_at);
+CREATE INDEX IF NOT EXISTS audit_log_target_idx ON audit_log (target, created_at);
+
+-- The audit log is append-only, rows can never be changed or removed
+CREATE TRIGGER IF NOT EXISTS audit_log_reject_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_reject_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
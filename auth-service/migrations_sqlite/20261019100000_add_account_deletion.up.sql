@@ -0,0 +1,94 @@
+-- Add up migration script here
+-- SQLite can't change a CHECK constraint, so the users table is rebuilt.
+-- Migrations run in a transaction where foreign keys can't be turned off, and
+-- dropping users would cascade, so the tables referencing it are set aside and
+-- recreated with their rows afterwards.
+CREATE TEMP TABLE user_roles_backup AS SELECT * FROM user_roles;
+CREATE TEMP TABLE user_devices_backup AS SELECT * FROM user_devices;
+CREATE TEMP TABLE phone_verifications_backup AS SELECT * FROM phone_verifications;
+
+DROP TABLE user_roles;
+DROP TABLE user_devices;
+DROP TABLE phone_verifications;
+
+CREATE TABLE users_new(
+   email TEXT NOT NULL PRIMARY KEY,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   password_reset_required BOOLEAN NOT NULL DEFAULT FALSE,
+   status TEXT NOT NULL DEFAULT 'active'
+      CHECK (status IN ('active', 'suspended', 'pending_verification', 'locked', 'pending_deletion')),
+   security_notifications BOOLEAN NOT NULL DEFAULT TRUE,
+   phone_number TEXT,
+   two_fa_channel TEXT NOT NULL DEFAULT 'email' CHECK (two_fa_channel IN ('email', 'sms')),
+   -- In unix seconds, like phone_verifications.expires_at
+   deletion_due_at INTEGER,
+   restore_token_hash TEXT
+);
+
+INSERT INTO users_new (
+   email, password_hash, requires_2fa, password_reset_required, status,
+   security_notifications, phone_number, two_fa_channel
+)
+SELECT
+   email, password_hash, requires_2fa, password_reset_required, status,
+   security_notifications, phone_number, two_fa_channel
+FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
+
+CREATE TABLE user_roles(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   PRIMARY KEY (email, role)
+);
+
+CREATE TABLE user_devices(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   fingerprint TEXT NOT NULL,
+   first_seen_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+   last_seen_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+   PRIMARY KEY (email, fingerprint)
+);
+
+-- expires_at is in unix seconds
+CREATE TABLE phone_verifications(
+   email TEXT PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   phone_number TEXT NOT NULL,
+   code TEXT NOT NULL,
+   attempts INTEGER NOT NULL DEFAULT 0,
+   expires_at INTEGER NOT NULL
+);
+
+INSERT INTO user_roles SELECT * FROM user_roles_backup;
+INSERT INTO user_devices SELECT * FROM user_devices_backup;
+INSERT INTO phone_verifications SELECT * FROM phone_verifications_backup;
+
+DROP TABLE user_roles_backup;
+DROP TABLE user_devices_backup;
+DROP TABLE phone_verifications_backup;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_restore_token_hash_idx
+   ON users (restore_token_hash) WHERE restore_token_hash IS NOT NULL;
+CREATE INDEX IF NOT EXISTS users_deletion_due_at_idx
+   ON users (deletion_due_at) WHERE status = 'pending_deletion';
+
+-- The audit log stays append-only, except that a purged user's personal data
+-- can be erased from it, matching the rule in ../migrations
+DROP TRIGGER IF EXISTS audit_log_reject_update;
+CREATE TRIGGER audit_log_reject_update BEFORE UPDATE ON audit_log
+WHEN NOT (
+   NEW.id = OLD.id
+   AND NEW.event_type = OLD.event_type
+   AND NEW.outcome = OLD.outcome
+   AND NEW.details IS OLD.details
+   AND NEW.created_at = OLD.created_at
+   AND (NEW.actor IS OLD.actor OR NEW.actor = '[erased]')
+   AND (NEW.target IS OLD.target OR NEW.target = '[erased]')
+   AND (NEW.ip IS OLD.ip OR NEW.ip IS NULL)
+   AND (NEW.user_agent IS OLD.user_agent OR NEW.user_agent IS NULL)
+)
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
@@ -3,24 +3,43 @@ use super::routes::{delete_account, elevate, login, logout, signup, verify_token
 use axum::{
     Router,
     http::{HeaderValue, Method, request},
-    routing::{delete, post},
+    routing::{delete, get, post, put},
 };
-use redis::{Client, RedisResult};
+use color_eyre::eyre::{Result, eyre};
+use redis::{Client, RedisResult, aio::MultiplexedConnection};
 use secrecy::ExposeSecret;
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use sqlx::{
+    PgPool, SqlitePool,
+    migrate::MigrateError,
+    postgres::PgPoolOptions,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+use std::str::FromStr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tokio::sync::RwLock;
 use tower_http::trace::TraceLayer;
 use tower_http::{
     cors::{AllowOrigin, CorsLayer},
     services::ServeDir,
 };
 
-use crate::domain::data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore};
+use crate::domain::data_stores::{AuditLog, BannedTokenStore, TwoFaCodeStore, UserStore};
 use crate::domain::email_client::EmailClient;
-use crate::routes::{change_password, verify_elevated_token};
-use crate::settings::{AllowedOrigins, AuthServiceSetting};
+use crate::domain::secret_provider::SecretProvider;
+use crate::routes::{
+    admin_delete_user, admin_disable_user, admin_enable_user, admin_force_password_reset,
+    admin_get_user, admin_import_users, admin_revoke_sessions, admin_search_users,
+    admin_set_requires_2fa, change_password, clear_dev_mailbox, dev_mailbox, export_account,
+    get_notification_preferences, request_account_export, restore_account, security_events,
+    set_two_fa_channel, start_phone_verification, update_notification_preferences,
+    verify_elevated_token, verify_phone_number,
+};
+use crate::services::capturing_email_client::CapturingEmailClient;
+use crate::services::env_secret_provider::EnvSecretProvider;
+use crate::services::file_secret_provider::FileSecretProvider;
+use crate::services::vault_secret_provider::configure_vault_secret_provider;
+use crate::settings::{AuthServiceSetting, Config, SecretProviderKind, SecretsConfig, Settings};
+use crate::utils::constants::env::{SECRETS, VAULT_TOKEN_ENV_VAR};
 use crate::utils::tracing::{make_span_with_request_id, on_request, on_response};
 
 pub struct AuthService {
@@ -28,35 +47,51 @@ pub struct AuthService {
 }
 
 impl AuthService {
-    pub fn new<U, B, T, E>(
+    pub fn new<U, B, T, E, A>(
         user_store: U,
         banned_token_store: B,
         two_fa_code_store: T,
         email_client: E,
+        audit_log: A,
     ) -> Self
     where
         U: UserStore + 'static,
         B: BannedTokenStore + 'static,
         T: TwoFaCodeStore + 'static,
         E: EmailClient + 'static,
+        A: AuditLog + 'static,
     {
         let state = AuthServiceState::new(
-            Arc::new(RwLock::new(user_store)),
-            Arc::new(RwLock::new(banned_token_store)),
-            Arc::new(RwLock::new(two_fa_code_store)),
+            Arc::new(user_store),
+            Arc::new(banned_token_store),
+            Arc::new(two_fa_code_store),
             Arc::new(email_client),
+            Arc::new(audit_log),
         );
 
         Self::with_state(state)
     }
 
-    pub fn with_state<U, B, T, E>(state: AuthServiceState<U, B, T, E>) -> Self
-    where
-        U: UserStore + 'static,
-        B: BannedTokenStore + 'static,
-        T: TwoFaCodeStore + 'static,
-        E: EmailClient + 'static,
-    {
+    pub fn with_state(state: AuthServiceState) -> Self {
+        let admin_router = Router::new()
+            .route("/users", get(admin_search_users))
+            .route("/users/import", post(admin_import_users))
+            .route(
+                "/users/{email}",
+                get(admin_get_user).delete(admin_delete_user),
+            )
+            .route("/users/{email}/disable", post(admin_disable_user))
+            .route("/users/{email}/enable", post(admin_enable_user))
+            .route(
+                "/users/{email}/force-password-reset",
+                post(admin_force_password_reset),
+            )
+            .route("/users/{email}/requires-2fa", put(admin_set_requires_2fa))
+            .route(
+                "/users/{email}/revoke-sessions",
+                post(admin_revoke_sessions),
+            );
+
         let router = Router::new()
             .route("/signup", post(signup))
             .route("/login", post(login))
@@ -67,12 +102,37 @@ impl AuthService {
             .route("/elevate", post(elevate))
             .route("/change-password", post(change_password))
             .route("/delete-account", delete(delete_account))
+            .route("/account/restore", post(restore_account))
+            .route(
+                "/account/export",
+                get(export_account).post(request_account_export),
+            )
+            .route("/account/security-events", get(security_events))
+            .route(
+                "/account/notification-preferences",
+                get(get_notification_preferences).put(update_notification_preferences),
+            )
+            .route("/account/phone", post(start_phone_verification))
+            .route("/account/phone/verify", post(verify_phone_number))
+            .route("/account/two-fa-channel", put(set_two_fa_channel))
+            .nest("/admin", admin_router)
             .fallback_service(ServeDir::new("assets"))
             .with_state(state);
 
         AuthService { router }
     }
 
+    // Serves the emails captured by the given client at /_dev/mailbox. Only for
+    // local development, the mailbox shows every email the service sends.
+    pub fn with_dev_mailbox(mut self, mailbox: CapturingEmailClient) -> Self {
+        let dev_router = Router::new()
+            .route("/_dev/mailbox", get(dev_mailbox).delete(clear_dev_mailbox))
+            .with_state(mailbox);
+
+        self.router = self.router.merge(dev_router);
+        self
+    }
+
     fn with_trace_layer(mut self) -> Self {
         self.router = self.router.layer(
             TraceLayer::new_for_http()
@@ -83,14 +143,16 @@ impl AuthService {
         self
     }
 
-    pub fn as_nested_router(mut self, allow_origin: Option<AllowedOrigins>) -> Router {
-        if let Some(allowed_origin) = allow_origin {
+    // Allowed origins are read from the settings on every request, so they
+    // follow config reloads
+    pub fn as_nested_router(mut self, cors_settings: Option<Settings>) -> Router {
+        if let Some(settings) = cors_settings {
             let cors = CorsLayer::new()
                 .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
                 .allow_credentials(true)
                 .allow_origin(AllowOrigin::predicate(
                     move |origin: &HeaderValue, _request_parts: &request::Parts| {
-                        allowed_origin.contains(origin)
+                        settings.load().auth.allowed_origins.contains(origin)
                     },
                 ));
 
@@ -102,13 +164,13 @@ impl AuthService {
     pub async fn as_standalone(
         self,
         listener: TcpListener,
-        allow_origins: Option<AllowedOrigins>,
+        cors_settings: Option<Settings>,
     ) -> Result<(), std::io::Error> {
-        let router = self.as_nested_router(allow_origins);
+        let router = self.as_nested_router(cors_settings);
 
         tracing::info!("listening on {}", listener.local_addr()?);
         axum_server::Server::<std::net::SocketAddr>::from_listener(listener)
-            .serve(router.into_make_service())
+            .serve(router.into_make_service_with_connect_info::<std::net::SocketAddr>())
             .await
     }
 
@@ -172,25 +234,56 @@ impl AuthService {
 pub async fn configure_postgresql() -> PgPool {
     // Create a new database connection pool
     let config = AuthServiceSetting::load();
-    let db_url = config.postgres.url.expose_secret();
+    let db_url = config
+        .postgres
+        .url
+        .as_ref()
+        .expect("DATABASE_URL must be set to use the postgres database backend")
+        .expose_secret();
     let pg_pool = get_postgres_pool(db_url)
         .await
         .expect("Failed to create Postgres connection pool!");
 
     // Run database migrations against our test database!
-    sqlx::migrate!()
-        .run(&pg_pool)
+    run_postgres_migrations(&pg_pool)
         .await
         .expect("Failed to run migrations");
 
     pg_pool
 }
 
-pub fn configure_redis() -> redis::Connection {
-    let redis_host_name = &AuthServiceSetting::load().redis.host_name;
+pub async fn run_postgres_migrations(pg_pool: &PgPool) -> Result<(), MigrateError> {
+    sqlx::migrate!().run(pg_pool).await
+}
+
+pub async fn configure_sqlite() -> SqlitePool {
+    let config = AuthServiceSetting::load();
+    let sqlite_pool = get_sqlite_pool(&config.database.sqlite_url)
+        .await
+        .expect("Failed to create SQLite connection pool!");
+
+    run_sqlite_migrations(&sqlite_pool)
+        .await
+        .expect("Failed to run SQLite migrations");
+
+    sqlite_pool
+}
+
+pub async fn run_sqlite_migrations(sqlite_pool: &SqlitePool) -> Result<(), MigrateError> {
+    sqlx::migrate!("./migrations_sqlite").run(sqlite_pool).await
+}
+
+pub async fn configure_redis() -> MultiplexedConnection {
+    let settings = AuthServiceSetting::load();
+    let redis_host_name = &settings
+        .redis
+        .as_ref()
+        .expect("redis.host_name must be set to use the redis token store")
+        .host_name;
     get_redis_client(redis_host_name)
         .expect("Failed to get Redis client")
-        .get_connection()
+        .get_multiplexed_async_connection()
+        .await
         .expect("Failed to get Redis connection")
 }
 
@@ -199,6 +292,50 @@ pub async fn get_postgres_pool(url: &str) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new().max_connections(5).connect(url).await
 }
 
+pub async fn get_sqlite_pool(url: &str) -> Result<SqlitePool, sqlx::Error> {
+    // Foreign keys are enforced by default, the database file is created on
+    // first start
+    let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+}
+
+// Fetches the secrets from the configured provider and builds the config with
+// them. The provider is returned so the secrets can be fetched again later.
+pub async fn load_settings() -> Result<(Settings, Box<dyn SecretProvider>)> {
+    let secrets_config = SecretsConfig::new()?;
+    let secret_provider = configure_secret_provider(&secrets_config)?;
+    let secrets = secret_provider.get_secrets(&SECRETS).await?;
+    let config = Config::with_secrets(&secrets)?;
+
+    Ok((Settings::with_secrets(config, secrets), secret_provider))
+}
+
+pub fn configure_secret_provider(config: &SecretsConfig) -> Result<Box<dyn SecretProvider>> {
+    match config.provider {
+        SecretProviderKind::Env => Ok(Box::new(EnvSecretProvider::default())),
+        SecretProviderKind::File => {
+            let directory = config
+                .directory
+                .as_ref()
+                .ok_or_else(|| eyre!("secrets.directory must be set for the file provider"))?;
+            Ok(Box::new(FileSecretProvider::new(directory)))
+        }
+        SecretProviderKind::Vault => {
+            let vault = config
+                .vault
+                .as_ref()
+                .ok_or_else(|| eyre!("secrets.vault must be set for the vault provider"))?;
+            let token = EnvSecretProvider::default()
+                .read(VAULT_TOKEN_ENV_VAR)?
+                .ok_or_else(|| eyre!("{VAULT_TOKEN_ENV_VAR} must be set for the vault provider"))?;
+            Ok(Box::new(configure_vault_secret_provider(vault, token)))
+        }
+    }
+}
+
 pub fn get_redis_client(redis_hostname: &str) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
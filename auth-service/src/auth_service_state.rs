@@ -1,60 +1,109 @@
-use crate::domain::data_stores::BannedTokenStore as BannedTokensStoreTrait;
-use crate::domain::data_stores::TwoFaCodeStore as TwoFaCodeStoreTrait;
-use crate::domain::data_stores::UserStore as UserStoreTrait;
-use crate::domain::email_client::EmailClient as EmailClientTrait;
+use crate::domain::clock::{Clock, SystemClock};
+use crate::domain::data_stores::{
+    AuditLog, BannedTokenStore, EmailOutbox, TwoFaCodeStore, UserStore,
+};
+use crate::domain::email_client::EmailClient;
+use crate::domain::sms_client::SmsClient;
+use crate::settings::{AuthServiceSetting, Settings};
+use axum::extract::FromRef;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-
-pub struct AuthServiceState<UserStore, BannedTokenStore, TwoFaCodeStore, EmailClient>
-where
-    UserStore: UserStoreTrait,
-    BannedTokenStore: BannedTokensStoreTrait,
-    TwoFaCodeStore: TwoFaCodeStoreTrait,
-    EmailClient: EmailClientTrait,
-{
-    pub user_store: Arc<RwLock<UserStore>>,
-    pub banned_token_store: Arc<RwLock<BannedTokenStore>>,
-    pub two_fa_code_store: Arc<RwLock<TwoFaCodeStore>>,
-    pub email_client: Arc<EmailClient>,
+
+// The stores handle concurrent access themselves, through connection pools or
+// concurrent maps, so they are shared without a lock around them.
+pub type SharedUserStore = Arc<dyn UserStore>;
+pub type SharedBannedTokenStore = Arc<dyn BannedTokenStore>;
+pub type SharedTwoFaCodeStore = Arc<dyn TwoFaCodeStore>;
+pub type SharedClock = Arc<dyn Clock>;
+
+// The stores and clients are trait objects so that handlers only need to name
+// `AuthServiceState`. Optional dependencies are added through the `with_*`
+// methods, which keeps new ones out of every handler signature.
+#[derive(Clone)]
+pub struct AuthServiceState {
+    pub user_store: SharedUserStore,
+    pub banned_token_store: SharedBannedTokenStore,
+    pub two_fa_code_store: SharedTwoFaCodeStore,
+    pub email_client: Arc<dyn EmailClient>,
+    pub audit_log: Arc<dyn AuditLog>,
+    // SMS two-factor authentication is unavailable without one
+    pub sms_client: Option<Arc<dyn SmsClient>>,
+    // Set when emails are queued rather than sent directly
+    pub email_outbox: Option<Arc<dyn EmailOutbox>>,
+    // Used whenever tokens are issued or checked for expiry
+    pub clock: SharedClock,
+    // Loaded once per request, so a reload never applies halfway through one
+    pub settings: Settings,
 }
 
-impl<UserStore, BannedTokenStore, TwoFaCodeStore, EmailClient>
-    AuthServiceState<UserStore, BannedTokenStore, TwoFaCodeStore, EmailClient>
-where
-    UserStore: UserStoreTrait,
-    BannedTokenStore: BannedTokensStoreTrait,
-    TwoFaCodeStore: TwoFaCodeStoreTrait,
-    EmailClient: EmailClientTrait,
-{
-    pub fn new(
-        user_store: Arc<RwLock<UserStore>>,
-        banned_token_store: Arc<RwLock<BannedTokenStore>>,
-        two_fa_code_store: Arc<RwLock<TwoFaCodeStore>>,
-        email_client: Arc<EmailClient>,
-    ) -> Self {
+impl AuthServiceState {
+    pub fn new<U, B, T, E, A>(
+        user_store: Arc<U>,
+        banned_token_store: Arc<B>,
+        two_fa_code_store: Arc<T>,
+        email_client: Arc<E>,
+        audit_log: Arc<A>,
+    ) -> Self
+    where
+        U: UserStore + 'static,
+        B: BannedTokenStore + 'static,
+        T: TwoFaCodeStore + 'static,
+        E: EmailClient + 'static,
+        A: AuditLog + 'static,
+    {
         AuthServiceState {
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            audit_log,
+            sms_client: None,
+            email_outbox: None,
+            clock: Arc::new(SystemClock),
+            settings: AuthServiceSetting::handle(),
         }
     }
+
+    pub fn with_sms_client(mut self, sms_client: Arc<dyn SmsClient>) -> Self {
+        self.sms_client = Some(sms_client);
+        self
+    }
+
+    pub fn with_email_outbox(mut self, email_outbox: Arc<dyn EmailOutbox>) -> Self {
+        self.email_outbox = Some(email_outbox);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
 }
 
-impl<UserStore, BannedTokenStore, TwoFaCodeStore, EmailClient> Clone
-    for AuthServiceState<UserStore, BannedTokenStore, TwoFaCodeStore, EmailClient>
-where
-    UserStore: UserStoreTrait,
-    BannedTokenStore: BannedTokensStoreTrait,
-    TwoFaCodeStore: TwoFaCodeStoreTrait,
-    EmailClient: EmailClientTrait,
-{
-    fn clone(&self) -> Self {
-        Self {
-            user_store: self.user_store.clone(),
-            banned_token_store: self.banned_token_store.clone(),
-            two_fa_code_store: self.two_fa_code_store.clone(),
-            email_client: self.email_client.clone(),
-        }
+impl FromRef<AuthServiceState> for SharedUserStore {
+    fn from_ref(state: &AuthServiceState) -> Self {
+        state.user_store.clone()
+    }
+}
+
+impl FromRef<AuthServiceState> for SharedBannedTokenStore {
+    fn from_ref(state: &AuthServiceState) -> Self {
+        state.banned_token_store.clone()
+    }
+}
+
+impl FromRef<AuthServiceState> for SharedClock {
+    fn from_ref(state: &AuthServiceState) -> Self {
+        state.clock.clone()
+    }
+}
+
+impl FromRef<AuthServiceState> for Settings {
+    fn from_ref(state: &AuthServiceState) -> Self {
+        state.settings.clone()
     }
 }
@@ -0,0 +1,323 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use auth_service::auth_service::{
+    get_postgres_pool, get_redis_client, get_sqlite_pool, load_settings, run_postgres_migrations,
+    run_sqlite_migrations,
+};
+use auth_service::domain::data_stores::{AuditLog, BannedTokenStore, UserStore};
+use auth_service::domain::email::Email;
+use auth_service::domain::password::Password;
+use auth_service::domain::role::Role;
+use auth_service::services::data_stores::{
+    HashSetBannedTokenStore, PostgresAuditLog, PostgresBannedTokenStore, PostgresUserStore,
+    RedisBannedTokenStore, SqliteAuditLog, SqliteUserStore,
+};
+use auth_service::services::user_admin::{UserAdmin, UserImport, UserRecord};
+use auth_service::settings::{AuthServiceSetting, DatabaseBackend, TokenStoreBackend};
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{Result, WrapErr, eyre};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+#[derive(Parser)]
+#[command(
+    name = "auth-admin",
+    about = "Manage the users of the auth service, using the same config as the service"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Run the database migrations")]
+    Migrate,
+    #[command(about = "Create a user, printing a temporary password when none is given")]
+    CreateUser {
+        email: String,
+        // Ends up in the shell history, prefer the generated temporary password
+        #[arg(long)]
+        password: Option<String>,
+        #[arg(long)]
+        requires_2fa: bool,
+        #[arg(long = "role", help = "A role to assign, can be repeated")]
+        roles: Vec<String>,
+    },
+    #[command(
+        about = "Set a new password and sign the user out, printing a temporary password when none is given"
+    )]
+    ResetPassword {
+        email: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    #[command(
+        name = "enable-2fa",
+        about = "Require two-factor authentication for a user"
+    )]
+    Enable2fa { email: String },
+    #[command(
+        name = "disable-2fa",
+        about = "Stop requiring two-factor authentication for a user"
+    )]
+    Disable2fa { email: String },
+    #[command(about = "Make a locked user active again")]
+    Unlock { email: String },
+    #[command(about = "Sign a user out of every session")]
+    RevokeSessions { email: String },
+    #[command(
+        about = "Write every user as JSON, without passwords or password hashes",
+        long_about = "Write every user as JSON, without passwords or password hashes. Importing the file again gives every user a temporary password they have to change on their next login."
+    )]
+    ExportUsers {
+        #[arg(long, help = "Write to this file instead of stdout")]
+        output: Option<PathBuf>,
+    },
+    #[command(
+        about = "Create the users in a JSON file as written by export-users, each with a password, a passwordHash or neither",
+        long_about = "Create the users in a JSON file as written by export-users, each with a password, a passwordHash or neither. Users with neither get a temporary password they have to change on their next login, which is the case for every user in an export. Each user is created with its roles or not at all, and the ones that fail are listed."
+    )]
+    ImportUsers {
+        #[arg(help = "The file to read, - for stdin")]
+        input: PathBuf,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    color_eyre::install().expect("Failed to install color_eyre");
+    let cli = Cli::parse();
+
+    match run(cli.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(command: Command) -> Result<()> {
+    let (settings, _) = load_settings().await?;
+    AuthServiceSetting::init(settings)
+        .map_err(|_| eyre!("The config was used before it was loaded"))?;
+
+    if let Command::Migrate = command {
+        return migrate().await;
+    }
+
+    let admin = configure_user_admin().await?;
+    match command {
+        Command::Migrate => unreachable!("Handled above"),
+        Command::CreateUser {
+            email,
+            password,
+            requires_2fa,
+            roles,
+        } => {
+            let roles = roles
+                .iter()
+                .map(|role| Role::parse(role).map_err(|e| eyre!("{e}: '{role}'")))
+                .collect::<Result<Vec<_>>>()?;
+            let temporary_password = admin
+                .create_user(
+                    &parse_email(email)?,
+                    parse_password(password)?,
+                    requires_2fa,
+                    &roles,
+                )
+                .await?;
+            println!("User created");
+            print_temporary_password(temporary_password);
+        }
+        Command::ResetPassword { email, password } => {
+            let temporary_password = admin
+                .reset_password(&parse_email(email)?, parse_password(password)?)
+                .await?;
+            println!("Password reset, the user has been signed out");
+            print_temporary_password(temporary_password);
+        }
+        Command::Enable2fa { email } => {
+            admin.set_requires_2fa(&parse_email(email)?, true).await?;
+            println!("Two-factor authentication is now required");
+        }
+        Command::Disable2fa { email } => {
+            admin.set_requires_2fa(&parse_email(email)?, false).await?;
+            println!("Two-factor authentication is no longer required");
+        }
+        Command::Unlock { email } => {
+            if admin.unlock(&parse_email(email)?).await? {
+                println!("User unlocked");
+            } else {
+                println!("User is not locked, nothing changed");
+            }
+        }
+        Command::RevokeSessions { email } => {
+            admin.revoke_sessions(&parse_email(email)?).await?;
+            println!("Sessions revoked");
+        }
+        Command::ExportUsers { output } => {
+            let records = admin.export_users().await?;
+            let json = serde_json::to_string_pretty(&records)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)
+                        .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!("Exported {} users to {}", records.len(), path.display());
+                }
+                None => println!("{json}"),
+            }
+        }
+        Command::ImportUsers { input } => {
+            let records: Vec<UserRecord> = serde_json::from_str(&read_input(&input)?)
+                .wrap_err("The input is not a list of user records")?;
+            let report = admin.import_users(UserImport::try_from(records)?).await;
+
+            println!(
+                "Created {} users, skipped {} that already existed, {} failed",
+                report.created.len(),
+                report.skipped.len(),
+                report.failed.len()
+            );
+            for email in &report.skipped {
+                println!("  skipped {email}");
+            }
+            for user in &report.failed {
+                println!("  failed {}: {}", user.email, user.error);
+            }
+            for user in &report.created {
+                if let Some(password) = &user.temporary_password {
+                    println!(
+                        "  {} temporary password: {}",
+                        user.email,
+                        password.as_ref().expose_secret()
+                    );
+                }
+            }
+            if !report.failed.is_empty() {
+                return Err(eyre!("{} users could not be imported", report.failed.len()));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn migrate() -> Result<()> {
+    let config = AuthServiceSetting::load();
+    match config.database.backend {
+        DatabaseBackend::Postgres => run_postgres_migrations(&connect_postgres().await?).await?,
+        DatabaseBackend::Sqlite => {
+            let sqlite_pool = get_sqlite_pool(&config.database.sqlite_url).await?;
+            run_sqlite_migrations(&sqlite_pool).await?
+        }
+    }
+    println!("Migrations are up to date");
+    Ok(())
+}
+
+async fn configure_user_admin() -> Result<UserAdmin> {
+    let config = AuthServiceSetting::load();
+    let (user_store, audit_log, pg_pool): (Arc<dyn UserStore>, Arc<dyn AuditLog>, _) =
+        match config.database.backend {
+            DatabaseBackend::Postgres => {
+                let pg_pool = connect_postgres().await?;
+                (
+                    Arc::new(PostgresUserStore::new(pg_pool.clone())),
+                    Arc::new(PostgresAuditLog::new(pg_pool.clone())),
+                    Some(pg_pool),
+                )
+            }
+            DatabaseBackend::Sqlite => {
+                let sqlite_pool = get_sqlite_pool(&config.database.sqlite_url).await?;
+                (
+                    Arc::new(SqliteUserStore::new(sqlite_pool.clone())),
+                    Arc::new(SqliteAuditLog::new(sqlite_pool)),
+                    None,
+                )
+            }
+        };
+
+    let banned_token_store: Arc<dyn BannedTokenStore> = match config.token_store.backend {
+        TokenStoreBackend::Redis => {
+            let host_name = &config
+                .redis
+                .as_ref()
+                .ok_or_else(|| eyre!("redis.host_name must be set"))?
+                .host_name;
+            let connection = get_redis_client(host_name)?
+                .get_multiplexed_async_connection()
+                .await?;
+            Arc::new(RedisBannedTokenStore::new(
+                connection,
+                AuthServiceSetting::handle(),
+            ))
+        }
+        TokenStoreBackend::Postgres => Arc::new(PostgresBannedTokenStore::new(
+            pg_pool.ok_or_else(|| eyre!("token_store.backend postgres requires postgres"))?,
+            AuthServiceSetting::handle(),
+        )),
+        TokenStoreBackend::Memory => {
+            eprintln!(
+                "token_store.backend is memory, sessions live in the service process and are not revoked"
+            );
+            Arc::new(HashSetBannedTokenStore::new(AuthServiceSetting::handle()))
+        }
+    };
+
+    Ok(UserAdmin::new(
+        user_store,
+        banned_token_store,
+        audit_log,
+        actor(),
+    ))
+}
+
+async fn connect_postgres() -> Result<PgPool> {
+    let config = AuthServiceSetting::load();
+    let url = config
+        .postgres
+        .url
+        .as_ref()
+        .ok_or_else(|| eyre!("DATABASE_URL must be set"))?;
+    Ok(get_postgres_pool(url.expose_secret()).await?)
+}
+
+// Recorded in the audit log, so changes can be traced to the operator
+fn actor() -> String {
+    match std::env::var("USER") {
+        Ok(user) => format!("auth-admin:{user}"),
+        Err(_) => "auth-admin".to_owned(),
+    }
+}
+
+fn parse_email(email: String) -> Result<Email> {
+    Email::try_from(Secret::new(email)).map_err(|e| eyre!(e))
+}
+
+fn parse_password(password: Option<String>) -> Result<Option<Password>> {
+    password
+        .map(|password| Password::try_from(Secret::new(password)).map_err(|e| eyre!(e)))
+        .transpose()
+}
+
+fn print_temporary_password(password: Option<Password>) {
+    if let Some(password) = password {
+        println!(
+            "Temporary password, to be changed on first login: {}",
+            password.as_ref().expose_secret()
+        );
+    }
+}
+
+fn read_input(input: &PathBuf) -> Result<String> {
+    if input.as_os_str() == "-" {
+        let mut json = String::new();
+        std::io::stdin().read_to_string(&mut json)?;
+        return Ok(json);
+    }
+    std::fs::read_to_string(input).wrap_err_with(|| format!("Failed to read {}", input.display()))
+}
@@ -7,10 +7,12 @@ use color_eyre::Report;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::utils::auth::TokenAuthError;
+use crate::utils::{auth::TokenAuthError, extractors::RoleRejection};
 
 use super::{
-    data_stores::{BannedTokenStoreError, TwoFaCodeStoreError, UserStoreError},
+    data_stores::{
+        AuditLogError, BannedTokenStoreError, EmailOutboxError, TwoFaCodeStoreError, UserStoreError,
+    },
     two_fa_error::TwoFaError,
     user::UserError,
 };
@@ -28,12 +30,34 @@ pub enum AuthApiError {
     #[error("User already exists")]
     UserAlreadyExists,
 
+    #[error("Email domain is not allowed to register")]
+    EmailDomainNotAllowed,
+
     #[error("Invalid input: {0}")]
     InvalidInput(Box<dyn std::error::Error + Send + Sync>),
 
     #[error("Missing token")]
     MissingToken,
 
+    #[error("Insufficient permissions")]
+    Forbidden,
+
+    #[error("Account is suspended")]
+    AccountSuspended,
+
+    #[error("Account is pending verification")]
+    AccountPendingVerification,
+
+    #[error("Account is locked")]
+    AccountLocked,
+
+    #[error("Account is scheduled for deletion")]
+    AccountPendingDeletion,
+
+    // An admin changing the status of an account that is waiting to be deleted
+    #[error("Account is scheduled for deletion, it can only be restored or deleted")]
+    AccountDeletionScheduled,
+
     #[error("Authentication failed: {0}")]
     AuthenticationError(Box<dyn std::error::Error + Send + Sync>),
 
@@ -43,6 +67,18 @@ pub enum AuthApiError {
     #[error("Invalid two-factor authentication code")]
     InvalidTwoFaCode,
 
+    #[error("Invalid or expired verification code")]
+    InvalidVerificationCode,
+
+    #[error("Invalid or expired restore link")]
+    InvalidRestoreToken,
+
+    #[error("A verified phone number is required")]
+    PhoneNumberNotVerified,
+
+    #[error("SMS is not available")]
+    SmsUnavailable,
+
     #[error("Unexpected error")]
     UnexpectedError(#[from] Report),
 }
@@ -50,11 +86,23 @@ pub enum AuthApiError {
 impl IntoResponse for AuthApiError {
     fn into_response(self) -> Response {
         let (status_code, error_message) = match self {
-            AuthApiError::InvalidInput(_) | AuthApiError::MissingToken => {
-                (StatusCode::BAD_REQUEST, self.to_string())
-            }
+            AuthApiError::InvalidInput(_)
+            | AuthApiError::MissingToken
+            | AuthApiError::InvalidVerificationCode
+            | AuthApiError::InvalidRestoreToken => (StatusCode::BAD_REQUEST, self.to_string()),
 
-            AuthApiError::UserAlreadyExists => (StatusCode::CONFLICT, self.to_string()),
+            AuthApiError::UserAlreadyExists
+            | AuthApiError::PhoneNumberNotVerified
+            | AuthApiError::AccountDeletionScheduled => (StatusCode::CONFLICT, self.to_string()),
+
+            AuthApiError::SmsUnavailable => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
+
+            AuthApiError::EmailDomainNotAllowed
+            | AuthApiError::Forbidden
+            | AuthApiError::AccountSuspended
+            | AuthApiError::AccountPendingVerification
+            | AuthApiError::AccountLocked
+            | AuthApiError::AccountPendingDeletion => (StatusCode::FORBIDDEN, self.to_string()),
 
             AuthApiError::AuthenticationError(_)
             | AuthApiError::UserNotFound
@@ -77,9 +125,9 @@ impl IntoResponse for AuthApiError {
 impl From<UserError> for AuthApiError {
     fn from(error: UserError) -> Self {
         match error {
-            UserError::InvalidEmail | UserError::InvalidPassword => {
-                AuthApiError::InvalidInput(Box::new(error))
-            }
+            UserError::InvalidEmail
+            | UserError::InvalidPassword
+            | UserError::InvalidPhoneNumber => AuthApiError::InvalidInput(Box::new(error)),
         }
     }
 }
@@ -91,6 +139,14 @@ impl From<UserStoreError> for AuthApiError {
             UserStoreError::UnexpectedError(e) => AuthApiError::UnexpectedError(e),
             UserStoreError::UserNotFound => AuthApiError::UserNotFound,
             UserStoreError::IncorrectPassword => AuthApiError::AuthenticationError(Box::new(error)),
+            UserStoreError::RoleNotFound => AuthApiError::InvalidInput(Box::new(error)),
+            UserStoreError::UserSuspended => AuthApiError::AccountSuspended,
+            UserStoreError::UserPendingVerification => AuthApiError::AccountPendingVerification,
+            UserStoreError::UserLocked => AuthApiError::AccountLocked,
+            UserStoreError::UserPendingDeletion => AuthApiError::AccountPendingDeletion,
+            UserStoreError::InvalidVerificationCode => AuthApiError::InvalidVerificationCode,
+            UserStoreError::InvalidRestoreToken => AuthApiError::InvalidRestoreToken,
+            UserStoreError::PhoneNumberNotVerified => AuthApiError::PhoneNumberNotVerified,
         }
     }
 }
@@ -107,6 +163,12 @@ impl From<TokenAuthError> for AuthApiError {
     }
 }
 
+impl From<RoleRejection> for AuthApiError {
+    fn from(rejection: RoleRejection) -> Self {
+        rejection.error
+    }
+}
+
 impl From<BannedTokenStoreError> for AuthApiError {
     fn from(error: BannedTokenStoreError) -> Self {
         match error {
@@ -115,6 +177,24 @@ impl From<BannedTokenStoreError> for AuthApiError {
     }
 }
 
+impl From<AuditLogError> for AuthApiError {
+    fn from(error: AuditLogError) -> Self {
+        match error {
+            AuditLogError::InvalidRecord(_) => AuthApiError::UnexpectedError(error.into()),
+            AuditLogError::UnexpectedError(e) => AuthApiError::UnexpectedError(e),
+        }
+    }
+}
+
+impl From<EmailOutboxError> for AuthApiError {
+    fn from(error: EmailOutboxError) -> Self {
+        match error {
+            EmailOutboxError::InvalidEmail(_) => AuthApiError::UnexpectedError(error.into()),
+            EmailOutboxError::UnexpectedError(e) => AuthApiError::UnexpectedError(e),
+        }
+    }
+}
+
 impl From<TwoFaCodeStoreError> for AuthApiError {
     fn from(error: TwoFaCodeStoreError) -> Self {
         match error {
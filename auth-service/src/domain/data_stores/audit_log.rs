@@ -0,0 +1,293 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AuditLogError {
+    #[error("Invalid audit record: {0}")]
+    InvalidRecord(String),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] color_eyre::Report),
+}
+
+// What a purged user's email is replaced with in the events they were part of
+pub const ERASED_SUBJECT: &str = "[erased]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventType {
+    Signup,
+    Login,
+    TwoFaCodeSent,
+    TwoFaVerification,
+    Elevate,
+    PasswordChange,
+    Logout,
+    AccountDeletion,
+    TokenVerification,
+    PhoneVerificationStarted,
+    PhoneVerification,
+    TwoFaChannelChange,
+    AdminSearchUsers,
+    AdminViewUser,
+    AdminDisableUser,
+    AdminEnableUser,
+    AdminForcePasswordReset,
+    AdminSetRequires2Fa,
+    AdminRevokeSessions,
+    AdminDeleteUser,
+    AdminCreateUser,
+    AdminResetPassword,
+    AdminUnlockUser,
+    AdminExportUsers,
+    AccountExport,
+    AccountRestore,
+    AccountPurge,
+}
+
+impl AuditEventType {
+    pub const ALL: [AuditEventType; 27] = [
+        AuditEventType::Signup,
+        AuditEventType::Login,
+        AuditEventType::TwoFaCodeSent,
+        AuditEventType::TwoFaVerification,
+        AuditEventType::Elevate,
+        AuditEventType::PasswordChange,
+        AuditEventType::Logout,
+        AuditEventType::AccountDeletion,
+        AuditEventType::TokenVerification,
+        AuditEventType::PhoneVerificationStarted,
+        AuditEventType::PhoneVerification,
+        AuditEventType::TwoFaChannelChange,
+        AuditEventType::AdminSearchUsers,
+        AuditEventType::AdminViewUser,
+        AuditEventType::AdminDisableUser,
+        AuditEventType::AdminEnableUser,
+        AuditEventType::AdminForcePasswordReset,
+        AuditEventType::AdminSetRequires2Fa,
+        AuditEventType::AdminRevokeSessions,
+        AuditEventType::AdminDeleteUser,
+        AuditEventType::AdminCreateUser,
+        AuditEventType::AdminResetPassword,
+        AuditEventType::AdminUnlockUser,
+        AuditEventType::AdminExportUsers,
+        AuditEventType::AccountExport,
+        AuditEventType::AccountRestore,
+        AuditEventType::AccountPurge,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEventType::Signup => "signup",
+            AuditEventType::Login => "login",
+            AuditEventType::TwoFaCodeSent => "two_fa_code_sent",
+            AuditEventType::TwoFaVerification => "two_fa_verification",
+            AuditEventType::Elevate => "elevate",
+            AuditEventType::PasswordChange => "password_change",
+            AuditEventType::Logout => "logout",
+            AuditEventType::AccountDeletion => "account_deletion",
+            AuditEventType::TokenVerification => "token_verification",
+            AuditEventType::PhoneVerificationStarted => "phone_verification_started",
+            AuditEventType::PhoneVerification => "phone_verification",
+            AuditEventType::TwoFaChannelChange => "two_fa_channel_change",
+            AuditEventType::AdminSearchUsers => "admin_search_users",
+            AuditEventType::AdminViewUser => "admin_view_user",
+            AuditEventType::AdminDisableUser => "admin_disable_user",
+            AuditEventType::AdminEnableUser => "admin_enable_user",
+            AuditEventType::AdminForcePasswordReset => "admin_force_password_reset",
+            AuditEventType::AdminSetRequires2Fa => "admin_set_requires_2fa",
+            AuditEventType::AdminRevokeSessions => "admin_revoke_sessions",
+            AuditEventType::AdminDeleteUser => "admin_delete_user",
+            AuditEventType::AdminCreateUser => "admin_create_user",
+            AuditEventType::AdminResetPassword => "admin_reset_password",
+            AuditEventType::AdminUnlockUser => "admin_unlock_user",
+            AuditEventType::AdminExportUsers => "admin_export_users",
+            AuditEventType::AccountExport => "account_export",
+            AuditEventType::AccountRestore => "account_restore",
+            AuditEventType::AccountPurge => "account_purge",
+        }
+    }
+}
+
+impl FromStr for AuditEventType {
+    type Err = AuditLogError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditEventType::ALL
+            .into_iter()
+            .find(|event_type| event_type.as_str() == s)
+            .ok_or_else(|| AuditLogError::InvalidRecord(format!("unknown event type '{}'", s)))
+    }
+}
+
+impl Display for AuditEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditOutcome {
+    #[default]
+    Success,
+    Failure,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::Failure => "failure",
+        }
+    }
+}
+
+impl FromStr for AuditOutcome {
+    type Err = AuditLogError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(AuditOutcome::Success),
+            "failure" => Ok(AuditOutcome::Failure),
+            _ => Err(AuditLogError::InvalidRecord(format!(
+                "unknown outcome '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for AuditOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub actor: Option<String>,
+    pub event_type: AuditEventType,
+    pub outcome: AuditOutcome,
+    pub target: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(event_type: AuditEventType) -> Self {
+        AuditEvent {
+            actor: None,
+            event_type,
+            outcome: AuditOutcome::Success,
+            target: None,
+            ip: None,
+            user_agent: None,
+            details: None,
+        }
+    }
+
+    pub fn actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn outcome(mut self, outcome: AuditOutcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    pub fn ip(mut self, ip: Option<String>) -> Self {
+        self.ip = ip;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: Option<String>) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub event: AuditEvent,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditRecord {
+    // The record as shown to `subject`. Who performed an event on them, e.g. an
+    // admin, and from where is not theirs to see.
+    pub fn seen_by(mut self, subject: &str) -> Self {
+        if self.event.actor.as_deref() != Some(subject) {
+            self.event.actor = None;
+            self.event.ip = None;
+            self.event.user_agent = None;
+        }
+        self
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuditLog: Send + Sync {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError>;
+    // Most recent first, matching events the user performed or was the target of
+    async fn recent_events(
+        &self,
+        subject: &str,
+        limit: u32,
+    ) -> Result<Vec<AuditRecord>, AuditLogError>;
+    // Replaces the subject with ERASED_SUBJECT wherever they are the actor or
+    // target, and clears the ip and user agent of the events they performed.
+    // Returns the number of events changed.
+    async fn erase_subject(&self, subject: &str) -> Result<u64, AuditLogError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type_round_trips_through_str() {
+        for event_type in AuditEventType::ALL {
+            assert_eq!(
+                event_type.as_str().parse::<AuditEventType>().unwrap(),
+                event_type
+            );
+        }
+        assert!("unknown".parse::<AuditEventType>().is_err());
+    }
+
+    #[test]
+    fn test_seen_by_hides_who_acted_on_the_subject() {
+        let record = |event: AuditEvent| AuditRecord {
+            event: event
+                .ip(Some("10.0.0.1".to_owned()))
+                .user_agent(Some("curl".to_owned())),
+            occurred_at: Utc::now(),
+        };
+
+        let own = record(AuditEvent::new(AuditEventType::Login).actor("user@example.com"));
+        assert_eq!(own.clone().seen_by("user@example.com"), own);
+
+        let by_admin = record(
+            AuditEvent::new(AuditEventType::AdminDisableUser)
+                .actor("admin@example.com")
+                .target("user@example.com"),
+        )
+        .seen_by("user@example.com");
+        assert_eq!(by_admin.event.actor, None);
+        assert_eq!(by_admin.event.ip, None);
+        assert_eq!(by_admin.event.user_agent, None);
+        assert_eq!(by_admin.event.target.as_deref(), Some("user@example.com"));
+    }
+}
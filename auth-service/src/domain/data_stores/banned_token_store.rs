@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::domain::email::Email;
+
 #[derive(Debug, Error)]
 pub enum BannedTokenStoreError {
     #[error("Database error: {0}")]
@@ -8,6 +10,36 @@ pub enum BannedTokenStoreError {
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Send + Sync {
-    async fn ban_token(&mut self, token: String) -> Result<(), BannedTokenStoreError>;
+    async fn ban_token(&self, token: String) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError>;
+    // Tokens carry the generation they were issued under, bumping it revokes
+    // every token previously issued to the user.
+    async fn revoke_all_tokens(&self, email: &Email) -> Result<(), BannedTokenStoreError>;
+    async fn token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError>;
+    // Revokes every token of a purged user. Like a banned token, the
+    // generation is only kept until the tokens it revoked have expired.
+    async fn forget_user(&self, email: &Email) -> Result<(), BannedTokenStoreError>;
+}
+
+#[async_trait::async_trait]
+impl<S: BannedTokenStore + ?Sized> BannedTokenStore for Box<S> {
+    async fn ban_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        (**self).ban_token(token).await
+    }
+
+    async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
+        (**self).contains_token(token).await
+    }
+
+    async fn revoke_all_tokens(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        (**self).revoke_all_tokens(email).await
+    }
+
+    async fn token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        (**self).token_generation(email).await
+    }
+
+    async fn forget_user(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        (**self).forget_user(email).await
+    }
 }
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::domain::{email::Email, email_client::EmailMessage};
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxError {
+    #[error("Invalid queued email: {0}")]
+    InvalidEmail(String),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] color_eyre::Report),
+}
+
+#[derive(Debug, Clone)]
+pub struct QueuedEmail {
+    pub id: i64,
+    pub recipient: Email,
+    pub message: EmailMessage,
+    // Delivery attempts made so far, including the one this email was claimed for
+    pub attempts: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmailOutboxStats {
+    pub pending: u64,
+    pub dead: u64,
+}
+
+// Emails are written here by the request handlers and delivered later by the
+// outbox worker. Delivered emails are removed, emails that keep failing are
+// kept as dead letters.
+#[async_trait::async_trait]
+pub trait EmailOutbox: Send + Sync {
+    async fn enqueue(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailOutboxError>;
+    // Claims up to `limit` emails that are due at `now`. Claimed emails are
+    // hidden from other workers for `lease`, after which they are handed out
+    // again.
+    async fn claim_due(
+        &self,
+        limit: u32,
+        lease: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<QueuedEmail>, EmailOutboxError>;
+    async fn mark_sent(&self, id: i64) -> Result<(), EmailOutboxError>;
+    async fn reschedule(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), EmailOutboxError>;
+    // Gives up on an email. Its content is dropped, it can hold links and codes
+    // that must not outlive delivery, only the recipient, subject and error
+    // are kept to see what failed.
+    async fn dead_letter(&self, id: i64, error: &str) -> Result<(), EmailOutboxError>;
+    async fn stats(&self) -> Result<EmailOutboxStats, EmailOutboxError>;
+    // Deletes every email to `recipient`, dead letters included, so nothing of
+    // a deleted user is left behind. Returns how many were deleted.
+    async fn forget_recipient(&self, recipient: &Email) -> Result<u64, EmailOutboxError>;
+}
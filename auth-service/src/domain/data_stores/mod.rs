@@ -1,7 +1,16 @@
+mod audit_log;
 mod banned_token_store;
+mod email_outbox;
 mod two_fa_code_store;
 mod user_store;
 
+pub use audit_log::{
+    AuditEvent, AuditEventType, AuditLog, AuditLogError, AuditOutcome, AuditRecord, ERASED_SUBJECT,
+};
 pub use banned_token_store::{BannedTokenStore, BannedTokenStoreError};
-pub use two_fa_code_store::{TwoFaCodeStore, TwoFaCodeStoreError};
-pub use user_store::{UserStore, UserStoreError};
+pub use email_outbox::{EmailOutbox, EmailOutboxError, EmailOutboxStats, QueuedEmail};
+pub use two_fa_code_store::{TWO_FA_CODE_TTL, TwoFaCodeStore, TwoFaCodeStoreError};
+pub use user_store::{
+    MAX_PHONE_VERIFICATION_ATTEMPTS, PHONE_VERIFICATION_TTL_SECONDS, UserPage, UserQuery,
+    UserStore, UserStoreError,
+};
@@ -1,6 +1,11 @@
 use crate::domain::{email::Email, two_fa_attempt_id::TwoFaAttemptId, two_fa_code::TwoFaCode};
+use std::time::Duration;
+
 use thiserror::Error;
 
+// How long a 2FA code can be used after it was sent, in every store
+pub const TWO_FA_CODE_TTL: Duration = Duration::from_secs(600);
+
 #[derive(Debug, Error)]
 pub enum TwoFaCodeStoreError {
     #[error("User not found")]
@@ -16,20 +21,20 @@ pub enum TwoFaCodeStoreError {
 #[cfg(debug_assertions)]
 impl PartialEq for TwoFaCodeStoreError {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::UserNotFound, Self::UserNotFound) => true,
-            (Self::InvalidAttemptId, Self::InvalidAttemptId) => true,
-            (Self::Invalid2FACode, Self::Invalid2FACode) => true,
-            (Self::UnexpectedError(_), Self::UnexpectedError(_)) => true,
-            _ => false,
-        }
+        matches!(
+            (self, other),
+            (Self::UserNotFound, Self::UserNotFound)
+                | (Self::InvalidAttemptId, Self::InvalidAttemptId)
+                | (Self::Invalid2FACode, Self::Invalid2FACode)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
 }
 
 #[async_trait::async_trait]
 pub trait TwoFaCodeStore: Send + Sync {
     async fn store_code(
-        &mut self,
+        &self,
         user_id: Email,
         login_attempt_id: TwoFaAttemptId,
         two_fa_code: TwoFaCode,
@@ -47,5 +52,41 @@ pub trait TwoFaCodeStore: Send + Sync {
         user_id: &Email,
     ) -> Result<(TwoFaAttemptId, TwoFaCode), TwoFaCodeStoreError>;
 
-    async fn delete(&mut self, user_id: &Email) -> Result<(), TwoFaCodeStoreError>;
+    async fn delete(&self, user_id: &Email) -> Result<(), TwoFaCodeStoreError>;
+}
+
+#[async_trait::async_trait]
+impl<S: TwoFaCodeStore + ?Sized> TwoFaCodeStore for Box<S> {
+    async fn store_code(
+        &self,
+        user_id: Email,
+        login_attempt_id: TwoFaAttemptId,
+        two_fa_code: TwoFaCode,
+    ) -> Result<(), TwoFaCodeStoreError> {
+        (**self)
+            .store_code(user_id, login_attempt_id, two_fa_code)
+            .await
+    }
+
+    async fn validate(
+        &self,
+        user_id: &Email,
+        login_attempt_id: &TwoFaAttemptId,
+        two_fa_code: &TwoFaCode,
+    ) -> Result<(), TwoFaCodeStoreError> {
+        (**self)
+            .validate(user_id, login_attempt_id, two_fa_code)
+            .await
+    }
+
+    async fn get_login_attempt_id_and_two_fa_code(
+        &self,
+        user_id: &Email,
+    ) -> Result<(TwoFaAttemptId, TwoFaCode), TwoFaCodeStoreError> {
+        (**self).get_login_attempt_id_and_two_fa_code(user_id).await
+    }
+
+    async fn delete(&self, user_id: &Email) -> Result<(), TwoFaCodeStoreError> {
+        (**self).delete(user_id).await
+    }
 }
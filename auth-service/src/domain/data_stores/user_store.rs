@@ -1,12 +1,56 @@
+use chrono::{DateTime, Utc};
 use color_eyre::Report;
 use thiserror::Error;
 
 use crate::domain::{
+    device_fingerprint::{DeviceFingerprint, KnownDevice},
     email::Email,
     password::Password,
-    user::{User, ValidatedUser},
+    phone_number::PhoneNumber,
+    restore_token::RestoreToken,
+    role::Role,
+    two_fa_channel::TwoFaChannel,
+    two_fa_code::TwoFaCode,
+    user::{ImportedUser, User, UserProfile, ValidatedUser},
+    user_status::UserStatus,
 };
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserQuery {
+    pub email_contains: Option<String>,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl UserQuery {
+    pub fn offset(&self) -> u64 {
+        u64::from(self.page.saturating_sub(1)) * u64::from(self.per_page)
+    }
+
+    // A LIKE pattern for email_contains. Wildcards in the term are escaped with
+    // a backslash so the term is matched literally.
+    pub fn email_like_pattern(&self) -> Option<String> {
+        self.email_contains.as_deref().map(|term| {
+            let escaped = term
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{}%", escaped)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<UserProfile>,
+    pub total: u64,
+}
+
+// How long a phone verification code stays valid, and how many wrong codes
+// are accepted before the verification has to be started over
+pub const PHONE_VERIFICATION_TTL_SECONDS: i64 = 600;
+pub const MAX_PHONE_VERIFICATION_ATTEMPTS: i32 = 5;
+
 #[derive(Debug, Error)]
 pub enum UserStoreError {
     #[error("User already exists")]
@@ -15,27 +59,67 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Incorrect password")]
     IncorrectPassword,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("User is suspended")]
+    UserSuspended,
+    #[error("User has not verified their account")]
+    UserPendingVerification,
+    #[error("User is locked")]
+    UserLocked,
+    #[error("User is scheduled for deletion")]
+    UserPendingDeletion,
+    #[error("Invalid or expired verification code")]
+    InvalidVerificationCode,
+    #[error("Invalid or expired restore token")]
+    InvalidRestoreToken,
+    #[error("User has no verified phone number")]
+    PhoneNumberNotVerified,
     #[error("Unexpected error {0}")]
     UnexpectedError(#[source] Report),
 }
 
+impl UserStoreError {
+    // Maps a non-active status to the error `authenticate_user` refuses it with
+    pub fn from_inactive_status(status: UserStatus) -> Option<Self> {
+        match status {
+            UserStatus::Active => None,
+            UserStatus::Suspended => Some(UserStoreError::UserSuspended),
+            UserStatus::PendingVerification => Some(UserStoreError::UserPendingVerification),
+            UserStatus::Locked => Some(UserStoreError::UserLocked),
+            UserStatus::PendingDeletion => Some(UserStoreError::UserPendingDeletion),
+        }
+    }
+}
+
 impl PartialEq for UserStoreError {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::UserAlreadyExists, Self::UserAlreadyExists) => true,
-            (Self::UserNotFound, Self::UserNotFound) => true,
-            (Self::IncorrectPassword, Self::IncorrectPassword) => true,
-            (Self::UnexpectedError(_), Self::UnexpectedError(_)) => true,
-            _ => false,
-        }
+        matches!(
+            (self, other),
+            (Self::UserAlreadyExists, Self::UserAlreadyExists)
+                | (Self::UserNotFound, Self::UserNotFound)
+                | (Self::IncorrectPassword, Self::IncorrectPassword)
+                | (Self::RoleNotFound, Self::RoleNotFound)
+                | (Self::UserSuspended, Self::UserSuspended)
+                | (Self::UserPendingVerification, Self::UserPendingVerification)
+                | (Self::UserLocked, Self::UserLocked)
+                | (Self::UserPendingDeletion, Self::UserPendingDeletion)
+                | (Self::InvalidVerificationCode, Self::InvalidVerificationCode)
+                | (Self::InvalidRestoreToken, Self::InvalidRestoreToken)
+                | (Self::PhoneNumberNotVerified, Self::PhoneNumberNotVerified)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
     }
 }
 
 #[async_trait::async_trait]
 pub trait UserStore: Send + Sync {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
+    // Stores the hash as-is, `authenticate_user` replaces it with one of our
+    // own on the first successful login
+    async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError>;
     async fn set_new_password(
-        &mut self,
+        &self,
         email: &Email,
         new_password: Password,
     ) -> Result<(), UserStoreError>;
@@ -44,6 +128,82 @@ pub trait UserStore: Send + Sync {
         email: &Email,
         password: &Password,
     ) -> Result<ValidatedUser, UserStoreError>;
+    // Whether the password is still kept under another hash than new ones get,
+    // e.g. the one the user was imported with, until they next log in
+    async fn password_needs_rehash(&self, email: &Email) -> Result<bool, UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
-    async fn delete_user(&mut self, user: &Email) -> Result<(), UserStoreError>;
+    async fn delete_user(&self, user: &Email) -> Result<(), UserStoreError>;
+    async fn assign_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError>;
+    async fn revoke_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError>;
+    async fn get_roles(&self, email: &Email) -> Result<Vec<Role>, UserStoreError>;
+    async fn get_user_profile(&self, email: &Email) -> Result<UserProfile, UserStoreError>;
+    async fn search_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+    async fn get_status(&self, email: &Email) -> Result<UserStatus, UserStoreError>;
+    // Fails with UserPendingDeletion for a user scheduled for deletion, which
+    // only `restore_user` or deleting the user ends
+    async fn set_status(&self, email: &Email, status: UserStatus) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError>;
+    // Returns true if the device had not been seen for this user before
+    async fn remember_device(
+        &self,
+        email: &Email,
+        fingerprint: &DeviceFingerprint,
+    ) -> Result<bool, UserStoreError>;
+    // Most recently seen first
+    async fn known_devices(&self, email: &Email) -> Result<Vec<KnownDevice>, UserStoreError>;
+    async fn security_notifications_enabled(&self, email: &Email) -> Result<bool, UserStoreError>;
+    async fn set_security_notifications(
+        &self,
+        email: &Email,
+        enabled: bool,
+    ) -> Result<(), UserStoreError>;
+    // Keeps the number as pending until it is confirmed with the code sent to
+    // it. Starting a new verification replaces any pending one.
+    async fn start_phone_verification(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+        code: &TwoFaCode,
+    ) -> Result<(), UserStoreError>;
+    // Makes the pending number the user's phone number if the code matches
+    async fn confirm_phone_verification(
+        &self,
+        email: &Email,
+        code: &TwoFaCode,
+    ) -> Result<PhoneNumber, UserStoreError>;
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFaChannel,
+    ) -> Result<(), UserStoreError>;
+    // Marks the user as pending deletion until `due_at`, restorable with the
+    // token until then. Scheduling again replaces the date and the token.
+    async fn schedule_deletion(
+        &self,
+        email: &Email,
+        due_at: DateTime<Utc>,
+        restore_token: &RestoreToken,
+    ) -> Result<(), UserStoreError>;
+    // Makes the user pending deletion with this token active again, as long as
+    // the deletion is not yet due
+    async fn restore_user(
+        &self,
+        restore_token: &RestoreToken,
+        now: DateTime<Utc>,
+    ) -> Result<Email, UserStoreError>;
+    // Users pending deletion whose deletion was due at `now`, earliest first
+    async fn due_deletions(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Email>, UserStoreError>;
 }
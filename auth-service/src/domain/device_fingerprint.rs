@@ -0,0 +1,109 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use chrono::{DateTime, Utc};
+
+// Identifies the device a user signs in from. Only the network prefix of the
+// address is used so that a device keeps its fingerprint when its ISP hands
+// out a new address in the same range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceFingerprint(String);
+
+impl DeviceFingerprint {
+    pub fn new(user_agent: Option<&str>, ip: Option<&str>) -> Self {
+        let user_agent = user_agent.map(str::trim).unwrap_or_default();
+        let prefix = ip
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+            .map(network_prefix)
+            .unwrap_or_else(|| "unknown".to_owned());
+
+        DeviceFingerprint(format!("{}|{}", prefix, user_agent))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+// A device the user has signed in from
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownDevice {
+    pub fingerprint: DeviceFingerprint,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl From<String> for DeviceFingerprint {
+    fn from(fingerprint: String) -> Self {
+        DeviceFingerprint(fingerprint)
+    }
+}
+
+impl Display for DeviceFingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn network_prefix(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{}/24", Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => network_prefix(IpAddr::V4(ip)),
+            None => {
+                let [a, b, c, ..] = ip.segments();
+                format!("{}/48", Ipv6Addr::new(a, b, c, 0, 0, 0, 0, 0))
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0";
+
+    #[test]
+    fn test_same_network_and_agent_share_fingerprint() {
+        assert_eq!(
+            DeviceFingerprint::new(Some(FIREFOX), Some("203.0.113.7")),
+            DeviceFingerprint::new(Some(FIREFOX), Some("203.0.113.200"))
+        );
+        assert_eq!(
+            DeviceFingerprint::new(Some(FIREFOX), Some("2001:db8:1:2::1")),
+            DeviceFingerprint::new(Some(FIREFOX), Some("2001:db8:1:ffff::9"))
+        );
+    }
+
+    #[test]
+    fn test_different_network_or_agent_changes_fingerprint() {
+        let fingerprint = DeviceFingerprint::new(Some(FIREFOX), Some("203.0.113.7"));
+
+        assert_ne!(
+            fingerprint,
+            DeviceFingerprint::new(Some(FIREFOX), Some("198.51.100.7"))
+        );
+        assert_ne!(
+            fingerprint,
+            DeviceFingerprint::new(Some("curl/8.5.0"), Some("203.0.113.7"))
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_format() {
+        assert_eq!(
+            DeviceFingerprint::new(Some("curl/8.5.0"), Some("::ffff:10.1.2.3")).as_str(),
+            "10.1.2.0/24|curl/8.5.0"
+        );
+        assert_eq!(
+            DeviceFingerprint::new(None, Some("not an ip")).as_str(),
+            "unknown|"
+        );
+    }
+}
@@ -15,13 +15,23 @@ impl TryFrom<Secret<String>> for Email {
     type Error = UserError;
 
     fn try_from(email: Secret<String>) -> Result<Self, Self::Error> {
-        if !EMAIL_REGEX.is_match(&email.expose_secret()) {
+        if !EMAIL_REGEX.is_match(email.expose_secret()) {
             return Err(UserError::InvalidEmail);
         }
         Ok(Email(email))
     }
 }
 
+impl Email {
+    pub fn domain(&self) -> &str {
+        self.0
+            .expose_secret()
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or_default()
+    }
+}
+
 impl PartialEq for Email {
     fn eq(&self, other: &Self) -> bool {
         self.0.expose_secret() == other.0.expose_secret()
@@ -34,11 +44,7 @@ impl AsRef<Secret<String>> for Email {
     }
 }
 
-impl Eq for Email {
-    fn assert_receiver_is_total_eq(&self) {
-        self.0.expose_secret().assert_receiver_is_total_eq();
-    }
-}
+impl Eq for Email {}
 
 impl Hash for Email {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
@@ -1,8 +1,46 @@
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::Result;
 
 use super::email::Email;
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub attachments: Vec<EmailAttachment>,
+    // When the content is no longer of use, e.g. a sign-in code that has run
+    // out. A queued email is given up on rather than sent after this.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl EmailMessage {
+    pub fn with_attachment(mut self, attachment: EmailAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    pub fn expiring_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
 #[async_trait::async_trait]
 pub trait EmailClient: Send + Sync {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()>;
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl<C: EmailClient + ?Sized> EmailClient for Box<C> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        (**self).send_email(recipient, message).await
+    }
 }
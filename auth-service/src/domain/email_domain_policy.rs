@@ -0,0 +1,131 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::email::Email;
+
+static DISPOSABLE_EMAIL_DOMAINS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    include_str!("disposable_email_domains.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum EmailDomainPolicy {
+    Allowlist {
+        allowed_domains: Vec<String>,
+    },
+    Denylist {
+        #[serde(default)]
+        denied_domains: Vec<String>,
+        #[serde(default = "default_block_disposable")]
+        block_disposable: bool,
+    },
+}
+
+fn default_block_disposable() -> bool {
+    true
+}
+
+impl Default for EmailDomainPolicy {
+    fn default() -> Self {
+        EmailDomainPolicy::Denylist {
+            denied_domains: Vec::new(),
+            block_disposable: true,
+        }
+    }
+}
+
+impl EmailDomainPolicy {
+    pub fn is_allowed(&self, email: &Email) -> bool {
+        let domain = email.domain().to_lowercase();
+
+        match self {
+            EmailDomainPolicy::Allowlist { allowed_domains } => {
+                matches_any(&domain, allowed_domains.iter().map(String::as_str))
+            }
+            EmailDomainPolicy::Denylist {
+                denied_domains,
+                block_disposable,
+            } => {
+                if matches_any(&domain, denied_domains.iter().map(String::as_str)) {
+                    return false;
+                }
+                !(*block_disposable
+                    && matches_any(&domain, DISPOSABLE_EMAIL_DOMAINS.iter().copied()))
+            }
+        }
+    }
+}
+
+// A listed domain also covers its subdomains, so "example.com" matches "mail.example.com".
+fn matches_any<'a>(domain: &str, listed: impl IntoIterator<Item = &'a str>) -> bool {
+    listed.into_iter().any(|listed| {
+        let listed = listed.trim().trim_start_matches('@');
+        domain.eq_ignore_ascii_case(listed)
+            || domain
+                .strip_suffix(&listed.to_lowercase())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::try_from(Secret::from(address.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn test_default_policy_blocks_disposable_domains() {
+        let policy = EmailDomainPolicy::default();
+        assert!(!policy.is_allowed(&email("someone@mailinator.com")));
+        assert!(!policy.is_allowed(&email("someone@YOPMAIL.com")));
+        assert!(policy.is_allowed(&email("someone@example.com")));
+    }
+
+    #[test]
+    fn test_denylist_blocks_listed_domains_and_subdomains() {
+        let policy = EmailDomainPolicy::Denylist {
+            denied_domains: vec!["competitor.com".to_owned()],
+            block_disposable: false,
+        };
+        assert!(!policy.is_allowed(&email("someone@competitor.com")));
+        assert!(!policy.is_allowed(&email("someone@eu.competitor.com")));
+        assert!(policy.is_allowed(&email("someone@notcompetitor.com")));
+        assert!(policy.is_allowed(&email("someone@mailinator.com")));
+    }
+
+    #[test]
+    fn test_allowlist_only_allows_listed_domains() {
+        let policy = EmailDomainPolicy::Allowlist {
+            allowed_domains: vec!["corp.example".to_owned()],
+        };
+        assert!(policy.is_allowed(&email("someone@corp.example")));
+        assert!(policy.is_allowed(&email("someone@eng.corp.example")));
+        assert!(!policy.is_allowed(&email("someone@example.com")));
+    }
+
+    #[test]
+    fn test_deserialize_policy() {
+        let policy: EmailDomainPolicy =
+            serde_json::from_str(r#"{ "mode": "allowlist", "allowed_domains": ["corp.example"] }"#)
+                .unwrap();
+        assert!(matches!(policy, EmailDomainPolicy::Allowlist { .. }));
+
+        let policy: EmailDomainPolicy = serde_json::from_str(r#"{ "mode": "denylist" }"#).unwrap();
+        assert!(matches!(
+            policy,
+            EmailDomainPolicy::Denylist {
+                block_disposable: true,
+                ..
+            }
+        ));
+    }
+}
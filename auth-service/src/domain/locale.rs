@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+// Languages emails can be sent in. Only the subjects are translated for now,
+// the bodies are rendered from the same templates for every locale.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Nb,
+}
@@ -1,9 +1,22 @@
 pub mod auth_api_error;
+pub mod clock;
 pub mod data_stores;
+pub mod device_fingerprint;
 pub mod email;
 pub mod email_client;
+pub mod email_domain_policy;
+pub mod locale;
 pub mod password;
+pub mod password_hash;
+pub mod phone_number;
+pub mod restore_token;
+pub mod role;
+pub mod secret_provider;
+pub mod security_notification;
+pub mod sms_client;
 pub mod two_fa_attempt_id;
+pub mod two_fa_channel;
 pub mod two_fa_code;
 pub mod two_fa_error;
 pub mod user;
+pub mod user_status;
@@ -19,6 +19,14 @@ impl TryFrom<Secret<String>> for Password {
     }
 }
 
+impl Password {
+    // Skips the password policy, only for checking a password someone already
+    // has against its hash
+    pub fn unchecked(value: Secret<String>) -> Self {
+        Password(value)
+    }
+}
+
 impl AsRef<Secret<String>> for Password {
     fn as_ref(&self) -> &Secret<String> {
         &self.0
@@ -0,0 +1,150 @@
+use std::fmt::Debug;
+
+use secrecy::{ExposeSecret, Secret};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PasswordHashError {
+    #[error(
+        "Unsupported password hash, expected bcrypt or a PHC string for argon2, scrypt or pbkdf2"
+    )]
+    UnsupportedScheme,
+    #[error("Malformed {0} password hash")]
+    Malformed(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordHashScheme {
+    Argon2,
+    Bcrypt,
+    Scrypt,
+    Pbkdf2,
+}
+
+impl PasswordHashScheme {
+    // Recognizes the scheme by its identifier, e.g. $argon2id$ or $2b$
+    pub fn detect(hash: &str) -> Option<Self> {
+        let id = hash.strip_prefix('$')?.split('$').next()?;
+        match id {
+            "argon2id" | "argon2i" | "argon2d" => Some(PasswordHashScheme::Argon2),
+            "2a" | "2b" | "2x" | "2y" => Some(PasswordHashScheme::Bcrypt),
+            "scrypt" => Some(PasswordHashScheme::Scrypt),
+            "pbkdf2-sha256" | "pbkdf2-sha512" => Some(PasswordHashScheme::Pbkdf2),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasswordHashScheme::Argon2 => "argon2",
+            PasswordHashScheme::Bcrypt => "bcrypt",
+            PasswordHashScheme::Scrypt => "scrypt",
+            PasswordHashScheme::Pbkdf2 => "pbkdf2",
+        }
+    }
+}
+
+// A hash computed by another system, imported as-is. Users with one are
+// verified against it by its scheme and rehashed on their next login.
+#[derive(Clone)]
+pub struct PasswordHash {
+    scheme: PasswordHashScheme,
+    hash: Secret<String>,
+}
+
+impl PasswordHash {
+    pub fn scheme(&self) -> PasswordHashScheme {
+        self.scheme
+    }
+}
+
+// bcrypt hashes are $2b$ + cost + $ + 53 characters of salt and hash
+const BCRYPT_HASH_LENGTH: usize = 60;
+
+impl TryFrom<Secret<String>> for PasswordHash {
+    type Error = PasswordHashError;
+
+    fn try_from(hash: Secret<String>) -> Result<Self, Self::Error> {
+        let value = hash.expose_secret();
+        let scheme =
+            PasswordHashScheme::detect(value).ok_or(PasswordHashError::UnsupportedScheme)?;
+
+        let well_formed = match scheme {
+            PasswordHashScheme::Bcrypt => value.len() == BCRYPT_HASH_LENGTH,
+            _ => argon2::PasswordHash::new(value)
+                .is_ok_and(|parsed| parsed.salt.is_some() && parsed.hash.is_some()),
+        };
+        if !well_formed {
+            return Err(PasswordHashError::Malformed(scheme.as_str()));
+        }
+
+        Ok(PasswordHash { scheme, hash })
+    }
+}
+
+impl AsRef<Secret<String>> for PasswordHash {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.hash
+    }
+}
+
+impl Debug for PasswordHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PasswordHash({}, *Masked*)", self.scheme.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(hash: &str) -> Result<PasswordHash, PasswordHashError> {
+        PasswordHash::try_from(Secret::new(hash.to_owned()))
+    }
+
+    #[test]
+    fn test_detects_supported_schemes() {
+        let hashes = [
+            (
+                "$argon2id$v=19$m=19456,t=2,p=1$c29tZXNhbHQ$CTFhFdXPJO1aFaMaO6Mm5c8y7cJHAph8ArZWb2GRPPc",
+                PasswordHashScheme::Argon2,
+            ),
+            (
+                "$2b$04$EGdrhbKUv8Oc9vGiXX0HQOxSg445d458Muh7DAHskb6QbtCvdxcie",
+                PasswordHashScheme::Bcrypt,
+            ),
+            (
+                "$scrypt$ln=4,r=8,p=1$c29tZXNhbHQ$cm0lQ2BRMh0ejqG6V+cBvHpYxXhtHgl/LO7DLE/6t0g",
+                PasswordHashScheme::Scrypt,
+            ),
+            (
+                "$pbkdf2-sha256$i=1000,l=32$c29tZXNhbHQ$Cw1Z09k9z+4Y2JAPBeGBtwGhBfNadFcFUQ+OTL1S7hU",
+                PasswordHashScheme::Pbkdf2,
+            ),
+        ];
+
+        for (hash, scheme) in hashes {
+            assert_eq!(parse(hash).unwrap().scheme(), scheme, "{hash}");
+        }
+    }
+
+    #[test]
+    fn test_rejects_unsupported_and_malformed_hashes() {
+        assert_eq!(
+            parse("5f4dcc3b5aa765d61d8327deb882cf99").unwrap_err(),
+            PasswordHashError::UnsupportedScheme
+        );
+        assert_eq!(
+            parse("$1$salt$md5crypt").unwrap_err(),
+            PasswordHashError::UnsupportedScheme
+        );
+        assert_eq!(
+            parse("$2b$04$tooshort").unwrap_err(),
+            PasswordHashError::Malformed("bcrypt")
+        );
+        assert_eq!(
+            parse("$argon2id$v=19$m=19456,t=2,p=1").unwrap_err(),
+            PasswordHashError::Malformed("argon2")
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use secrecy::{ExposeSecret, Secret};
+
+use super::user::UserError;
+
+// E.164: a plus sign followed by at most 15 digits, the first being a country code digit
+const E164_REGEX_PATTERN: &str = r"^\+[1-9][0-9]{1,14}$";
+static E164_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(E164_REGEX_PATTERN).unwrap());
+
+#[derive(Debug, Clone)]
+pub struct PhoneNumber(Secret<String>);
+
+impl TryFrom<Secret<String>> for PhoneNumber {
+    type Error = UserError;
+
+    // Accepts common formatting such as "+47 912 34 567" or "+1 (555) 123-4567"
+    // and stores the number in its canonical form.
+    fn try_from(phone_number: Secret<String>) -> Result<Self, Self::Error> {
+        let canonical: String = phone_number
+            .expose_secret()
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '(' | ')'))
+            .collect();
+
+        if !E164_REGEX.is_match(&canonical) {
+            return Err(UserError::InvalidPhoneNumber);
+        }
+        Ok(PhoneNumber(Secret::new(canonical)))
+    }
+}
+
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for PhoneNumber {}
+
+impl AsRef<Secret<String>> for PhoneNumber {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(phone_number: &str) -> Result<PhoneNumber, UserError> {
+        PhoneNumber::try_from(Secret::new(phone_number.to_owned()))
+    }
+
+    #[test]
+    fn test_valid_numbers_are_canonicalised() {
+        for (input, expected) in [
+            ("+4791234567", "+4791234567"),
+            ("+47 912 34 567", "+4791234567"),
+            ("+1 (555) 123-4567", "+15551234567"),
+        ] {
+            let phone_number = parse(input).unwrap();
+            assert_eq!(phone_number.as_ref().expose_secret(), expected);
+        }
+    }
+
+    #[test]
+    fn test_invalid_numbers_are_rejected() {
+        for input in [
+            "",
+            "4791234567",
+            "+0791234567",
+            "+47912345678901234",
+            "+47 912 ABC",
+            "+",
+        ] {
+            assert_eq!(parse(input), Err(UserError::InvalidPhoneNumber), "{input}");
+        }
+    }
+}
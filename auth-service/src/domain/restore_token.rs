@@ -0,0 +1,71 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+const TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RestoreTokenError {
+    #[error("Invalid restore token")]
+    InvalidToken,
+}
+
+// The token in the link that restores an account scheduled for deletion. Only
+// its hash is kept with the user. The emailed link sits in the outbox until it
+// is sent or given up on, both of which drop it.
+#[derive(Debug, Clone)]
+pub struct RestoreToken(Secret<String>);
+
+impl RestoreToken {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; TOKEN_BYTES];
+        rand::rng().fill_bytes(&mut bytes);
+        RestoreToken(Secret::new(URL_SAFE_NO_PAD.encode(bytes)))
+    }
+
+    pub fn parse(token: Secret<String>) -> Result<Self, RestoreTokenError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token.expose_secret())
+            .map_err(|_| RestoreTokenError::InvalidToken)?;
+        if bytes.len() != TOKEN_BYTES {
+            return Err(RestoreTokenError::InvalidToken);
+        }
+        Ok(RestoreToken(token))
+    }
+
+    pub fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.0.expose_secret().as_bytes()))
+    }
+}
+
+impl AsRef<Secret<String>> for RestoreToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_token_parses() {
+        let token = RestoreToken::generate();
+        let parsed = RestoreToken::parse(token.as_ref().clone()).unwrap();
+
+        assert_eq!(parsed.hash(), token.hash());
+        assert_ne!(RestoreToken::generate().hash(), token.hash());
+    }
+
+    #[test]
+    fn test_malformed_tokens_are_rejected() {
+        for token in ["", "not a token", "c2hvcnQ"] {
+            assert_eq!(
+                RestoreToken::parse(Secret::new(token.to_owned())).unwrap_err(),
+                RestoreTokenError::InvalidToken
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fmt::Display, sync::LazyLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const ROLE_REGEX_PATTERN: &str = r"^[a-z][a-z0-9_-]{0,63}$";
+static ROLE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(ROLE_REGEX_PATTERN).unwrap());
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RoleError {
+    #[error("Invalid role name")]
+    InvalidRole,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Role(String);
+
+impl Role {
+    pub const USER: &'static str = "user";
+    pub const ADMIN: &'static str = "admin";
+
+    pub fn parse(name: &str) -> Result<Self, RoleError> {
+        if !ROLE_REGEX.is_match(name) {
+            return Err(RoleError::InvalidRole);
+        }
+        Ok(Role(name.to_owned()))
+    }
+
+    pub fn user() -> Self {
+        Role(Self::USER.to_owned())
+    }
+
+    pub fn admin() -> Self {
+        Role(Self::ADMIN.to_owned())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = RoleError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Role::parse(&name)
+    }
+}
+
+impl From<Role> for String {
+    fn from(role: Role) -> Self {
+        role.0
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_role() {
+        assert_eq!(Role::parse("admin"), Ok(Role::admin()));
+        assert_eq!(
+            Role::parse("support_agent").unwrap().as_str(),
+            "support_agent"
+        );
+        assert_eq!(Role::parse("Admin"), Err(RoleError::InvalidRole));
+        assert_eq!(Role::parse(""), Err(RoleError::InvalidRole));
+        assert_eq!(Role::parse("admin; DROP"), Err(RoleError::InvalidRole));
+    }
+
+    #[test]
+    fn test_deserialize_rejects_invalid_role() {
+        assert!(serde_json::from_str::<Role>(r#""user""#).is_ok());
+        assert!(serde_json::from_str::<Role>(r#""NOT A ROLE""#).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+
+// Secret values keyed by the environment variable that would otherwise hold
+// them, e.g. JWT_SECRET
+#[derive(Debug, Clone, Default)]
+pub struct SecretValues(HashMap<String, Secret<String>>);
+
+impl SecretValues {
+    pub fn insert(&mut self, name: &str, value: Secret<String>) {
+        self.0.insert(name.to_owned(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(|value| value.expose_secret().as_str())
+    }
+
+    // Values in `other` replace the ones held for the same name
+    pub fn extend(&mut self, other: SecretValues) {
+        self.0.extend(other.0);
+    }
+
+    // The names whose values differ from `other`, including names only one
+    // side holds
+    pub fn changed(&self, other: &SecretValues) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .0
+            .keys()
+            .chain(other.0.keys())
+            .filter(|name| self.get(name) != other.get(name))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SecretProvider: Send + Sync {
+    // None when the provider does not hold the secret
+    async fn get_secret(&self, name: &str) -> Result<Option<Secret<String>>>;
+
+    // Providers that can fetch several secrets at once should override this
+    async fn get_secrets(&self, names: &[&str]) -> Result<SecretValues> {
+        let mut secrets = SecretValues::default();
+        for name in names {
+            if let Some(value) = self.get_secret(name).await? {
+                secrets.insert(name, value);
+            }
+        }
+        Ok(secrets)
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: SecretProvider + ?Sized> SecretProvider for Box<P> {
+    async fn get_secret(&self, name: &str) -> Result<Option<Secret<String>>> {
+        (**self).get_secret(name).await
+    }
+
+    async fn get_secrets(&self, names: &[&str]) -> Result<SecretValues> {
+        (**self).get_secrets(names).await
+    }
+}
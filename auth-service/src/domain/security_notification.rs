@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecurityNotification {
+    NewSignIn {
+        ip: Option<String>,
+        user_agent: Option<String>,
+        at: DateTime<Utc>,
+    },
+    Elevated {
+        at: DateTime<Utc>,
+    },
+    PasswordChanged {
+        at: DateTime<Utc>,
+    },
+    AccountDeleted {
+        at: DateTime<Utc>,
+    },
+}
+
+impl SecurityNotification {
+    pub fn summary(&self) -> &'static str {
+        match self {
+            SecurityNotification::NewSignIn { .. } => {
+                "We noticed a sign-in to your account from a new device."
+            }
+            SecurityNotification::Elevated { .. } => {
+                "Your password was re-entered to allow sensitive account changes."
+            }
+            SecurityNotification::PasswordChanged { .. } => {
+                "The password for your account was changed."
+            }
+            SecurityNotification::AccountDeleted { .. } => "Your account was deleted.",
+        }
+    }
+
+    pub fn advice(&self) -> &'static str {
+        match self {
+            SecurityNotification::AccountDeleted { .. } => {
+                "If this wasn't you, contact support immediately."
+            }
+            _ => "If this wasn't you, change your password and contact support immediately.",
+        }
+    }
+
+    pub fn occurred_at(&self) -> String {
+        let at = match self {
+            SecurityNotification::NewSignIn { at, .. }
+            | SecurityNotification::Elevated { at }
+            | SecurityNotification::PasswordChanged { at }
+            | SecurityNotification::AccountDeleted { at } => at,
+        };
+        at.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    }
+
+    // The IP address and device of a new sign-in
+    pub fn sign_in_details(&self) -> Option<(&str, &str)> {
+        match self {
+            SecurityNotification::NewSignIn { ip, user_agent, .. } => Some((
+                ip.as_deref().unwrap_or("unknown"),
+                user_agent.as_deref().unwrap_or("unknown"),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_in_details_default_to_unknown() {
+        let notification = SecurityNotification::NewSignIn {
+            ip: Some("203.0.113.7".to_owned()),
+            user_agent: None,
+            at: DateTime::from_timestamp(0, 0).unwrap(),
+        };
+
+        assert_eq!(notification.occurred_at(), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            notification.sign_in_details(),
+            Some(("203.0.113.7", "unknown"))
+        );
+
+        let notification = SecurityNotification::PasswordChanged {
+            at: DateTime::from_timestamp(0, 0).unwrap(),
+        };
+        assert_eq!(notification.sign_in_details(), None);
+    }
+}
@@ -0,0 +1,15 @@
+use color_eyre::eyre::Result;
+
+use super::phone_number::PhoneNumber;
+
+#[async_trait::async_trait]
+pub trait SmsClient: Send + Sync {
+    async fn send_sms(&self, recipient: &PhoneNumber, message: &str) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl<C: SmsClient + ?Sized> SmsClient for Box<C> {
+    async fn send_sms(&self, recipient: &PhoneNumber, message: &str) -> Result<()> {
+        (**self).send_sms(recipient, message).await
+    }
+}
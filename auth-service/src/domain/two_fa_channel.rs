@@ -0,0 +1,62 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TwoFaChannelError {
+    #[error("Invalid two-factor channel")]
+    InvalidChannel,
+}
+
+// Where login codes are sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TwoFaChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFaChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TwoFaChannel::Email => "email",
+            TwoFaChannel::Sms => "sms",
+        }
+    }
+}
+
+impl FromStr for TwoFaChannel {
+    type Err = TwoFaChannelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "email" => Ok(TwoFaChannel::Email),
+            "sms" => Ok(TwoFaChannel::Sms),
+            _ => Err(TwoFaChannelError::InvalidChannel),
+        }
+    }
+}
+
+impl Display for TwoFaChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_round_trips_through_str() {
+        for channel in [TwoFaChannel::Email, TwoFaChannel::Sms] {
+            assert_eq!(channel.as_str().parse::<TwoFaChannel>(), Ok(channel));
+        }
+        assert_eq!(
+            "carrier_pigeon".parse::<TwoFaChannel>(),
+            Err(TwoFaChannelError::InvalidChannel)
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use secrecy::Secret;
 use thiserror::Error;
 
-use super::{email::Email, password::Password};
+use super::{
+    email::Email, password::Password, password_hash::PasswordHash, phone_number::PhoneNumber,
+    role::Role, two_fa_channel::TwoFaChannel, user_status::UserStatus,
+};
 
 #[derive(Debug, Error, PartialEq)]
 pub enum UserError {
@@ -9,6 +12,8 @@ pub enum UserError {
     InvalidEmail,
     #[error("Invalid Password: Must be at least 8 characters")]
     InvalidPassword,
+    #[error("Invalid phone number: Must be in E.164 format, e.g. +4791234567")]
+    InvalidPhoneNumber,
 }
 
 #[derive(Debug, Clone)]
@@ -16,6 +21,10 @@ pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub status: UserStatus,
+    // Only set once the user has verified it
+    pub phone_number: Option<PhoneNumber>,
+    pub two_fa_channel: TwoFaChannel,
 }
 
 impl User {
@@ -24,19 +33,37 @@ impl User {
             email,
             password,
             requires_2fa,
+            status: UserStatus::Active,
+            phone_number: None,
+            two_fa_channel: TwoFaChannel::Email,
         }
     }
 
+    pub fn with_status(mut self, status: UserStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_phone_number(mut self, phone_number: Option<PhoneNumber>) -> Self {
+        self.phone_number = phone_number;
+        self
+    }
+
+    pub fn with_two_fa_channel(mut self, two_fa_channel: TwoFaChannel) -> Self {
+        self.two_fa_channel = two_fa_channel;
+        self
+    }
+
     pub fn parse(
         email: Secret<String>,
         password: Secret<String>,
         requires_2fa: bool,
     ) -> Result<Self, UserError> {
-        Ok(User {
-            email: Email::try_from(email)?,
-            password: Password::try_from(password)?,
+        Ok(User::new(
+            Email::try_from(email)?,
+            Password::try_from(password)?,
             requires_2fa,
-        })
+        ))
     }
 
     pub fn email(&self) -> &Email {
@@ -54,6 +81,18 @@ impl User {
     pub fn requires_2fa(&self) -> bool {
         self.requires_2fa
     }
+
+    pub fn status(&self) -> UserStatus {
+        self.status
+    }
+
+    pub fn phone_number(&self) -> Option<&PhoneNumber> {
+        self.phone_number.as_ref()
+    }
+
+    pub fn two_fa_channel(&self) -> TwoFaChannel {
+        self.two_fa_channel
+    }
 }
 
 impl PartialEq for User {
@@ -62,6 +101,29 @@ impl PartialEq for User {
     }
 }
 
+// A user brought over from another system along with the password hash it
+// had there. Stores add it together with its roles in one go, so a failed
+// import leaves nothing behind.
+#[derive(Debug, Clone)]
+pub struct ImportedUser {
+    pub email: Email,
+    pub password_hash: PasswordHash,
+    pub requires_2fa: bool,
+    pub status: UserStatus,
+    pub password_reset_required: bool,
+    // On top of the user role every user has
+    pub roles: Vec<Role>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserProfile {
+    pub email: Email,
+    pub requires_2fa: bool,
+    pub status: UserStatus,
+    pub password_reset_required: bool,
+    pub roles: Vec<Role>,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ValidatedUser {
     Requires2Fa(Email),
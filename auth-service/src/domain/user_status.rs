@@ -0,0 +1,87 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum UserStatusError {
+    #[error("Invalid user status")]
+    InvalidStatus,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserStatus {
+    #[default]
+    Active,
+    Suspended,
+    PendingVerification,
+    Locked,
+    // Deleted by the user and waiting out the grace period before it is purged
+    PendingDeletion,
+}
+
+impl UserStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserStatus::Active => "active",
+            UserStatus::Suspended => "suspended",
+            UserStatus::PendingVerification => "pending_verification",
+            UserStatus::Locked => "locked",
+            UserStatus::PendingDeletion => "pending_deletion",
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, UserStatus::Active)
+    }
+}
+
+impl FromStr for UserStatus {
+    type Err = UserStatusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(UserStatus::Active),
+            "suspended" => Ok(UserStatus::Suspended),
+            "pending_verification" => Ok(UserStatus::PendingVerification),
+            "locked" => Ok(UserStatus::Locked),
+            "pending_deletion" => Ok(UserStatus::PendingDeletion),
+            _ => Err(UserStatusError::InvalidStatus),
+        }
+    }
+}
+
+impl Display for UserStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_round_trips_through_str() {
+        for status in [
+            UserStatus::Active,
+            UserStatus::Suspended,
+            UserStatus::PendingVerification,
+            UserStatus::Locked,
+            UserStatus::PendingDeletion,
+        ] {
+            assert_eq!(status.as_str().parse::<UserStatus>(), Ok(status));
+        }
+        assert_eq!(
+            "disabled".parse::<UserStatus>(),
+            Err(UserStatusError::InvalidStatus)
+        );
+    }
+
+    #[test]
+    fn test_status_serializes_as_snake_case() {
+        let json = serde_json::to_string(&UserStatus::PendingVerification).unwrap();
+        assert_eq!(json, "\"pending_verification\"");
+    }
+}
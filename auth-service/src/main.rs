@@ -1,42 +1,249 @@
+use std::process::ExitCode;
 use std::sync::Arc;
 
-use auth_service::auth_service::{AuthService, configure_postgresql, configure_redis};
+use auth_service::auth_service::{
+    AuthService, configure_postgresql, configure_redis, configure_sqlite, load_settings,
+};
+use auth_service::auth_service_state::AuthServiceState;
+use auth_service::domain::data_stores::{
+    AuditLog, BannedTokenStore, EmailOutbox, TwoFaCodeStore, UserStore,
+};
+use auth_service::domain::email_client::EmailClient;
+use auth_service::domain::secret_provider::SecretProvider;
+use auth_service::services::account_purge::AccountPurge;
+use auth_service::services::capturing_email_client::CapturingEmailClient;
+use auth_service::services::config_reloader::ConfigReloader;
 use auth_service::services::data_stores::{
-    PostgresUserStore, RedisBannedTokenStore, RedisTwoFaCodeStore,
+    HashMapTwoFaCodeStore, HashSetBannedTokenStore, PostgresAuditLog, PostgresBannedTokenStore,
+    PostgresEmailOutbox, PostgresTwoFaCodeStore, PostgresUserStore, RedisBannedTokenStore,
+    RedisTwoFaCodeStore, SqliteAuditLog, SqliteUserStore,
 };
+use auth_service::services::email_outbox_worker::EmailOutboxWorker;
+use auth_service::services::outbox_email_client::OutboxEmailClient;
 use auth_service::services::postmark_email_client::configure_postmark_email_client;
-use auth_service::settings::AuthServiceSetting;
+use auth_service::services::secret_refresher::SecretRefresher;
+use auth_service::services::smtp_email_client::configure_smtp_email_client;
+use auth_service::services::token_store_cleanup::TokenStoreCleanup;
+use auth_service::services::webhook_sms_client::configure_webhook_sms_client;
+use auth_service::settings::{
+    AuthServiceSetting, CONFIG_FILE_PATH, DatabaseBackend, EmailProvider, Settings,
+    TokenStoreBackend,
+};
 use auth_service::utils::constants::prod;
 use auth_service::utils::tracing::init_tracing;
+use clap::{Parser, Subcommand};
+use sqlx::PgPool;
 use tokio::net::TcpListener;
-use tokio::sync::Mutex;
+
+#[derive(Parser)]
+#[command(about = "Authentication service")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Run the service, the default when no command is given")]
+    Serve,
+    #[command(about = "Load and validate the config, listing every problem found")]
+    CheckConfig,
+    #[command(about = "Print the config as loaded, with secrets redacted")]
+    PrintConfig,
+}
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     color_eyre::install().expect("Failed to install color_eyre");
+    let cli = Cli::parse();
+
+    // Loading the config up front reports every problem with it, instead of
+    // panicking on the first one when it is first used
+    let (config, secret_provider) = match load_settings().await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{e:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config, secret_provider).await,
+        Command::CheckConfig => println!("Config is valid"),
+        Command::PrintConfig => println!(
+            "{}",
+            serde_json::to_string_pretty(&**config.load()).expect("Failed to serialize config")
+        ),
+    }
+    ExitCode::SUCCESS
+}
+
+async fn serve(settings: Settings, secret_provider: Box<dyn SecretProvider>) {
     init_tracing().expect("Failed to initialize tracing");
+    AuthServiceSetting::init(settings)
+        .expect("The config must not be used before the settings are initialized");
+
+    let secret_refresher = SecretRefresher::new(
+        secret_provider,
+        AuthServiceSetting::handle(),
+        AuthServiceSetting::load().secrets.refresh_interval_in_secs,
+    );
+    tokio::spawn(secret_refresher.run());
+
     let settings = AuthServiceSetting::load();
 
-    let pg_pool = configure_postgresql().await;
-    let user_store = PostgresUserStore::new(pg_pool);
+    let config_reloader = ConfigReloader::new(
+        AuthServiceSetting::handle(),
+        CONFIG_FILE_PATH,
+        prod::CONFIG_POLL_INTERVAL,
+    );
+    tokio::spawn(config_reloader.run());
+
+    let dev_mailbox = settings.dev.mailbox.then(CapturingEmailClient::new);
+    let delivery_client: Box<dyn EmailClient> = match &dev_mailbox {
+        Some(mailbox) => {
+            tracing::warn!("dev.mailbox is enabled, emails are captured instead of sent");
+            Box::new(mailbox.clone())
+        }
+        None => configure_email_client(settings.email_client.provider),
+    };
 
-    let redis_connection = Arc::new(Mutex::new(configure_redis()));
-    let two_fa_code_store = RedisTwoFaCodeStore::new(redis_connection.clone());
-    let banned_token_store = RedisBannedTokenStore::new(redis_connection);
+    match settings.database.backend {
+        DatabaseBackend::Postgres => {
+            let pg_pool = configure_postgresql().await;
+            let email_outbox = Arc::new(PostgresEmailOutbox::new(pg_pool.clone()));
 
-    let email_client = configure_postmark_email_client();
+            // Requests only queue emails, the worker delivers them through the provider
+            let outbox_worker = EmailOutboxWorker::new(
+                email_outbox.clone(),
+                delivery_client,
+                settings.email_outbox.clone(),
+            );
+            tokio::spawn(outbox_worker.run());
+
+            let user_store = PostgresUserStore::new(pg_pool.clone());
+            user_store.spawn_sweeper();
+
+            run(
+                user_store,
+                OutboxEmailClient::new(email_outbox.clone()),
+                Some(email_outbox),
+                PostgresAuditLog::new(pg_pool.clone()),
+                Some(pg_pool),
+                dev_mailbox,
+            )
+            .await;
+        }
+        DatabaseBackend::Sqlite => {
+            let sqlite_pool = configure_sqlite().await;
+
+            // The email outbox lives in Postgres, so emails are sent directly
+            run(
+                SqliteUserStore::new(sqlite_pool.clone()),
+                delivery_client,
+                None,
+                SqliteAuditLog::new(sqlite_pool),
+                None,
+                dev_mailbox,
+            )
+            .await;
+        }
+    }
+}
+
+async fn run<U, E, A>(
+    user_store: U,
+    email_client: E,
+    email_outbox: Option<Arc<dyn EmailOutbox>>,
+    audit_log: A,
+    pg_pool: Option<PgPool>,
+    dev_mailbox: Option<CapturingEmailClient>,
+) where
+    U: UserStore + 'static,
+    E: EmailClient + 'static,
+    A: AuditLog + 'static,
+{
+    let (two_fa_code_store, banned_token_store) = configure_token_stores(pg_pool).await;
 
     let listener = TcpListener::bind(prod::APP_ADDRESS)
         .await
         .expect("Failed to bind to ip address");
 
-    AuthService::new(
-        user_store,
-        banned_token_store,
-        two_fa_code_store,
-        email_client,
-    )
-    .as_standalone(listener, Some(settings.auth.allowed_origins.clone()))
-    .await
-    .expect("Failed to start application");
+    let mut app_state = AuthServiceState::new(
+        Arc::new(user_store),
+        Arc::new(banned_token_store),
+        Arc::new(two_fa_code_store),
+        Arc::new(email_client),
+        Arc::new(audit_log),
+    );
+    if let Some(email_outbox) = email_outbox {
+        app_state = app_state.with_email_outbox(email_outbox);
+    }
+    match configure_webhook_sms_client() {
+        Some(sms_client) => app_state = app_state.with_sms_client(Arc::new(sms_client)),
+        None => tracing::info!("sms_client is not configured, SMS two-factor is unavailable"),
+    }
+
+    let account_purge = AccountPurge::new(
+        app_state.clone(),
+        AuthServiceSetting::load()
+            .account_deletion
+            .purge_interval_in_secs,
+    );
+    tokio::spawn(account_purge.run());
+
+    let mut auth_service = AuthService::with_state(app_state);
+    if let Some(mailbox) = dev_mailbox {
+        auth_service = auth_service.with_dev_mailbox(mailbox);
+    }
+
+    auth_service
+        .as_standalone(listener, Some(AuthServiceSetting::handle()))
+        .await
+        .expect("Failed to start application");
+}
+
+async fn configure_token_stores(
+    pg_pool: Option<PgPool>,
+) -> (Box<dyn TwoFaCodeStore>, Box<dyn BannedTokenStore>) {
+    let handle = AuthServiceSetting::handle();
+    let settings = handle.load();
+
+    match settings.token_store.backend {
+        TokenStoreBackend::Redis => {
+            let redis_connection = configure_redis().await;
+            (
+                Box::new(RedisTwoFaCodeStore::new(redis_connection.clone())),
+                Box::new(RedisBannedTokenStore::new(redis_connection, handle.clone())),
+            )
+        }
+        TokenStoreBackend::Postgres => {
+            let pg_pool =
+                pg_pool.expect("token_store.backend postgres requires database.backend postgres");
+            let cleanup = TokenStoreCleanup::new(
+                pg_pool.clone(),
+                handle.clone(),
+                settings.token_store.cleanup_interval_in_secs,
+            );
+            tokio::spawn(cleanup.run());
+            (
+                Box::new(PostgresTwoFaCodeStore::new(pg_pool.clone())),
+                Box::new(PostgresBannedTokenStore::new(pg_pool, handle.clone())),
+            )
+        }
+        TokenStoreBackend::Memory => {
+            let two_fa_code_store = HashMapTwoFaCodeStore::new();
+            let banned_token_store = HashSetBannedTokenStore::new(handle.clone());
+            two_fa_code_store.spawn_sweeper(settings.token_store.cleanup_interval_in_secs);
+            banned_token_store.spawn_sweeper(settings.token_store.cleanup_interval_in_secs);
+            (Box::new(two_fa_code_store), Box::new(banned_token_store))
+        }
+    }
+}
+
+fn configure_email_client(provider: EmailProvider) -> Box<dyn EmailClient> {
+    match provider {
+        EmailProvider::Postmark => Box::new(configure_postmark_email_client()),
+        EmailProvider::Smtp => Box::new(configure_smtp_email_client()),
+    }
 }
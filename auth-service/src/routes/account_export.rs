@@ -0,0 +1,230 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{
+        auth_api_error::AuthApiError,
+        data_stores::{AuditEvent, AuditEventType},
+        device_fingerprint::KnownDevice,
+        email::Email,
+        email_client::EmailAttachment,
+        role::Role,
+        two_fa_channel::TwoFaChannel,
+        user_status::UserStatus,
+    },
+    routes::SecurityEventResponse,
+    services::email_templates::{AccountExportEmail, EmailTemplate},
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
+    },
+};
+
+const EXPORT_FILENAME: &str = "account-export.json";
+// Well past what the security events page shows, while keeping the export of
+// a long-lived account a reasonable size
+const MAX_EXPORTED_EVENTS: u32 = 1000;
+
+// Everything we hold on a user. Sessions are stateless tokens, so the devices
+// they were started from are what is kept of them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountExport {
+    #[serde(rename = "exportedAt")]
+    pub exported_at: DateTime<Utc>,
+    pub profile: ExportedProfile,
+    #[serde(rename = "twoFactor")]
+    pub two_factor: ExportedTwoFactor,
+    pub devices: Vec<ExportedDevice>,
+    #[serde(rename = "securityEvents")]
+    pub security_events: Vec<SecurityEventResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedProfile {
+    pub email: String,
+    pub status: UserStatus,
+    pub roles: Vec<Role>,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+    #[serde(rename = "securityNotifications")]
+    pub security_notifications: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedTwoFactor {
+    pub required: bool,
+    pub channel: TwoFaChannel,
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedDevice {
+    pub fingerprint: String,
+    #[serde(rename = "firstSeenAt")]
+    pub first_seen_at: DateTime<Utc>,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl From<KnownDevice> for ExportedDevice {
+    fn from(device: KnownDevice) -> Self {
+        ExportedDevice {
+            fingerprint: device.fingerprint.to_string(),
+            first_seen_at: device.first_seen_at,
+            last_seen_at: device.last_seen_at,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Export account", skip_all, err(Debug))]
+pub async fn export_account(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::AccountExport, &metadata).details("download");
+
+    let result = async {
+        let email = elevated_user(&app_state, &jar).await?;
+        event.actor = Some(email.as_ref().expose_secret().to_owned());
+
+        let export = build_account_export(&app_state, &email).await?;
+
+        Ok((
+            [(
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{EXPORT_FILENAME}\""),
+            )],
+            Json(export),
+        ))
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+// Assembles the export in the background and emails it as an attachment, for
+// accounts with too much history to wait for
+#[tracing::instrument(name = "Request account export", skip_all, err(Debug))]
+pub async fn request_account_export(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event =
+        request_event(AuditEventType::AccountExport, &metadata).details("email requested");
+
+    let result = async {
+        let email = elevated_user(&app_state, &jar).await?;
+        event.actor = Some(email.as_ref().expose_secret().to_owned());
+
+        tokio::spawn(email_account_export(app_state.clone(), email));
+
+        Ok(StatusCode::ACCEPTED)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+pub async fn build_account_export(
+    app_state: &AuthServiceState,
+    email: &Email,
+) -> Result<AccountExport, AuthApiError> {
+    let user = app_state.user_store.get_user(email).await?;
+    let profile = app_state.user_store.get_user_profile(email).await?;
+    let security_notifications = app_state
+        .user_store
+        .security_notifications_enabled(email)
+        .await?;
+    let devices = app_state.user_store.known_devices(email).await?;
+    let security_events = app_state
+        .audit_log
+        .recent_events(email.as_ref().expose_secret(), MAX_EXPORTED_EVENTS)
+        .await?;
+
+    Ok(AccountExport {
+        exported_at: app_state.clock.now(),
+        profile: ExportedProfile {
+            email: email.as_ref().expose_secret().to_owned(),
+            status: profile.status,
+            roles: profile.roles,
+            password_reset_required: profile.password_reset_required,
+            security_notifications,
+        },
+        two_factor: ExportedTwoFactor {
+            required: user.requires_2fa(),
+            channel: user.two_fa_channel(),
+            phone_number: user
+                .phone_number()
+                .map(|phone_number| phone_number.as_ref().expose_secret().to_owned()),
+        },
+        devices: devices.into_iter().map(Into::into).collect(),
+        security_events: security_events
+            .into_iter()
+            .map(|record| record.seen_by(email.as_ref().expose_secret()).into())
+            .collect(),
+    })
+}
+
+async fn email_account_export(app_state: AuthServiceState, email: Email) {
+    let result = async {
+        let export = build_account_export(&app_state, &email).await?;
+        let locale = app_state.settings.load().email_client.locale;
+
+        let message = AccountExportEmail {
+            exported_at: export.exported_at,
+        }
+        .render(locale)?
+        .with_attachment(EmailAttachment {
+            filename: EXPORT_FILENAME.to_owned(),
+            content_type: "application/json".to_owned(),
+            content: serde_json::to_vec_pretty(&export).map_err(|e| eyre!(e))?,
+        });
+
+        app_state.email_client.send_email(&email, &message).await?;
+        Ok::<_, AuthApiError>(())
+    }
+    .await;
+
+    if let Err(e) = &result {
+        tracing::error!(error = ?e, "Failed to email account export");
+    }
+
+    let event = AuditEvent::new(AuditEventType::AccountExport)
+        .actor(email.as_ref().expose_secret())
+        .details("emailed");
+    record_outcome(&*app_state.audit_log, event, &result).await;
+}
+
+async fn elevated_user(
+    app_state: &AuthServiceState,
+    jar: &CookieJar,
+) -> Result<Email, AuthApiError> {
+    let config = app_state.settings.load();
+    let elevated_token = auth::extract_token(jar, &config.auth.elevated_jwt.cookie_name)?;
+
+    let claims = auth::validate_elevated_auth_token(
+        elevated_token,
+        &*app_state.banned_token_store,
+        &config,
+        &*app_state.clock,
+    )
+    .await?;
+
+    Ok(Email::try_from(claims.sub)?)
+}
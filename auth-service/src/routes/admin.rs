@@ -0,0 +1,421 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{
+        auth_api_error::AuthApiError,
+        data_stores::{AuditEvent, AuditEventType, ERASED_SUBJECT, UserQuery, UserStoreError},
+        email::Email,
+        role::Role,
+        user::UserProfile,
+        user_status::UserStatus,
+    },
+    services::{
+        account_purge::purge_account,
+        user_admin::{UserAdmin, UserImport, UserRecord},
+    },
+    utils::{
+        audit::{record_outcome, request_event},
+        extractors::{Admin, RequestMetadata, RequireRole, RoleRejection},
+    },
+};
+
+// Taken as is so that refused attempts are audited like any other failure
+type AdminAttempt = Result<RequireRole<Admin>, RoleRejection>;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct UserSearchParams {
+    pub email: Option<String>,
+    pub page: Option<u32>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u32>,
+}
+
+impl From<UserSearchParams> for UserQuery {
+    fn from(params: UserSearchParams) -> Self {
+        UserQuery {
+            email_contains: params.email.filter(|email| !email.is_empty()),
+            page: params.page.unwrap_or(1).max(1),
+            per_page: params
+                .per_page
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUserResponse {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub status: UserStatus,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+    pub roles: Vec<Role>,
+}
+
+impl From<UserProfile> for AdminUserResponse {
+    fn from(profile: UserProfile) -> Self {
+        AdminUserResponse {
+            email: profile.email.as_ref().expose_secret().to_owned(),
+            requires_2fa: profile.requires_2fa,
+            status: profile.status,
+            password_reset_required: profile.password_reset_required,
+            roles: profile.roles,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUserListResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub page: u32,
+    #[serde(rename = "perPage")]
+    pub per_page: u32,
+    pub total: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRequires2FaRequest {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminImportedUser {
+    pub email: String,
+    // Only set for users imported with neither a password nor a hash
+    #[serde(rename = "temporaryPassword", skip_serializing_if = "Option::is_none")]
+    pub temporary_password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminFailedImport {
+    pub email: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminImportResponse {
+    pub created: Vec<AdminImportedUser>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<AdminFailedImport>,
+}
+
+#[tracing::instrument(name = "Admin search users", skip_all, err(Debug))]
+pub async fn admin_search_users(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Query(params): Query<UserSearchParams>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let query = UserQuery::from(params);
+    let mut event = admin_event(AuditEventType::AdminSearchUsers, &admin, &metadata);
+    if let Some(email_contains) = &query.email_contains {
+        event = event.details(format!("email contains '{}'", email_contains));
+    }
+
+    let result = async {
+        admin?;
+        let page = app_state.user_store.search_users(&query).await?;
+
+        Ok(Json(AdminUserListResponse {
+            users: page.users.into_iter().map(Into::into).collect(),
+            page: query.page,
+            per_page: query.per_page,
+            total: page.total,
+        }))
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin get user", skip_all, err(Debug))]
+pub async fn admin_get_user(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event = admin_event(AuditEventType::AdminViewUser, &admin, &metadata).target(&email);
+
+    let result = async {
+        admin?;
+        let email = Email::try_from(Secret::from(email))?;
+        let profile = app_state.user_store.get_user_profile(&email).await?;
+
+        Ok(Json(AdminUserResponse::from(profile)))
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin disable user", skip_all, err(Debug))]
+pub async fn admin_disable_user(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event = admin_event(AuditEventType::AdminDisableUser, &admin, &metadata).target(&email);
+
+    let result = async {
+        admin?;
+        let email = Email::try_from(Secret::from(email))?;
+
+        app_state
+            .user_store
+            .set_status(&email, UserStatus::Suspended)
+            .await
+            .map_err(status_change_error)?;
+
+        // A suspended user must not keep using the sessions they already have
+        app_state
+            .banned_token_store
+            .revoke_all_tokens(&email)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin enable user", skip_all, err(Debug))]
+pub async fn admin_enable_user(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event = admin_event(AuditEventType::AdminEnableUser, &admin, &metadata).target(&email);
+
+    let result = async {
+        admin?;
+        let email = Email::try_from(Secret::from(email))?;
+
+        app_state
+            .user_store
+            .set_status(&email, UserStatus::Active)
+            .await
+            .map_err(status_change_error)?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin force password reset", skip_all, err(Debug))]
+pub async fn admin_force_password_reset(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event =
+        admin_event(AuditEventType::AdminForcePasswordReset, &admin, &metadata).target(&email);
+
+    let result = async {
+        admin?;
+        let email = Email::try_from(Secret::from(email))?;
+
+        app_state
+            .user_store
+            .set_password_reset_required(&email, true)
+            .await?;
+
+        // Force the user through login again so they are told to reset
+        app_state
+            .banned_token_store
+            .revoke_all_tokens(&email)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin set requires 2FA", skip_all, err(Debug))]
+pub async fn admin_set_requires_2fa(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Path(email): Path<String>,
+    Json(request): Json<SetRequires2FaRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event = admin_event(AuditEventType::AdminSetRequires2Fa, &admin, &metadata)
+        .target(&email)
+        .details(format!("requires_2fa={}", request.requires_2fa));
+
+    let result = async {
+        admin?;
+        let email = Email::try_from(Secret::from(email))?;
+
+        app_state
+            .user_store
+            .set_requires_2fa(&email, request.requires_2fa)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin revoke sessions", skip_all, err(Debug))]
+pub async fn admin_revoke_sessions(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event = admin_event(AuditEventType::AdminRevokeSessions, &admin, &metadata).target(&email);
+
+    let result = async {
+        admin?;
+        let email = Email::try_from(Secret::from(email))?;
+
+        // Make sure the user exists so typos surface as 404
+        app_state.user_store.get_user(&email).await?;
+
+        app_state
+            .banned_token_store
+            .revoke_all_tokens(&email)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin delete user", skip_all, err(Debug))]
+pub async fn admin_delete_user(
+    State(app_state): State<AuthServiceState>,
+    admin: AdminAttempt,
+    metadata: RequestMetadata,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = admin_event(AuditEventType::AdminDeleteUser, &admin, &metadata).target(&email);
+
+    let result = async {
+        admin?;
+        let email = Email::try_from(Secret::from(email))?;
+
+        // Deletes right away, also for accounts waiting out their grace period
+        purge_account(&app_state, &email).await?;
+
+        // The user has just been erased from the audit log, so the event
+        // doesn't name them either
+        event.target = Some(ERASED_SUBJECT.to_owned());
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Admin import users", skip_all, err(Debug))]
+pub async fn admin_import_users(
+    State(app_state): State<AuthServiceState>,
+    admin: RequireRole<Admin>,
+    Json(records): Json<Vec<UserRecord>>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let users = UserImport::try_from(records)
+        .map_err(|e| AuthApiError::InvalidInput(format!("{e:#}").into()))?;
+
+    let user_admin = UserAdmin::new(
+        app_state.user_store.clone(),
+        app_state.banned_token_store.clone(),
+        app_state.audit_log.clone(),
+        actor(&admin),
+    );
+    let report = user_admin.import_users(users).await;
+
+    Ok(Json(AdminImportResponse {
+        created: report
+            .created
+            .into_iter()
+            .map(|user| AdminImportedUser {
+                email: user.email,
+                temporary_password: user
+                    .temporary_password
+                    .map(|password| password.as_ref().expose_secret().to_owned()),
+            })
+            .collect(),
+        skipped: report.skipped,
+        failed: report
+            .failed
+            .into_iter()
+            .map(|user| AdminFailedImport {
+                email: user.email,
+                error: match user.error {
+                    // Logged, the details are not for the response
+                    UserStoreError::UnexpectedError(e) => {
+                        tracing::error!(error = ?e, "Failed to import user");
+                        "Unexpected error".to_owned()
+                    }
+                    error => error.to_string(),
+                },
+            })
+            .collect(),
+    }))
+}
+
+fn actor(admin: &RequireRole<Admin>) -> String {
+    admin.claims.sub.expose_secret().to_owned()
+}
+
+fn admin_event(
+    event_type: AuditEventType,
+    admin: &AdminAttempt,
+    metadata: &RequestMetadata,
+) -> AuditEvent {
+    let event = request_event(event_type, metadata);
+    match admin {
+        Ok(admin) => event.actor(actor(admin)),
+        Err(RoleRejection {
+            subject: Some(subject),
+            ..
+        }) => event.actor(subject),
+        Err(_) => event,
+    }
+}
+
+// A scheduled deletion is only ended by the user restoring their account or by
+// deleting it, so the status of such an account is left alone
+fn status_change_error(error: UserStoreError) -> AuthApiError {
+    match error {
+        UserStoreError::UserPendingDeletion => AuthApiError::AccountDeletionScheduled,
+        error => error.into(),
+    }
+}
@@ -1,15 +1,21 @@
 use axum::response::IntoResponse;
 use axum::{Json, extract::State, http::StatusCode};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 
-use crate::domain::data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore};
+use crate::domain::data_stores::AuditEventType;
 use crate::domain::email::Email;
+use crate::domain::security_notification::SecurityNotification;
 use crate::{
     auth_service_state::AuthServiceState,
-    domain::{auth_api_error::AuthApiError, email_client::EmailClient, password::Password},
-    utils::{auth, constants::JWT_ELEVATED_COOKIE_NAME},
+    domain::{auth_api_error::AuthApiError, password::Password},
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
+        notifications::notify_security_event,
+    },
 };
 
 #[derive(Deserialize)]
@@ -18,31 +24,43 @@ pub struct ChangePasswordRequest {
 }
 
 #[tracing::instrument(name = "Change Password", skip_all, err(Debug))]
-pub async fn change_password<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn change_password(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
     Json(request): Json<ChangePasswordRequest>,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let token = auth::extract_token(&jar, *JWT_ELEVATED_COOKIE_NAME)?;
-    let claim =
-        auth::validate_elevated_auth_token(token, &*app_state.banned_token_store.read().await)
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::PasswordChange, &metadata);
+
+    let result = async {
+        let config = app_state.settings.load();
+        let token = auth::extract_token(&jar, &config.auth.elevated_jwt.cookie_name)?;
+        let claim = auth::validate_elevated_auth_token(
+            token,
+            &*app_state.banned_token_store,
+            &config,
+            &*app_state.clock,
+        )
+        .await?;
+        event.actor = Some(claim.sub.expose_secret().to_owned());
+
+        let email = Email::try_from(claim.sub)?;
+        let new_password = Password::try_from(request.new_password)?;
+
+        app_state
+            .user_store
+            .set_new_password(&email, new_password)
             .await?;
 
-    let email = Email::try_from(claim.sub)?;
-    let new_password = Password::try_from(request.new_password)?;
+        let notification = SecurityNotification::PasswordChanged {
+            at: app_state.clock.now(),
+        };
+        notify_security_event(&app_state, &email, notification).await;
 
-    app_state
-        .user_store
-        .write()
-        .await
-        .set_new_password(&email, new_password)
-        .await?;
+        Ok((jar, StatusCode::OK))
+    }
+    .await;
 
-    Ok((jar, StatusCode::OK))
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
 }
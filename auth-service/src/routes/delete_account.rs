@@ -1,47 +1,103 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
 
 use crate::{
     auth_service_state::AuthServiceState,
     domain::{
         auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
+        data_stores::{AuditEventType, TwoFaCodeStoreError},
         email::Email,
-        email_client::EmailClient,
+        restore_token::RestoreToken,
+    },
+    services::email_templates::{AccountDeletionScheduledEmail, EmailTemplate},
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
     },
-    settings::AuthServiceSetting,
-    utils::auth,
 };
 
+// Closes the account and schedules it for deletion once the grace period is
+// over. Until then the link emailed to the user restores it.
 #[tracing::instrument(name = "Delete Account", skip_all, err(Debug))]
-pub async fn delete_account<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn delete_account(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let config = AuthServiceSetting::load();
-    let jwt_elevated_cookie_name = &config.auth.elevated_jwt.cookie_name;
-    let elevated_token = auth::extract_token(&jar, jwt_elevated_cookie_name)?;
-
-    let claims = auth::validate_elevated_auth_token(
-        elevated_token,
-        &*app_state.banned_token_store.read().await,
-    )
-    .await?;
-
-    let user_email = Email::try_from(claims.sub)?;
-
-    app_state
-        .user_store
-        .write()
-        .await
-        .delete_user(&user_email)
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::AccountDeletion, &metadata);
+
+    let result = async {
+        let config = app_state.settings.load();
+        let jwt_elevated_cookie_name = &config.auth.elevated_jwt.cookie_name;
+        let elevated_token = auth::extract_token(&jar, jwt_elevated_cookie_name)?;
+
+        let claims = auth::validate_elevated_auth_token(
+            elevated_token,
+            &*app_state.banned_token_store,
+            &config,
+            &*app_state.clock,
+        )
         .await?;
+        event.actor = Some(claims.sub.expose_secret().to_owned());
+
+        let user_email = Email::try_from(claims.sub)?;
+
+        let grace_period = Duration::days(config.account_deletion.grace_period_in_days.into());
+        let due_at = app_state.clock.now() + grace_period;
+        let restore_token = RestoreToken::generate();
+        app_state
+            .user_store
+            .schedule_deletion(&user_email, due_at, &restore_token)
+            .await?;
+
+        // The account can't be used while it waits to be deleted
+        app_state
+            .banned_token_store
+            .revoke_all_tokens(&user_email)
+            .await?;
+        match app_state.two_fa_code_store.delete(&user_email).await {
+            Ok(()) | Err(TwoFaCodeStoreError::UserNotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        // Sent whatever the notification preference, the link is the only way
+        // back into the account
+        if let Err(e) = send_restore_link(&app_state, &user_email, &restore_token, due_at).await {
+            tracing::error!(error = ?e, "Failed to send account restore link");
+        }
+
+        Ok((jar, StatusCode::NO_CONTENT))
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+async fn send_restore_link(
+    app_state: &AuthServiceState,
+    email: &Email,
+    restore_token: &RestoreToken,
+    due_at: DateTime<Utc>,
+) -> Result<(), AuthApiError> {
+    let config = app_state.settings.load();
+
+    let mut link =
+        reqwest::Url::parse(&config.account_deletion.restore_url).map_err(|e| eyre!(e))?;
+    link.query_pairs_mut()
+        .append_pair("token", restore_token.as_ref().expose_secret());
+
+    let message = AccountDeletionScheduledEmail {
+        link: link.as_str(),
+        due_at,
+    }
+    .render(config.email_client.locale)?
+    .expiring_at(due_at);
 
-    Ok((jar, StatusCode::NO_CONTENT))
+    app_state.email_client.send_email(email, &message).await?;
+    Ok(())
 }
@@ -0,0 +1,102 @@
+use askama::Template;
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::auth_api_error::AuthApiError,
+    services::capturing_email_client::{CapturedEmail, CapturingEmailClient},
+};
+
+#[derive(Template)]
+#[template(path = "dev/mailbox.html")]
+struct MailboxPage {
+    emails: Vec<CapturedEmail>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MailboxMessageResponse {
+    pub recipient: String,
+    pub subject: String,
+    #[serde(rename = "htmlBody")]
+    pub html_body: String,
+    #[serde(rename = "textBody")]
+    pub text_body: String,
+    pub attachments: Vec<MailboxAttachmentResponse>,
+    #[serde(rename = "sentAt")]
+    pub sent_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MailboxAttachmentResponse {
+    pub filename: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub size: usize,
+}
+
+impl From<CapturedEmail> for MailboxMessageResponse {
+    fn from(email: CapturedEmail) -> Self {
+        MailboxMessageResponse {
+            recipient: email.recipient,
+            subject: email.message.subject,
+            html_body: email.message.html_body,
+            text_body: email.message.text_body,
+            attachments: email
+                .message
+                .attachments
+                .into_iter()
+                .map(|attachment| MailboxAttachmentResponse {
+                    filename: attachment.filename,
+                    content_type: attachment.content_type,
+                    size: attachment.content.len(),
+                })
+                .collect(),
+            sent_at: email.sent_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MailboxResponse {
+    pub messages: Vec<MailboxMessageResponse>,
+}
+
+// Only mounted when dev.mailbox is enabled, see AuthService::with_dev_mailbox
+#[tracing::instrument(name = "Dev mailbox", skip_all, err(Debug))]
+pub async fn dev_mailbox(
+    State(mailbox): State<CapturingEmailClient>,
+    headers: HeaderMap,
+) -> Result<Response, AuthApiError> {
+    let emails = mailbox.messages();
+
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"));
+
+    if wants_json {
+        let response = MailboxResponse {
+            messages: emails.into_iter().map(Into::into).collect(),
+        };
+        return Ok(Json(response).into_response());
+    }
+
+    let page = MailboxPage { emails }
+        .render()
+        .map_err(|e| AuthApiError::UnexpectedError(eyre!(e)))?;
+
+    Ok(Html(page).into_response())
+}
+
+#[tracing::instrument(name = "Clear dev mailbox", skip_all)]
+pub async fn clear_dev_mailbox(State(mailbox): State<CapturingEmailClient>) -> StatusCode {
+    mailbox.clear();
+    StatusCode::NO_CONTENT
+}
@@ -1,20 +1,21 @@
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 
 use crate::{
     auth_service_state::AuthServiceState,
     domain::{
-        auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
-        email::Email,
-        email_client::EmailClient,
-        password::Password,
-        user::UserError,
+        auth_api_error::AuthApiError, data_stores::AuditEventType, email::Email,
+        security_notification::SecurityNotification, user::UserError,
+    },
+    routes::login::authenticate,
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
+        notifications::notify_security_event,
     },
-    settings::AuthServiceSetting,
-    utils::auth,
 };
 
 #[derive(Debug, Deserialize)]
@@ -25,14 +26,15 @@ pub struct ElevateRequest {
 
 pub struct ValidElevateRequest {
     email: Email,
-    password: Password,
+    // Checked against the password policy by `authenticate`
+    password: Secret<String>,
 }
 
 impl ValidElevateRequest {
     pub fn parse(elevate_request: ElevateRequest) -> Result<Self, UserError> {
         Ok(ValidElevateRequest {
             email: Email::try_from(elevate_request.email)?,
-            password: Password::try_from(elevate_request.password)?,
+            password: elevate_request.password,
         })
     }
 
@@ -40,40 +42,67 @@ impl ValidElevateRequest {
         &self.email
     }
 
-    pub fn password(&self) -> &Password {
+    pub fn password(&self) -> &Secret<String> {
         &self.password
     }
 }
 
 #[tracing::instrument(name = "Elevate auth", skip_all, err(Debug))]
-pub async fn elevate<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn elevate(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
     Json(elevate_request): Json<ElevateRequest>,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let config = AuthServiceSetting::load();
-    let cookie = jar
-        .get(&config.auth.jwt.cookie_name)
-        .ok_or(AuthApiError::MissingToken)?;
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::Elevate, &metadata);
+
+    let result = async {
+        let config = app_state.settings.load();
+        let cookie = jar
+            .get(&config.auth.jwt.cookie_name)
+            .ok_or(AuthApiError::MissingToken)?;
 
-    auth::validate_auth_token(cookie.value(), &*app_state.banned_token_store.read().await).await?;
+        let claims = auth::validate_auth_token(
+            cookie.value(),
+            &*app_state.banned_token_store,
+            &config,
+            &*app_state.clock,
+        )
+        .await?;
+        event.actor = Some(claims.sub.expose_secret().to_owned());
 
-    let elevate_request = ValidElevateRequest::parse(elevate_request)?;
+        let elevate_request = ValidElevateRequest::parse(elevate_request)?;
 
-    app_state
-        .user_store
-        .read()
-        .await
-        .authenticate_user(elevate_request.email(), elevate_request.password())
+        let user_store = &app_state.user_store;
+        authenticate(
+            &app_state,
+            elevate_request.email(),
+            elevate_request.password().clone(),
+        )
         .await?;
+        let roles = user_store.get_roles(elevate_request.email()).await?;
+        let generation = app_state
+            .banned_token_store
+            .token_generation(elevate_request.email())
+            .await?;
 
-    let elevated_cookie = auth::generate_elevated_auth_cookie(elevate_request.email(), &config)?;
+        let elevated_cookie = auth::generate_elevated_auth_cookie(
+            elevate_request.email(),
+            &roles,
+            generation,
+            &config,
+            &*app_state.clock,
+        )?;
+
+        let notification = SecurityNotification::Elevated {
+            at: app_state.clock.now(),
+        };
+        notify_security_event(&app_state, elevate_request.email(), notification).await;
+
+        Ok((jar.add(elevated_cookie), StatusCode::OK))
+    }
+    .await;
 
-    Ok((jar.add(elevated_cookie), StatusCode::OK))
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
 }
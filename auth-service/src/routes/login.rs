@@ -1,24 +1,34 @@
-use std::sync::Arc;
-
+use askama::Template;
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     auth_service_state::AuthServiceState,
     domain::{
         auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
+        clock::Clock,
+        data_stores::{AuditEventType, TWO_FA_CODE_TTL, UserStoreError},
         email::Email,
-        email_client::EmailClient,
         password::Password,
         two_fa_attempt_id::TwoFaAttemptId,
+        two_fa_channel::TwoFaChannel,
         two_fa_code::TwoFaCode,
-        user::{UserError, ValidatedUser},
+        user::{User, UserError, UserProfile, ValidatedUser},
+    },
+    services::{
+        email_templates::{EmailTemplate, TwoFaCodeEmail},
+        sms_templates::TwoFaCodeSms,
+    },
+    settings::Config,
+    utils::{
+        audit::{record_outcome, request_event},
+        auth::generate_auth_cookie,
+        extractors::RequestMetadata,
+        notifications::notify_if_new_device,
     },
-    settings::{AuthServiceSetting, Config},
-    utils::auth::generate_auth_cookie,
 };
 
 #[derive(Debug, Deserialize)]
@@ -30,14 +40,15 @@ pub struct LoginRequest {
 #[derive(Debug)]
 pub struct ValidLoginRequest {
     email: Email,
-    password: Password,
+    // Checked against the password policy by `authenticate`
+    password: Secret<String>,
 }
 
 impl ValidLoginRequest {
     pub fn parse(login_request: LoginRequest) -> Result<Self, UserError> {
         Ok(Self {
             email: Email::try_from(login_request.email)?,
-            password: Password::try_from(login_request.password)?,
+            password: login_request.password,
         })
     }
 
@@ -45,7 +56,7 @@ impl ValidLoginRequest {
         &self.email
     }
 
-    pub fn password(&self) -> &Password {
+    pub fn password(&self) -> &Secret<String> {
         &self.password
     }
 
@@ -59,6 +70,20 @@ impl ValidLoginRequest {
 pub enum LoginResponse {
     RegularAuth,
     TwoFactorAuth(TwoFactorAuthResponse),
+    PasswordResetRequired(PasswordResetRequiredResponse),
+}
+
+impl LoginResponse {
+    pub fn authenticated(profile: &UserProfile) -> Self {
+        if profile.password_reset_required {
+            LoginResponse::PasswordResetRequired(PasswordResetRequiredResponse {
+                message: "Password reset required".to_string(),
+                password_reset_required: true,
+            })
+        } else {
+            LoginResponse::RegularAuth
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -68,60 +93,129 @@ pub struct TwoFactorAuthResponse {
     pub attempt_id: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordResetRequiredResponse {
+    pub message: String,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+}
+
 #[tracing::instrument(name = "Login", skip_all, err(Debug))]
-pub async fn login<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn login(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
     Json(login_request): Json<LoginRequest>,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let config = AuthServiceSetting::load();
-
-    let login_request = ValidLoginRequest::parse(login_request)?;
-
-    let validated_user = app_state
-        .user_store
-        .read()
-        .await
-        .authenticate_user(login_request.email(), login_request.password())
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event =
+        request_event(AuditEventType::Login, &metadata).actor(login_request.email.expose_secret());
+
+    let result = async {
+        let config = app_state.settings.load();
+
+        let login_request = ValidLoginRequest::parse(login_request)?;
+
+        let user_store = &app_state.user_store;
+        let validated_user = authenticate(
+            &app_state,
+            login_request.email(),
+            login_request.password().clone(),
+        )
         .await?;
 
-    match validated_user {
-        ValidatedUser::Requires2Fa(email) => handle_2fa(email, app_state, jar).await,
-        ValidatedUser::No2Fa(email) => handle_no_2fa(&email, jar, &config).await,
+        match validated_user {
+            ValidatedUser::Requires2Fa(email) => {
+                handle_2fa(email, &app_state, &config, &metadata, jar).await
+            }
+            ValidatedUser::No2Fa(email) => {
+                let profile = user_store.get_user_profile(&email).await?;
+                let generation = app_state
+                    .banned_token_store
+                    .token_generation(&email)
+                    .await?;
+                let response =
+                    handle_no_2fa(&profile, generation, jar, &config, &*app_state.clock).await?;
+
+                notify_if_new_device(&app_state, &email, &metadata).await;
+                Ok(response)
+            }
+        }
     }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
 }
 
-async fn handle_2fa<U, B, T, E>(
+// A password the policy rejects is still checked against the hash of users
+// whose password never went through the policy: those on a hash from another
+// system, e.g. imported ones, and those who logged in that way and are yet to
+// reset it. They are then required to reset it.
+pub async fn authenticate(
+    app_state: &AuthServiceState,
+    email: &Email,
+    password: Secret<String>,
+) -> Result<ValidatedUser, AuthApiError> {
+    let user_store = &app_state.user_store;
+    let policy_error = match Password::try_from(password.clone()) {
+        Ok(password) => return Ok(user_store.authenticate_user(email, &password).await?),
+        Err(e) => e,
+    };
+
+    let predates_policy = match user_store.password_needs_rehash(email).await {
+        Ok(true) => true,
+        Ok(false) => {
+            user_store
+                .get_user_profile(email)
+                .await?
+                .password_reset_required
+        }
+        // Answered as before, without telling whether the user exists
+        Err(UserStoreError::UserNotFound) => false,
+        Err(e) => return Err(e.into()),
+    };
+    if !predates_policy {
+        return Err(policy_error.into());
+    }
+
+    match user_store
+        .authenticate_user(email, &Password::unchecked(password))
+        .await
+    {
+        Ok(validated_user) => {
+            user_store.set_password_reset_required(email, true).await?;
+            Ok(validated_user)
+        }
+        // Answered as before, without telling whether the user exists
+        Err(UserStoreError::IncorrectPassword | UserStoreError::UserNotFound) => {
+            Err(policy_error.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn handle_2fa(
     email: Email,
-    app_state: AuthServiceState<U, B, T, E>,
+    app_state: &AuthServiceState,
+    config: &Config,
+    metadata: &RequestMetadata,
     jar: CookieJar,
-) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
+) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthApiError> {
     let login_attempt_id = TwoFaAttemptId::new();
     let code = TwoFaCode::new();
 
     app_state
         .two_fa_code_store
-        .write()
-        .await
         .store_code(email.clone(), login_attempt_id.clone(), code.clone())
         .await?;
 
-    app_state
-        .email_client
-        .send_email(&email, "2FA Code", code.as_str())
-        .await?;
+    let user = app_state.user_store.get_user(&email).await?;
+    let channel = send_two_fa_code(app_state, config, &user, &code).await?;
+
+    let event = request_event(AuditEventType::TwoFaCodeSent, metadata)
+        .actor(email.as_ref().expose_secret())
+        .details(format!("channel={}", channel));
+    app_state.audit_log.record(event).await?;
 
     let two_factor_auth_response = TwoFactorAuthResponse {
         message: "2FA required".to_string(),
@@ -137,14 +231,56 @@ where
     ))
 }
 
+// Sends the code through the user's preferred channel, falling back to email
+// when SMS is not configured. Returns the channel that was used.
+async fn send_two_fa_code(
+    app_state: &AuthServiceState,
+    config: &Config,
+    user: &User,
+    code: &TwoFaCode,
+) -> Result<TwoFaChannel, AuthApiError> {
+    if user.two_fa_channel() == TwoFaChannel::Sms {
+        match (user.phone_number(), &app_state.sms_client) {
+            (Some(phone_number), Some(sms_client)) => {
+                let message = TwoFaCodeSms {
+                    code: code.as_str(),
+                }
+                .render()
+                .map_err(|e| AuthApiError::UnexpectedError(eyre!(e)))?;
+                sms_client.send_sms(phone_number, &message).await?;
+                return Ok(TwoFaChannel::Sms);
+            }
+            _ => tracing::warn!("SMS is the preferred 2FA channel but cannot be used"),
+        }
+    }
+
+    let message = TwoFaCodeEmail {
+        code: code.as_str(),
+    }
+    .render(config.email_client.locale)?
+    .expiring_at(app_state.clock.now() + TWO_FA_CODE_TTL);
+    app_state
+        .email_client
+        .send_email(user.email(), &message)
+        .await?;
+
+    Ok(TwoFaChannel::Email)
+}
+
 async fn handle_no_2fa(
-    email: &Email,
+    profile: &UserProfile,
+    generation: u64,
     mut jar: CookieJar,
-    config: &Arc<Config>,
+    config: &Config,
+    clock: &dyn Clock,
 ) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthApiError> {
-    let auth_cookie = generate_auth_cookie(email, config)?;
+    let auth_cookie =
+        generate_auth_cookie(&profile.email, &profile.roles, generation, config, clock)?;
 
     jar = jar.add(auth_cookie);
 
-    Ok((jar, (StatusCode::OK, Json(LoginResponse::RegularAuth))))
+    Ok((
+        jar,
+        (StatusCode::OK, Json(LoginResponse::authenticated(profile))),
+    ))
 }
@@ -1,50 +1,57 @@
 use axum::{extract::State, http::StatusCode};
 use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
 
 use crate::{
     auth_service_state::AuthServiceState,
-    domain::{
-        auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
-        email_client::EmailClient,
-    },
-    settings::AuthServiceSetting,
+    domain::{auth_api_error::AuthApiError, data_stores::AuditEventType},
     utils::{
+        audit::{record_outcome, request_event},
         auth::{self, create_removal_cookie},
-        constants::{JWT_COOKIE_NAME, JWT_ELEVATED_COOKIE_NAME},
+        extractors::RequestMetadata,
     },
 };
 
 #[tracing::instrument(name = "Logout", skip_all, err(Debug))]
-pub async fn logout<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn logout(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     mut jar: CookieJar,
-) -> Result<(CookieJar, StatusCode), AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let config = AuthServiceSetting::load();
-    let jwt_cookie_name = config.auth.jwt.cookie_name.clone();
-    let jwt_elevated_cookie_name = config.auth.elevated_jwt.cookie_name.clone();
-
-    let token = auth::extract_token(&jar, &jwt_cookie_name)?.to_owned();
-
-    auth::validate_auth_token(&token, &*app_state.banned_token_store.read().await).await?;
-
-    let mut banned_token_store = app_state.banned_token_store.write().await;
-
-    if let Some(cookie) = jar.get(&jwt_elevated_cookie_name) {
-        banned_token_store
-            .ban_token(cookie.value().to_owned())
-            .await?;
-        jar = jar.remove(create_removal_cookie(*JWT_ELEVATED_COOKIE_NAME))
+) -> Result<(CookieJar, StatusCode), AuthApiError> {
+    let mut event = request_event(AuditEventType::Logout, &metadata);
+
+    let result = async {
+        let config = app_state.settings.load();
+        let jwt_cookie_name = &config.auth.jwt.cookie_name;
+        let jwt_elevated_cookie_name = &config.auth.elevated_jwt.cookie_name;
+
+        let token = auth::extract_token(&jar, jwt_cookie_name)?.to_owned();
+
+        let claims = auth::validate_auth_token(
+            &token,
+            &*app_state.banned_token_store,
+            &config,
+            &*app_state.clock,
+        )
+        .await?;
+        event.actor = Some(claims.sub.expose_secret().to_owned());
+
+        let banned_token_store = &app_state.banned_token_store;
+
+        if let Some(cookie) = jar.get(jwt_elevated_cookie_name) {
+            banned_token_store
+                .ban_token(cookie.value().to_owned())
+                .await?;
+            jar = jar.remove(create_removal_cookie(jwt_elevated_cookie_name))
+        }
+
+        banned_token_store.ban_token(token).await?;
+        jar = jar.remove(create_removal_cookie(jwt_cookie_name));
+
+        Ok((jar, StatusCode::OK))
     }
+    .await;
 
-    banned_token_store.ban_token(token).await?;
-    jar = jar.remove(create_removal_cookie(*JWT_COOKIE_NAME));
-
-    Ok((jar, StatusCode::OK))
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
 }
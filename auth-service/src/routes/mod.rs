@@ -1,19 +1,49 @@
+mod account_export;
+mod admin;
 mod change_password;
 mod delete_account;
+mod dev_mailbox;
 mod elevate;
 mod login;
 mod logout;
+mod notification_preferences;
+mod phone;
+mod restore_account;
+mod security_events;
 mod signup;
 mod verify_2fa;
 mod verify_elevated_token;
 mod verify_token;
 
+pub use account_export::{
+    AccountExport, ExportedDevice, ExportedProfile, ExportedTwoFactor, build_account_export,
+    export_account, request_account_export,
+};
+pub use admin::{
+    AdminFailedImport, AdminImportResponse, AdminImportedUser, AdminUserListResponse,
+    AdminUserResponse, SetRequires2FaRequest, UserSearchParams, admin_delete_user,
+    admin_disable_user, admin_enable_user, admin_force_password_reset, admin_get_user,
+    admin_import_users, admin_revoke_sessions, admin_search_users, admin_set_requires_2fa,
+};
 pub use change_password::{ChangePasswordRequest, change_password};
 pub use delete_account::delete_account;
+pub use dev_mailbox::{
+    MailboxAttachmentResponse, MailboxMessageResponse, MailboxResponse, clear_dev_mailbox,
+    dev_mailbox,
+};
 pub use elevate::elevate;
-pub use login::{TwoFactorAuthResponse, login};
+pub use login::{LoginResponse, PasswordResetRequiredResponse, TwoFactorAuthResponse, login};
 pub use logout::logout;
+pub use notification_preferences::{
+    NotificationPreferences, get_notification_preferences, update_notification_preferences,
+};
+pub use phone::{
+    PhoneNumberRequest, TwoFaChannelRequest, VerifyPhoneNumberRequest, set_two_fa_channel,
+    start_phone_verification, verify_phone_number,
+};
+pub use restore_account::{RestoreAccountRequest, restore_account};
+pub use security_events::{SecurityEventResponse, SecurityEventsResponse, security_events};
 pub use signup::signup;
 pub use verify_2fa::{Verify2FARequest, verify_two_fa};
 pub use verify_elevated_token::{VerifyElevatedTokenRequest, verify_elevated_token};
-pub use verify_token::verify_token;
+pub use verify_token::{VerifyTokenResponse, verify_token};
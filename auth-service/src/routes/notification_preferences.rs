@@ -0,0 +1,65 @@
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{auth_api_error::AuthApiError, email::Email},
+    utils::auth,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    #[serde(rename = "securityNotifications")]
+    pub security_notifications: bool,
+}
+
+#[tracing::instrument(name = "Get notification preferences", skip_all, err(Debug))]
+pub async fn get_notification_preferences(
+    State(app_state): State<AuthServiceState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let email = authenticated_email(&app_state, &jar).await?;
+
+    let security_notifications = app_state
+        .user_store
+        .security_notifications_enabled(&email)
+        .await?;
+
+    Ok(Json(NotificationPreferences {
+        security_notifications,
+    }))
+}
+
+#[tracing::instrument(name = "Update notification preferences", skip_all, err(Debug))]
+pub async fn update_notification_preferences(
+    State(app_state): State<AuthServiceState>,
+    jar: CookieJar,
+    Json(request): Json<NotificationPreferences>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let email = authenticated_email(&app_state, &jar).await?;
+
+    app_state
+        .user_store
+        .set_security_notifications(&email, request.security_notifications)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn authenticated_email(
+    app_state: &AuthServiceState,
+    jar: &CookieJar,
+) -> Result<Email, AuthApiError> {
+    let config = app_state.settings.load();
+    let token = auth::extract_token(jar, &config.auth.jwt.cookie_name)?;
+    let claims = auth::validate_auth_token(
+        token,
+        &*app_state.banned_token_store,
+        &config,
+        &*app_state.clock,
+    )
+    .await?;
+
+    Ok(Email::try_from(claims.sub)?)
+}
@@ -0,0 +1,165 @@
+use askama::Template;
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{
+        auth_api_error::AuthApiError, data_stores::AuditEventType, email::Email,
+        phone_number::PhoneNumber, two_fa_channel::TwoFaChannel, two_fa_code::TwoFaCode,
+    },
+    services::sms_templates::PhoneVerificationSms,
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFaChannelRequest {
+    pub channel: TwoFaChannel,
+}
+
+// Sends a verification code to the number. It only becomes the user's phone
+// number once the code is confirmed through verify_phone_number.
+#[tracing::instrument(name = "Start phone verification", skip_all, err(Debug))]
+pub async fn start_phone_verification(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
+    jar: CookieJar,
+    Json(request): Json<PhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::PhoneVerificationStarted, &metadata);
+
+    let result = async {
+        let email = elevated_email(&app_state, &jar).await?;
+        event.actor = Some(email.as_ref().expose_secret().to_owned());
+
+        let sms_client = app_state
+            .sms_client
+            .as_ref()
+            .ok_or(AuthApiError::SmsUnavailable)?;
+        let phone_number = PhoneNumber::try_from(request.phone_number)?;
+        let code = TwoFaCode::new();
+
+        app_state
+            .user_store
+            .start_phone_verification(&email, &phone_number, &code)
+            .await?;
+
+        let message = PhoneVerificationSms {
+            code: code.as_str(),
+        }
+        .render()
+        .map_err(|e| AuthApiError::UnexpectedError(eyre!(e)))?;
+        sms_client.send_sms(&phone_number, &message).await?;
+
+        Ok(StatusCode::ACCEPTED)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Verify phone number", skip_all, err(Debug))]
+pub async fn verify_phone_number(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
+    jar: CookieJar,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::PhoneVerification, &metadata);
+
+    let result = async {
+        let config = app_state.settings.load();
+        let token = auth::extract_token(&jar, &config.auth.jwt.cookie_name)?;
+        let claims = auth::validate_auth_token(
+            token,
+            &*app_state.banned_token_store,
+            &config,
+            &*app_state.clock,
+        )
+        .await?;
+        event.actor = Some(claims.sub.expose_secret().to_owned());
+
+        let email = Email::try_from(claims.sub)?;
+        let code =
+            TwoFaCode::parse(request.code).map_err(|_| AuthApiError::InvalidVerificationCode)?;
+
+        app_state
+            .user_store
+            .confirm_phone_verification(&email, &code)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+#[tracing::instrument(name = "Set two-factor channel", skip_all, err(Debug))]
+pub async fn set_two_fa_channel(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
+    jar: CookieJar,
+    Json(request): Json<TwoFaChannelRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::TwoFaChannelChange, &metadata)
+        .details(format!("channel={}", request.channel));
+
+    let result = async {
+        let email = elevated_email(&app_state, &jar).await?;
+        event.actor = Some(email.as_ref().expose_secret().to_owned());
+
+        if request.channel == TwoFaChannel::Sms && app_state.sms_client.is_none() {
+            return Err(AuthApiError::SmsUnavailable);
+        }
+
+        app_state
+            .user_store
+            .set_two_fa_channel(&email, request.channel)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
+
+// Changing how a user receives login codes needs a recently elevated session
+async fn elevated_email(
+    app_state: &AuthServiceState,
+    jar: &CookieJar,
+) -> Result<Email, AuthApiError> {
+    let config = app_state.settings.load();
+    let token = auth::extract_token(jar, &config.auth.elevated_jwt.cookie_name)?;
+    let claims = auth::validate_elevated_auth_token(
+        token,
+        &*app_state.banned_token_store,
+        &config,
+        &*app_state.clock,
+    )
+    .await?;
+
+    Ok(Email::try_from(claims.sub)?)
+}
@@ -0,0 +1,47 @@
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{
+        auth_api_error::AuthApiError, data_stores::AuditEventType, restore_token::RestoreToken,
+    },
+    utils::{
+        audit::{record_outcome, request_event},
+        extractors::RequestMetadata,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreAccountRequest {
+    pub token: Secret<String>,
+}
+
+// Takes back the deletion of an account that is still in its grace period.
+// The user signs in again afterwards, their sessions were revoked on deletion.
+#[tracing::instrument(name = "Restore account", skip_all, err(Debug))]
+pub async fn restore_account(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
+    Json(request): Json<RestoreAccountRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::AccountRestore, &metadata);
+
+    let result = async {
+        let restore_token =
+            RestoreToken::parse(request.token).map_err(|_| AuthApiError::InvalidRestoreToken)?;
+
+        let email = app_state
+            .user_store
+            .restore_user(&restore_token, app_state.clock.now())
+            .await?;
+        event.actor = Some(email.as_ref().expose_secret().to_owned());
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
+}
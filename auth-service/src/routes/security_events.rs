@@ -0,0 +1,90 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{auth_api_error::AuthApiError, data_stores::AuditRecord},
+    utils::auth,
+};
+
+const DEFAULT_EVENT_LIMIT: u32 = 20;
+const MAX_EVENT_LIMIT: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct SecurityEventsParams {
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecurityEventResponse {
+    #[serde(rename = "eventType")]
+    pub event_type: String,
+    pub outcome: String,
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub details: Option<String>,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl From<AuditRecord> for SecurityEventResponse {
+    fn from(record: AuditRecord) -> Self {
+        SecurityEventResponse {
+            event_type: record.event.event_type.to_string(),
+            outcome: record.event.outcome.to_string(),
+            actor: record.event.actor,
+            ip: record.event.ip,
+            user_agent: record.event.user_agent,
+            details: record.event.details,
+            occurred_at: record.occurred_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecurityEventsResponse {
+    pub events: Vec<SecurityEventResponse>,
+}
+
+#[tracing::instrument(name = "Security events", skip_all, err(Debug))]
+pub async fn security_events(
+    State(app_state): State<AuthServiceState>,
+    jar: CookieJar,
+    Query(params): Query<SecurityEventsParams>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let config = app_state.settings.load();
+    let token = auth::extract_token(&jar, &config.auth.jwt.cookie_name)?;
+    let claims = auth::validate_auth_token(
+        token,
+        &*app_state.banned_token_store,
+        &config,
+        &*app_state.clock,
+    )
+    .await?;
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_EVENT_LIMIT)
+        .clamp(1, MAX_EVENT_LIMIT);
+
+    let records = app_state
+        .audit_log
+        .recent_events(claims.sub.expose_secret(), limit)
+        .await?;
+
+    Ok(Json(SecurityEventsResponse {
+        events: records
+            .into_iter()
+            .map(|record| record.seen_by(claims.sub.expose_secret()).into())
+            .collect(),
+    }))
+}
@@ -1,14 +1,14 @@
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 
 use crate::{
     auth_service_state::AuthServiceState,
-    domain::{
-        auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
-        email_client::EmailClient,
-        user::User,
+    domain::{auth_api_error::AuthApiError, data_stores::AuditEventType, user::User},
+    utils::{
+        audit::{record_outcome, request_event},
+        extractors::RequestMetadata,
+        notifications::remember_device,
     },
 };
 
@@ -21,22 +21,35 @@ pub struct SignupRequest {
 }
 
 #[tracing::instrument(name = "Signup", skip_all, err(Debug))]
-pub async fn signup<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn signup(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     Json(request): Json<SignupRequest>,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let user = User::parse(request.email, request.password, request.requires_2fa)?;
-
-    app_state.user_store.write().await.add_user(user).await?;
-
-    Ok((
-        StatusCode::CREATED,
-        String::from("User created successfully!"),
-    ))
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event =
+        request_event(AuditEventType::Signup, &metadata).actor(request.email.expose_secret());
+
+    let result = async {
+        let user = User::parse(request.email, request.password, request.requires_2fa)?;
+
+        let config = app_state.settings.load();
+        if !config.signup.domain_policy.is_allowed(user.email()) {
+            return Err(AuthApiError::EmailDomainNotAllowed);
+        }
+
+        let email = user.email().clone();
+        app_state.user_store.add_user(user).await?;
+
+        // The device used to sign up is not a new device on first login
+        remember_device(&app_state, &email, &metadata).await;
+
+        Ok((
+            StatusCode::CREATED,
+            String::from("User created successfully!"),
+        ))
+    }
+    .await;
+
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
 }
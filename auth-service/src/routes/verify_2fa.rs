@@ -1,20 +1,21 @@
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 
 use crate::{
     auth_service_state::AuthServiceState,
     domain::{
-        auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
-        email::Email,
-        email_client::EmailClient,
-        two_fa_attempt_id::TwoFaAttemptId,
-        two_fa_code::TwoFaCode,
+        auth_api_error::AuthApiError, data_stores::AuditEventType, email::Email,
+        two_fa_attempt_id::TwoFaAttemptId, two_fa_code::TwoFaCode,
+    },
+    routes::LoginResponse,
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
+        notifications::notify_if_new_device,
     },
-    settings::AuthServiceSetting,
-    utils::auth,
 };
 
 #[derive(Debug, Deserialize)]
@@ -27,46 +28,60 @@ pub struct Verify2FARequest {
 }
 
 #[tracing::instrument(name = "Verify 2FA", skip_all, err(Debug))]
-pub async fn verify_two_fa<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn verify_two_fa(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let config = AuthServiceSetting::load();
-    let email = Email::try_from(request.email)?;
-    let login_attempt_id = TwoFaAttemptId::parse(&request.login_attempt_id)?;
-    let two_fa_code = TwoFaCode::parse(request.two_factor_code.clone())?;
+) -> Result<impl IntoResponse, AuthApiError> {
+    let event = request_event(AuditEventType::TwoFaVerification, &metadata)
+        .actor(request.email.expose_secret());
 
-    let (stored_attempt_id, stored_two_fa_code) = app_state
-        .two_fa_code_store
-        .read()
-        .await
-        .get_login_attempt_id_and_two_fa_code(&email)
-        .await?;
+    let result = async {
+        let config = app_state.settings.load();
+        let email = Email::try_from(request.email)?;
+        let login_attempt_id = TwoFaAttemptId::parse(&request.login_attempt_id)?;
+        let two_fa_code = TwoFaCode::parse(request.two_factor_code.clone())?;
 
-    if stored_attempt_id != login_attempt_id {
-        return Err(AuthApiError::InvalidLoginAttemptId);
-    }
-    if stored_two_fa_code != two_fa_code {
-        return Err(AuthApiError::InvalidTwoFaCode);
-    }
+        let (stored_attempt_id, stored_two_fa_code) = app_state
+            .two_fa_code_store
+            .get_login_attempt_id_and_two_fa_code(&email)
+            .await?;
+
+        if stored_attempt_id != login_attempt_id {
+            return Err(AuthApiError::InvalidLoginAttemptId);
+        }
+        if stored_two_fa_code != two_fa_code {
+            return Err(AuthApiError::InvalidTwoFaCode);
+        }
 
-    app_state
-        .two_fa_code_store
-        .write()
-        .await
-        .delete(&email)
-        .await?;
+        app_state.two_fa_code_store.delete(&email).await?;
 
-    let auth_cookie = auth::generate_auth_cookie(&email, &config)?;
+        let profile = app_state.user_store.get_user_profile(&email).await?;
+        let generation = app_state
+            .banned_token_store
+            .token_generation(&email)
+            .await?;
+        let auth_cookie = auth::generate_auth_cookie(
+            &email,
+            &profile.roles,
+            generation,
+            &config,
+            &*app_state.clock,
+        )?;
 
-    let update_jar = jar.add(auth_cookie);
+        let update_jar = jar.add(auth_cookie);
+
+        notify_if_new_device(&app_state, &email, &metadata).await;
+
+        Ok((
+            StatusCode::OK,
+            update_jar,
+            Json(LoginResponse::authenticated(&profile)),
+        ))
+    }
+    .await;
 
-    Ok((update_jar, StatusCode::OK))
+    record_outcome(&*app_state.audit_log, event, &result).await;
+    result
 }
@@ -1,14 +1,18 @@
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use secrecy::ExposeSecret;
 use serde::Deserialize;
 
 use crate::{
     auth_service_state::AuthServiceState,
     domain::{
-        auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
-        email_client::EmailClient,
+        auth_api_error::AuthApiError, data_stores::AuditEventType, email::Email,
+        user_status::UserStatus,
+    },
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
     },
-    utils::auth,
 };
 
 #[derive(Debug, Deserialize)]
@@ -17,20 +21,39 @@ pub struct VerifyElevatedTokenRequest {
 }
 
 #[tracing::instrument(name = "Verify Elevated Token", skip_all, err(Debug))]
-pub async fn verify_elevated_token<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn verify_elevated_token(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     Json(token_request): Json<VerifyElevatedTokenRequest>,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let banned_token_store = app_state.banned_token_store.read().await;
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::TokenVerification, &metadata);
+
+    let result = async {
+        let config = app_state.settings.load();
+        let claims = auth::validate_elevated_auth_token(
+            &token_request.token,
+            &*app_state.banned_token_store,
+            &config,
+            &*app_state.clock,
+        )
+        .await?;
+        event.actor = Some(claims.sub.expose_secret().to_owned());
+
+        let email = Email::try_from(claims.sub)?;
+        let status = app_state.user_store.get_status(&email).await?;
+        match status {
+            UserStatus::Suspended => return Err(AuthApiError::AccountSuspended),
+            UserStatus::PendingDeletion => return Err(AuthApiError::AccountPendingDeletion),
+            _ => {}
+        }
 
-    let _claims =
-        auth::validate_elevated_auth_token(&token_request.token, &*banned_token_store).await?;
+        Ok(StatusCode::OK)
+    }
+    .await;
 
-    Ok(StatusCode::OK)
+    // Successful verifications are too frequent to be worth recording
+    if result.is_err() {
+        record_outcome(&*app_state.audit_log, event, &result).await;
+    }
+    result
 }
@@ -1,14 +1,18 @@
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
-use serde::Deserialize;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     auth_service_state::AuthServiceState,
     domain::{
-        auth_api_error::AuthApiError,
-        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
-        email_client::EmailClient,
+        auth_api_error::AuthApiError, data_stores::AuditEventType, email::Email, role::Role,
+        user_status::UserStatus,
+    },
+    utils::{
+        audit::{record_outcome, request_event},
+        auth,
+        extractors::RequestMetadata,
     },
-    utils::auth,
 };
 
 #[derive(Debug, Deserialize)]
@@ -16,20 +20,50 @@ pub struct VerifyTokenRequest {
     pub token: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyTokenResponse {
+    pub roles: Vec<Role>,
+}
+
 #[tracing::instrument(name = "Verify Token", skip_all, err(Debug))]
-pub async fn verify_token<U, B, T, E>(
-    State(app_state): State<AuthServiceState<U, B, T, E>>,
+pub async fn verify_token(
+    State(app_state): State<AuthServiceState>,
+    metadata: RequestMetadata,
     Json(token_request): Json<VerifyTokenRequest>,
-) -> Result<impl IntoResponse, AuthApiError>
-where
-    U: UserStore,
-    B: BannedTokenStore,
-    T: TwoFaCodeStore,
-    E: EmailClient,
-{
-    let banned_token_store = app_state.banned_token_store.read().await;
-
-    let _claims = auth::validate_auth_token(&token_request.token, &*banned_token_store).await?;
-
-    Ok(StatusCode::OK)
+) -> Result<impl IntoResponse, AuthApiError> {
+    let mut event = request_event(AuditEventType::TokenVerification, &metadata);
+
+    let result = async {
+        let config = app_state.settings.load();
+        let claims = auth::validate_auth_token(
+            &token_request.token,
+            &*app_state.banned_token_store,
+            &config,
+            &*app_state.clock,
+        )
+        .await?;
+        event.actor = Some(claims.sub.expose_secret().to_owned());
+
+        let email = Email::try_from(claims.sub.clone())?;
+        let status = app_state.user_store.get_status(&email).await?;
+        match status {
+            UserStatus::Suspended => return Err(AuthApiError::AccountSuspended),
+            UserStatus::PendingDeletion => return Err(AuthApiError::AccountPendingDeletion),
+            _ => {}
+        }
+
+        Ok((
+            StatusCode::OK,
+            Json(VerifyTokenResponse {
+                roles: claims.roles,
+            }),
+        ))
+    }
+    .await;
+
+    // Successful verifications are too frequent to be worth recording
+    if result.is_err() {
+        record_outcome(&*app_state.audit_log, event, &result).await;
+    }
+    result
 }
@@ -0,0 +1,369 @@
+use std::time::Duration;
+
+use secrecy::ExposeSecret;
+use tokio::time::MissedTickBehavior;
+
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{
+        auth_api_error::AuthApiError,
+        data_stores::{AuditEvent, AuditEventType, ERASED_SUBJECT, TwoFaCodeStoreError},
+        email::Email,
+        security_notification::SecurityNotification,
+    },
+    utils::{
+        audit::record_outcome,
+        notifications::{security_notifications_enabled, send_security_notification},
+    },
+};
+
+// Accounts purged per run, anything left over is picked up by the next one
+const PURGE_BATCH_SIZE: u32 = 100;
+
+// Purges the accounts whose deletion grace period is over
+pub struct AccountPurge {
+    app_state: AuthServiceState,
+    interval: Duration,
+}
+
+impl AccountPurge {
+    pub fn new(app_state: AuthServiceState, interval: Duration) -> Self {
+        Self {
+            app_state,
+            interval,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            self.purge_due().await;
+        }
+    }
+
+    // Returns the number of accounts purged
+    #[tracing::instrument(name = "Purging due account deletions", skip_all)]
+    pub async fn purge_due(&self) -> usize {
+        let now = self.app_state.clock.now();
+        let due = match self
+            .app_state
+            .user_store
+            .due_deletions(now, PURGE_BATCH_SIZE)
+            .await
+        {
+            Ok(due) => due,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to look up due account deletions");
+                return 0;
+            }
+        };
+
+        let mut purged = 0;
+        for email in due {
+            // The preference goes away with the account, so read it beforehand
+            let notify = security_notifications_enabled(&self.app_state, &email).await;
+
+            let result = purge_account(&self.app_state, &email).await;
+            let event = match &result {
+                Ok(()) => AuditEvent::new(AuditEventType::AccountPurge).target(ERASED_SUBJECT),
+                Err(_) => AuditEvent::new(AuditEventType::AccountPurge)
+                    .target(email.as_ref().expose_secret()),
+            };
+            record_outcome(&*self.app_state.audit_log, event, &result).await;
+
+            match result {
+                Ok(()) => purged += 1,
+                Err(e) => {
+                    tracing::error!(error = ?e, "Failed to purge account");
+                    continue;
+                }
+            }
+
+            if notify {
+                let notification = SecurityNotification::AccountDeleted { at: now };
+                let locale = self.app_state.settings.load().email_client.locale;
+                send_security_notification(
+                    &*self.app_state.email_client,
+                    &email,
+                    &notification,
+                    locale,
+                )
+                .await;
+            }
+        }
+
+        if purged > 0 {
+            tracing::info!(purged, "Purged accounts past their deletion grace period");
+        }
+        purged
+    }
+}
+
+// Deletes the account for good together with its 2FA code, sessions and queued
+// emails, and erases the user from the audit log. Admins use it to skip the grace period.
+// The user goes last, so a purge that fails halfway is due again and finished
+// by the next run.
+pub async fn purge_account(
+    app_state: &AuthServiceState,
+    email: &Email,
+) -> Result<(), AuthApiError> {
+    // Reports a missing user before anything is erased
+    app_state.user_store.get_user(email).await?;
+
+    match app_state.two_fa_code_store.delete(email).await {
+        Ok(()) | Err(TwoFaCodeStoreError::UserNotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+    app_state.banned_token_store.forget_user(email).await?;
+    if let Some(email_outbox) = &app_state.email_outbox {
+        email_outbox.forget_recipient(email).await?;
+    }
+    app_state
+        .audit_log
+        .erase_subject(email.as_ref().expose_secret())
+        .await?;
+    app_state.user_store.delete_user(email).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use chrono::{Duration as ChronoDuration, Utc};
+    use color_eyre::eyre::eyre;
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::{
+        domain::{
+            clock::MockClock,
+            data_stores::{AuditLog, AuditLogError, AuditRecord, EmailOutbox, UserStore},
+            email_client::EmailMessage,
+            password::Password,
+            restore_token::RestoreToken,
+            two_fa_attempt_id::TwoFaAttemptId,
+            two_fa_code::TwoFaCode,
+            user::User,
+        },
+        services::data_stores::{
+            HashMapTwoFaCodeStore, HashMapUserStore, HashSetBannedTokenStore, MockEmailClient,
+            VecAuditLog, VecEmailOutbox,
+        },
+        settings::AuthServiceSetting,
+    };
+
+    // Fails to erase the first time, like a database that is briefly down
+    struct FlakyAuditLog {
+        inner: VecAuditLog,
+        failed: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl AuditLog for FlakyAuditLog {
+        async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+            self.inner.record(event).await
+        }
+
+        async fn recent_events(
+            &self,
+            subject: &str,
+            limit: u32,
+        ) -> Result<Vec<AuditRecord>, AuditLogError> {
+            self.inner.recent_events(subject, limit).await
+        }
+
+        async fn erase_subject(&self, subject: &str) -> Result<u64, AuditLogError> {
+            if !self.failed.swap(true, Ordering::SeqCst) {
+                return Err(AuditLogError::UnexpectedError(eyre!("Connection lost")));
+            }
+            self.inner.erase_subject(subject).await
+        }
+    }
+
+    async fn state_with_user(email: &str) -> (AuthServiceState, Email) {
+        state_with_user_and_audit_log(email, Arc::new(VecAuditLog::new())).await
+    }
+
+    async fn state_with_user_and_audit_log<A: AuditLog + 'static>(
+        email: &str,
+        audit_log: Arc<A>,
+    ) -> (AuthServiceState, Email) {
+        let email = Email::try_from(Secret::from(email.to_owned())).unwrap();
+        let user_store = Arc::new(HashMapUserStore::default());
+        user_store
+            .add_user(User::new(
+                email.clone(),
+                Password::try_from(Secret::from("passwordpassword".to_owned())).unwrap(),
+                false,
+            ))
+            .await
+            .unwrap();
+
+        let app_state = AuthServiceState::new(
+            user_store,
+            Arc::new(HashSetBannedTokenStore::new(AuthServiceSetting::handle())),
+            Arc::new(HashMapTwoFaCodeStore::new()),
+            Arc::new(MockEmailClient),
+            audit_log,
+        );
+        (app_state, email)
+    }
+
+    #[tokio::test]
+    async fn test_purge_account_removes_everything_kept_on_the_user() {
+        let (app_state, email) = state_with_user("purged@example.com").await;
+        app_state
+            .two_fa_code_store
+            .store_code(email.clone(), TwoFaAttemptId::new(), TwoFaCode::new())
+            .await
+            .unwrap();
+        app_state
+            .audit_log
+            .record(
+                AuditEvent::new(AuditEventType::Login)
+                    .actor("purged@example.com")
+                    .ip(Some("10.0.0.1".to_owned())),
+            )
+            .await
+            .unwrap();
+
+        purge_account(&app_state, &email).await.unwrap();
+
+        assert!(app_state.user_store.get_user(&email).await.is_err());
+        assert!(
+            app_state
+                .two_fa_code_store
+                .get_login_attempt_id_and_two_fa_code(&email)
+                .await
+                .is_err()
+        );
+        // Revoked, the generation goes once the revoked tokens have expired
+        assert_eq!(
+            app_state
+                .banned_token_store
+                .token_generation(&email)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(
+            app_state
+                .audit_log
+                .recent_events("purged@example.com", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(matches!(
+            purge_account(&app_state, &email).await,
+            Err(AuthApiError::UserNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_purge_account_deletes_the_users_queued_emails() {
+        let (app_state, email) = state_with_user("purged@example.com").await;
+        let email_outbox = Arc::new(VecEmailOutbox::new());
+        let app_state = app_state.with_email_outbox(email_outbox.clone());
+        let other = Email::try_from(Secret::from("other@example.com".to_owned())).unwrap();
+        let message = EmailMessage {
+            subject: "Your account will be deleted".to_owned(),
+            html_body: String::new(),
+            text_body: String::new(),
+            attachments: Vec::new(),
+            expires_at: None,
+        };
+        for recipient in [&email, &email, &other] {
+            email_outbox.enqueue(recipient, &message).await.unwrap();
+        }
+        // Dead letters keep the recipient too
+        let id = email_outbox.entries()[0].email.id;
+        email_outbox.dead_letter(id, "bounced").await.unwrap();
+
+        purge_account(&app_state, &email).await.unwrap();
+
+        let recipients: Vec<_> = email_outbox
+            .entries()
+            .into_iter()
+            .map(|entry| entry.email.recipient)
+            .collect();
+        assert_eq!(recipients, vec![other]);
+    }
+
+    #[tokio::test]
+    async fn test_purge_due_skips_accounts_in_their_grace_period() {
+        let (app_state, email) = state_with_user("due@example.com").await;
+        let now = Utc::now();
+        app_state
+            .user_store
+            .schedule_deletion(
+                &email,
+                now + ChronoDuration::days(1),
+                &RestoreToken::generate(),
+            )
+            .await
+            .unwrap();
+
+        let clock = Arc::new(MockClock::new(now));
+        let purge = AccountPurge::new(
+            app_state.clone().with_clock(clock.clone()),
+            Duration::from_secs(60),
+        );
+        assert_eq!(purge.purge_due().await, 0);
+
+        clock.advance(Duration::from_secs(24 * 60 * 60));
+        assert_eq!(purge.purge_due().await, 1);
+        assert!(app_state.user_store.get_user(&email).await.is_err());
+
+        let events = app_state
+            .audit_log
+            .recent_events(ERASED_SUBJECT, 10)
+            .await
+            .unwrap();
+        assert_eq!(events[0].event.event_type, AuditEventType::AccountPurge);
+    }
+
+    #[tokio::test]
+    async fn test_purge_that_failed_halfway_is_finished_by_the_next_run() {
+        let audit_log = Arc::new(FlakyAuditLog {
+            inner: VecAuditLog::new(),
+            failed: AtomicBool::new(false),
+        });
+        let (app_state, email) =
+            state_with_user_and_audit_log("flaky@example.com", audit_log.clone()).await;
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Login).actor("flaky@example.com"))
+            .await
+            .unwrap();
+        let now = Utc::now();
+        app_state
+            .user_store
+            .schedule_deletion(&email, now, &RestoreToken::generate())
+            .await
+            .unwrap();
+
+        let purge = AccountPurge::new(
+            app_state.clone().with_clock(Arc::new(MockClock::new(now))),
+            Duration::from_secs(60),
+        );
+        assert_eq!(purge.purge_due().await, 0);
+        assert!(app_state.user_store.get_user(&email).await.is_ok());
+
+        assert_eq!(purge.purge_due().await, 1);
+        assert!(app_state.user_store.get_user(&email).await.is_err());
+        let events = audit_log
+            .recent_events("flaky@example.com", 10)
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Result, eyre};
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    email::Email,
+    email_client::{EmailClient, EmailMessage},
+};
+
+// Only the most recent emails are kept so a long running dev server does not grow without bound
+const MAILBOX_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct CapturedEmail {
+    pub recipient: String,
+    pub message: EmailMessage,
+    pub sent_at: DateTime<Utc>,
+}
+
+// Keeps sent emails in memory instead of delivering them, for local development.
+// Clones share the same mailbox.
+#[derive(Debug, Clone, Default)]
+pub struct CapturingEmailClient {
+    mailbox: Arc<Mutex<VecDeque<CapturedEmail>>>,
+}
+
+impl CapturingEmailClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Newest first
+    pub fn messages(&self) -> Vec<CapturedEmail> {
+        self.mailbox
+            .lock()
+            .map(|mailbox| mailbox.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut mailbox) = self.mailbox.lock() {
+            mailbox.clear();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for CapturingEmailClient {
+    #[tracing::instrument(name = "Capturing email", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let mut mailbox = self.mailbox.lock().map_err(|e| eyre!(e.to_string()))?;
+
+        if mailbox.len() == MAILBOX_CAPACITY {
+            mailbox.pop_front();
+        }
+        mailbox.push_back(CapturedEmail {
+            recipient: recipient.as_ref().expose_secret().to_owned(),
+            message: message.clone(),
+            sent_at: Utc::now(),
+        });
+
+        tracing::info!(subject = %message.subject, "Captured email, see /_dev/mailbox");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn message(subject: &str) -> EmailMessage {
+        EmailMessage {
+            subject: subject.to_owned(),
+            html_body: "<p>body</p>".to_owned(),
+            text_body: "body".to_owned(),
+            attachments: Vec::new(),
+            expires_at: None,
+        }
+    }
+
+    fn recipient() -> Email {
+        Email::try_from(Secret::new("user@example.com".to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_newest_first() {
+        let email_client = CapturingEmailClient::new();
+        email_client
+            .send_email(&recipient(), &message("first"))
+            .await
+            .unwrap();
+        email_client
+            .send_email(&recipient(), &message("second"))
+            .await
+            .unwrap();
+
+        let subjects: Vec<_> = email_client
+            .messages()
+            .into_iter()
+            .map(|captured| captured.message.subject)
+            .collect();
+        assert_eq!(subjects, vec!["second", "first"]);
+        assert_eq!(email_client.messages()[0].recipient, "user@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_keeps_only_the_most_recent_messages() {
+        let email_client = CapturingEmailClient::new();
+        for i in 0..MAILBOX_CAPACITY + 5 {
+            email_client
+                .send_email(&recipient(), &message(&i.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let messages = email_client.messages();
+        assert_eq!(messages.len(), MAILBOX_CAPACITY);
+        assert_eq!(
+            messages[0].message.subject,
+            (MAILBOX_CAPACITY + 4).to_string()
+        );
+        assert_eq!(messages[MAILBOX_CAPACITY - 1].message.subject, "5");
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_the_mailbox() {
+        let email_client = CapturingEmailClient::new();
+        let handle = email_client.clone();
+
+        email_client
+            .send_email(&recipient(), &message("shared"))
+            .await
+            .unwrap();
+        assert_eq!(handle.messages().len(), 1);
+
+        handle.clear();
+        assert!(email_client.messages().is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use std::{path::PathBuf, time::Duration, time::SystemTime};
+
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    time::MissedTickBehavior,
+};
+
+use crate::settings::Settings;
+
+// Reloads the config when its file changes or the process receives SIGHUP.
+// Changes apply to requests started after the reload, a config that fails to
+// load or validate is logged and the current one stays in place.
+pub struct ConfigReloader {
+    settings: Settings,
+    path: PathBuf,
+    poll_interval: Duration,
+}
+
+impl ConfigReloader {
+    pub fn new(settings: Settings, path: impl Into<PathBuf>, poll_interval: Duration) -> Self {
+        Self {
+            settings,
+            path: path.into(),
+            poll_interval,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut hangup = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last_modified = self.modified().await;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let modified = self.modified().await;
+                    if modified == last_modified {
+                        continue;
+                    }
+                    last_modified = modified;
+                }
+                _ = hangup.recv() => tracing::info!("Received SIGHUP"),
+            }
+            self.reload();
+        }
+    }
+
+    #[tracing::instrument(name = "Reloading config", skip_all)]
+    pub fn reload(&self) {
+        match self.settings.reload_from(&self.path) {
+            Ok(changes) if changes.is_empty() => tracing::info!("Config reloaded, nothing changed"),
+            Ok(changes) => {
+                for change in changes {
+                    tracing::info!(%change, "Config changed");
+                }
+            }
+            Err(e) => tracing::error!(error = %e, "Keeping the current config"),
+        }
+    }
+
+    async fn modified(&self) -> Option<SystemTime> {
+        let metadata = tokio::fs::metadata(&self.path).await.ok()?;
+        metadata.modified().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use serde_json::Value;
+    use tracing_subscriber::fmt::MakeWriter;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::settings::{CONFIG_FILE_PATH, Config};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    // Collects what gets logged while it is the default subscriber's writer
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Logs {
+        fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    impl io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Logs {
+        type Writer = Logs;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    // The config file with the JWT cookie renamed
+    fn config_with_cookie_name(cookie_name: &str) -> String {
+        let mut config: Value =
+            serde_json::from_str(&std::fs::read_to_string(CONFIG_FILE_PATH).unwrap()).unwrap();
+        config["auth"]["jwt"]["cookie_name"] = cookie_name.into();
+        config.to_string()
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        })
+        .await
+        .expect("Timed out waiting for the reloader");
+    }
+
+    #[tokio::test]
+    async fn test_changed_file_is_reloaded_unless_invalid() {
+        let logs = Logs::default();
+        // The runtime is single threaded, so the reloader logs here too
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_writer(logs.clone())
+                .with_ansi(false)
+                .finish(),
+        );
+
+        let directory = std::env::temp_dir().join(format!("config-reloader-{}", Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        let path = directory.join("config.json");
+        std::fs::write(&path, config_with_cookie_name("jwt")).unwrap();
+
+        let settings = Settings::new(Config::new().unwrap());
+        let reloader =
+            tokio::spawn(ConfigReloader::new(settings.clone(), &path, POLL_INTERVAL).run());
+        // Gives the reloader time to note when the file was last modified
+        tokio::time::sleep(POLL_INTERVAL * 5).await;
+
+        std::fs::write(&path, config_with_cookie_name("reloaded")).unwrap();
+        wait_until(|| settings.load().auth.jwt.cookie_name == "reloaded").await;
+
+        // Clashes with the elevated JWT cookie
+        std::fs::write(&path, config_with_cookie_name("jwt_elevated")).unwrap();
+        wait_until(|| logs.contents().contains("Keeping the current config")).await;
+        assert_eq!(settings.load().auth.jwt.cookie_name, "reloaded");
+        assert!(
+            logs.contents()
+                .contains("auth.jwt.cookie_name and auth.elevated_jwt.cookie_name must differ")
+        );
+
+        reloader.abort();
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+// Cases every BannedTokenStore has to pass, run against each backend with
+// banned_token_store_tests!. Expiry depends on how a backend keeps time and
+// is tested next to each store instead.
+use secrecy::Secret;
+
+use crate::domain::{data_stores::BannedTokenStore, email::Email};
+
+fn parse_email(address: &str) -> Email {
+    Email::try_from(Secret::new(address.to_owned())).unwrap()
+}
+
+pub async fn banned_token_is_found(store: &dyn BannedTokenStore) {
+    assert!(!store.contains_token("token").await.unwrap());
+
+    store.ban_token("token".to_owned()).await.unwrap();
+
+    assert!(store.contains_token("token").await.unwrap());
+    assert!(!store.contains_token("other_token").await.unwrap());
+}
+
+pub async fn banning_twice_is_not_an_error(store: &dyn BannedTokenStore) {
+    store.ban_token("token".to_owned()).await.unwrap();
+    store.ban_token("token".to_owned()).await.unwrap();
+
+    assert!(store.contains_token("token").await.unwrap());
+}
+
+pub async fn revoke_all_tokens_bumps_generation(store: &dyn BannedTokenStore) {
+    let email = parse_email("test@example.com");
+    assert_eq!(store.token_generation(&email).await.unwrap(), 0);
+
+    store.revoke_all_tokens(&email).await.unwrap();
+    store.revoke_all_tokens(&email).await.unwrap();
+
+    assert_eq!(store.token_generation(&email).await.unwrap(), 2);
+}
+
+pub async fn generations_are_kept_per_user(store: &dyn BannedTokenStore) {
+    let email = parse_email("test@example.com");
+    let other = parse_email("other@example.com");
+
+    store.revoke_all_tokens(&email).await.unwrap();
+
+    assert_eq!(store.token_generation(&email).await.unwrap(), 1);
+    assert_eq!(store.token_generation(&other).await.unwrap(), 0);
+}
+
+pub async fn forget_user_revokes_all_tokens(store: &dyn BannedTokenStore) {
+    let email = parse_email("test@example.com");
+    store.revoke_all_tokens(&email).await.unwrap();
+
+    store.forget_user(&email).await.unwrap();
+
+    assert_eq!(store.token_generation(&email).await.unwrap(), 2);
+}
+
+pub async fn revoking_after_forgetting_keeps_counting(store: &dyn BannedTokenStore) {
+    let email = parse_email("test@example.com");
+
+    // An account created again with the same email
+    store.forget_user(&email).await.unwrap();
+    store.revoke_all_tokens(&email).await.unwrap();
+
+    assert_eq!(store.token_generation(&email).await.unwrap(), 2);
+}
+
+// Expands to one test per case, each with a store from `$setup`, an async fn
+// returning whatever has to outlive the test (e.g. a container) and the store.
+macro_rules! banned_token_store_tests {
+    ($setup:ident) => {
+        $crate::services::data_stores::banned_token_store_suite::banned_token_store_tests!(
+            @cases $setup;
+            banned_token_is_found,
+            banning_twice_is_not_an_error,
+            revoke_all_tokens_bumps_generation,
+            generations_are_kept_per_user,
+            forget_user_revokes_all_tokens,
+            revoking_after_forgetting_keeps_counting,
+        );
+    };
+    (@cases $setup:ident; $($case:ident),* $(,)?) => {
+        mod banned_token_store_suite {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    let (_guard, store) = super::$setup().await;
+                    $crate::services::data_stores::banned_token_store_suite::$case(&store).await;
+                }
+            )*
+        }
+    };
+}
+
+pub(crate) use banned_token_store_tests;
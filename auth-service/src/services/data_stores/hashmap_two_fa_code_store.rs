@@ -1,48 +1,112 @@
-use std::collections::HashMap;
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
 
 use crate::domain::{
-    data_stores::{TwoFaCodeStore, TwoFaCodeStoreError},
+    clock::{Clock, SystemClock},
+    data_stores::{TWO_FA_CODE_TTL, TwoFaCodeStore, TwoFaCodeStoreError},
     email::Email,
     two_fa_attempt_id::TwoFaAttemptId,
     two_fa_code::TwoFaCode,
 };
 
-#[derive(Default)]
+#[derive(Debug, Clone)]
+struct StoredCode {
+    login_attempt_id: TwoFaAttemptId,
+    two_fa_code: TwoFaCode,
+    expires_at: DateTime<Utc>,
+}
+
+// Clones share the same codes, which lets the sweeper prune the store while
+// the service holds on to it.
+#[derive(Clone)]
 pub struct HashMapTwoFaCodeStore {
-    codes: HashMap<Email, (TwoFaAttemptId, TwoFaCode)>,
+    codes: Arc<DashMap<Email, StoredCode>>,
+    clock: Arc<dyn Clock>,
+    ttl: Duration,
+}
+
+impl Default for HashMapTwoFaCodeStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HashMapTwoFaCodeStore {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock), TWO_FA_CODE_TTL)
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>, ttl: Duration) -> Self {
         Self {
-            codes: HashMap::new(),
+            codes: Arc::default(),
+            clock,
+            ttl,
         }
     }
 
     pub fn has_login_attempt_id(&self, attempt_id: &TwoFaAttemptId) -> bool {
+        let now = self.clock.now();
+        self.codes
+            .iter()
+            .any(|stored| stored.login_attempt_id == *attempt_id && stored.expires_at > now)
+    }
+
+    // Removes the expired codes, returning how many were removed
+    pub fn remove_expired(&self) -> usize {
+        let now = self.clock.now();
+        let before = self.codes.len();
+        self.codes.retain(|_, stored| stored.expires_at > now);
+
+        before.saturating_sub(self.codes.len())
+    }
+
+    pub fn spawn_sweeper(&self, interval: Duration) -> JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                let removed = store.remove_expired();
+                if removed > 0 {
+                    tracing::info!(removed, "Removed expired 2FA codes");
+                }
+            }
+        })
+    }
+
+    // Expired codes are treated as missing even before the sweeper removes them
+    fn get_unexpired(&self, user_id: &Email) -> Result<StoredCode, TwoFaCodeStoreError> {
+        let now = self.clock.now();
         self.codes
-            .values()
-            .find(|(id, _)| id == attempt_id)
-            .is_some()
+            .get(user_id)
+            .filter(|stored| stored.expires_at > now)
+            .map(|stored| stored.clone())
+            .ok_or(TwoFaCodeStoreError::UserNotFound)
     }
 }
 
 #[async_trait::async_trait]
 impl TwoFaCodeStore for HashMapTwoFaCodeStore {
     async fn store_code(
-        &mut self,
+        &self,
         user_id: Email,
         login_attempt_id: TwoFaAttemptId,
         two_fa_code: TwoFaCode,
     ) -> Result<(), TwoFaCodeStoreError> {
-        if self.codes.contains_key(&user_id) {
-            self.codes.entry(user_id).and_modify(|(id, code)| {
-                *id = login_attempt_id;
-                *code = two_fa_code;
-            });
-        } else {
-            self.codes.insert(user_id, (login_attempt_id, two_fa_code));
-        }
+        let expires_at = self.clock.now() + self.ttl;
+        self.codes.insert(
+            user_id,
+            StoredCode {
+                login_attempt_id,
+                two_fa_code,
+                expires_at,
+            },
+        );
         Ok(())
     }
 
@@ -52,14 +116,12 @@ impl TwoFaCodeStore for HashMapTwoFaCodeStore {
         login_attempt_id: &TwoFaAttemptId,
         two_fa_code: &TwoFaCode,
     ) -> Result<(), TwoFaCodeStoreError> {
-        let Some((id, code)) = self.codes.get(user_id) else {
-            return Err(TwoFaCodeStoreError::UserNotFound);
-        };
+        let stored = self.get_unexpired(user_id)?;
 
-        if id != login_attempt_id {
+        if stored.login_attempt_id != *login_attempt_id {
             return Err(TwoFaCodeStoreError::InvalidAttemptId);
         }
-        if code != two_fa_code {
+        if stored.two_fa_code != *two_fa_code {
             return Err(TwoFaCodeStoreError::Invalid2FACode);
         }
         Ok(())
@@ -69,16 +131,12 @@ impl TwoFaCodeStore for HashMapTwoFaCodeStore {
         &self,
         user_id: &Email,
     ) -> Result<(TwoFaAttemptId, TwoFaCode), TwoFaCodeStoreError> {
-        let Some((id, code)) = self.codes.get(user_id) else {
-            return Err(TwoFaCodeStoreError::UserNotFound);
-        };
-        Ok((id.clone(), code.clone()))
+        let stored = self.get_unexpired(user_id)?;
+        Ok((stored.login_attempt_id, stored.two_fa_code))
     }
 
-    async fn delete(&mut self, user_id: &Email) -> Result<(), TwoFaCodeStoreError> {
-        self.codes
-            .remove(user_id)
-            .ok_or(TwoFaCodeStoreError::UserNotFound)?;
+    async fn delete(&self, user_id: &Email) -> Result<(), TwoFaCodeStoreError> {
+        self.codes.remove(user_id);
         Ok(())
     }
 }
@@ -88,27 +146,21 @@ mod tests {
     use secrecy::Secret;
 
     use super::*;
+    use crate::{
+        domain::clock::MockClock,
+        services::data_stores::two_fa_code_store_suite::two_fa_code_store_tests,
+    };
 
-    #[tokio::test]
-    async fn test_store_code() {
-        let mut store = HashMapTwoFaCodeStore::new();
-        let user_id = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let session_id = TwoFaAttemptId::new();
-        let two_fa_code = TwoFaCode::new();
-
-        store
-            .store_code(user_id.clone(), session_id.clone(), two_fa_code.clone())
-            .await
-            .unwrap();
-
-        let (id, code) = store.codes.get(&user_id).unwrap();
-        assert_eq!(id, &session_id);
-        assert_eq!(code, &two_fa_code);
+    async fn setup() -> ((), HashMapTwoFaCodeStore) {
+        ((), HashMapTwoFaCodeStore::new())
     }
 
+    two_fa_code_store_tests!(setup);
+
     #[tokio::test]
-    async fn should_pass_valid_login_attempt_id_and_code() {
-        let mut store = HashMapTwoFaCodeStore::new();
+    async fn code_should_expire_after_ttl() {
+        let clock = Arc::new(MockClock::default());
+        let store = HashMapTwoFaCodeStore::with_clock(clock.clone(), TWO_FA_CODE_TTL);
         let user_id = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
         let session_id = TwoFaAttemptId::new();
         let two_fa_code = TwoFaCode::new();
@@ -118,67 +170,20 @@ mod tests {
             .await
             .unwrap();
 
+        clock.advance(TWO_FA_CODE_TTL - Duration::from_secs(1));
         assert!(
             store
                 .validate(&user_id, &session_id, &two_fa_code)
                 .await
                 .is_ok()
         );
-    }
-
-    #[tokio::test]
-    async fn should_fail_with_invalid_login_attempt_id() {
-        let mut store = HashMapTwoFaCodeStore::new();
-        let user_id = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let session_id = TwoFaAttemptId::new();
-        let two_fa_code = TwoFaCode::new();
-
-        store
-            .store_code(user_id.clone(), session_id.clone(), two_fa_code.clone())
-            .await
-            .unwrap();
-
-        assert_eq!(
-            store
-                .validate(&user_id, &TwoFaAttemptId::new(), &two_fa_code)
-                .await,
-            Err(TwoFaCodeStoreError::InvalidAttemptId)
-        );
-    }
-
-    #[tokio::test]
-    async fn should_fail_with_invalid_code() {
-        let mut store = HashMapTwoFaCodeStore::new();
-        let user_id = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let session_id = TwoFaAttemptId::new();
-        let two_fa_code = TwoFaCode::new();
-
-        store
-            .store_code(user_id.clone(), session_id.clone(), two_fa_code.clone())
-            .await
-            .unwrap();
 
+        clock.advance(Duration::from_secs(1));
         assert_eq!(
-            store
-                .validate(&user_id, &session_id, &TwoFaCode::new())
-                .await,
-            Err(TwoFaCodeStoreError::Invalid2FACode)
+            store.validate(&user_id, &session_id, &two_fa_code).await,
+            Err(TwoFaCodeStoreError::UserNotFound)
         );
-    }
-
-    #[tokio::test]
-    async fn delete_should_pass_with_valid_user_id() {
-        let mut store = HashMapTwoFaCodeStore::new();
-        let user_id = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let session_id = TwoFaAttemptId::new();
-        let two_fa_code = TwoFaCode::new();
-
-        store
-            .store_code(user_id.clone(), session_id.clone(), two_fa_code.clone())
-            .await
-            .unwrap();
-
-        store.delete(&user_id).await.unwrap();
-        assert!(store.codes.get(&user_id).is_none());
+        assert!(!store.has_login_attempt_id(&session_id));
+        assert_eq!(store.remove_expired(), 1);
     }
 }
@@ -1,38 +1,134 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::eyre;
+use dashmap::{DashMap, DashSet, mapref::entry::Entry};
+use secrecy::ExposeSecret;
 
 use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
+    clock::{Clock, SystemClock},
+    data_stores::{
+        MAX_PHONE_VERIFICATION_ATTEMPTS, PHONE_VERIFICATION_TTL_SECONDS, UserPage, UserQuery,
+        UserStore, UserStoreError,
+    },
+    device_fingerprint::{DeviceFingerprint, KnownDevice},
     email::Email,
     password::Password,
-    user::{User, ValidatedUser},
+    password_hash::PasswordHash,
+    phone_number::PhoneNumber,
+    restore_token::RestoreToken,
+    role::Role,
+    two_fa_channel::TwoFaChannel,
+    two_fa_code::TwoFaCode,
+    user::{ImportedUser, User, UserProfile, ValidatedUser},
+    user_status::UserStatus,
 };
+use crate::utils::password_hash::verify_password_hash;
 
-#[derive(Debug, Default)]
 pub struct HashMapUserStore {
-    users: HashMap<Email, User>,
+    users: DashMap<Email, User>,
+    roles: DashMap<Email, HashSet<Role>>,
+    password_reset_required: DashSet<Email>,
+    devices: DashMap<Email, HashMap<DeviceFingerprint, KnownDevice>>,
+    notifications_disabled: DashSet<Email>,
+    phone_verifications: DashMap<Email, PendingPhoneVerification>,
+    // Users keep their imported hash until they log in, passwords are
+    // otherwise kept in plain text
+    imported_hashes: DashMap<Email, PasswordHash>,
+    pending_deletions: DashMap<Email, PendingDeletion>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for HashMapUserStore {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl HashMapUserStore {
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            users: DashMap::default(),
+            roles: DashMap::default(),
+            password_reset_required: DashSet::default(),
+            devices: DashMap::default(),
+            notifications_disabled: DashSet::default(),
+            phone_verifications: DashMap::default(),
+            imported_hashes: DashMap::default(),
+            pending_deletions: DashMap::default(),
+            clock,
+        }
+    }
+}
+
+// Only the roles the migrations create exist
+fn is_known_role(role: &Role) -> bool {
+    [Role::USER, Role::ADMIN].contains(&role.as_str())
+}
+
+#[derive(Debug)]
+struct PendingDeletion {
+    due_at: DateTime<Utc>,
+    restore_token_hash: String,
+}
+
+#[derive(Debug)]
+struct PendingPhoneVerification {
+    phone_number: PhoneNumber,
+    code: TwoFaCode,
+    attempts: i32,
+    expires_at: DateTime<Utc>,
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashMapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if self.users.contains_key(user.email()) {
-            return Err(UserStoreError::UserAlreadyExists);
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        match self.users.entry(user.email().to_owned()) {
+            Entry::Occupied(_) => Err(UserStoreError::UserAlreadyExists),
+            Entry::Vacant(entry) => {
+                self.roles
+                    .insert(user.email().to_owned(), HashSet::from([Role::user()]));
+                entry.insert(user);
+                Ok(())
+            }
         }
-
-        self.users.insert(user.email().to_owned(), user);
-        Ok(())
     }
 
     async fn set_new_password(
-        &mut self,
+        &self,
         email: &Email,
         new_password: Password,
     ) -> Result<(), UserStoreError> {
-        let user = self
-            .users
+        self.users
             .get_mut(email)
-            .ok_or(UserStoreError::UserNotFound)?;
-        user.password = new_password;
+            .ok_or(UserStoreError::UserNotFound)?
+            .password = new_password;
+        self.imported_hashes.remove(email);
+        self.password_reset_required.remove(email);
+        Ok(())
+    }
+
+    async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError> {
+        // Checked before adding the user, so a failed import leaves nothing behind
+        if !user.roles.iter().all(is_known_role) {
+            return Err(UserStoreError::RoleNotFound);
+        }
+        // Stands in for the password until the first login replaces it
+        let placeholder = Password::try_from(user.password_hash.as_ref().clone())
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        self.add_user(
+            User::new(user.email.clone(), placeholder, user.requires_2fa).with_status(user.status),
+        )
+        .await?;
+        if let Some(mut roles) = self.roles.get_mut(&user.email) {
+            roles.extend(user.roles);
+        }
+        if user.password_reset_required {
+            self.password_reset_required.insert(user.email.clone());
+        }
+        self.imported_hashes.insert(user.email, user.password_hash);
         Ok(())
     }
 
@@ -41,124 +137,396 @@ impl UserStore for HashMapUserStore {
         email: &Email,
         password: &Password,
     ) -> Result<ValidatedUser, UserStoreError> {
+        if let Some(hash) = self.imported_hashes.get(email).map(|hash| hash.clone()) {
+            verify_password_hash(hash.as_ref().clone(), password.clone())
+                .await
+                .map_err(|_| UserStoreError::IncorrectPassword)?;
+            self.imported_hashes.remove(email);
+            self.users
+                .get_mut(email)
+                .ok_or(UserStoreError::UserNotFound)?
+                .password = password.clone();
+        }
+
         let user = self.get_user(email).await?;
         if !user.password_matches(password) {
             Err(UserStoreError::IncorrectPassword)
+        } else if let Some(err) = UserStoreError::from_inactive_status(user.status()) {
+            Err(err)
         } else {
             Ok(ValidatedUser::new(email.clone(), user.requires_2fa()))
         }
     }
 
+    async fn password_needs_rehash(&self, email: &Email) -> Result<bool, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.imported_hashes.contains_key(email))
+    }
+
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         self.users
             .get(email)
-            .cloned()
+            .map(|user| user.clone())
             .ok_or(UserStoreError::UserNotFound)
     }
 
-    async fn delete_user(&mut self, user: &Email) -> Result<(), UserStoreError> {
+    async fn delete_user(&self, user: &Email) -> Result<(), UserStoreError> {
+        self.roles.remove(user);
+        self.imported_hashes.remove(user);
+        self.password_reset_required.remove(user);
+        self.devices.remove(user);
+        self.notifications_disabled.remove(user);
+        self.phone_verifications.remove(user);
+        self.pending_deletions.remove(user);
         self.users
             .remove(user)
             .map(|_| ())
             .ok_or(UserStoreError::UserNotFound)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use secrecy::Secret;
+    async fn assign_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if !is_known_role(role) {
+            return Err(UserStoreError::RoleNotFound);
+        }
+        self.roles
+            .entry(email.clone())
+            .or_default()
+            .insert(role.clone());
+        Ok(())
+    }
 
-    use super::*;
+    async fn revoke_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        if let Some(mut roles) = self.roles.get_mut(email) {
+            roles.remove(role);
+        }
+        Ok(())
+    }
 
-    #[tokio::test]
-    async fn test_add_user_succeeds() {
-        let mut store = HashMapUserStore::default();
-        let user = User::parse(
-            Secret::from("test@example.com".to_string()),
-            Secret::from("passwordpassword".to_string()),
-            false,
-        )
-        .unwrap();
-        assert!(store.add_user(user).await.is_ok());
+    async fn get_roles(&self, email: &Email) -> Result<Vec<Role>, UserStoreError> {
+        let mut roles: Vec<Role> = self
+            .roles
+            .get(email)
+            .map(|roles| roles.iter().cloned().collect())
+            .unwrap_or_default();
+        roles.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Ok(roles)
     }
 
-    #[tokio::test]
-    async fn test_add_user_fails_if_user_exists() {
-        let mut store = HashMapUserStore::default();
-        let user = User::parse(
-            Secret::from("test@example.com".to_string()),
-            Secret::from("passwordpassword".to_string()),
-            false,
-        )
-        .unwrap();
-        let user2 = User::parse(
-            Secret::from("test@example.com".to_string()),
-            Secret::from("passwordpassword".to_string()),
-            false,
-        )
-        .unwrap();
-        assert!(store.add_user(user).await.is_ok());
-        assert_eq!(
-            store.add_user(user2).await,
-            Err(UserStoreError::UserAlreadyExists)
-        );
+    async fn get_user_profile(&self, email: &Email) -> Result<UserProfile, UserStoreError> {
+        let user = self.get_user(email).await?;
+        Ok(UserProfile {
+            email: email.clone(),
+            requires_2fa: user.requires_2fa(),
+            status: user.status(),
+            password_reset_required: self.password_reset_required.contains(email),
+            roles: self.get_roles(email).await?,
+        })
     }
 
-    #[tokio::test]
-    async fn test_get_user() {
-        let mut user_store = HashMapUserStore::default();
-        let user = User::parse(
-            Secret::from("test@example.com".to_string()),
-            Secret::from("passwordpassword".to_string()),
-            false,
-        )
-        .unwrap();
-        assert!(user_store.add_user(user.clone()).await.is_ok());
-        let user_two = user_store.get_user(user.email()).await.unwrap();
-        assert_eq!(user_two, user);
-    }
-
-    #[tokio::test]
-    async fn test_validate_user() {
-        let user = User::parse(
-            Secret::from("test@example.com".to_string()),
-            Secret::from("passwordpassword".to_string()),
-            false,
-        )
-        .unwrap();
-        let mut user_store = HashMapUserStore::default();
-        assert!(user_store.add_user(user.clone()).await.is_ok());
-        assert!(
-            user_store
-                .authenticate_user(
-                    user.email(),
-                    &Password::try_from(Secret::from("passwordpassword".to_string())).unwrap()
-                )
-                .await
-                .is_ok()
+    async fn search_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let needle = query.email_contains.as_deref().map(str::to_lowercase);
+        let mut emails: Vec<Email> = self
+            .users
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|email| match &needle {
+                Some(needle) => email
+                    .as_ref()
+                    .expose_secret()
+                    .to_lowercase()
+                    .contains(needle.as_str()),
+                None => true,
+            })
+            .collect();
+        emails.sort_by(|a, b| a.as_ref().expose_secret().cmp(b.as_ref().expose_secret()));
+
+        let mut users = Vec::new();
+        for email in emails
+            .iter()
+            .skip(query.offset() as usize)
+            .take(query.per_page as usize)
+        {
+            users.push(self.get_user_profile(email).await?);
+        }
+
+        Ok(UserPage {
+            users,
+            total: emails.len() as u64,
+        })
+    }
+
+    async fn get_status(&self, email: &Email) -> Result<UserStatus, UserStoreError> {
+        self.get_user(email).await.map(|user| user.status())
+    }
+
+    async fn set_status(&self, email: &Email, status: UserStatus) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        if user.status == UserStatus::PendingDeletion {
+            return Err(UserStoreError::UserPendingDeletion);
+        }
+        user.status = status;
+        Ok(())
+    }
+
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        self.users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?
+            .requires_2fa = requires_2fa;
+        Ok(())
+    }
+
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if required {
+            self.password_reset_required.insert(email.clone());
+        } else {
+            self.password_reset_required.remove(email);
+        }
+        Ok(())
+    }
+
+    async fn remember_device(
+        &self,
+        email: &Email,
+        fingerprint: &DeviceFingerprint,
+    ) -> Result<bool, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let now = self.clock.now();
+        let mut devices = self.devices.entry(email.clone()).or_default();
+        match devices.get_mut(fingerprint) {
+            Some(device) => {
+                device.last_seen_at = now;
+                Ok(false)
+            }
+            None => {
+                devices.insert(
+                    fingerprint.clone(),
+                    KnownDevice {
+                        fingerprint: fingerprint.clone(),
+                        first_seen_at: now,
+                        last_seen_at: now,
+                    },
+                );
+                Ok(true)
+            }
+        }
+    }
+
+    async fn known_devices(&self, email: &Email) -> Result<Vec<KnownDevice>, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let mut devices: Vec<_> = self
+            .devices
+            .get(email)
+            .map(|devices| devices.values().cloned().collect())
+            .unwrap_or_default();
+        devices.sort_by_key(|device| Reverse(device.last_seen_at));
+        Ok(devices)
+    }
+
+    async fn security_notifications_enabled(&self, email: &Email) -> Result<bool, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(!self.notifications_disabled.contains(email))
+    }
+
+    async fn set_security_notifications(
+        &self,
+        email: &Email,
+        enabled: bool,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if enabled {
+            self.notifications_disabled.remove(email);
+        } else {
+            self.notifications_disabled.insert(email.clone());
+        }
+        Ok(())
+    }
+
+    async fn start_phone_verification(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+        code: &TwoFaCode,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.phone_verifications.insert(
+            email.clone(),
+            PendingPhoneVerification {
+                phone_number: phone_number.clone(),
+                code: code.clone(),
+                attempts: 0,
+                expires_at: self.clock.now() + Duration::seconds(PHONE_VERIFICATION_TTL_SECONDS),
+            },
         );
-        assert_eq!(
-            user_store
-                .authenticate_user(
-                    user.email(),
-                    &Password::try_from(Secret::from("wrongpassword".to_string())).unwrap()
-                )
-                .await,
-            Err(UserStoreError::IncorrectPassword)
+        Ok(())
+    }
+
+    async fn confirm_phone_verification(
+        &self,
+        email: &Email,
+        code: &TwoFaCode,
+    ) -> Result<PhoneNumber, UserStoreError> {
+        let mut pending = self
+            .phone_verifications
+            .get_mut(email)
+            .ok_or(UserStoreError::InvalidVerificationCode)?;
+
+        // The entry has to be released before it can be removed from the map
+        if pending.expires_at <= self.clock.now() {
+            drop(pending);
+            self.phone_verifications.remove(email);
+            return Err(UserStoreError::InvalidVerificationCode);
+        }
+        if &pending.code != code {
+            pending.attempts += 1;
+            let exhausted = pending.attempts >= MAX_PHONE_VERIFICATION_ATTEMPTS;
+            drop(pending);
+            if exhausted {
+                self.phone_verifications.remove(email);
+            }
+            return Err(UserStoreError::InvalidVerificationCode);
+        }
+        drop(pending);
+
+        let (_, pending) = self
+            .phone_verifications
+            .remove(email)
+            .ok_or(UserStoreError::InvalidVerificationCode)?;
+        self.users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?
+            .phone_number = Some(pending.phone_number.clone());
+        Ok(pending.phone_number)
+    }
+
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFaChannel,
+    ) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        if channel == TwoFaChannel::Sms && user.phone_number.is_none() {
+            return Err(UserStoreError::PhoneNumberNotVerified);
+        }
+        user.two_fa_channel = channel;
+        Ok(())
+    }
+
+    async fn schedule_deletion(
+        &self,
+        email: &Email,
+        due_at: DateTime<Utc>,
+        restore_token: &RestoreToken,
+    ) -> Result<(), UserStoreError> {
+        self.users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?
+            .status = UserStatus::PendingDeletion;
+        self.pending_deletions.insert(
+            email.clone(),
+            PendingDeletion {
+                due_at,
+                restore_token_hash: restore_token.hash(),
+            },
         );
+        Ok(())
     }
 
-    #[tokio::test]
-    async fn test_delete_user() {
-        let user = User::parse(
-            Secret::from("test@example.com".to_string()),
-            Secret::from("passwordpassword".to_string()),
-            false,
-        )
-        .unwrap();
-        let mut user_store = HashMapUserStore::default();
-        assert!(user_store.add_user(user.clone()).await.is_ok());
-        assert!(user_store.delete_user(user.email()).await.is_ok());
-        assert!(user_store.get_user(user.email()).await.is_err());
+    async fn restore_user(
+        &self,
+        restore_token: &RestoreToken,
+        now: DateTime<Utc>,
+    ) -> Result<Email, UserStoreError> {
+        let hash = restore_token.hash();
+        let email = self
+            .pending_deletions
+            .iter()
+            .find(|entry| entry.restore_token_hash == hash && entry.due_at > now)
+            .map(|entry| entry.key().clone())
+            .ok_or(UserStoreError::InvalidRestoreToken)?;
+
+        let mut user = self
+            .users
+            .get_mut(&email)
+            .ok_or(UserStoreError::InvalidRestoreToken)?;
+        if user.status != UserStatus::PendingDeletion {
+            return Err(UserStoreError::InvalidRestoreToken);
+        }
+        user.status = UserStatus::Active;
+        drop(user);
+
+        self.pending_deletions.remove(&email);
+        Ok(email)
+    }
+
+    async fn due_deletions(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Email>, UserStoreError> {
+        let mut due: Vec<(DateTime<Utc>, Email)> = self
+            .pending_deletions
+            .iter()
+            .filter(|entry| entry.due_at <= now)
+            .filter(|entry| {
+                self.users
+                    .get(entry.key())
+                    .is_some_and(|user| user.status == UserStatus::PendingDeletion)
+            })
+            .map(|entry| (entry.due_at, entry.key().clone()))
+            .collect();
+        due.sort_by_key(|(due_at, _)| *due_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|(_, email)| email)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::clock::MockClock, services::data_stores::user_store_suite::user_store_tests,
+    };
+
+    async fn setup(clock: Arc<MockClock>) -> ((), HashMapUserStore) {
+        ((), HashMapUserStore::with_clock(clock))
     }
+
+    user_store_tests!(setup);
 }
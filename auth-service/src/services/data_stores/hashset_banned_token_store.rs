@@ -1,52 +1,200 @@
-use std::collections::HashSet;
+use std::{sync::Arc, time::Duration};
 
-use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
 
-#[derive(Debug, Default)]
+use crate::{
+    domain::{
+        clock::{Clock, SystemClock},
+        data_stores::{BannedTokenStore, BannedTokenStoreError},
+        email::Email,
+    },
+    settings::Settings,
+};
+
+// Clones share the same tokens, which lets the sweeper prune the store while
+// the service holds on to it.
+#[derive(Clone)]
 pub struct HashSetBannedTokenStore {
-    banned_tokens: HashSet<String>,
+    // Each token is kept until it expires, like the keys set with set_ex in Redis
+    banned_tokens: Arc<DashMap<String, DateTime<Utc>>>,
+    token_generations: Arc<DashMap<Email, TokenGeneration>>,
+    clock: Arc<dyn Clock>,
+    settings: Settings,
+}
+
+#[derive(Default)]
+struct TokenGeneration {
+    generation: u64,
+    // Set once the user is forgotten
+    expires_at: Option<DateTime<Utc>>,
 }
 
 impl HashSetBannedTokenStore {
-    pub fn new() -> Self {
+    pub fn new(settings: Settings) -> Self {
+        Self::with_clock(settings, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(settings: Settings, clock: Arc<dyn Clock>) -> Self {
         Self {
-            banned_tokens: HashSet::new(),
+            banned_tokens: Arc::default(),
+            token_generations: Arc::default(),
+            clock,
+            settings,
         }
     }
+
+    // Removes the expired tokens and the generations of forgotten users,
+    // returning how many were removed
+    pub fn remove_expired(&self) -> usize {
+        let now = self.clock.now();
+        let before = self.banned_tokens.len() + self.token_generations.len();
+        self.banned_tokens.retain(|_, expires_at| *expires_at > now);
+        self.token_generations
+            .retain(|_, generation| generation.expires_at.is_none_or(|at| at > now));
+
+        before.saturating_sub(self.banned_tokens.len() + self.token_generations.len())
+    }
+
+    fn expires_at(&self) -> DateTime<Utc> {
+        // Read per call so that a reloaded config applies
+        let ttl = self.settings.load().auth.jwt.time_to_live;
+        self.clock.now() + Duration::from_secs(u64::try_from(ttl).unwrap_or_default())
+    }
+
+    pub fn spawn_sweeper(&self, interval: Duration) -> JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                let removed = store.remove_expired();
+                if removed > 0 {
+                    tracing::info!(
+                        removed,
+                        "Removed expired banned tokens and token generations"
+                    );
+                }
+            }
+        })
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashSetBannedTokenStore {
-    async fn ban_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
-        self.banned_tokens.insert(token);
+    async fn ban_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        // Banned tokens are kept for as long as an auth token is valid
+        self.banned_tokens.insert(token, self.expires_at());
         Ok(())
     }
 
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.banned_tokens.contains(token))
+        let now = self.clock.now();
+        Ok(self
+            .banned_tokens
+            .get(token)
+            .is_some_and(|expires_at| *expires_at > now))
+    }
+
+    async fn revoke_all_tokens(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        let mut generation = self.token_generations.entry(email.clone()).or_default();
+        generation.generation += 1;
+        generation.expires_at = None;
+        Ok(())
+    }
+
+    async fn token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        Ok(self
+            .token_generations
+            .get(email)
+            .map(|generation| generation.generation)
+            .unwrap_or_default())
+    }
+
+    async fn forget_user(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        let expires_at = self.expires_at();
+        let mut generation = self.token_generations.entry(email.clone()).or_default();
+        generation.generation += 1;
+        generation.expires_at = Some(expires_at);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use secrecy::Secret;
+
     use super::*;
+    use crate::{
+        domain::clock::MockClock,
+        services::data_stores::banned_token_store_suite::banned_token_store_tests,
+        settings::{AuthServiceSetting, Config},
+    };
+
+    async fn setup() -> ((), HashSetBannedTokenStore) {
+        (
+            (),
+            HashSetBannedTokenStore::new(AuthServiceSetting::handle()),
+        )
+    }
+
+    banned_token_store_tests!(setup);
 
     #[tokio::test]
-    async fn test_ban_token() {
-        let store = HashSetBannedTokenStore::new();
-        assert!(store.contains_token("token1").await.is_ok());
+    async fn test_banned_token_expires_after_ttl() {
+        let clock = Arc::new(MockClock::default());
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.time_to_live = 60;
+        let store = HashSetBannedTokenStore::with_clock(Settings::new(config), clock.clone());
+        store.ban_token("token1".to_string()).await.unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        assert!(store.contains_token("token1").await.unwrap());
+        assert_eq!(store.remove_expired(), 0);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(!store.contains_token("token1").await.unwrap());
+        assert_eq!(store.remove_expired(), 1);
     }
 
     #[tokio::test]
-    async fn test_token_is_banned() {
-        let mut store = HashSetBannedTokenStore::new();
+    async fn test_ban_uses_the_current_token_lifetime() {
+        let clock = Arc::new(MockClock::default());
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.time_to_live = 60;
+        let settings = Settings::new(config);
+        let store = HashSetBannedTokenStore::with_clock(settings.clone(), clock.clone());
+
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.time_to_live = 120;
+        settings.store(config);
         store.ban_token("token1".to_string()).await.unwrap();
+
+        clock.advance(Duration::from_secs(60));
         assert!(store.contains_token("token1").await.unwrap());
     }
 
     #[tokio::test]
-    async fn test_token_is_not_banned() {
-        let store = HashSetBannedTokenStore::new();
-        assert!(!store.contains_token("token2").await.unwrap());
+    async fn test_forgotten_user_generation_expires_after_ttl() {
+        let clock = Arc::new(MockClock::default());
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.time_to_live = 60;
+        let store = HashSetBannedTokenStore::with_clock(Settings::new(config), clock.clone());
+        let email = Email::try_from(Secret::new("test@example.com".to_owned())).unwrap();
+        let other = Email::try_from(Secret::new("other@example.com".to_owned())).unwrap();
+        store.revoke_all_tokens(&other).await.unwrap();
+        store.forget_user(&email).await.unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(store.remove_expired(), 0);
+        assert_eq!(store.token_generation(&email).await.unwrap(), 1);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(store.remove_expired(), 1);
+        assert_eq!(store.token_generation(&email).await.unwrap(), 0);
+        assert_eq!(store.token_generation(&other).await.unwrap(), 1);
     }
 }
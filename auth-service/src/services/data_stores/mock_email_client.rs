@@ -1,6 +1,9 @@
 use color_eyre::eyre::Result;
 
-use crate::domain::{email::Email, email_client::EmailClient};
+use crate::domain::{
+    email::Email,
+    email_client::{EmailClient, EmailMessage},
+};
 
 pub struct MockEmailClient;
 
@@ -12,10 +15,10 @@ impl Default for MockEmailClient {
 
 #[async_trait::async_trait]
 impl EmailClient for MockEmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         println!(
             "recipient: {:?}\nsubject: {}\ncontent: {}",
-            recipient, subject, content
+            recipient, message.subject, message.text_body
         );
         Ok(())
     }
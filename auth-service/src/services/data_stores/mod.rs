@@ -1,25 +1,47 @@
 #[cfg(test)]
-pub mod hashmap_two_fa_code_store;
+pub mod banned_token_store_suite;
 #[cfg(test)]
 pub mod hashmap_user_store;
 #[cfg(test)]
-pub mod hashset_banned_token_store;
-#[cfg(test)]
 pub mod mock_email_client;
+#[cfg(test)]
+pub mod two_fa_code_store_suite;
+#[cfg(test)]
+pub mod user_store_suite;
+#[cfg(test)]
+pub mod vec_audit_log;
+#[cfg(test)]
+pub mod vec_email_outbox;
 
+pub mod hashmap_two_fa_code_store;
+pub mod hashset_banned_token_store;
+pub mod postgres_audit_log;
+pub mod postgres_banned_token_store;
+pub mod postgres_email_outbox;
+pub mod postgres_two_fa_code_store;
 pub mod postgres_user_store;
 pub mod redis_banned_token_store;
 pub mod redis_two_fa_code_store;
+pub mod sqlite_audit_log;
+pub mod sqlite_user_store;
 
-#[cfg(test)]
-pub use hashmap_two_fa_code_store::HashMapTwoFaCodeStore;
 #[cfg(test)]
 pub use hashmap_user_store::HashMapUserStore;
 #[cfg(test)]
-pub use hashset_banned_token_store::HashSetBannedTokenStore;
-#[cfg(test)]
 pub use mock_email_client::MockEmailClient;
+#[cfg(test)]
+pub use vec_audit_log::VecAuditLog;
+#[cfg(test)]
+pub use vec_email_outbox::VecEmailOutbox;
 
+pub use hashmap_two_fa_code_store::HashMapTwoFaCodeStore;
+pub use hashset_banned_token_store::HashSetBannedTokenStore;
+pub use postgres_audit_log::PostgresAuditLog;
+pub use postgres_banned_token_store::PostgresBannedTokenStore;
+pub use postgres_email_outbox::PostgresEmailOutbox;
+pub use postgres_two_fa_code_store::PostgresTwoFaCodeStore;
 pub use postgres_user_store::PostgresUserStore;
 pub use redis_banned_token_store::RedisBannedTokenStore;
 pub use redis_two_fa_code_store::RedisTwoFaCodeStore;
+pub use sqlite_audit_log::SqliteAuditLog;
+pub use sqlite_user_store::SqliteUserStore;
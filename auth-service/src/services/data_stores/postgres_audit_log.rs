@@ -0,0 +1,258 @@
+use color_eyre::eyre::eyre;
+use sqlx::{Pool, Postgres};
+
+use crate::domain::data_stores::{
+    AuditEvent, AuditLog, AuditLogError, AuditRecord, ERASED_SUBJECT,
+};
+
+pub struct PostgresAuditLog {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresAuditLog {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        PostgresAuditLog { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for PostgresAuditLog {
+    #[tracing::instrument(name = "Recording audit event in PostgreSQL", skip_all)]
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO audit_log (actor, event_type, outcome, target, ip, user_agent, details)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            event.actor,
+            event.event_type.as_str(),
+            event.outcome.as_str(),
+            event.target,
+            event.ip,
+            event.user_agent,
+            event.details
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving audit events from PostgreSQL", skip_all)]
+    async fn recent_events(
+        &self,
+        subject: &str,
+        limit: u32,
+    ) -> Result<Vec<AuditRecord>, AuditLogError> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT actor, event_type, outcome, target, ip, user_agent, details, created_at
+                FROM audit_log
+                WHERE actor = $1 OR target = $1
+                ORDER BY created_at DESC, id DESC
+                LIMIT $2
+            "#,
+            subject,
+            i64::from(limit)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(eyre!(e)))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AuditRecord {
+                    event: AuditEvent {
+                        actor: row.actor,
+                        event_type: row.event_type.parse()?,
+                        outcome: row.outcome.parse()?,
+                        target: row.target,
+                        ip: row.ip,
+                        user_agent: row.user_agent,
+                        details: row.details,
+                    },
+                    occurred_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Erasing audit subject in PostgreSQL", skip_all)]
+    async fn erase_subject(&self, subject: &str) -> Result<u64, AuditLogError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE audit_log
+                SET actor = CASE WHEN actor = $1 THEN $2 ELSE actor END,
+                    target = CASE WHEN target = $1 THEN $2 ELSE target END,
+                    ip = CASE WHEN actor = $1 THEN NULL ELSE ip END,
+                    user_agent = CASE WHEN actor = $1 THEN NULL ELSE user_agent END
+                WHERE actor = $1 OR target = $1
+            "#,
+            subject,
+            ERASED_SUBJECT
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(eyre!(e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        auth_service::get_postgres_pool,
+        domain::data_stores::{AuditEventType, AuditOutcome},
+    };
+    use sqlx::PgPool;
+    use testcontainers_modules::{
+        postgres,
+        testcontainers::{ContainerAsync, runners::AsyncRunner},
+    };
+
+    async fn setup_and_connect_db_container() -> (ContainerAsync<postgres::Postgres>, PgPool) {
+        let container = postgres::Postgres::default()
+            .start()
+            .await
+            .expect("Failed to start container");
+
+        let db_port = container
+            .get_host_port_ipv4(5432)
+            .await
+            .expect("Failed to get the mapped port of the container");
+
+        let host = container
+            .get_host()
+            .await
+            .expect("Failed to get the container host address");
+
+        let db_url = format!("postgres://postgres:postgres@{}:{}", host, db_port);
+
+        let connection = get_postgres_pool(&db_url)
+            .await
+            .expect("Failed to connect to database");
+
+        sqlx::migrate!()
+            .run(&connection)
+            .await
+            .expect("Failed to migrate the database");
+
+        (container, connection)
+    }
+
+    #[tokio::test]
+    async fn test_record_and_read_recent_events() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let audit_log = PostgresAuditLog::new(pool);
+
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::Login)
+                    .actor("user@example.com")
+                    .outcome(AuditOutcome::Failure)
+                    .ip(Some("10.0.0.1".to_owned()))
+                    .user_agent(Some("curl/8.0".to_owned())),
+            )
+            .await
+            .unwrap();
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::AdminViewUser)
+                    .actor("admin@example.com")
+                    .target("user@example.com"),
+            )
+            .await
+            .unwrap();
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Login).actor("other@example.com"))
+            .await
+            .unwrap();
+
+        let events = audit_log
+            .recent_events("user@example.com", 10)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.event_type, AuditEventType::AdminViewUser);
+        assert_eq!(events[1].event.outcome, AuditOutcome::Failure);
+        assert_eq!(events[1].event.ip.as_deref(), Some("10.0.0.1"));
+
+        let events = audit_log
+            .recent_events("user@example.com", 1)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_is_append_only() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let audit_log = PostgresAuditLog::new(pool.clone());
+
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Logout).actor("user@example.com"))
+            .await
+            .unwrap();
+
+        let result = sqlx::query("DELETE FROM audit_log").execute(&pool).await;
+        assert!(result.is_err());
+
+        let result = sqlx::query("UPDATE audit_log SET actor = 'someone@example.com'")
+            .execute(&pool)
+            .await;
+        assert!(result.is_err());
+
+        let result = sqlx::query("UPDATE audit_log SET details = 'changed', ip = NULL")
+            .execute(&pool)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_erase_subject() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let audit_log = PostgresAuditLog::new(pool);
+
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::Login)
+                    .actor("user@example.com")
+                    .ip(Some("10.0.0.1".to_owned()))
+                    .user_agent(Some("curl/8.0".to_owned())),
+            )
+            .await
+            .unwrap();
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::AdminViewUser)
+                    .actor("admin@example.com")
+                    .target("user@example.com")
+                    .ip(Some("10.0.0.2".to_owned())),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            audit_log.erase_subject("user@example.com").await.unwrap(),
+            2
+        );
+        assert!(
+            audit_log
+                .recent_events("user@example.com", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let events = audit_log.recent_events(ERASED_SUBJECT, 10).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.actor.as_deref(), Some("admin@example.com"));
+        assert_eq!(events[0].event.ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(events[1].event.actor.as_deref(), Some(ERASED_SUBJECT));
+        assert_eq!(events[1].event.ip, None);
+        assert_eq!(events[1].event.user_agent, None);
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    domain::{
+        clock::{Clock, SystemClock},
+        data_stores::{BannedTokenStore, BannedTokenStoreError},
+        email::Email,
+    },
+    settings::Settings,
+};
+
+pub struct PostgresBannedTokenStore {
+    pool: sqlx::PgPool,
+    settings: Settings,
+    clock: Arc<dyn Clock>,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: Pool<Postgres>, settings: Settings) -> Self {
+        Self::with_clock(pool, settings, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(pool: Pool<Postgres>, settings: Settings, clock: Arc<dyn Clock>) -> Self {
+        PostgresBannedTokenStore {
+            pool,
+            settings,
+            clock,
+        }
+    }
+
+    // Banned tokens are only kept until the token itself would have expired,
+    // and so are the generations of forgotten users
+    #[tracing::instrument(name = "Deleting expired banned tokens from PostgreSQL", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64, BannedTokenStoreError> {
+        let now = self.clock.now();
+        let tokens = sqlx::query!("DELETE FROM banned_tokens WHERE expires_at <= $1", now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))?;
+        let generations = sqlx::query!("DELETE FROM token_generations WHERE expires_at <= $1", now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))?;
+
+        Ok(tokens.rows_affected() + generations.rows_affected())
+    }
+
+    fn expires_at(&self) -> DateTime<Utc> {
+        let ttl = self.settings.load().auth.jwt.time_to_live;
+        self.clock.now() + Duration::seconds(ttl)
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Banning token in PostgreSQL", skip_all)]
+    async fn ban_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        let expires_at = self.expires_at();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO banned_tokens (token, expires_at)
+                VALUES ($1, $2)
+                ON CONFLICT (token) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            "#,
+            token,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
+        sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM banned_tokens WHERE token = $1 AND expires_at > $2
+                ) AS "banned!"
+            "#,
+            token,
+            self.clock.now()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))
+    }
+
+    #[tracing::instrument(name = "Revoking all tokens in PostgreSQL", skip_all)]
+    async fn revoke_all_tokens(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO token_generations (email, generation)
+                VALUES ($1, 1)
+                ON CONFLICT (email) DO UPDATE
+                SET generation = token_generations.generation + 1, expires_at = NULL
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving token generation from PostgreSQL", skip_all)]
+    async fn token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        let generation = sqlx::query_scalar!(
+            "SELECT generation FROM token_generations WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))?;
+
+        Ok(generation.map(|g| g as u64).unwrap_or_default())
+    }
+
+    #[tracing::instrument(name = "Forgetting user in PostgreSQL", skip_all)]
+    async fn forget_user(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO token_generations (email, generation, expires_at)
+                VALUES ($1, 1, $2)
+                ON CONFLICT (email) DO UPDATE
+                SET generation = token_generations.generation + 1, expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref().expose_secret(),
+            self.expires_at()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::auth_service::get_postgres_pool;
+    use crate::{
+        domain::clock::MockClock,
+        services::data_stores::banned_token_store_suite::banned_token_store_tests,
+        settings::AuthServiceSetting,
+    };
+    use sqlx::PgPool;
+    use testcontainers_modules::{
+        postgres,
+        testcontainers::{ContainerAsync, runners::AsyncRunner},
+    };
+
+    async fn setup_and_connect_db_container() -> (ContainerAsync<postgres::Postgres>, PgPool) {
+        let container = postgres::Postgres::default()
+            .start()
+            .await
+            .expect("Failed to start container");
+
+        let db_port = container
+            .get_host_port_ipv4(5432)
+            .await
+            .expect("Failed to get the mapped port of the container");
+
+        let host = container
+            .get_host()
+            .await
+            .expect("Failed to get the container host address");
+
+        let db_url = format!("postgres://postgres:postgres@{}:{}", host, db_port);
+
+        let connection = get_postgres_pool(&db_url)
+            .await
+            .expect("Failed to connect to database");
+
+        sqlx::migrate!()
+            .run(&connection)
+            .await
+            .expect("Failed to migrate the database");
+
+        (container, connection)
+    }
+
+    async fn setup() -> (ContainerAsync<postgres::Postgres>, PostgresBannedTokenStore) {
+        let (container, pool) = setup_and_connect_db_container().await;
+        (
+            container,
+            PostgresBannedTokenStore::new(pool, AuthServiceSetting::handle()),
+        )
+    }
+
+    banned_token_store_tests!(setup);
+
+    #[tokio::test]
+    async fn test_expired_ban_is_ignored_and_deleted() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let clock = Arc::new(MockClock::default());
+        let store =
+            PostgresBannedTokenStore::with_clock(pool, AuthServiceSetting::handle(), clock.clone());
+        let ttl = AuthServiceSetting::load().auth.jwt.time_to_live as u64;
+
+        store.ban_token("expired".to_owned()).await.unwrap();
+        clock.advance(std::time::Duration::from_secs(ttl / 2));
+        store.ban_token("active".to_owned()).await.unwrap();
+        clock.advance(std::time::Duration::from_secs(ttl / 2 + 1));
+
+        assert!(!store.contains_token("expired").await.unwrap());
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+        assert!(store.contains_token("active").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_forgotten_user_generation_is_deleted_once_expired() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let clock = Arc::new(MockClock::default());
+        let store =
+            PostgresBannedTokenStore::with_clock(pool, AuthServiceSetting::handle(), clock.clone());
+        let ttl = AuthServiceSetting::load().auth.jwt.time_to_live as u64;
+        let email = Email::try_from(Secret::new("test@example.com".to_owned())).unwrap();
+        let other = Email::try_from(Secret::new("other@example.com".to_owned())).unwrap();
+        store.revoke_all_tokens(&other).await.unwrap();
+        store.forget_user(&email).await.unwrap();
+
+        clock.advance(std::time::Duration::from_secs(ttl - 1));
+        assert_eq!(store.delete_expired().await.unwrap(), 0);
+        assert_eq!(store.token_generation(&email).await.unwrap(), 1);
+
+        clock.advance(std::time::Duration::from_secs(1));
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+        assert_eq!(store.token_generation(&email).await.unwrap(), 0);
+        assert_eq!(store.token_generation(&other).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,465 @@
+use std::time::Duration;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::domain::{
+    data_stores::{EmailOutbox, EmailOutboxError, EmailOutboxStats, QueuedEmail},
+    email::Email,
+    email_client::{EmailAttachment, EmailMessage},
+};
+
+// How an attachment is kept in the attachments column
+#[derive(Serialize, Deserialize)]
+struct StoredAttachment {
+    filename: String,
+    content_type: String,
+    content: String,
+}
+
+fn store_attachments(attachments: &[EmailAttachment]) -> Result<String, EmailOutboxError> {
+    let stored: Vec<_> = attachments
+        .iter()
+        .map(|attachment| StoredAttachment {
+            filename: attachment.filename.clone(),
+            content_type: attachment.content_type.clone(),
+            content: STANDARD.encode(&attachment.content),
+        })
+        .collect();
+    serde_json::to_string(&stored).map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))
+}
+
+fn load_attachments(stored: &str) -> Result<Vec<EmailAttachment>, EmailOutboxError> {
+    let stored: Vec<StoredAttachment> =
+        serde_json::from_str(stored).map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+    stored
+        .into_iter()
+        .map(|attachment| {
+            Ok(EmailAttachment {
+                filename: attachment.filename,
+                content_type: attachment.content_type,
+                content: STANDARD
+                    .decode(attachment.content)
+                    .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?,
+            })
+        })
+        .collect()
+}
+
+pub struct PostgresEmailOutbox {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresEmailOutbox {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        PostgresEmailOutbox { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for PostgresEmailOutbox {
+    #[tracing::instrument(name = "Queueing email in PostgreSQL", skip_all)]
+    async fn enqueue(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailOutboxError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO email_outbox
+                    (recipient, subject, html_body, text_body, attachments, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            recipient.as_ref().expose_secret(),
+            message.subject,
+            message.html_body,
+            message.text_body,
+            store_attachments(&message.attachments)?,
+            message.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming queued emails from PostgreSQL", skip_all)]
+    async fn claim_due(
+        &self,
+        limit: u32,
+        lease: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<QueuedEmail>, EmailOutboxError> {
+        // SKIP LOCKED lets several workers claim from the table at once
+        // without handing out the same email twice.
+        let rows = sqlx::query!(
+            r#"
+                UPDATE email_outbox
+                SET attempts = attempts + 1,
+                    next_attempt_at = $3 + make_interval(secs => $2)
+                WHERE id IN (
+                    SELECT id FROM email_outbox
+                    WHERE status = 'pending' AND next_attempt_at <= $3
+                    ORDER BY next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, recipient, subject, html_body, text_body, attachments,
+                    expires_at, attempts
+            "#,
+            i64::from(limit),
+            lease.as_secs_f64(),
+            now
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let recipient = Email::try_from(Secret::new(row.recipient))
+                    .map_err(|e| EmailOutboxError::InvalidEmail(e.to_string()))?;
+
+                Ok(QueuedEmail {
+                    id: row.id,
+                    recipient,
+                    message: EmailMessage {
+                        subject: row.subject,
+                        html_body: row.html_body,
+                        text_body: row.text_body,
+                        attachments: load_attachments(&row.attachments)?,
+                        expires_at: row.expires_at,
+                    },
+                    attempts: u32::try_from(row.attempts).unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Removing sent email from PostgreSQL", skip_all)]
+    async fn mark_sent(&self, id: i64) -> Result<(), EmailOutboxError> {
+        sqlx::query!("DELETE FROM email_outbox WHERE id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Rescheduling queued email in PostgreSQL", skip_all)]
+    async fn reschedule(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), EmailOutboxError> {
+        sqlx::query!(
+            r#"
+                UPDATE email_outbox
+                SET next_attempt_at = $2, last_error = $3
+                WHERE id = $1
+            "#,
+            id,
+            retry_at,
+            error
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Dead-lettering queued email in PostgreSQL", skip_all)]
+    async fn dead_letter(&self, id: i64, error: &str) -> Result<(), EmailOutboxError> {
+        sqlx::query!(
+            r#"
+                UPDATE email_outbox
+                SET status = 'dead', last_error = $2,
+                    html_body = '', text_body = '', attachments = '[]'
+                WHERE id = $1
+            "#,
+            id,
+            error
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Counting queued emails in PostgreSQL", skip_all)]
+    async fn stats(&self) -> Result<EmailOutboxStats, EmailOutboxError> {
+        let row = sqlx::query!(
+            r#"
+                SELECT
+                    COUNT(*) FILTER (WHERE status = 'pending') AS "pending!",
+                    COUNT(*) FILTER (WHERE status = 'dead') AS "dead!"
+                FROM email_outbox
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+
+        Ok(EmailOutboxStats {
+            pending: u64::try_from(row.pending).unwrap_or_default(),
+            dead: u64::try_from(row.dead).unwrap_or_default(),
+        })
+    }
+
+    #[tracing::instrument(name = "Deleting a recipient's emails from PostgreSQL", skip_all)]
+    async fn forget_recipient(&self, recipient: &Email) -> Result<u64, EmailOutboxError> {
+        let result = sqlx::query!(
+            "DELETE FROM email_outbox WHERE recipient = $1",
+            recipient.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_service::get_postgres_pool;
+    use chrono::SubsecRound;
+    use sqlx::PgPool;
+    use testcontainers_modules::{
+        postgres,
+        testcontainers::{ContainerAsync, runners::AsyncRunner},
+    };
+
+    async fn setup_and_connect_db_container() -> (ContainerAsync<postgres::Postgres>, PgPool) {
+        let container = postgres::Postgres::default()
+            .start()
+            .await
+            .expect("Failed to start container");
+
+        let db_port = container
+            .get_host_port_ipv4(5432)
+            .await
+            .expect("Failed to get the mapped port of the container");
+
+        let host = container
+            .get_host()
+            .await
+            .expect("Failed to get the container host address");
+
+        let db_url = format!("postgres://postgres:postgres@{}:{}", host, db_port);
+
+        let connection = get_postgres_pool(&db_url)
+            .await
+            .expect("Failed to connect to database");
+
+        sqlx::migrate!()
+            .run(&connection)
+            .await
+            .expect("Failed to migrate the database");
+
+        (container, connection)
+    }
+
+    fn message(subject: &str) -> EmailMessage {
+        EmailMessage {
+            subject: subject.to_owned(),
+            html_body: "<p>body</p>".to_owned(),
+            text_body: "body".to_owned(),
+            attachments: Vec::new(),
+            expires_at: None,
+        }
+    }
+
+    fn recipient() -> Email {
+        Email::try_from(Secret::new("user@example.com".to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_attachments_and_expiry_survive_the_queue() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let outbox = PostgresEmailOutbox::new(pool);
+        // Whole seconds, Postgres keeps timestamps to the microsecond
+        let expires_at = Utc::now().trunc_subsecs(0) + chrono::Duration::minutes(10);
+        let message = message("export")
+            .with_attachment(EmailAttachment {
+                filename: "export.json".to_owned(),
+                content_type: "application/json".to_owned(),
+                content: vec![0, 159, 146, 150],
+            })
+            .expiring_at(expires_at);
+
+        outbox.enqueue(&recipient(), &message).await.unwrap();
+
+        let claimed = outbox
+            .claim_due(10, Duration::from_secs(60), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(claimed[0].message, message);
+    }
+
+    #[tokio::test]
+    async fn test_claimed_emails_are_leased_until_rescheduled() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let outbox = PostgresEmailOutbox::new(pool);
+
+        outbox
+            .enqueue(&recipient(), &message("first"))
+            .await
+            .unwrap();
+        outbox
+            .enqueue(&recipient(), &message("second"))
+            .await
+            .unwrap();
+
+        let claimed = outbox
+            .claim_due(10, Duration::from_secs(60), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 2);
+        assert!(claimed.iter().all(|email| email.attempts == 1));
+        assert_eq!(claimed[0].recipient, recipient());
+
+        // Leased emails are not handed out again
+        assert!(
+            outbox
+                .claim_due(10, Duration::from_secs(60), Utc::now())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        outbox
+            .reschedule(claimed[0].id, "timed out", Utc::now())
+            .await
+            .unwrap();
+        let retried = outbox
+            .claim_due(10, Duration::from_secs(60), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].message.subject, claimed[0].message.subject);
+        assert_eq!(retried[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_sent_and_dead_emails_are_not_claimed() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let outbox = PostgresEmailOutbox::new(pool);
+
+        outbox
+            .enqueue(&recipient(), &message("sent"))
+            .await
+            .unwrap();
+        outbox
+            .enqueue(&recipient(), &message("dead"))
+            .await
+            .unwrap();
+        outbox
+            .enqueue(&recipient(), &message("pending"))
+            .await
+            .unwrap();
+
+        let claimed = outbox
+            .claim_due(2, Duration::ZERO, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 2);
+        outbox.mark_sent(claimed[0].id).await.unwrap();
+        outbox
+            .dead_letter(claimed[1].id, "mailbox does not exist")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            outbox.stats().await.unwrap(),
+            EmailOutboxStats {
+                pending: 1,
+                dead: 1
+            }
+        );
+
+        let claimed = outbox
+            .claim_due(10, Duration::ZERO, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].message.subject, "pending");
+    }
+    #[tokio::test]
+    async fn test_dead_emails_keep_no_content() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let outbox = PostgresEmailOutbox::new(pool.clone());
+        let message = message("restore").with_attachment(EmailAttachment {
+            filename: "export.json".to_owned(),
+            content_type: "application/json".to_owned(),
+            content: vec![1, 2, 3],
+        });
+
+        outbox.enqueue(&recipient(), &message).await.unwrap();
+        let claimed = outbox
+            .claim_due(1, Duration::ZERO, Utc::now())
+            .await
+            .unwrap();
+        outbox
+            .dead_letter(claimed[0].id, "mailbox does not exist")
+            .await
+            .unwrap();
+
+        let (subject, html_body, text_body, attachments, last_error): (
+            String,
+            String,
+            String,
+            String,
+            Option<String>,
+        ) = sqlx::query_as(
+            "SELECT subject, html_body, text_body, attachments, last_error FROM email_outbox",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(subject, "restore");
+        assert_eq!(html_body, "");
+        assert_eq!(text_body, "");
+        assert_eq!(attachments, "[]");
+        assert_eq!(last_error.as_deref(), Some("mailbox does not exist"));
+    }
+
+    #[tokio::test]
+    async fn test_forget_recipient_deletes_pending_and_dead_emails() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let outbox = PostgresEmailOutbox::new(pool);
+        let other = Email::try_from(Secret::new("other@example.com".to_owned())).unwrap();
+
+        for (to, subject) in [
+            (recipient(), "dead"),
+            (recipient(), "pending"),
+            (other, "other"),
+        ] {
+            outbox.enqueue(&to, &message(subject)).await.unwrap();
+        }
+        let claimed = outbox
+            .claim_due(1, Duration::ZERO, Utc::now())
+            .await
+            .unwrap();
+        outbox.dead_letter(claimed[0].id, "bounced").await.unwrap();
+
+        assert_eq!(outbox.forget_recipient(&recipient()).await.unwrap(), 2);
+
+        let remaining = outbox
+            .claim_due(10, Duration::ZERO, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].message.subject, "other");
+        assert_eq!(outbox.stats().await.unwrap().dead, 0);
+    }
+}
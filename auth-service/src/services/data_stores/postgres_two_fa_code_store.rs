@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
+use sqlx::{Pool, Postgres};
+
+use crate::domain::{
+    clock::{Clock, SystemClock},
+    data_stores::{TWO_FA_CODE_TTL, TwoFaCodeStore, TwoFaCodeStoreError},
+    email::Email,
+    two_fa_attempt_id::TwoFaAttemptId,
+    two_fa_code::TwoFaCode,
+};
+
+pub struct PostgresTwoFaCodeStore {
+    pool: sqlx::PgPool,
+    clock: Arc<dyn Clock>,
+}
+
+impl PostgresTwoFaCodeStore {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self::with_clock(pool, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(pool: Pool<Postgres>, clock: Arc<dyn Clock>) -> Self {
+        PostgresTwoFaCodeStore { pool, clock }
+    }
+
+    // Expired codes are never returned, this only reclaims their rows
+    #[tracing::instrument(name = "Deleting expired 2FA codes from PostgreSQL", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64, TwoFaCodeStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM two_fa_codes WHERE expires_at <= $1",
+            self.clock.now()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFaCodeStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFaCodeStore for PostgresTwoFaCodeStore {
+    #[tracing::instrument(name = "Storing 2FA code in PostgreSQL", skip_all)]
+    async fn store_code(
+        &self,
+        user_id: Email,
+        login_attempt_id: TwoFaAttemptId,
+        two_fa_code: TwoFaCode,
+    ) -> Result<(), TwoFaCodeStoreError> {
+        let expires_at = self.clock.now() + TWO_FA_CODE_TTL;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (email) DO UPDATE
+                SET login_attempt_id = EXCLUDED.login_attempt_id,
+                    code = EXCLUDED.code,
+                    expires_at = EXCLUDED.expires_at
+            "#,
+            user_id.as_ref().expose_secret(),
+            login_attempt_id.to_string(),
+            two_fa_code.as_str(),
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFaCodeStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    async fn validate(
+        &self,
+        user_id: &Email,
+        login_attempt_id: &TwoFaAttemptId,
+        two_fa_code: &TwoFaCode,
+    ) -> Result<(), TwoFaCodeStoreError> {
+        let (stored_login_attempt_id, stored_two_fa_code) =
+            self.get_login_attempt_id_and_two_fa_code(user_id).await?;
+
+        if stored_login_attempt_id != *login_attempt_id {
+            return Err(TwoFaCodeStoreError::InvalidAttemptId);
+        }
+        if stored_two_fa_code != *two_fa_code {
+            return Err(TwoFaCodeStoreError::Invalid2FACode);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from PostgreSQL", skip_all)]
+    async fn get_login_attempt_id_and_two_fa_code(
+        &self,
+        user_id: &Email,
+    ) -> Result<(TwoFaAttemptId, TwoFaCode), TwoFaCodeStoreError> {
+        let row = sqlx::query!(
+            r#"
+                SELECT login_attempt_id, code
+                FROM two_fa_codes
+                WHERE email = $1 AND expires_at > $2
+            "#,
+            user_id.as_ref().expose_secret(),
+            self.clock.now()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFaCodeStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(TwoFaCodeStoreError::UserNotFound)?;
+
+        let login_attempt_id = TwoFaAttemptId::parse(&row.login_attempt_id)
+            .map_err(|e| TwoFaCodeStoreError::UnexpectedError(eyre!(e)))?;
+        let two_fa_code = TwoFaCode::parse(row.code)
+            .map_err(|e| TwoFaCodeStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok((login_attempt_id, two_fa_code))
+    }
+
+    #[tracing::instrument(name = "Deleting 2FA code from PostgreSQL", skip_all)]
+    async fn delete(&self, user_id: &Email) -> Result<(), TwoFaCodeStoreError> {
+        sqlx::query!(
+            "DELETE FROM two_fa_codes WHERE email = $1",
+            user_id.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFaCodeStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_service::get_postgres_pool;
+    use crate::{
+        domain::clock::MockClock,
+        services::data_stores::two_fa_code_store_suite::two_fa_code_store_tests,
+    };
+    use secrecy::Secret;
+    use sqlx::PgPool;
+    use testcontainers_modules::{
+        postgres,
+        testcontainers::{ContainerAsync, runners::AsyncRunner},
+    };
+
+    async fn setup_and_connect_db_container() -> (ContainerAsync<postgres::Postgres>, PgPool) {
+        let container = postgres::Postgres::default()
+            .start()
+            .await
+            .expect("Failed to start container");
+
+        let db_port = container
+            .get_host_port_ipv4(5432)
+            .await
+            .expect("Failed to get the mapped port of the container");
+
+        let host = container
+            .get_host()
+            .await
+            .expect("Failed to get the container host address");
+
+        let db_url = format!("postgres://postgres:postgres@{}:{}", host, db_port);
+
+        let connection = get_postgres_pool(&db_url)
+            .await
+            .expect("Failed to connect to database");
+
+        sqlx::migrate!()
+            .run(&connection)
+            .await
+            .expect("Failed to migrate the database");
+
+        (container, connection)
+    }
+
+    async fn setup() -> (ContainerAsync<postgres::Postgres>, PostgresTwoFaCodeStore) {
+        let (container, pool) = setup_and_connect_db_container().await;
+        (container, PostgresTwoFaCodeStore::new(pool))
+    }
+
+    two_fa_code_store_tests!(setup);
+
+    #[tokio::test]
+    async fn test_expired_code_is_not_found() {
+        let (_container, pool) = setup_and_connect_db_container().await;
+        let clock = Arc::new(MockClock::default());
+        let store = PostgresTwoFaCodeStore::with_clock(pool, clock.clone());
+
+        let email = Email::try_from(Secret::new("test@example.com".to_string())).unwrap();
+        let attempt_id = TwoFaAttemptId::new();
+        let code = TwoFaCode::new();
+        store
+            .store_code(email.clone(), attempt_id.clone(), code.clone())
+            .await
+            .unwrap();
+
+        clock.advance(TWO_FA_CODE_TTL);
+
+        assert_eq!(
+            store.validate(&email, &attempt_id, &code).await,
+            Err(TwoFaCodeStoreError::UserNotFound)
+        );
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+    }
+}
@@ -1,73 +1,255 @@
-use argon2::{
-    Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version,
-    password_hash::{PasswordHasher, SaltString, rand_core},
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
 };
-use color_eyre::eyre::{Result, eyre};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use dashmap::{DashMap, Entry};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{Pool, Postgres};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
 
 use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
+    clock::{Clock, SystemClock},
+    data_stores::{
+        MAX_PHONE_VERIFICATION_ATTEMPTS, PHONE_VERIFICATION_TTL_SECONDS, UserPage, UserQuery,
+        UserStore, UserStoreError,
+    },
+    device_fingerprint::{DeviceFingerprint, KnownDevice},
     email::Email,
     password::Password,
-    user::{User, ValidatedUser},
+    phone_number::PhoneNumber,
+    restore_token::RestoreToken,
+    role::Role,
+    two_fa_channel::TwoFaChannel,
+    two_fa_code::TwoFaCode,
+    user::{ImportedUser, User, UserProfile, ValidatedUser},
+    user_status::UserStatus,
 };
+use crate::utils::password_hash::{compute_password_hash, needs_rehash, verify_password_hash};
+
+// Statuses are looked up on every token verification, so keep them around
+// briefly. Writes through this store invalidate the entry immediately, and a
+// read that overlapped a write doesn't cache what it saw. The cache is per
+// process: with several instances, a status changed through another one takes
+// effect here once the entry expires, up to the TTL later.
+const STATUS_CACHE_TTL: Duration = Duration::from_secs(30);
 
 pub struct PostgresUserStore {
     pool: sqlx::PgPool,
+    status_cache: Arc<DashMap<Email, (UserStatus, DateTime<Utc>)>>,
+    // Bumped by every status write, see `cache_status`
+    status_writes: AtomicU64,
+    clock: Arc<dyn Clock>,
 }
 
 impl PostgresUserStore {
     pub fn new(pool: Pool<Postgres>) -> Self {
-        PostgresUserStore { pool }
+        Self::with_clock(pool, Arc::new(SystemClock))
     }
-}
 
-#[async_trait::async_trait]
-impl UserStore for PostgresUserStore {
-    #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let password = user.password().clone();
-        let password_hash = compute_password_hash(password)
+    pub fn with_clock(pool: Pool<Postgres>, clock: Arc<dyn Clock>) -> Self {
+        PostgresUserStore {
+            pool,
+            status_cache: Arc::default(),
+            status_writes: AtomicU64::new(0),
+            clock,
+        }
+    }
+
+    // Sweeps the status cache once per TTL so it only holds recently seen users
+    pub fn spawn_sweeper(&self) -> JoinHandle<()> {
+        let status_cache = self.status_cache.clone();
+        let clock = self.clock.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(STATUS_CACHE_TTL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                let removed = remove_expired_statuses(&status_cache, clock.now());
+                if removed > 0 {
+                    tracing::debug!(removed, "Removed expired user statuses");
+                }
+            }
+        })
+    }
+
+    fn cached_status(&self, email: &Email) -> Option<UserStatus> {
+        self.status_cache
+            .get(email)
+            .filter(|entry| self.clock.now() < entry.1 + STATUS_CACHE_TTL)
+            .map(|entry| entry.0)
+    }
+
+    // Caches a status read from the database, unless a status was written
+    // since `writes_seen` was taken before the read. The read may then hold the
+    // old status, and caching it would undo the write's invalidation.
+    fn cache_status(&self, email: &Email, status: UserStatus, writes_seen: u64) {
+        // The entry's lock orders this against `forget_status`
+        let entry = self.status_cache.entry(email.clone());
+        if self.status_writes.load(Ordering::SeqCst) == writes_seen {
+            entry.insert((status, self.clock.now()));
+        }
+    }
+
+    // Called after every write that may change a user's status
+    fn forget_status(&self, email: &Email) {
+        let entry = self.status_cache.entry(email.clone());
+        self.status_writes.fetch_add(1, Ordering::SeqCst);
+        if let Entry::Occupied(entry) = entry {
+            entry.remove();
+        }
+    }
+
+    async fn insert_user(
+        &self,
+        email: &Email,
+        password_hash: &Secret<String>,
+        requires_2fa: bool,
+        status: UserStatus,
+        password_reset_required: bool,
+        roles: &[Role],
+    ) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
             .await
-            .map_err(|e| UserStoreError::UnexpectedError(e))?;
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
 
         let query = sqlx::query!(
             r#"
-                INSERT INTO users (email, password_hash, requires_2fa)
-                VALUES ($1, $2, $3)
+                INSERT INTO users (email, password_hash, requires_2fa, status, password_reset_required)
+                VALUES ($1, $2, $3, $4, $5)
             "#,
-            user.email().as_ref().expose_secret(),
+            email.as_ref().expose_secret(),
             password_hash.expose_secret(),
-            user.requires_2fa()
+            requires_2fa,
+            status.as_str(),
+            password_reset_required
         );
 
-        query.execute(&self.pool).await.map_err(|e| {
-            if let Some(db_err) = e.as_database_error() {
-                if db_err.constraint().is_some() {
-                    return UserStoreError::UserAlreadyExists;
-                }
+        query.execute(&mut *transaction).await.map_err(|e| {
+            if let Some(db_err) = e.as_database_error()
+                && db_err.constraint().is_some()
+            {
+                return UserStoreError::UserAlreadyExists;
             }
             UserStoreError::UnexpectedError(eyre!(e))
         })?;
 
+        let roles = std::iter::once(Role::USER).chain(roles.iter().map(Role::as_str));
+        for role in roles {
+            sqlx::query!(
+                r#"
+                    INSERT INTO user_roles (email, role)
+                    VALUES ($1, $2)
+                    ON CONFLICT DO NOTHING
+                "#,
+                email.as_ref().expose_secret(),
+                role
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| {
+                if let Some(db_err) = e.as_database_error()
+                    && db_err.constraint() == Some("user_roles_role_fkey")
+                {
+                    return UserStoreError::RoleNotFound;
+                }
+                UserStoreError::UnexpectedError(eyre!(e))
+            })?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
         Ok(())
     }
 
+    // Replaces an imported or outdated hash now that the password is known.
+    // A failure only means the old hash is kept until the next login.
+    #[tracing::instrument(name = "Rehashing password in PostgreSQL", skip_all)]
+    async fn rehash_password(&self, email: &Email, old_hash: &Secret<String>, password: &Password) {
+        let new_hash = match compute_password_hash(password.clone()).await {
+            Ok(new_hash) => new_hash,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to rehash password");
+                return;
+            }
+        };
+
+        // Matching the old hash keeps a password changed in the meantime
+        let query = sqlx::query!(
+            r#"
+                UPDATE users
+                SET password_hash = $1
+                WHERE email = $2 AND password_hash = $3
+            "#,
+            new_hash.expose_secret(),
+            email.as_ref().expose_secret(),
+            old_hash.expose_secret()
+        );
+
+        if let Err(e) = query.execute(&self.pool).await {
+            tracing::error!(error = ?e, "Failed to store rehashed password");
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for PostgresUserStore {
+    #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let password = user.password().clone();
+        let password_hash = compute_password_hash(password)
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        self.insert_user(
+            user.email(),
+            &password_hash,
+            user.requires_2fa(),
+            user.status(),
+            false,
+            &[],
+        )
+        .await
+    }
+
+    #[tracing::instrument(name = "Importing user to PostgreSQL", skip_all)]
+    async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError> {
+        self.insert_user(
+            &user.email,
+            user.password_hash.as_ref(),
+            user.requires_2fa,
+            user.status,
+            user.password_reset_required,
+            &user.roles,
+        )
+        .await
+    }
+
     #[tracing::instrument(name = "Set new password", skip_all)]
     async fn set_new_password(
-        &mut self,
+        &self,
         email: &Email,
         new_password: Password,
     ) -> Result<(), UserStoreError> {
         let password_hash = compute_password_hash(new_password)
             .await
-            .map_err(|e| UserStoreError::UnexpectedError(e))?;
+            .map_err(UserStoreError::UnexpectedError)?;
 
         let query = sqlx::query!(
             r#"
                 UPDATE users
-                SET password_hash = $1
+                SET password_hash = $1, password_reset_required = FALSE
                 WHERE email = $2
             "#,
             password_hash.expose_secret(),
@@ -75,10 +257,10 @@ impl UserStore for PostgresUserStore {
         );
 
         query.execute(&self.pool).await.map_err(|e| {
-            if let Some(db_err) = e.as_database_error() {
-                if db_err.constraint().is_some() {
-                    return UserStoreError::UserAlreadyExists;
-                }
+            if let Some(db_err) = e.as_database_error()
+                && db_err.constraint().is_some()
+            {
+                return UserStoreError::UserAlreadyExists;
             }
             UserStoreError::UnexpectedError(e.into())
         })?;
@@ -94,7 +276,7 @@ impl UserStore for PostgresUserStore {
     ) -> Result<ValidatedUser, UserStoreError> {
         let query = sqlx::query!(
             r#"
-                SELECT email, password_hash, requires_2fa
+                SELECT email, password_hash, requires_2fa, status
                 FROM users
                 WHERE email = $1
             "#,
@@ -110,20 +292,47 @@ impl UserStore for PostgresUserStore {
             return Err(UserStoreError::UserNotFound);
         };
 
-        verify_password_hash(Secret::from(row.password_hash), password.clone())
+        let password_hash = Secret::from(row.password_hash);
+        verify_password_hash(password_hash.clone(), password.clone())
             .await
             .map_err(|_| UserStoreError::IncorrectPassword)?;
 
+        if let Some(err) = UserStoreError::from_inactive_status(parse_status(&row.status)?) {
+            return Err(err);
+        }
+
+        if needs_rehash(&password_hash) {
+            self.rehash_password(email, &password_hash, password).await;
+        }
+
         let email = Email::try_from(Secret::from(row.email))
             .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
         Ok(ValidatedUser::new(email, row.requires_2fa))
     }
 
+    #[tracing::instrument(name = "Checking password hash in PostgreSQL", skip_all)]
+    async fn password_needs_rehash(&self, email: &Email) -> Result<bool, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+                SELECT password_hash
+                FROM users
+                WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        Ok(needs_rehash(&Secret::from(row.password_hash)))
+    }
+
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         let query = sqlx::query!(
             r#"
-                SELECT email, password_hash, requires_2fa
+                SELECT email, password_hash, requires_2fa, status, phone_number, two_fa_channel
                 FROM users
                 WHERE email = $1
             "#,
@@ -145,13 +354,16 @@ impl UserStore for PostgresUserStore {
             Secret::from(row.password_hash),
             row.requires_2fa,
         )
-        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .with_status(parse_status(&row.status)?)
+        .with_phone_number(row.phone_number.map(parse_phone_number).transpose()?)
+        .with_two_fa_channel(parse_two_fa_channel(&row.two_fa_channel)?);
 
         Ok(user)
     }
 
     #[tracing::instrument(name = "Delete user from user store", skip_all)]
-    async fn delete_user(&mut self, user: &Email) -> Result<(), UserStoreError> {
+    async fn delete_user(&self, user: &Email) -> Result<(), UserStoreError> {
         let query = sqlx::query!(
             r#"
                 DELETE FROM users
@@ -165,63 +377,680 @@ impl UserStore for PostgresUserStore {
             .await
             .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
 
+        self.forget_status(user);
+
         if result.rows_affected() == 0 {
             return Err(UserStoreError::UserNotFound);
         }
 
         Ok(())
     }
-}
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Password,
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(expected_password_hash.expose_secret())?;
-
-            Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
+    #[tracing::instrument(name = "Assign role in PostgreSQL", skip_all)]
+    async fn assign_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        let query = sqlx::query!(
+            r#"
+                INSERT INTO user_roles (email, role)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+            "#,
+            email.as_ref().expose_secret(),
+            role.as_str()
+        );
+
+        query.execute(&self.pool).await.map_err(|e| {
+            if let Some(db_err) = e.as_database_error() {
+                match db_err.constraint() {
+                    Some("user_roles_email_fkey") => return UserStoreError::UserNotFound,
+                    Some("user_roles_role_fkey") => return UserStoreError::RoleNotFound,
+                    _ => {}
+                }
+            }
+            UserStoreError::UnexpectedError(eyre!(e))
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoke role in PostgreSQL", skip_all)]
+    async fn revoke_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        let query = sqlx::query!(
+            r#"
+                DELETE FROM user_roles
+                WHERE email = $1 AND role = $2
+            "#,
+            email.as_ref().expose_secret(),
+            role.as_str()
+        );
+
+        query
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving roles from PostgreSQL", skip_all)]
+    async fn get_roles(&self, email: &Email) -> Result<Vec<Role>, UserStoreError> {
+        let query = sqlx::query!(
+            r#"
+                SELECT role
+                FROM user_roles
+                WHERE email = $1
+                ORDER BY role
+            "#,
+            email.as_ref().expose_secret()
+        );
+
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Role::parse(&row.role).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving user profile from PostgreSQL", skip_all)]
+    async fn get_user_profile(&self, email: &Email) -> Result<UserProfile, UserStoreError> {
+        let query = sqlx::query!(
+            r#"
+                SELECT
+                    users.email,
+                    users.requires_2fa,
+                    users.status,
+                    users.password_reset_required,
+                    COALESCE(
+                        array_agg(user_roles.role ORDER BY user_roles.role)
+                            FILTER (WHERE user_roles.role IS NOT NULL),
+                        '{}'
+                    ) AS "roles!"
+                FROM users
+                LEFT JOIN user_roles ON user_roles.email = users.email
+                WHERE users.email = $1
+                GROUP BY users.email
+            "#,
+            email.as_ref().expose_secret()
+        );
+
+        let row = query
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        into_user_profile(
+            row.email,
+            row.requires_2fa,
+            row.status,
+            row.password_reset_required,
+            row.roles,
+        )
+    }
+
+    #[tracing::instrument(name = "Searching users in PostgreSQL", skip_all)]
+    async fn search_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let pattern = query.email_like_pattern();
+
+        let total = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!"
+                FROM users
+                WHERE $1::TEXT IS NULL OR email ILIKE $1
+            "#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT
+                    users.email,
+                    users.requires_2fa,
+                    users.status,
+                    users.password_reset_required,
+                    COALESCE(
+                        array_agg(user_roles.role ORDER BY user_roles.role)
+                            FILTER (WHERE user_roles.role IS NOT NULL),
+                        '{}'
+                    ) AS "roles!"
+                FROM users
+                LEFT JOIN user_roles ON user_roles.email = users.email
+                WHERE $1::TEXT IS NULL OR users.email ILIKE $1
+                GROUP BY users.email
+                ORDER BY users.email
+                LIMIT $2 OFFSET $3
+            "#,
+            pattern,
+            i64::from(query.per_page),
+            query.offset() as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        let users = rows
+            .into_iter()
+            .map(|row| {
+                into_user_profile(
+                    row.email,
+                    row.requires_2fa,
+                    row.status,
+                    row.password_reset_required,
+                    row.roles,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total as u64,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user status from PostgreSQL", skip_all)]
+    async fn get_status(&self, email: &Email) -> Result<UserStatus, UserStoreError> {
+        if let Some(status) = self.cached_status(email) {
+            return Ok(status);
+        }
+        // Drop a stale entry up front so it doesn't outlive a user that is gone
+        self.status_cache.remove(email);
+        let writes_seen = self.status_writes.load(Ordering::SeqCst);
+
+        let status = sqlx::query_scalar!(
+            r#"
+                SELECT status
+                FROM users
+                WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        let status = parse_status(&status)?;
+        self.cache_status(email, status, writes_seen);
+
+        Ok(status)
+    }
+
+    #[tracing::instrument(name = "Set user status in PostgreSQL", skip_all)]
+    async fn set_status(&self, email: &Email, status: UserStatus) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET status = $1
+                WHERE email = $2 AND status <> $3
+            "#,
+            status.as_str(),
+            email.as_ref().expose_secret(),
+            UserStatus::PendingDeletion.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        self.forget_status(email);
+
+        if result.rows_affected() == 0 {
+            // Either missing or pending deletion
+            return match self.get_status(email).await? {
+                UserStatus::PendingDeletion => Err(UserStoreError::UserPendingDeletion),
+                _ => Err(UserStoreError::UnexpectedError(eyre!(
+                    "Status update matched no user"
+                ))),
+            };
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Set requires 2FA in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET requires_2fa = $1
+                WHERE email = $2
+            "#,
+            requires_2fa,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Set password reset required in PostgreSQL", skip_all)]
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET password_reset_required = $1
+                WHERE email = $2
+            "#,
+            required,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Remember device in PostgreSQL", skip_all)]
+    async fn remember_device(
+        &self,
+        email: &Email,
+        fingerprint: &DeviceFingerprint,
+    ) -> Result<bool, UserStoreError> {
+        // xmax is only zero for a freshly inserted row, not an updated one
+        let row = sqlx::query!(
+            r#"
+                INSERT INTO user_devices (email, fingerprint, first_seen_at, last_seen_at)
+                VALUES ($1, $2, $3, $3)
+                ON CONFLICT (email, fingerprint) DO UPDATE SET last_seen_at = EXCLUDED.last_seen_at
+                RETURNING (xmax = 0) AS "inserted!"
+            "#,
+            email.as_ref().expose_secret(),
+            fingerprint.as_str(),
+            self.clock.now()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            if let Some(db_err) = e.as_database_error()
+                && db_err.constraint() == Some("user_devices_email_fkey")
+            {
+                return UserStoreError::UserNotFound;
+            }
+            UserStoreError::UnexpectedError(eyre!(e))
+        })?;
+
+        Ok(row.inserted)
+    }
+
+    #[tracing::instrument(name = "Retrieving known devices from PostgreSQL", skip_all)]
+    async fn known_devices(&self, email: &Email) -> Result<Vec<KnownDevice>, UserStoreError> {
+        // Tells a user without devices apart from a missing one
+        self.get_status(email).await?;
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT fingerprint, first_seen_at, last_seen_at
+                FROM user_devices
+                WHERE email = $1
+                ORDER BY last_seen_at DESC
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| KnownDevice {
+                fingerprint: DeviceFingerprint::from(row.fingerprint),
+                first_seen_at: row.first_seen_at,
+                last_seen_at: row.last_seen_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Retrieving notification preference from PostgreSQL", skip_all)]
+    async fn security_notifications_enabled(&self, email: &Email) -> Result<bool, UserStoreError> {
+        sqlx::query_scalar!(
+            r#"
+                SELECT security_notifications
+                FROM users
+                WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::UserNotFound)
+    }
+
+    #[tracing::instrument(name = "Set notification preference in PostgreSQL", skip_all)]
+    async fn set_security_notifications(
+        &self,
+        email: &Email,
+        enabled: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET security_notifications = $1
+                WHERE email = $2
+            "#,
+            enabled,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Starting phone verification in PostgreSQL", skip_all)]
+    async fn start_phone_verification(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+        code: &TwoFaCode,
+    ) -> Result<(), UserStoreError> {
+        let expires_at =
+            self.clock.now() + chrono::Duration::seconds(PHONE_VERIFICATION_TTL_SECONDS);
+
+        sqlx::query!(
+            r#"
+                INSERT INTO phone_verifications (email, phone_number, code, expires_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (email) DO UPDATE
+                SET phone_number = EXCLUDED.phone_number,
+                    code = EXCLUDED.code,
+                    attempts = 0,
+                    expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref().expose_secret(),
+            phone_number.as_ref().expose_secret(),
+            code.as_str(),
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            if let Some(db_err) = e.as_database_error()
+                && db_err.constraint() == Some("phone_verifications_email_fkey")
+            {
+                return UserStoreError::UserNotFound;
+            }
+            UserStoreError::UnexpectedError(eyre!(e))
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Confirming phone verification in PostgreSQL", skip_all)]
+    async fn confirm_phone_verification(
+        &self,
+        email: &Email,
+        code: &TwoFaCode,
+    ) -> Result<PhoneNumber, UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        let pending = sqlx::query!(
+            r#"
+                SELECT phone_number, code, attempts, expires_at <= $2 AS "expired!"
+                FROM phone_verifications
+                WHERE email = $1
+                FOR UPDATE
+            "#,
+            email.as_ref().expose_secret(),
+            self.clock.now()
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::InvalidVerificationCode)?;
+
+        let matches = pending.code == code.as_str();
+        let attempts = pending.attempts + 1;
+
+        if matches || pending.expired || attempts >= MAX_PHONE_VERIFICATION_ATTEMPTS {
+            sqlx::query!(
+                "DELETE FROM phone_verifications WHERE email = $1",
+                email.as_ref().expose_secret()
             )
-            .verify_password(
-                password_candidate.as_ref().expose_secret().as_bytes(),
-                &expected_password_hash,
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        } else {
+            sqlx::query!(
+                "UPDATE phone_verifications SET attempts = $2 WHERE email = $1",
+                email.as_ref().expose_secret(),
+                attempts
             )
-            .map_err(|e| e.into())
-        })
-    })
-    .await?;
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        }
+
+        if !matches || pending.expired {
+            transaction
+                .commit()
+                .await
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+            return Err(UserStoreError::InvalidVerificationCode);
+        }
+
+        sqlx::query!(
+            "UPDATE users SET phone_number = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            pending.phone_number
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        parse_phone_number(pending.phone_number)
+    }
+
+    #[tracing::instrument(name = "Setting two-factor channel in PostgreSQL", skip_all)]
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFaChannel,
+    ) -> Result<(), UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+                UPDATE users
+                SET two_fa_channel = $2
+                WHERE email = $1 AND ($2 <> 'sms' OR phone_number IS NOT NULL)
+                RETURNING email
+            "#,
+            email.as_ref().expose_secret(),
+            channel.as_str()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if row.is_none() {
+            // Tell a missing user apart from one without a phone number
+            self.get_user(email).await?;
+            return Err(UserStoreError::PhoneNumberNotVerified);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Scheduling user deletion in PostgreSQL", skip_all)]
+    async fn schedule_deletion(
+        &self,
+        email: &Email,
+        due_at: DateTime<Utc>,
+        restore_token: &RestoreToken,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET status = $2, deletion_due_at = $3, restore_token_hash = $4
+                WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            UserStatus::PendingDeletion.as_str(),
+            due_at,
+            restore_token.hash()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        self.forget_status(email);
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
 
-    result
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Restoring user in PostgreSQL", skip_all)]
+    async fn restore_user(
+        &self,
+        restore_token: &RestoreToken,
+        now: DateTime<Utc>,
+    ) -> Result<Email, UserStoreError> {
+        let email = sqlx::query_scalar!(
+            r#"
+                UPDATE users
+                SET status = $3, deletion_due_at = NULL, restore_token_hash = NULL
+                WHERE restore_token_hash = $1 AND status = $4 AND deletion_due_at > $2
+                RETURNING email
+            "#,
+            restore_token.hash(),
+            now,
+            UserStatus::Active.as_str(),
+            UserStatus::PendingDeletion.as_str()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::InvalidRestoreToken)?;
+
+        let email = Email::try_from(Secret::from(email))
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        self.forget_status(&email);
+
+        Ok(email)
+    }
+
+    #[tracing::instrument(name = "Retrieving due deletions from PostgreSQL", skip_all)]
+    async fn due_deletions(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Email>, UserStoreError> {
+        let emails = sqlx::query_scalar!(
+            r#"
+                SELECT email
+                FROM users
+                WHERE status = $1 AND deletion_due_at <= $2
+                ORDER BY deletion_due_at
+                LIMIT $3
+            "#,
+            UserStatus::PendingDeletion.as_str(),
+            now,
+            i64::from(limit)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        emails
+            .into_iter()
+            .map(|email| {
+                Email::try_from(Secret::from(email))
+                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+            })
+            .collect()
+    }
 }
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Password) -> Result<Secret<String>> {
-    let current_span: tracing::Span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(move || {
-            let salt: SaltString = SaltString::generate(rand_core::OsRng);
-            let hasher = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
-            );
-            hasher
-                .hash_password(password.as_ref().expose_secret().as_bytes(), &salt)
-                .map(|h| Secret::from(h.to_string()))
-                .map_err(Into::into)
-        })
+fn parse_phone_number(phone_number: String) -> Result<PhoneNumber, UserStoreError> {
+    PhoneNumber::try_from(Secret::new(phone_number))
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn parse_two_fa_channel(channel: &str) -> Result<TwoFaChannel, UserStoreError> {
+    channel
+        .parse::<TwoFaChannel>()
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn parse_status(status: &str) -> Result<UserStatus, UserStoreError> {
+    status
+        .parse::<UserStatus>()
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn into_user_profile(
+    email: String,
+    requires_2fa: bool,
+    status: String,
+    password_reset_required: bool,
+    roles: Vec<String>,
+) -> Result<UserProfile, UserStoreError> {
+    let email = Email::try_from(Secret::from(email))
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+    let roles = roles
+        .iter()
+        .map(|role| Role::parse(role).map_err(|e| UserStoreError::UnexpectedError(eyre!(e))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(UserProfile {
+        email,
+        requires_2fa,
+        status: parse_status(&status)?,
+        password_reset_required,
+        roles,
     })
-    .await?;
+}
 
-    result
+// Removes the expired statuses, returning how many were removed
+fn remove_expired_statuses(
+    status_cache: &DashMap<Email, (UserStatus, DateTime<Utc>)>,
+    now: DateTime<Utc>,
+) -> usize {
+    let before = status_cache.len();
+    status_cache.retain(|_, (_, cached_at)| now < *cached_at + STATUS_CACHE_TTL);
+
+    before.saturating_sub(status_cache.len())
 }
 
 #[cfg(test)]
@@ -230,6 +1059,10 @@ mod tests {
     use crate::auth_service::get_postgres_pool;
 
     use super::*;
+    use crate::{
+        domain::{clock::MockClock, password_hash::PasswordHash},
+        services::data_stores::user_store_suite::user_store_tests,
+    };
     use secrecy::{ExposeSecret, Secret};
     use sqlx::PgPool;
     use testcontainers_modules::{
@@ -344,251 +1177,98 @@ mod tests {
     //         .expect("Failed to migrate the database");
     // }
 
-    fn create_test_user() -> User {
-        let unique_id = uuid::Uuid::new_v4();
-        User::new(
-            Email::try_from(Secret::from(format!("test{}@example.com", unique_id))).unwrap(),
-            Password::try_from(Secret::from("password123".to_string())).unwrap(),
-            false,
-        )
-    }
-
-    fn create_test_user_with_2fa() -> User {
-        let unique_id = uuid::Uuid::new_v4();
-        User::new(
-            Email::try_from(Secret::from(format!("test2fa{}@example.com", unique_id))).unwrap(),
-            Password::try_from(Secret::from("password123".to_string())).unwrap(),
-            true,
-        )
-    }
-
-    #[tokio::test]
-    async fn test_add_user_success() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool.clone());
-        let user = create_test_user();
-
-        let result = store.add_user(user.clone()).await;
-        assert!(result.is_ok());
-
-        // Verify user was added to database
-        let stored_user = store.get_user(user.email()).await;
-
-        assert_eq!(stored_user, Ok(user));
+    async fn setup(
+        clock: Arc<MockClock>,
+    ) -> (ContainerAsync<postgres::Postgres>, PostgresUserStore) {
+        let (container, pool) = setup_and_connect_db_container().await;
+        (container, PostgresUserStore::with_clock(pool, clock))
     }
 
-    #[tokio::test]
-    async fn test_add_user_duplicate_email() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool);
-        let user = create_test_user();
-
-        // Add user first time
-        store.add_user(user.clone()).await.unwrap();
-
-        // Try to add same user again
-        let result = store.add_user(user).await;
-        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
-    }
+    user_store_tests!(setup);
 
     #[tokio::test]
-    async fn test_authenticate_user_success() {
+    async fn test_imported_hash_is_replaced_on_login() {
         let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool);
-        let user = create_test_user();
-        let email = user.email().clone();
-        let password = user.password().clone();
-
-        // Add user first
-        store.add_user(user).await.unwrap();
-
-        // Authenticate user
-        let result = store.authenticate_user(&email, &password).await;
-        assert!(result.is_ok());
-
-        let validated_user = result.unwrap();
-        assert_eq!(validated_user.email(), &email);
-        assert_eq!(validated_user, ValidatedUser::No2Fa(email));
-    }
-
-    #[tokio::test]
-    async fn test_authenticate_user_with_2fa() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool);
-        let user = create_test_user_with_2fa();
-        let email = user.email().clone();
-        let password = user.password().clone();
-
-        // Add user first
-        store.add_user(user).await.unwrap();
-
-        // Authenticate user
-        let result = store.authenticate_user(&email, &password).await;
-        assert!(result.is_ok());
-
-        let validated_user = result.unwrap();
-        assert_eq!(validated_user.email(), &email);
-        assert_eq!(validated_user, ValidatedUser::Requires2Fa(email));
-    }
-
-    #[tokio::test]
-    async fn test_authenticate_user_not_found() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let store = PostgresUserStore::new(pool);
-        let email = Email::try_from(Secret::from("nonexistent@example.com".to_string())).unwrap();
+        let store = PostgresUserStore::new(pool.clone());
+        let email = Email::try_from(Secret::from("test@example.com".to_string())).unwrap();
         let password = Password::try_from(Secret::from("password123".to_string())).unwrap();
-
-        let result = store.authenticate_user(&email, &password).await;
-        assert_eq!(result, Err(UserStoreError::UserNotFound));
-    }
-
-    #[tokio::test]
-    async fn test_set_new_password() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool);
-        let user = create_test_user();
-        let email = user.email().clone();
-        let new_password = Password::try_from(Secret::from("newpassword123".to_string())).unwrap();
-
-        store.add_user(user).await.unwrap();
+        let bcrypt_hash = bcrypt::hash(password.as_ref().expose_secret(), 4).unwrap();
 
         store
-            .set_new_password(&email, new_password.clone())
+            .import_user(ImportedUser {
+                email: email.clone(),
+                password_hash: PasswordHash::try_from(Secret::new(bcrypt_hash)).unwrap(),
+                requires_2fa: false,
+                status: UserStatus::Active,
+                password_reset_required: false,
+                roles: Vec::new(),
+            })
             .await
             .unwrap();
 
-        let result = store.authenticate_user(&email, &new_password).await;
-        assert!(result.is_ok());
-
-        let validated_user = result.unwrap();
-        assert_eq!(validated_user.email(), &email);
-        assert_eq!(validated_user, ValidatedUser::No2Fa(email));
-    }
-
-    #[tokio::test]
-    async fn test_authenticate_user_wrong_password() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool);
-        let user = create_test_user();
-        let email = user.email().clone();
-        let wrong_password = Password::try_from(Secret::from("wrongpassword".to_string())).unwrap();
-
-        // Add user first
-        store.add_user(user).await.unwrap();
-
-        // Try to authenticate with wrong password
-        let result = store.authenticate_user(&email, &wrong_password).await;
-        assert_eq!(result, Err(UserStoreError::IncorrectPassword));
-    }
-
-    #[tokio::test]
-    async fn test_get_user_success() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool);
-        let user = create_test_user();
-        let email = user.email().clone();
-
-        // Add user first
-        store.add_user(user.clone()).await.unwrap();
-
-        // Get user
-        let result = store.get_user(&email).await;
-        assert!(result.is_ok());
-
-        let retrieved_user = result.unwrap();
-        assert_eq!(retrieved_user.email(), user.email());
-        assert_eq!(retrieved_user.requires_2fa(), user.requires_2fa());
-    }
-
-    #[tokio::test]
-    async fn test_get_user_not_found() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let store = PostgresUserStore::new(pool);
-        let email = Email::try_from(Secret::from("nonexistent@example.com".to_string())).unwrap();
-
-        let result = store.get_user(&email).await;
-        assert_eq!(result, Err(UserStoreError::UserNotFound));
-    }
-
-    #[tokio::test]
-    async fn test_delete_user_success() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool.clone());
-        let user = create_test_user();
-        let email = user.email().clone();
-
-        // Add user first
-        store.add_user(user.clone()).await.unwrap();
-
-        // Delete user
-        let result = store.delete_user(&email).await;
-        assert!(result.is_ok());
-
-        // Verify user was deleted
-        let result = store.get_user(user.email()).await;
-        assert_eq!(result, Err(UserStoreError::UserNotFound));
-    }
-
-    #[tokio::test]
-    async fn test_delete_user_not_found() {
-        let (_container, pool) = setup_and_connect_db_container().await;
-        let mut store = PostgresUserStore::new(pool);
-        let email = Email::try_from(Secret::from("nonexistent@example.com".to_string())).unwrap();
+        let stored_hash = || async {
+            sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE email = $1")
+                .bind(email.as_ref().expose_secret())
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+        assert!(stored_hash().await.starts_with("$2b$"));
 
-        let result = store.delete_user(&email).await;
-        assert_eq!(result, Err(UserStoreError::UserNotFound));
+        store.authenticate_user(&email, &password).await.unwrap();
+        assert!(stored_hash().await.starts_with("$argon2id$"));
     }
 
-    #[tokio::test]
-    async fn test_compute_password_hash() {
-        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
-        let hash_result = compute_password_hash(password.clone()).await;
+    #[test]
+    fn test_remove_expired_statuses() {
+        let now = Utc::now();
+        let status_cache = DashMap::new();
+        status_cache.insert(
+            Email::try_from(Secret::from("stale@example.com".to_owned())).unwrap(),
+            (UserStatus::Active, now - STATUS_CACHE_TTL),
+        );
+        status_cache.insert(
+            Email::try_from(Secret::from("fresh@example.com".to_owned())).unwrap(),
+            (UserStatus::Active, now),
+        );
 
-        assert!(hash_result.is_ok());
-        let hash = hash_result.unwrap();
-        assert!(!hash.expose_secret().is_empty());
-        assert_ne!(hash.expose_secret(), password.as_ref().expose_secret());
+        assert_eq!(remove_expired_statuses(&status_cache, now), 1);
+        assert_eq!(status_cache.len(), 1);
     }
 
-    #[tokio::test]
-    async fn test_verify_password_hash_success() {
-        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
-        let hash = compute_password_hash(password.clone()).await.unwrap();
-
-        let result = verify_password_hash(hash, password).await;
-        assert!(result.is_ok());
+    // The cache alone, the pool is never connected to
+    fn store_without_database(clock: Arc<MockClock>) -> PostgresUserStore {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        PostgresUserStore::with_clock(pool, clock)
     }
 
     #[tokio::test]
-    async fn test_verify_password_hash_failure() {
-        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
-        let wrong_password = Password::try_from(Secret::from("wrongpassword".to_owned())).unwrap();
-        let hash = compute_password_hash(password).await.unwrap();
+    async fn test_cached_status_expires_by_the_clock() {
+        let clock = Arc::new(MockClock::default());
+        let store = store_without_database(clock.clone());
+        let email = Email::try_from(Secret::from("user@example.com".to_owned())).unwrap();
 
-        let result = verify_password_hash(hash, wrong_password).await;
-        assert!(result.is_err());
-    }
-
-    #[tokio::test]
-    async fn test_verify_password_hash_invalid_hash() {
-        let invalid_hash = Secret::from("invalid_hash_format".to_owned());
-        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+        store.cache_status(&email, UserStatus::Active, 0);
+        clock.advance(STATUS_CACHE_TTL - Duration::from_secs(1));
+        assert_eq!(store.cached_status(&email), Some(UserStatus::Active));
 
-        let result = verify_password_hash(invalid_hash, password).await;
-        assert!(result.is_err());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(store.cached_status(&email), None);
     }
 
     #[tokio::test]
-    async fn test_compute_password_hash_deterministic_salt() {
-        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
-        let hash1 = compute_password_hash(password.clone()).await.unwrap();
-        let hash2 = compute_password_hash(password.clone()).await.unwrap();
+    async fn test_read_overlapping_a_status_write_is_not_cached() {
+        let store = store_without_database(Arc::new(MockClock::default()));
+        let email = Email::try_from(Secret::from("user@example.com".to_owned())).unwrap();
 
-        // Hashes should be different due to random salt
-        assert_ne!(hash1.expose_secret(), hash2.expose_secret());
+        // A read starts, the user is suspended, then the read finishes with
+        // the status from before the suspension
+        let writes_seen = store.status_writes.load(Ordering::SeqCst);
+        store.forget_status(&email);
+        store.cache_status(&email, UserStatus::Active, writes_seen);
 
-        // But both should verify successfully
-        assert!(verify_password_hash(hash1, password.clone()).await.is_ok());
-        assert!(verify_password_hash(hash2, password.clone()).await.is_ok());
+        assert_eq!(store.cached_status(&email), None);
     }
 }
@@ -1,47 +1,163 @@
-use std::sync::Arc;
-
 use color_eyre::eyre::eyre;
-use redis::{Commands, Connection};
-use tokio::sync::Mutex;
+use redis::{AsyncCommands, aio::MultiplexedConnection};
+use secrecy::ExposeSecret;
 
 use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    settings::AuthServiceSetting,
+    domain::{
+        data_stores::{BannedTokenStore, BannedTokenStoreError},
+        email::Email,
+    },
+    settings::Settings,
 };
 
 #[derive(Clone)]
 pub struct RedisBannedTokenStore {
-    conn: Arc<Mutex<Connection>>,
+    conn: MultiplexedConnection,
+    settings: Settings,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
-        Self { conn: conn }
+    pub fn new(conn: MultiplexedConnection, settings: Settings) -> Self {
+        Self { conn, settings }
     }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
-    async fn ban_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
+    async fn ban_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
         let key = get_key(&token);
 
-        let ttl = AuthServiceSetting::load().auth.jwt.time_to_live as u64;
-        let mut conn = self.conn.lock().await;
-        conn.set_ex(key, true, ttl)
+        let ttl = self.settings.load().auth.jwt.time_to_live as u64;
+        self.conn
+            .clone()
+            .set_ex::<_, _, ()>(key, true, ttl)
+            .await
             .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))
     }
 
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
         let key = get_key(token);
-        let mut conn = self.conn.lock().await;
-        conn.exists(&key)
+        self.conn
+            .clone()
+            .exists(&key)
+            .await
+            .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))
+    }
+
+    async fn revoke_all_tokens(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        let key = get_generation_key(email);
+        // Keeps the generation of a forgotten user whose email is used again
+        redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .ignore()
+            .persist(&key)
+            .ignore()
+            .query_async::<()>(&mut self.conn.clone())
+            .await
+            .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))
+    }
+
+    async fn token_generation(&self, email: &Email) -> Result<u64, BannedTokenStoreError> {
+        let key = get_generation_key(email);
+        self.conn
+            .clone()
+            .get::<_, Option<u64>>(key)
+            .await
+            .map(Option::unwrap_or_default)
+            .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))
+    }
+
+    async fn forget_user(&self, email: &Email) -> Result<(), BannedTokenStoreError> {
+        let key = get_generation_key(email);
+        let ttl = self.settings.load().auth.jwt.time_to_live;
+        redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .ignore()
+            .expire(&key, ttl)
+            .ignore()
+            .query_async::<()>(&mut self.conn.clone())
+            .await
             .map_err(|e| BannedTokenStoreError::DatabaseError(eyre!(e)))
     }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const TOKEN_GENERATION_KEY_PREFIX: &str = "token_generation:";
 
 fn get_key(token: &str) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
 }
+
+fn get_generation_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        TOKEN_GENERATION_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        services::data_stores::banned_token_store_suite::banned_token_store_tests,
+        settings::AuthServiceSetting,
+    };
+    use testcontainers_modules::{
+        redis::Redis,
+        testcontainers::{ContainerAsync, runners::AsyncRunner},
+    };
+
+    async fn setup() -> (ContainerAsync<Redis>, RedisBannedTokenStore) {
+        let container = Redis::default()
+            .start()
+            .await
+            .expect("Failed to start Redis container");
+
+        let host = container
+            .get_host()
+            .await
+            .expect("Failed to get container host");
+
+        let port = container
+            .get_host_port_ipv4(6379)
+            .await
+            .expect("Failed to get Redis port");
+
+        let redis_url = format!("redis://{}:{}/", host, port);
+
+        let client = redis::Client::open(redis_url).expect("Failed to create Redis client");
+
+        let connection = client
+            .get_multiplexed_async_connection()
+            .await
+            .expect("Failed to connect to Redis");
+
+        (
+            container,
+            RedisBannedTokenStore::new(connection, AuthServiceSetting::handle()),
+        )
+    }
+
+    banned_token_store_tests!(setup);
+
+    #[tokio::test]
+    async fn test_forgotten_user_generation_expires() {
+        let (_container, store) = setup().await;
+        let email = Email::try_from(secrecy::Secret::new("test@example.com".to_owned())).unwrap();
+        let key = get_generation_key(&email);
+        let ttl = AuthServiceSetting::load().auth.jwt.time_to_live;
+
+        store.forget_user(&email).await.unwrap();
+        let remaining: i64 = store.conn.clone().ttl(&key).await.unwrap();
+        assert!(remaining > 0 && remaining <= ttl);
+
+        // No longer expires once the email is in use again
+        store.revoke_all_tokens(&email).await.unwrap();
+        let remaining: i64 = store.conn.clone().ttl(&key).await.unwrap();
+        assert_eq!(remaining, -1);
+    }
+}
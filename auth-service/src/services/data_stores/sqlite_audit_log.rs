@@ -0,0 +1,255 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use sqlx::SqlitePool;
+
+use crate::domain::data_stores::{
+    AuditEvent, AuditLog, AuditLogError, AuditRecord, ERASED_SUBJECT,
+};
+
+pub struct SqliteAuditLog {
+    pool: SqlitePool,
+}
+
+impl SqliteAuditLog {
+    pub fn new(pool: SqlitePool) -> Self {
+        SqliteAuditLog { pool }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct AuditRow {
+    actor: Option<String>,
+    event_type: String,
+    outcome: String,
+    target: Option<String>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    details: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[async_trait::async_trait]
+impl AuditLog for SqliteAuditLog {
+    #[tracing::instrument(name = "Recording audit event in SQLite", skip_all)]
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        sqlx::query(
+            r#"
+                INSERT INTO audit_log
+                    (actor, event_type, outcome, target, ip, user_agent, details, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+        )
+        .bind(event.actor)
+        .bind(event.event_type.as_str())
+        .bind(event.outcome.as_str())
+        .bind(event.target)
+        .bind(event.ip)
+        .bind(event.user_agent)
+        .bind(event.details)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving audit events from SQLite", skip_all)]
+    async fn recent_events(
+        &self,
+        subject: &str,
+        limit: u32,
+    ) -> Result<Vec<AuditRecord>, AuditLogError> {
+        let rows: Vec<AuditRow> = sqlx::query_as(
+            r#"
+                SELECT actor, event_type, outcome, target, ip, user_agent, details, created_at
+                FROM audit_log
+                WHERE actor = ?1 OR target = ?1
+                ORDER BY created_at DESC, id DESC
+                LIMIT ?2
+            "#,
+        )
+        .bind(subject)
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(eyre!(e)))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AuditRecord {
+                    event: AuditEvent {
+                        actor: row.actor,
+                        event_type: row.event_type.parse()?,
+                        outcome: row.outcome.parse()?,
+                        target: row.target,
+                        ip: row.ip,
+                        user_agent: row.user_agent,
+                        details: row.details,
+                    },
+                    occurred_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Erasing audit subject in SQLite", skip_all)]
+    async fn erase_subject(&self, subject: &str) -> Result<u64, AuditLogError> {
+        let result = sqlx::query(
+            r#"
+                UPDATE audit_log
+                SET actor = CASE WHEN actor = ?1 THEN ?2 ELSE actor END,
+                    target = CASE WHEN target = ?1 THEN ?2 ELSE target END,
+                    ip = CASE WHEN actor = ?1 THEN NULL ELSE ip END,
+                    user_agent = CASE WHEN actor = ?1 THEN NULL ELSE user_agent END
+                WHERE actor = ?1 OR target = ?1
+            "#,
+        )
+        .bind(subject)
+        .bind(ERASED_SUBJECT)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(eyre!(e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::data_stores::{AuditEventType, AuditOutcome};
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn setup_db() -> SqlitePool {
+        // In-memory databases live as long as their connection, so the pool
+        // must hold on to exactly one
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to open database");
+
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to migrate the database");
+
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_record_and_read_recent_events() {
+        let pool = setup_db().await;
+        let audit_log = SqliteAuditLog::new(pool);
+
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::Login)
+                    .actor("user@example.com")
+                    .outcome(AuditOutcome::Failure)
+                    .ip(Some("10.0.0.1".to_owned()))
+                    .user_agent(Some("curl/8.0".to_owned())),
+            )
+            .await
+            .unwrap();
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::AdminViewUser)
+                    .actor("admin@example.com")
+                    .target("user@example.com"),
+            )
+            .await
+            .unwrap();
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Login).actor("other@example.com"))
+            .await
+            .unwrap();
+
+        let events = audit_log
+            .recent_events("user@example.com", 10)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.event_type, AuditEventType::AdminViewUser);
+        assert_eq!(events[1].event.outcome, AuditOutcome::Failure);
+        assert_eq!(events[1].event.ip.as_deref(), Some("10.0.0.1"));
+
+        let events = audit_log
+            .recent_events("user@example.com", 1)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_is_append_only() {
+        let pool = setup_db().await;
+        let audit_log = SqliteAuditLog::new(pool.clone());
+
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Logout).actor("user@example.com"))
+            .await
+            .unwrap();
+
+        let result = sqlx::query("DELETE FROM audit_log").execute(&pool).await;
+        assert!(result.is_err());
+
+        let result = sqlx::query("UPDATE audit_log SET actor = 'someone@example.com'")
+            .execute(&pool)
+            .await;
+        assert!(result.is_err());
+
+        let result = sqlx::query("UPDATE audit_log SET details = 'changed', ip = NULL")
+            .execute(&pool)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_erase_subject() {
+        let pool = setup_db().await;
+        let audit_log = SqliteAuditLog::new(pool);
+
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::Login)
+                    .actor("user@example.com")
+                    .ip(Some("10.0.0.1".to_owned()))
+                    .user_agent(Some("curl/8.0".to_owned())),
+            )
+            .await
+            .unwrap();
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::AdminViewUser)
+                    .actor("admin@example.com")
+                    .target("user@example.com")
+                    .ip(Some("10.0.0.2".to_owned())),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            audit_log.erase_subject("user@example.com").await.unwrap(),
+            2
+        );
+        assert!(
+            audit_log
+                .recent_events("user@example.com", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let events = audit_log.recent_events(ERASED_SUBJECT, 10).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.actor.as_deref(), Some("admin@example.com"));
+        assert_eq!(events[0].event.ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(events[1].event.actor.as_deref(), Some(ERASED_SUBJECT));
+        assert_eq!(events[1].event.ip, None);
+        assert_eq!(events[1].event.user_agent, None);
+    }
+}
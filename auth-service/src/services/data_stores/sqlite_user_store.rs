@@ -0,0 +1,949 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+use crate::domain::{
+    clock::{Clock, SystemClock},
+    data_stores::{
+        MAX_PHONE_VERIFICATION_ATTEMPTS, PHONE_VERIFICATION_TTL_SECONDS, UserPage, UserQuery,
+        UserStore, UserStoreError,
+    },
+    device_fingerprint::{DeviceFingerprint, KnownDevice},
+    email::Email,
+    password::Password,
+    phone_number::PhoneNumber,
+    restore_token::RestoreToken,
+    role::Role,
+    two_fa_channel::TwoFaChannel,
+    two_fa_code::TwoFaCode,
+    user::{ImportedUser, User, UserProfile, ValidatedUser},
+    user_status::UserStatus,
+};
+use crate::utils::password_hash::{compute_password_hash, needs_rehash, verify_password_hash};
+
+// The SQLite counterpart of PostgresUserStore, for single-node installations.
+// Queries are checked at runtime since the offline query cache only covers
+// Postgres.
+pub struct SqliteUserStore {
+    pool: SqlitePool,
+    clock: Arc<dyn Clock>,
+}
+
+impl SqliteUserStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self::with_clock(pool, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(pool: SqlitePool, clock: Arc<dyn Clock>) -> Self {
+        SqliteUserStore { pool, clock }
+    }
+
+    // In the format CURRENT_TIMESTAMP writes, so rows sort the same either way
+    fn current_timestamp(&self) -> String {
+        self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    status: String,
+    phone_number: Option<String>,
+    two_fa_channel: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct UserProfileRow {
+    email: String,
+    requires_2fa: bool,
+    status: String,
+    password_reset_required: bool,
+    // Comma separated, NULL when the user has no roles
+    roles: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PhoneVerificationRow {
+    phone_number: String,
+    code: String,
+    attempts: i32,
+    expired: bool,
+}
+
+const USER_PROFILE_QUERY: &str = r#"
+    SELECT
+        users.email,
+        users.requires_2fa,
+        users.status,
+        users.password_reset_required,
+        group_concat(user_roles.role) AS roles
+    FROM users
+    LEFT JOIN user_roles ON user_roles.email = users.email
+"#;
+
+#[async_trait::async_trait]
+impl UserStore for SqliteUserStore {
+    #[tracing::instrument(name = "Adding user to SQLite", skip_all)]
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let password = user.password().clone();
+        let password_hash = compute_password_hash(password)
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        self.insert_user(
+            user.email(),
+            &password_hash,
+            user.requires_2fa(),
+            user.status(),
+            false,
+            &[],
+        )
+        .await
+    }
+
+    #[tracing::instrument(name = "Importing user to SQLite", skip_all)]
+    async fn import_user(&self, user: ImportedUser) -> Result<(), UserStoreError> {
+        self.insert_user(
+            &user.email,
+            user.password_hash.as_ref(),
+            user.requires_2fa,
+            user.status,
+            user.password_reset_required,
+            &user.roles,
+        )
+        .await
+    }
+
+    #[tracing::instrument(name = "Set new password in SQLite", skip_all)]
+    async fn set_new_password(
+        &self,
+        email: &Email,
+        new_password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(new_password)
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        sqlx::query(
+            r#"
+                UPDATE users
+                SET password_hash = ?1, password_reset_required = FALSE
+                WHERE email = ?2
+            "#,
+        )
+        .bind(password_hash.expose_secret())
+        .bind(email.as_ref().expose_secret())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Validating user credentials in SQLite", skip_all)]
+    async fn authenticate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<ValidatedUser, UserStoreError> {
+        let row = self.fetch_user_row(email).await?;
+
+        let password_hash = Secret::from(row.password_hash);
+        verify_password_hash(password_hash.clone(), password.clone())
+            .await
+            .map_err(|_| UserStoreError::IncorrectPassword)?;
+
+        if let Some(err) = UserStoreError::from_inactive_status(parse_status(&row.status)?) {
+            return Err(err);
+        }
+
+        if needs_rehash(&password_hash) {
+            self.rehash_password(email, &password_hash, password).await;
+        }
+
+        let email = Email::try_from(Secret::from(row.email))
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        Ok(ValidatedUser::new(email, row.requires_2fa))
+    }
+
+    #[tracing::instrument(name = "Checking password hash in SQLite", skip_all)]
+    async fn password_needs_rehash(&self, email: &Email) -> Result<bool, UserStoreError> {
+        let row = self.fetch_user_row(email).await?;
+        Ok(needs_rehash(&Secret::from(row.password_hash)))
+    }
+
+    #[tracing::instrument(name = "Retrieving user from SQLite", skip_all)]
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        let row = self.fetch_user_row(email).await?;
+
+        let user = User::parse(
+            Secret::from(row.email),
+            Secret::from(row.password_hash),
+            row.requires_2fa,
+        )
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .with_status(parse_status(&row.status)?)
+        .with_phone_number(row.phone_number.map(parse_phone_number).transpose()?)
+        .with_two_fa_channel(parse_two_fa_channel(&row.two_fa_channel)?);
+
+        Ok(user)
+    }
+
+    #[tracing::instrument(name = "Delete user from SQLite", skip_all)]
+    async fn delete_user(&self, user: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query("DELETE FROM users WHERE email = ?1")
+            .bind(user.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Assign role in SQLite", skip_all)]
+    async fn assign_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+                INSERT INTO user_roles (email, role)
+                VALUES (?1, ?2)
+                ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(role.as_str())
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            // SQLite does not name the violated constraint, so look up which
+            // side of the reference is missing
+            Err(e)
+                if e.as_database_error()
+                    .is_some_and(|db_err| db_err.is_foreign_key_violation()) =>
+            {
+                let role_exists: bool =
+                    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM roles WHERE name = ?1)")
+                        .bind(role.as_str())
+                        .fetch_one(&self.pool)
+                        .await
+                        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+                if role_exists {
+                    Err(UserStoreError::UserNotFound)
+                } else {
+                    Err(UserStoreError::RoleNotFound)
+                }
+            }
+            Err(e) => Err(UserStoreError::UnexpectedError(eyre!(e))),
+        }
+    }
+
+    #[tracing::instrument(name = "Revoke role in SQLite", skip_all)]
+    async fn revoke_role(&self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        sqlx::query("DELETE FROM user_roles WHERE email = ?1 AND role = ?2")
+            .bind(email.as_ref().expose_secret())
+            .bind(role.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving roles from SQLite", skip_all)]
+    async fn get_roles(&self, email: &Email) -> Result<Vec<Role>, UserStoreError> {
+        let roles: Vec<String> =
+            sqlx::query_scalar("SELECT role FROM user_roles WHERE email = ?1 ORDER BY role")
+                .bind(email.as_ref().expose_secret())
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        roles
+            .iter()
+            .map(|role| Role::parse(role).map_err(|e| UserStoreError::UnexpectedError(eyre!(e))))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving user profile from SQLite", skip_all)]
+    async fn get_user_profile(&self, email: &Email) -> Result<UserProfile, UserStoreError> {
+        let row: UserProfileRow = sqlx::query_as(&format!(
+            "{USER_PROFILE_QUERY} WHERE users.email = ?1 GROUP BY users.email"
+        ))
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        into_user_profile(row)
+    }
+
+    #[tracing::instrument(name = "Searching users in SQLite", skip_all)]
+    async fn search_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        // LIKE is case-insensitive for ASCII in SQLite, like ILIKE in Postgres
+        let pattern = query.email_like_pattern();
+
+        let total: i64 = sqlx::query_scalar(
+            r#"
+                SELECT COUNT(*)
+                FROM users
+                WHERE ?1 IS NULL OR email LIKE ?1 ESCAPE '\'
+            "#,
+        )
+        .bind(&pattern)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        let rows: Vec<UserProfileRow> = sqlx::query_as(&format!(
+            r#"
+                {USER_PROFILE_QUERY}
+                WHERE ?1 IS NULL OR users.email LIKE ?1 ESCAPE '\'
+                GROUP BY users.email
+                ORDER BY users.email
+                LIMIT ?2 OFFSET ?3
+            "#
+        ))
+        .bind(&pattern)
+        .bind(i64::from(query.per_page))
+        .bind(query.offset() as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        let users = rows
+            .into_iter()
+            .map(into_user_profile)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total as u64,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user status from SQLite", skip_all)]
+    async fn get_status(&self, email: &Email) -> Result<UserStatus, UserStoreError> {
+        let status: String = sqlx::query_scalar("SELECT status FROM users WHERE email = ?1")
+            .bind(email.as_ref().expose_secret())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        parse_status(&status)
+    }
+
+    #[tracing::instrument(name = "Set user status in SQLite", skip_all)]
+    async fn set_status(&self, email: &Email, status: UserStatus) -> Result<(), UserStoreError> {
+        let result = sqlx::query("UPDATE users SET status = ?1 WHERE email = ?2 AND status <> ?3")
+            .bind(status.as_str())
+            .bind(email.as_ref().expose_secret())
+            .bind(UserStatus::PendingDeletion.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            // Either missing or pending deletion
+            return match self.get_status(email).await? {
+                UserStatus::PendingDeletion => Err(UserStoreError::UserPendingDeletion),
+                _ => Err(UserStoreError::UnexpectedError(eyre!(
+                    "Status update matched no user"
+                ))),
+            };
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Set requires 2FA in SQLite", skip_all)]
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        self.update_user_column("requires_2fa", requires_2fa, email)
+            .await
+    }
+
+    #[tracing::instrument(name = "Set password reset required in SQLite", skip_all)]
+    async fn set_password_reset_required(
+        &self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        self.update_user_column("password_reset_required", required, email)
+            .await
+    }
+
+    #[tracing::instrument(name = "Remember device in SQLite", skip_all)]
+    async fn remember_device(
+        &self,
+        email: &Email,
+        fingerprint: &DeviceFingerprint,
+    ) -> Result<bool, UserStoreError> {
+        let now = self.current_timestamp();
+        let inserted = sqlx::query(
+            r#"
+                INSERT INTO user_devices (email, fingerprint, first_seen_at, last_seen_at)
+                VALUES (?1, ?2, ?3, ?3)
+                ON CONFLICT (email, fingerprint) DO NOTHING
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(fingerprint.as_str())
+        .bind(&now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            if let Some(db_err) = e.as_database_error()
+                && db_err.is_foreign_key_violation()
+            {
+                return UserStoreError::UserNotFound;
+            }
+            UserStoreError::UnexpectedError(eyre!(e))
+        })?
+        .rows_affected()
+            == 1;
+
+        if !inserted {
+            sqlx::query(
+                r#"
+                    UPDATE user_devices
+                    SET last_seen_at = ?3
+                    WHERE email = ?1 AND fingerprint = ?2
+                "#,
+            )
+            .bind(email.as_ref().expose_secret())
+            .bind(fingerprint.as_str())
+            .bind(&now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        }
+
+        Ok(inserted)
+    }
+
+    #[tracing::instrument(name = "Retrieving known devices from SQLite", skip_all)]
+    async fn known_devices(&self, email: &Email) -> Result<Vec<KnownDevice>, UserStoreError> {
+        // Tells a user without devices apart from a missing one
+        self.get_status(email).await?;
+
+        let rows: Vec<(String, DateTime<Utc>, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+                SELECT fingerprint, first_seen_at, last_seen_at
+                FROM user_devices
+                WHERE email = ?1
+                ORDER BY last_seen_at DESC
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(fingerprint, first_seen_at, last_seen_at)| KnownDevice {
+                fingerprint: DeviceFingerprint::from(fingerprint),
+                first_seen_at,
+                last_seen_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Retrieving notification preference from SQLite", skip_all)]
+    async fn security_notifications_enabled(&self, email: &Email) -> Result<bool, UserStoreError> {
+        sqlx::query_scalar("SELECT security_notifications FROM users WHERE email = ?1")
+            .bind(email.as_ref().expose_secret())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+            .ok_or(UserStoreError::UserNotFound)
+    }
+
+    #[tracing::instrument(name = "Set notification preference in SQLite", skip_all)]
+    async fn set_security_notifications(
+        &self,
+        email: &Email,
+        enabled: bool,
+    ) -> Result<(), UserStoreError> {
+        self.update_user_column("security_notifications", enabled, email)
+            .await
+    }
+
+    #[tracing::instrument(name = "Starting phone verification in SQLite", skip_all)]
+    async fn start_phone_verification(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+        code: &TwoFaCode,
+    ) -> Result<(), UserStoreError> {
+        sqlx::query(
+            r#"
+                INSERT INTO phone_verifications (email, phone_number, code, expires_at)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (email) DO UPDATE
+                SET phone_number = excluded.phone_number,
+                    code = excluded.code,
+                    attempts = 0,
+                    expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(phone_number.as_ref().expose_secret())
+        .bind(code.as_str())
+        .bind(self.clock.now().timestamp() + PHONE_VERIFICATION_TTL_SECONDS)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            if let Some(db_err) = e.as_database_error()
+                && db_err.is_foreign_key_violation()
+            {
+                return UserStoreError::UserNotFound;
+            }
+            UserStoreError::UnexpectedError(eyre!(e))
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Confirming phone verification in SQLite", skip_all)]
+    async fn confirm_phone_verification(
+        &self,
+        email: &Email,
+        code: &TwoFaCode,
+    ) -> Result<PhoneNumber, UserStoreError> {
+        // SQLite has no FOR UPDATE, taking the write lock up front keeps two
+        // confirmations from reading the same attempt count
+        let mut transaction = self
+            .pool
+            .begin_with("BEGIN IMMEDIATE")
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        let pending: PhoneVerificationRow = sqlx::query_as(
+            r#"
+                SELECT phone_number, code, attempts, expires_at <= ?2 AS expired
+                FROM phone_verifications
+                WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(self.clock.now().timestamp())
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::InvalidVerificationCode)?;
+
+        let matches = pending.code == code.as_str();
+        let attempts = pending.attempts + 1;
+
+        if matches || pending.expired || attempts >= MAX_PHONE_VERIFICATION_ATTEMPTS {
+            sqlx::query("DELETE FROM phone_verifications WHERE email = ?1")
+                .bind(email.as_ref().expose_secret())
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        } else {
+            sqlx::query("UPDATE phone_verifications SET attempts = ?2 WHERE email = ?1")
+                .bind(email.as_ref().expose_secret())
+                .bind(attempts)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+        }
+
+        if !matches || pending.expired {
+            commit(transaction).await?;
+            return Err(UserStoreError::InvalidVerificationCode);
+        }
+
+        sqlx::query("UPDATE users SET phone_number = ?2 WHERE email = ?1")
+            .bind(email.as_ref().expose_secret())
+            .bind(&pending.phone_number)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        commit(transaction).await?;
+
+        parse_phone_number(pending.phone_number)
+    }
+
+    #[tracing::instrument(name = "Setting two-factor channel in SQLite", skip_all)]
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFaChannel,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+                UPDATE users
+                SET two_fa_channel = ?2
+                WHERE email = ?1 AND (?2 <> 'sms' OR phone_number IS NOT NULL)
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(channel.as_str())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            // Tell a missing user apart from one without a phone number
+            self.get_user(email).await?;
+            return Err(UserStoreError::PhoneNumberNotVerified);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Scheduling user deletion in SQLite", skip_all)]
+    async fn schedule_deletion(
+        &self,
+        email: &Email,
+        due_at: DateTime<Utc>,
+        restore_token: &RestoreToken,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+                UPDATE users
+                SET status = ?2, deletion_due_at = ?3, restore_token_hash = ?4
+                WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(UserStatus::PendingDeletion.as_str())
+        .bind(due_at.timestamp())
+        .bind(restore_token.hash())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Restoring user in SQLite", skip_all)]
+    async fn restore_user(
+        &self,
+        restore_token: &RestoreToken,
+        now: DateTime<Utc>,
+    ) -> Result<Email, UserStoreError> {
+        let email: String = sqlx::query_scalar(
+            r#"
+                UPDATE users
+                SET status = ?3, deletion_due_at = NULL, restore_token_hash = NULL
+                WHERE restore_token_hash = ?1 AND status = ?4 AND deletion_due_at > ?2
+                RETURNING email
+            "#,
+        )
+        .bind(restore_token.hash())
+        .bind(now.timestamp())
+        .bind(UserStatus::Active.as_str())
+        .bind(UserStatus::PendingDeletion.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::InvalidRestoreToken)?;
+
+        Email::try_from(Secret::from(email)).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+    }
+
+    #[tracing::instrument(name = "Retrieving due deletions from SQLite", skip_all)]
+    async fn due_deletions(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Email>, UserStoreError> {
+        let emails: Vec<String> = sqlx::query_scalar(
+            r#"
+                SELECT email
+                FROM users
+                WHERE status = ?1 AND deletion_due_at <= ?2
+                ORDER BY deletion_due_at
+                LIMIT ?3
+            "#,
+        )
+        .bind(UserStatus::PendingDeletion.as_str())
+        .bind(now.timestamp())
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        emails
+            .into_iter()
+            .map(|email| {
+                Email::try_from(Secret::from(email))
+                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+            })
+            .collect()
+    }
+}
+
+impl SqliteUserStore {
+    async fn insert_user(
+        &self,
+        email: &Email,
+        password_hash: &Secret<String>,
+        requires_2fa: bool,
+        status: UserStatus,
+        password_reset_required: bool,
+        roles: &[Role],
+    ) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        sqlx::query(
+            r#"
+                INSERT INTO users (email, password_hash, requires_2fa, status, password_reset_required)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(password_hash.expose_secret())
+        .bind(requires_2fa)
+        .bind(status.as_str())
+        .bind(password_reset_required)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| {
+            if let Some(db_err) = e.as_database_error()
+                && db_err.is_unique_violation()
+            {
+                return UserStoreError::UserAlreadyExists;
+            }
+            UserStoreError::UnexpectedError(eyre!(e))
+        })?;
+
+        let roles = std::iter::once(Role::USER).chain(roles.iter().map(Role::as_str));
+        for role in roles {
+            sqlx::query(
+                "INSERT INTO user_roles (email, role) VALUES (?1, ?2) ON CONFLICT DO NOTHING",
+            )
+            .bind(email.as_ref().expose_secret())
+            .bind(role)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| {
+                // The user was just added, so only the role can be missing
+                if let Some(db_err) = e.as_database_error()
+                    && db_err.is_foreign_key_violation()
+                {
+                    return UserStoreError::RoleNotFound;
+                }
+                UserStoreError::UnexpectedError(eyre!(e))
+            })?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(())
+    }
+
+    // Replaces an imported or outdated hash now that the password is known.
+    // A failure only means the old hash is kept until the next login.
+    #[tracing::instrument(name = "Rehashing password in SQLite", skip_all)]
+    async fn rehash_password(&self, email: &Email, old_hash: &Secret<String>, password: &Password) {
+        let new_hash = match compute_password_hash(password.clone()).await {
+            Ok(new_hash) => new_hash,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to rehash password");
+                return;
+            }
+        };
+
+        // Matching the old hash keeps a password changed in the meantime
+        let result = sqlx::query(
+            "UPDATE users SET password_hash = ?1 WHERE email = ?2 AND password_hash = ?3",
+        )
+        .bind(new_hash.expose_secret())
+        .bind(email.as_ref().expose_secret())
+        .bind(old_hash.expose_secret())
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!(error = ?e, "Failed to store rehashed password");
+        }
+    }
+
+    async fn fetch_user_row(&self, email: &Email) -> Result<UserRow, UserStoreError> {
+        sqlx::query_as(
+            r#"
+                SELECT email, password_hash, requires_2fa, status, phone_number, two_fa_channel
+                FROM users
+                WHERE email = ?1
+            "#,
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?
+        .ok_or(UserStoreError::UserNotFound)
+    }
+
+    // Column names are only ever string literals from this file
+    async fn update_user_column<T>(
+        &self,
+        column: &'static str,
+        value: T,
+        email: &Email,
+    ) -> Result<(), UserStoreError>
+    where
+        T: 'static + Send + for<'q> sqlx::Encode<'q, Sqlite> + sqlx::Type<Sqlite>,
+    {
+        let sql = format!("UPDATE users SET {column} = ?1 WHERE email = ?2");
+        let result = sqlx::query(&sql)
+            .bind(value)
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+async fn commit(transaction: Transaction<'_, Sqlite>) -> Result<(), UserStoreError> {
+    transaction
+        .commit()
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn parse_phone_number(phone_number: String) -> Result<PhoneNumber, UserStoreError> {
+    PhoneNumber::try_from(Secret::new(phone_number))
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn parse_two_fa_channel(channel: &str) -> Result<TwoFaChannel, UserStoreError> {
+    channel
+        .parse::<TwoFaChannel>()
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn parse_status(status: &str) -> Result<UserStatus, UserStoreError> {
+    status
+        .parse::<UserStatus>()
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn into_user_profile(row: UserProfileRow) -> Result<UserProfile, UserStoreError> {
+    let email = Email::try_from(Secret::from(row.email))
+        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
+    let mut roles = row
+        .roles
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|role| !role.is_empty())
+        .map(|role| Role::parse(role).map_err(|e| UserStoreError::UnexpectedError(eyre!(e))))
+        .collect::<Result<Vec<_>, _>>()?;
+    roles.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+    Ok(UserProfile {
+        email,
+        requires_2fa: row.requires_2fa,
+        status: parse_status(&row.status)?,
+        password_reset_required: row.password_reset_required,
+        roles,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{clock::MockClock, password_hash::PasswordHash},
+        services::data_stores::user_store_suite::user_store_tests,
+    };
+    use secrecy::{ExposeSecret, Secret};
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn setup_db() -> SqlitePool {
+        // In-memory databases live as long as their connection, so the pool
+        // must hold on to exactly one
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to open database");
+
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to migrate the database");
+
+        pool
+    }
+
+    async fn setup(clock: Arc<MockClock>) -> ((), SqliteUserStore) {
+        ((), SqliteUserStore::with_clock(setup_db().await, clock))
+    }
+
+    user_store_tests!(setup);
+
+    #[tokio::test]
+    async fn test_imported_hash_is_replaced_on_login() {
+        let pool = setup_db().await;
+        let store = SqliteUserStore::new(pool.clone());
+        let email = Email::try_from(Secret::from("test@example.com".to_string())).unwrap();
+        let password = Password::try_from(Secret::from("password123".to_string())).unwrap();
+        let bcrypt_hash = bcrypt::hash(password.as_ref().expose_secret(), 4).unwrap();
+
+        store
+            .import_user(ImportedUser {
+                email: email.clone(),
+                password_hash: PasswordHash::try_from(Secret::new(bcrypt_hash)).unwrap(),
+                requires_2fa: false,
+                status: UserStatus::Active,
+                password_reset_required: false,
+                roles: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let stored_hash = || async {
+            sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE email = ?1")
+                .bind(email.as_ref().expose_secret())
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+        assert!(stored_hash().await.starts_with("$2b$"));
+
+        store.authenticate_user(&email, &password).await.unwrap();
+        assert!(stored_hash().await.starts_with("$argon2id$"));
+    }
+}
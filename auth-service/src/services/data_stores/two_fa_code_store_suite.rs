@@ -0,0 +1,211 @@
+// Cases every TwoFaCodeStore has to pass, run against each backend with
+// two_fa_code_store_tests!. Expiry depends on how a backend keeps time and
+// is tested next to each store instead.
+use secrecy::Secret;
+
+use crate::domain::{
+    data_stores::{TwoFaCodeStore, TwoFaCodeStoreError},
+    email::Email,
+    two_fa_attempt_id::TwoFaAttemptId,
+    two_fa_code::TwoFaCode,
+};
+
+fn parse_email(address: &str) -> Email {
+    Email::try_from(Secret::new(address.to_owned())).unwrap()
+}
+
+pub async fn stored_code_is_retrieved(store: &dyn TwoFaCodeStore) {
+    let email = parse_email("test@example.com");
+    let attempt_id = TwoFaAttemptId::new();
+    let code = TwoFaCode::new();
+
+    store
+        .store_code(email.clone(), attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.get_login_attempt_id_and_two_fa_code(&email).await,
+        Ok((attempt_id, code))
+    );
+}
+
+pub async fn storing_again_replaces_the_code(store: &dyn TwoFaCodeStore) {
+    let email = parse_email("test@example.com");
+    let attempt_id = TwoFaAttemptId::new();
+    let code = TwoFaCode::new();
+
+    store
+        .store_code(email.clone(), TwoFaAttemptId::new(), TwoFaCode::new())
+        .await
+        .unwrap();
+    store
+        .store_code(email.clone(), attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.get_login_attempt_id_and_two_fa_code(&email).await,
+        Ok((attempt_id, code))
+    );
+}
+
+pub async fn validate_accepts_the_stored_code(store: &dyn TwoFaCodeStore) {
+    let email = parse_email("test@example.com");
+    let attempt_id = TwoFaAttemptId::new();
+    let code = TwoFaCode::new();
+
+    store
+        .store_code(email.clone(), attempt_id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(store.validate(&email, &attempt_id, &code).await, Ok(()));
+}
+
+pub async fn validate_rejects_another_attempt_id(store: &dyn TwoFaCodeStore) {
+    let email = parse_email("test@example.com");
+    let code = TwoFaCode::new();
+
+    store
+        .store_code(email.clone(), TwoFaAttemptId::new(), code.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.validate(&email, &TwoFaAttemptId::new(), &code).await,
+        Err(TwoFaCodeStoreError::InvalidAttemptId)
+    );
+}
+
+pub async fn validate_rejects_another_code(store: &dyn TwoFaCodeStore) {
+    let email = parse_email("test@example.com");
+    let attempt_id = TwoFaAttemptId::new();
+    let code = TwoFaCode::parse("123456".to_owned()).unwrap();
+
+    store
+        .store_code(email.clone(), attempt_id.clone(), code)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .validate(
+                &email,
+                &attempt_id,
+                &TwoFaCode::parse("654321".to_owned()).unwrap()
+            )
+            .await,
+        Err(TwoFaCodeStoreError::Invalid2FACode)
+    );
+}
+
+pub async fn missing_code_is_not_found(store: &dyn TwoFaCodeStore) {
+    let email = parse_email("test@example.com");
+
+    assert_eq!(
+        store.get_login_attempt_id_and_two_fa_code(&email).await,
+        Err(TwoFaCodeStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store
+            .validate(&email, &TwoFaAttemptId::new(), &TwoFaCode::new())
+            .await,
+        Err(TwoFaCodeStoreError::UserNotFound)
+    );
+}
+
+pub async fn deleted_code_is_not_found(store: &dyn TwoFaCodeStore) {
+    let email = parse_email("test@example.com");
+
+    store
+        .store_code(email.clone(), TwoFaAttemptId::new(), TwoFaCode::new())
+        .await
+        .unwrap();
+    store.delete(&email).await.unwrap();
+
+    assert_eq!(
+        store.get_login_attempt_id_and_two_fa_code(&email).await,
+        Err(TwoFaCodeStoreError::UserNotFound)
+    );
+}
+
+pub async fn deleting_a_missing_code_is_not_an_error(store: &dyn TwoFaCodeStore) {
+    assert_eq!(store.delete(&parse_email("test@example.com")).await, Ok(()));
+}
+
+pub async fn codes_are_kept_per_user(store: &dyn TwoFaCodeStore) {
+    let first = parse_email("user1@example.com");
+    let second = parse_email("user2@example.com");
+    let (first_attempt_id, first_code) = (TwoFaAttemptId::new(), TwoFaCode::new());
+    let (second_attempt_id, second_code) = (TwoFaAttemptId::new(), TwoFaCode::new());
+
+    // Stored concurrently, which the service does for users logging in at once
+    let (stored_first, stored_second) = tokio::join!(
+        store.store_code(first.clone(), first_attempt_id.clone(), first_code.clone()),
+        store.store_code(
+            second.clone(),
+            second_attempt_id.clone(),
+            second_code.clone()
+        ),
+    );
+    stored_first.unwrap();
+    stored_second.unwrap();
+
+    assert_eq!(
+        store.validate(&first, &first_attempt_id, &first_code).await,
+        Ok(())
+    );
+    assert_eq!(
+        store
+            .validate(&second, &second_attempt_id, &second_code)
+            .await,
+        Ok(())
+    );
+    assert_eq!(
+        store
+            .validate(&first, &second_attempt_id, &second_code)
+            .await,
+        Err(TwoFaCodeStoreError::InvalidAttemptId)
+    );
+
+    store.delete(&first).await.unwrap();
+    assert_eq!(
+        store
+            .validate(&second, &second_attempt_id, &second_code)
+            .await,
+        Ok(())
+    );
+}
+
+// Expands to one test per case, each with a store from `$setup`, an async fn
+// returning whatever has to outlive the test (e.g. a container) and the store.
+macro_rules! two_fa_code_store_tests {
+    ($setup:ident) => {
+        $crate::services::data_stores::two_fa_code_store_suite::two_fa_code_store_tests!(
+            @cases $setup;
+            stored_code_is_retrieved,
+            storing_again_replaces_the_code,
+            validate_accepts_the_stored_code,
+            validate_rejects_another_attempt_id,
+            validate_rejects_another_code,
+            missing_code_is_not_found,
+            deleted_code_is_not_found,
+            deleting_a_missing_code_is_not_an_error,
+            codes_are_kept_per_user,
+        );
+    };
+    (@cases $setup:ident; $($case:ident),* $(,)?) => {
+        mod two_fa_code_store_suite {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    let (_guard, store) = super::$setup().await;
+                    $crate::services::data_stores::two_fa_code_store_suite::$case(&store).await;
+                }
+            )*
+        }
+    };
+}
+
+pub(crate) use two_fa_code_store_tests;
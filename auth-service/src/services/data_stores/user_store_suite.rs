@@ -0,0 +1,767 @@
+// Cases every UserStore has to pass, run against each backend with
+// user_store_tests!. Each store is built with the MockClock handed to the
+// case, so expiry can be tested without sleeping.
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, SubsecRound, Utc};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{
+    clock::{Clock, MockClock},
+    data_stores::{
+        MAX_PHONE_VERIFICATION_ATTEMPTS, PHONE_VERIFICATION_TTL_SECONDS, UserQuery, UserStore,
+        UserStoreError,
+    },
+    device_fingerprint::DeviceFingerprint,
+    email::Email,
+    password::Password,
+    password_hash::PasswordHash,
+    phone_number::PhoneNumber,
+    restore_token::RestoreToken,
+    role::Role,
+    two_fa_channel::TwoFaChannel,
+    two_fa_code::TwoFaCode,
+    user::{ImportedUser, User, ValidatedUser},
+    user_status::UserStatus,
+};
+
+// Whole seconds, which every backend stores without rounding
+pub fn mock_clock() -> Arc<MockClock> {
+    Arc::new(MockClock::new(Utc::now().trunc_subsecs(0)))
+}
+
+fn parse_email(address: &str) -> Email {
+    Email::try_from(Secret::from(address.to_owned())).unwrap()
+}
+
+fn parse_password(password: &str) -> Password {
+    Password::try_from(Secret::from(password.to_owned())).unwrap()
+}
+
+fn missing_email() -> Email {
+    parse_email("nonexistent@example.com")
+}
+
+fn create_test_user() -> User {
+    User::new(
+        parse_email(&format!("test{}@example.com", uuid::Uuid::new_v4())),
+        parse_password("password123"),
+        false,
+    )
+}
+
+fn create_test_user_with_2fa() -> User {
+    User::new(
+        parse_email(&format!("test2fa{}@example.com", uuid::Uuid::new_v4())),
+        parse_password("password123"),
+        true,
+    )
+}
+
+fn phone_number() -> PhoneNumber {
+    PhoneNumber::try_from(Secret::from("+4791234567".to_string())).unwrap()
+}
+
+pub async fn added_user_is_stored(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(store.get_user(user.email()).await, Ok(user));
+}
+
+pub async fn adding_a_user_twice_fails(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(
+        store.add_user(user).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+}
+
+pub async fn get_missing_user_fails(store: &dyn UserStore, _clock: &MockClock) {
+    assert_eq!(
+        store.get_user(&missing_email()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn authenticate_user_without_2fa(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    let email = user.email().clone();
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(
+        store.authenticate_user(&email, user.password()).await,
+        Ok(ValidatedUser::No2Fa(email))
+    );
+}
+
+pub async fn authenticate_user_with_2fa(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user_with_2fa();
+    let email = user.email().clone();
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(
+        store.authenticate_user(&email, user.password()).await,
+        Ok(ValidatedUser::Requires2Fa(email))
+    );
+}
+
+pub async fn authenticate_missing_user_fails(store: &dyn UserStore, _clock: &MockClock) {
+    assert_eq!(
+        store
+            .authenticate_user(&missing_email(), &parse_password("password123"))
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn authenticate_with_wrong_password_fails(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(
+        store
+            .authenticate_user(user.email(), &parse_password("wrongpassword"))
+            .await,
+        Err(UserStoreError::IncorrectPassword)
+    );
+}
+
+pub async fn set_new_password_replaces_the_password(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    let email = user.email().clone();
+    let new_password = parse_password("newpassword123");
+    store.add_user(user.clone()).await.unwrap();
+
+    store
+        .set_new_password(&email, new_password.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.authenticate_user(&email, &new_password).await,
+        Ok(ValidatedUser::No2Fa(email.clone()))
+    );
+    assert_eq!(
+        store.authenticate_user(&email, user.password()).await,
+        Err(UserStoreError::IncorrectPassword)
+    );
+}
+
+pub async fn imported_user_logs_in_with_their_password(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    let email = user.email().clone();
+    let bcrypt_hash = bcrypt::hash(user.password().as_ref().expose_secret(), 4).unwrap();
+
+    store
+        .import_user(ImportedUser {
+            email: email.clone(),
+            password_hash: PasswordHash::try_from(Secret::new(bcrypt_hash)).unwrap(),
+            requires_2fa: false,
+            status: UserStatus::Active,
+            password_reset_required: false,
+            roles: Vec::new(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(store.password_needs_rehash(&email).await, Ok(true));
+
+    assert_eq!(
+        store
+            .authenticate_user(&email, &parse_password("wrongpassword"))
+            .await,
+        Err(UserStoreError::IncorrectPassword)
+    );
+    // The first login replaces the imported hash, the second checks our own
+    for _ in 0..2 {
+        assert_eq!(
+            store.authenticate_user(&email, user.password()).await,
+            Ok(ValidatedUser::No2Fa(email.clone()))
+        );
+        assert_eq!(store.password_needs_rehash(&email).await, Ok(false));
+    }
+}
+
+pub async fn imported_user_keeps_roles_and_reset_flag(store: &dyn UserStore, _clock: &MockClock) {
+    let email = create_test_user().email().clone();
+    let bcrypt_hash = bcrypt::hash("password123", 4).unwrap();
+
+    store
+        .import_user(ImportedUser {
+            email: email.clone(),
+            password_hash: PasswordHash::try_from(Secret::new(bcrypt_hash)).unwrap(),
+            requires_2fa: true,
+            status: UserStatus::Active,
+            password_reset_required: true,
+            roles: vec![Role::admin()],
+        })
+        .await
+        .unwrap();
+
+    let profile = store.get_user_profile(&email).await.unwrap();
+    assert!(profile.requires_2fa);
+    assert!(profile.password_reset_required);
+    assert_eq!(profile.roles, vec![Role::admin(), Role::user()]);
+}
+
+pub async fn import_with_unknown_role_leaves_nothing_behind(
+    store: &dyn UserStore,
+    _clock: &MockClock,
+) {
+    let email = create_test_user().email().clone();
+    let bcrypt_hash = bcrypt::hash("password123", 4).unwrap();
+
+    assert_eq!(
+        store
+            .import_user(ImportedUser {
+                email: email.clone(),
+                password_hash: PasswordHash::try_from(Secret::new(bcrypt_hash)).unwrap(),
+                requires_2fa: false,
+                status: UserStatus::Active,
+                password_reset_required: false,
+                roles: vec![Role::parse("unknown").unwrap()],
+            })
+            .await,
+        Err(UserStoreError::RoleNotFound)
+    );
+    assert_eq!(
+        store.get_user(&email).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn delete_user_removes_the_user(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+
+    store.delete_user(user.email()).await.unwrap();
+
+    assert_eq!(
+        store.get_user(user.email()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn delete_missing_user_fails(store: &dyn UserStore, _clock: &MockClock) {
+    assert_eq!(
+        store.delete_user(&missing_email()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn new_user_has_user_role(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(store.get_roles(user.email()).await, Ok(vec![Role::user()]));
+}
+
+pub async fn assign_and_revoke_role(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+
+    store
+        .assign_role(user.email(), &Role::admin())
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_roles(user.email()).await,
+        Ok(vec![Role::admin(), Role::user()])
+    );
+
+    store
+        .revoke_role(user.email(), &Role::admin())
+        .await
+        .unwrap();
+    assert_eq!(store.get_roles(user.email()).await, Ok(vec![Role::user()]));
+}
+
+pub async fn assign_unknown_role_fails(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+
+    assert_eq!(
+        store
+            .assign_role(user.email(), &Role::parse("unknown").unwrap())
+            .await,
+        Err(UserStoreError::RoleNotFound)
+    );
+}
+
+pub async fn assign_role_to_missing_user_fails(store: &dyn UserStore, _clock: &MockClock) {
+    assert_eq!(
+        store.assign_role(&missing_email(), &Role::admin()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn inactive_users_cannot_authenticate(store: &dyn UserStore, clock: &MockClock) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+
+    for (status, error) in [
+        (UserStatus::Suspended, UserStoreError::UserSuspended),
+        (
+            UserStatus::PendingVerification,
+            UserStoreError::UserPendingVerification,
+        ),
+        (UserStatus::Locked, UserStoreError::UserLocked),
+    ] {
+        store.set_status(user.email(), status).await.unwrap();
+        assert_eq!(
+            store.authenticate_user(user.email(), user.password()).await,
+            Err(error)
+        );
+    }
+
+    store
+        .set_status(user.email(), UserStatus::Active)
+        .await
+        .unwrap();
+    assert!(
+        store
+            .authenticate_user(user.email(), user.password())
+            .await
+            .is_ok()
+    );
+
+    store
+        .schedule_deletion(
+            user.email(),
+            clock.now() + Duration::days(30),
+            &RestoreToken::generate(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        store.authenticate_user(user.email(), user.password()).await,
+        Err(UserStoreError::UserPendingDeletion)
+    );
+}
+
+pub async fn get_status_sees_updates_immediately(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user().with_status(UserStatus::PendingVerification);
+
+    store.add_user(user.clone()).await.unwrap();
+    assert_eq!(
+        store.get_status(user.email()).await,
+        Ok(UserStatus::PendingVerification)
+    );
+    assert_eq!(
+        store.get_user(user.email()).await.unwrap().status(),
+        UserStatus::PendingVerification
+    );
+
+    store
+        .set_status(user.email(), UserStatus::Suspended)
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_status(user.email()).await,
+        Ok(UserStatus::Suspended)
+    );
+
+    store.delete_user(user.email()).await.unwrap();
+    assert_eq!(
+        store.get_status(user.email()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn get_user_profile(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+    store.set_requires_2fa(user.email(), true).await.unwrap();
+    store
+        .set_password_reset_required(user.email(), true)
+        .await
+        .unwrap();
+
+    let profile = store.get_user_profile(user.email()).await.unwrap();
+    assert_eq!(profile.email, *user.email());
+    assert!(profile.requires_2fa);
+    assert_eq!(profile.status, UserStatus::Active);
+    assert!(profile.password_reset_required);
+    assert_eq!(profile.roles, vec![Role::user()]);
+
+    store
+        .set_new_password(user.email(), parse_password("newpassword123"))
+        .await
+        .unwrap();
+    let profile = store.get_user_profile(user.email()).await.unwrap();
+    assert!(!profile.password_reset_required);
+}
+
+pub async fn search_users_paginates(store: &dyn UserStore, _clock: &MockClock) {
+    for i in 0..3 {
+        let user = User::new(
+            parse_email(&format!("search{}@example.com", i)),
+            parse_password("password123"),
+            false,
+        );
+        store.add_user(user).await.unwrap();
+    }
+    store.add_user(create_test_user()).await.unwrap();
+
+    let query = UserQuery {
+        email_contains: Some("search".to_owned()),
+        page: 2,
+        per_page: 2,
+    };
+    let page = store.search_users(&query).await.unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(
+        page.users[0].email.as_ref().expose_secret(),
+        "search2@example.com"
+    );
+
+    // Matched literally, not as a LIKE wildcard
+    let query = UserQuery {
+        email_contains: Some("%".to_owned()),
+        page: 1,
+        per_page: 10,
+    };
+    assert_eq!(store.search_users(&query).await.unwrap().total, 0);
+}
+
+pub async fn set_flags_on_missing_user_fails(store: &dyn UserStore, _clock: &MockClock) {
+    assert_eq!(
+        store
+            .set_status(&missing_email(), UserStatus::Suspended)
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store.get_user_profile(&missing_email()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn remember_device(store: &dyn UserStore, clock: &MockClock) {
+    let user = create_test_user();
+    let email = user.email().clone();
+    store.add_user(user).await.unwrap();
+    let fingerprint = DeviceFingerprint::new(Some("curl/8.5.0"), Some("203.0.113.7"));
+    let other = DeviceFingerprint::new(Some("curl/8.5.0"), Some("198.51.100.7"));
+
+    assert!(store.remember_device(&email, &fingerprint).await.unwrap());
+    clock.advance(StdDuration::from_secs(60));
+    assert!(store.remember_device(&email, &other).await.unwrap());
+    clock.advance(StdDuration::from_secs(60));
+    assert!(!store.remember_device(&email, &fingerprint).await.unwrap());
+
+    // Most recently seen first
+    let devices = store.known_devices(&email).await.unwrap();
+    assert_eq!(devices.len(), 2);
+    assert_eq!(devices[0].fingerprint, fingerprint);
+    assert_eq!(devices[0].last_seen_at, clock.now());
+    assert_eq!(devices[0].first_seen_at, clock.now() - Duration::minutes(2));
+    assert_eq!(devices[1].fingerprint, other);
+
+    assert_eq!(
+        store.remember_device(&missing_email(), &fingerprint).await,
+        Err(UserStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store.known_devices(&missing_email()).await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn security_notification_preference(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user();
+    let email = user.email().clone();
+    store.add_user(user).await.unwrap();
+
+    assert!(store.security_notifications_enabled(&email).await.unwrap());
+
+    store
+        .set_security_notifications(&email, false)
+        .await
+        .unwrap();
+    assert!(!store.security_notifications_enabled(&email).await.unwrap());
+
+    assert_eq!(
+        store
+            .set_security_notifications(&missing_email(), true)
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn phone_verification_and_two_fa_channel(store: &dyn UserStore, _clock: &MockClock) {
+    let user = create_test_user_with_2fa();
+    let email = user.email().clone();
+    store.add_user(user).await.unwrap();
+
+    assert_eq!(
+        store.set_two_fa_channel(&email, TwoFaChannel::Sms).await,
+        Err(UserStoreError::PhoneNumberNotVerified)
+    );
+
+    let code = TwoFaCode::parse("123456".to_owned()).unwrap();
+    let wrong_code = TwoFaCode::parse("654321".to_owned()).unwrap();
+    store
+        .start_phone_verification(&email, &phone_number(), &code)
+        .await
+        .unwrap();
+    assert_eq!(store.get_user(&email).await.unwrap().phone_number(), None);
+    assert_eq!(
+        store.confirm_phone_verification(&email, &wrong_code).await,
+        Err(UserStoreError::InvalidVerificationCode)
+    );
+    assert_eq!(
+        store.confirm_phone_verification(&email, &code).await,
+        Ok(phone_number())
+    );
+    // The code can only be used once
+    assert_eq!(
+        store.confirm_phone_verification(&email, &code).await,
+        Err(UserStoreError::InvalidVerificationCode)
+    );
+
+    store
+        .set_two_fa_channel(&email, TwoFaChannel::Sms)
+        .await
+        .unwrap();
+    let user = store.get_user(&email).await.unwrap();
+    assert_eq!(user.phone_number(), Some(&phone_number()));
+    assert_eq!(user.two_fa_channel(), TwoFaChannel::Sms);
+
+    assert_eq!(
+        store
+            .start_phone_verification(&missing_email(), &phone_number(), &code)
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store
+            .set_two_fa_channel(&missing_email(), TwoFaChannel::Email)
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn phone_verification_gives_up_after_too_many_wrong_codes(
+    store: &dyn UserStore,
+    _clock: &MockClock,
+) {
+    let user = create_test_user();
+    let email = user.email().clone();
+    store.add_user(user).await.unwrap();
+    let code = TwoFaCode::parse("123456".to_owned()).unwrap();
+    let wrong_code = TwoFaCode::parse("654321".to_owned()).unwrap();
+
+    store
+        .start_phone_verification(&email, &phone_number(), &code)
+        .await
+        .unwrap();
+    for _ in 0..MAX_PHONE_VERIFICATION_ATTEMPTS {
+        assert_eq!(
+            store.confirm_phone_verification(&email, &wrong_code).await,
+            Err(UserStoreError::InvalidVerificationCode)
+        );
+    }
+
+    assert_eq!(
+        store.confirm_phone_verification(&email, &code).await,
+        Err(UserStoreError::InvalidVerificationCode)
+    );
+    assert_eq!(store.get_user(&email).await.unwrap().phone_number(), None);
+}
+
+pub async fn phone_verification_expires(store: &dyn UserStore, clock: &MockClock) {
+    let user = create_test_user();
+    let email = user.email().clone();
+    store.add_user(user).await.unwrap();
+    let code = TwoFaCode::new();
+
+    store
+        .start_phone_verification(&email, &phone_number(), &code)
+        .await
+        .unwrap();
+    clock.advance(StdDuration::from_secs(
+        PHONE_VERIFICATION_TTL_SECONDS as u64,
+    ));
+
+    assert_eq!(
+        store.confirm_phone_verification(&email, &code).await,
+        Err(UserStoreError::InvalidVerificationCode)
+    );
+    assert_eq!(store.get_user(&email).await.unwrap().phone_number(), None);
+}
+
+pub async fn set_status_leaves_scheduled_deletion_alone(store: &dyn UserStore, clock: &MockClock) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+    let token = RestoreToken::generate();
+    store
+        .schedule_deletion(user.email(), clock.now() + Duration::days(30), &token)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.set_status(user.email(), UserStatus::Active).await,
+        Err(UserStoreError::UserPendingDeletion)
+    );
+    assert_eq!(
+        store.get_status(user.email()).await,
+        Ok(UserStatus::PendingDeletion)
+    );
+    // Still restorable with the token
+    assert_eq!(
+        store.restore_user(&token, clock.now()).await,
+        Ok(user.email().clone())
+    );
+}
+
+pub async fn scheduled_deletion_can_be_restored_until_due(
+    store: &dyn UserStore,
+    clock: &MockClock,
+) {
+    let user = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+    let now = clock.now();
+    let token = RestoreToken::generate();
+
+    store
+        .schedule_deletion(user.email(), now + Duration::days(30), &token)
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_status(user.email()).await,
+        Ok(UserStatus::PendingDeletion)
+    );
+    assert_eq!(store.due_deletions(now, 10).await, Ok(vec![]));
+
+    assert_eq!(
+        store.restore_user(&RestoreToken::generate(), now).await,
+        Err(UserStoreError::InvalidRestoreToken)
+    );
+    assert_eq!(
+        store.restore_user(&token, now + Duration::days(30)).await,
+        Err(UserStoreError::InvalidRestoreToken)
+    );
+
+    assert_eq!(
+        store.restore_user(&token, now).await,
+        Ok(user.email().clone())
+    );
+    assert_eq!(store.get_status(user.email()).await, Ok(UserStatus::Active));
+    assert_eq!(
+        store.restore_user(&token, now).await,
+        Err(UserStoreError::InvalidRestoreToken)
+    );
+
+    assert_eq!(
+        store
+            .schedule_deletion(&missing_email(), now, &RestoreToken::generate())
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+pub async fn due_deletions_are_earliest_first(store: &dyn UserStore, clock: &MockClock) {
+    let user = create_test_user();
+    let other = create_test_user();
+    store.add_user(user.clone()).await.unwrap();
+    store.add_user(other.clone()).await.unwrap();
+    let now = clock.now();
+
+    store
+        .schedule_deletion(
+            user.email(),
+            now - Duration::hours(1),
+            &RestoreToken::generate(),
+        )
+        .await
+        .unwrap();
+    store
+        .schedule_deletion(
+            other.email(),
+            now - Duration::days(1),
+            &RestoreToken::generate(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.due_deletions(now, 10).await,
+        Ok(vec![other.email().clone(), user.email().clone()])
+    );
+    assert_eq!(
+        store.due_deletions(now, 1).await,
+        Ok(vec![other.email().clone()])
+    );
+
+    // Changing the status doesn't take the user out of the purge
+    assert_eq!(
+        store.set_status(user.email(), UserStatus::Suspended).await,
+        Err(UserStoreError::UserPendingDeletion)
+    );
+    assert_eq!(
+        store.due_deletions(now, 10).await,
+        Ok(vec![other.email().clone(), user.email().clone()])
+    );
+}
+
+// Expands to one test per case. `$setup` is an async fn taking the clock the
+// store should use and returning whatever has to outlive the test (e.g. a
+// container) and the store.
+macro_rules! user_store_tests {
+    ($setup:ident) => {
+        $crate::services::data_stores::user_store_suite::user_store_tests!(
+            @cases $setup;
+            added_user_is_stored,
+            adding_a_user_twice_fails,
+            get_missing_user_fails,
+            authenticate_user_without_2fa,
+            authenticate_user_with_2fa,
+            authenticate_missing_user_fails,
+            authenticate_with_wrong_password_fails,
+            set_new_password_replaces_the_password,
+            imported_user_logs_in_with_their_password,
+            imported_user_keeps_roles_and_reset_flag,
+            import_with_unknown_role_leaves_nothing_behind,
+            delete_user_removes_the_user,
+            delete_missing_user_fails,
+            new_user_has_user_role,
+            assign_and_revoke_role,
+            assign_unknown_role_fails,
+            assign_role_to_missing_user_fails,
+            inactive_users_cannot_authenticate,
+            get_status_sees_updates_immediately,
+            get_user_profile,
+            search_users_paginates,
+            set_flags_on_missing_user_fails,
+            remember_device,
+            security_notification_preference,
+            phone_verification_and_two_fa_channel,
+            phone_verification_gives_up_after_too_many_wrong_codes,
+            phone_verification_expires,
+            scheduled_deletion_can_be_restored_until_due,
+            set_status_leaves_scheduled_deletion_alone,
+            due_deletions_are_earliest_first,
+        );
+    };
+    (@cases $setup:ident; $($case:ident),* $(,)?) => {
+        mod user_store_suite {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    let clock = $crate::services::data_stores::user_store_suite::mock_clock();
+                    let (_guard, store) = super::$setup(clock.clone()).await;
+                    $crate::services::data_stores::user_store_suite::$case(&store, &clock).await;
+                }
+            )*
+        }
+    };
+}
+
+pub(crate) use user_store_tests;
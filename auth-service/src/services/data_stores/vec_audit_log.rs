@@ -0,0 +1,171 @@
+use std::sync::Mutex;
+
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+
+use crate::domain::data_stores::{
+    AuditEvent, AuditLog, AuditLogError, AuditRecord, ERASED_SUBJECT,
+};
+
+#[derive(Debug, Default)]
+pub struct VecAuditLog {
+    records: Mutex<Vec<AuditRecord>>,
+}
+
+impl VecAuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for VecAuditLog {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        self.records
+            .lock()
+            .map_err(|e| AuditLogError::UnexpectedError(eyre!(e.to_string())))?
+            .push(AuditRecord {
+                event,
+                occurred_at: Utc::now(),
+            });
+        Ok(())
+    }
+
+    async fn recent_events(
+        &self,
+        subject: &str,
+        limit: u32,
+    ) -> Result<Vec<AuditRecord>, AuditLogError> {
+        let records = self
+            .records
+            .lock()
+            .map_err(|e| AuditLogError::UnexpectedError(eyre!(e.to_string())))?;
+
+        Ok(records
+            .iter()
+            .rev()
+            .filter(|record| {
+                record.event.actor.as_deref() == Some(subject)
+                    || record.event.target.as_deref() == Some(subject)
+            })
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn erase_subject(&self, subject: &str) -> Result<u64, AuditLogError> {
+        let mut records = self
+            .records
+            .lock()
+            .map_err(|e| AuditLogError::UnexpectedError(eyre!(e.to_string())))?;
+
+        let mut erased = 0;
+        for record in records.iter_mut() {
+            let event = &mut record.event;
+            let is_actor = event.actor.as_deref() == Some(subject);
+            let is_target = event.target.as_deref() == Some(subject);
+            if is_actor {
+                event.actor = Some(ERASED_SUBJECT.to_owned());
+                event.ip = None;
+                event.user_agent = None;
+            }
+            if is_target {
+                event.target = Some(ERASED_SUBJECT.to_owned());
+            }
+            if is_actor || is_target {
+                erased += 1;
+            }
+        }
+        Ok(erased)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::data_stores::AuditEventType;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recent_events_are_newest_first() {
+        let audit_log = VecAuditLog::new();
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Signup).actor("test@example.com"))
+            .await
+            .unwrap();
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Login).actor("test@example.com"))
+            .await
+            .unwrap();
+        audit_log
+            .record(AuditEvent::new(AuditEventType::Login).actor("other@example.com"))
+            .await
+            .unwrap();
+
+        let events = audit_log
+            .recent_events("test@example.com", 10)
+            .await
+            .unwrap();
+        let event_types: Vec<_> = events.iter().map(|r| r.event.event_type).collect();
+        assert_eq!(
+            event_types,
+            vec![AuditEventType::Login, AuditEventType::Signup]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recent_events_respects_limit() {
+        let audit_log = VecAuditLog::new();
+        for _ in 0..5 {
+            audit_log
+                .record(AuditEvent::new(AuditEventType::Logout).actor("test@example.com"))
+                .await
+                .unwrap();
+        }
+
+        let events = audit_log
+            .recent_events("test@example.com", 3)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_erase_subject_keeps_other_actors() {
+        let audit_log = VecAuditLog::new();
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::Login)
+                    .actor("test@example.com")
+                    .ip(Some("10.0.0.1".to_owned())),
+            )
+            .await
+            .unwrap();
+        audit_log
+            .record(
+                AuditEvent::new(AuditEventType::AdminDeleteUser)
+                    .actor("admin@example.com")
+                    .target("test@example.com")
+                    .ip(Some("10.0.0.2".to_owned())),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            audit_log.erase_subject("test@example.com").await.unwrap(),
+            2
+        );
+
+        let events = audit_log.recent_events(ERASED_SUBJECT, 10).await.unwrap();
+        assert_eq!(events[0].event.actor.as_deref(), Some("admin@example.com"));
+        assert_eq!(events[0].event.ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(events[1].event.ip, None);
+        assert!(
+            audit_log
+                .recent_events("test@example.com", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
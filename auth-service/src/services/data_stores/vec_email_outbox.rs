@@ -0,0 +1,172 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+
+use crate::domain::{
+    clock::{Clock, SystemClock},
+    data_stores::{EmailOutbox, EmailOutboxError, EmailOutboxStats, QueuedEmail},
+    email::Email,
+    email_client::EmailMessage,
+};
+
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub email: QueuedEmail,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub dead: bool,
+}
+
+pub struct VecEmailOutbox {
+    entries: Mutex<Vec<OutboxEntry>>,
+    next_id: Mutex<i64>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for VecEmailOutbox {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl VecEmailOutbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            entries: Mutex::default(),
+            next_id: Mutex::default(),
+            clock,
+        }
+    }
+
+    pub fn entries(&self) -> Vec<OutboxEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    fn update(&self, id: i64, f: impl FnOnce(&mut OutboxEntry)) -> Result<(), EmailOutboxError> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e.to_string())))?;
+
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.email.id == id) {
+            f(entry);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for VecEmailOutbox {
+    async fn enqueue(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> Result<(), EmailOutboxError> {
+        let mut next_id = self
+            .next_id
+            .lock()
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e.to_string())))?;
+        *next_id += 1;
+
+        self.entries
+            .lock()
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e.to_string())))?
+            .push(OutboxEntry {
+                email: QueuedEmail {
+                    id: *next_id,
+                    recipient: recipient.clone(),
+                    message: message.clone(),
+                    attempts: 0,
+                },
+                next_attempt_at: self.clock.now(),
+                last_error: None,
+                dead: false,
+            });
+        Ok(())
+    }
+
+    async fn claim_due(
+        &self,
+        limit: u32,
+        lease: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<QueuedEmail>, EmailOutboxError> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e.to_string())))?;
+
+        Ok(entries
+            .iter_mut()
+            .filter(|entry| !entry.dead && entry.next_attempt_at <= now)
+            .take(limit as usize)
+            .map(|entry| {
+                entry.email.attempts += 1;
+                entry.next_attempt_at = now + lease;
+                entry.email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&self, id: i64) -> Result<(), EmailOutboxError> {
+        self.entries
+            .lock()
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e.to_string())))?
+            .retain(|entry| entry.email.id != id);
+        Ok(())
+    }
+
+    async fn reschedule(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), EmailOutboxError> {
+        self.update(id, |entry| {
+            entry.next_attempt_at = retry_at;
+            entry.last_error = Some(error.to_owned());
+        })
+    }
+
+    async fn dead_letter(&self, id: i64, error: &str) -> Result<(), EmailOutboxError> {
+        self.update(id, |entry| {
+            entry.dead = true;
+            entry.last_error = Some(error.to_owned());
+            entry.email.message.html_body.clear();
+            entry.email.message.text_body.clear();
+            entry.email.message.attachments.clear();
+        })
+    }
+
+    async fn stats(&self) -> Result<EmailOutboxStats, EmailOutboxError> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e.to_string())))?;
+        let dead = entries.iter().filter(|entry| entry.dead).count() as u64;
+
+        Ok(EmailOutboxStats {
+            pending: entries.len() as u64 - dead,
+            dead,
+        })
+    }
+
+    async fn forget_recipient(&self, recipient: &Email) -> Result<u64, EmailOutboxError> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|e| EmailOutboxError::UnexpectedError(eyre!(e.to_string())))?;
+        let before = entries.len();
+        entries.retain(|entry| &entry.email.recipient != recipient);
+
+        Ok((before - entries.len()) as u64)
+    }
+}
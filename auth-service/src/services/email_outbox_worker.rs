@@ -0,0 +1,442 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{task::JoinSet, time::MissedTickBehavior};
+
+use crate::{
+    domain::{
+        clock::{Clock, SystemClock},
+        data_stores::{EmailOutbox, EmailOutboxError, QueuedEmail},
+        email_client::EmailClient,
+    },
+    settings::EmailOutboxConfig,
+};
+
+// Claimed emails are hidden from other workers for this long. It has to outlast
+// a delivery attempt, otherwise an email could be sent twice.
+const CLAIM_LEASE: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default)]
+pub struct EmailOutboxMetrics {
+    sent: AtomicU64,
+    retried: AtomicU64,
+    dead_lettered: AtomicU64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmailOutboxMetricsSnapshot {
+    pub sent: u64,
+    pub retried: u64,
+    pub dead_lettered: u64,
+}
+
+impl EmailOutboxMetrics {
+    pub fn snapshot(&self) -> EmailOutboxMetricsSnapshot {
+        EmailOutboxMetricsSnapshot {
+            sent: self.sent.load(Ordering::Relaxed),
+            retried: self.retried.load(Ordering::Relaxed),
+            dead_lettered: self.dead_lettered.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Delivers the emails queued in the outbox through the configured email client,
+// retrying failed deliveries with exponential backoff.
+pub struct EmailOutboxWorker<O, E> {
+    outbox: Arc<O>,
+    email_client: Arc<E>,
+    config: Arc<EmailOutboxConfig>,
+    metrics: Arc<EmailOutboxMetrics>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<O, E> Clone for EmailOutboxWorker<O, E> {
+    fn clone(&self) -> Self {
+        Self {
+            outbox: self.outbox.clone(),
+            email_client: self.email_client.clone(),
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+impl<O, E> EmailOutboxWorker<O, E>
+where
+    O: EmailOutbox + 'static,
+    E: EmailClient + 'static,
+{
+    pub fn new(outbox: Arc<O>, email_client: E, config: EmailOutboxConfig) -> Self {
+        Self {
+            outbox,
+            email_client: Arc::new(email_client),
+            config: Arc::new(config),
+            metrics: Arc::new(EmailOutboxMetrics::default()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn metrics(&self) -> Arc<EmailOutboxMetrics> {
+        self.metrics.clone()
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.config.poll_interval_in_millis);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            // Keep going while there is a backlog instead of waiting for the next tick
+            loop {
+                match self.process_due().await {
+                    Ok(claimed) if claimed == self.config.batch_size as usize => continue,
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::error!(error = ?e, "Failed to claim queued emails");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // Delivers one batch of due emails, returning how many were claimed
+    #[tracing::instrument(name = "Processing email outbox", skip_all)]
+    pub async fn process_due(&self) -> Result<usize, EmailOutboxError> {
+        let emails = self
+            .outbox
+            .claim_due(self.config.batch_size, CLAIM_LEASE, self.clock.now())
+            .await?;
+        let claimed = emails.len();
+
+        let mut deliveries = JoinSet::new();
+        for email in emails {
+            let worker = self.clone();
+            deliveries.spawn(async move { worker.deliver(email).await });
+        }
+        while let Some(result) = deliveries.join_next().await {
+            if let Err(e) = result {
+                tracing::error!(error = ?e, "Email delivery task failed");
+            }
+        }
+
+        if claimed > 0 {
+            self.report().await;
+        }
+
+        Ok(claimed)
+    }
+
+    async fn deliver(&self, email: QueuedEmail) {
+        if email
+            .message
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= self.clock.now())
+        {
+            tracing::warn!(
+                email_id = email.id,
+                attempts = email.attempts,
+                "Giving up on queued email that expired before it was sent"
+            );
+            self.metrics.dead_lettered.fetch_add(1, Ordering::Relaxed);
+            if let Err(e) = self.outbox.dead_letter(email.id, "Expired").await {
+                tracing::error!(error = ?e, email_id = email.id, "Failed to update queued email");
+            }
+            return;
+        }
+
+        let result = self
+            .email_client
+            .send_email(&email.recipient, &email.message)
+            .await;
+
+        // A failure to update the outbox leaves the email claimed, it is picked
+        // up again once the lease runs out.
+        let updated = match result {
+            Ok(()) => {
+                self.metrics.sent.fetch_add(1, Ordering::Relaxed);
+                self.outbox.mark_sent(email.id).await
+            }
+            Err(e) if email.attempts >= self.config.max_attempts => {
+                tracing::error!(
+                    error = ?e,
+                    email_id = email.id,
+                    attempts = email.attempts,
+                    "Giving up on queued email"
+                );
+                self.metrics.dead_lettered.fetch_add(1, Ordering::Relaxed);
+                self.outbox.dead_letter(email.id, &format!("{e:#}")).await
+            }
+            Err(e) => {
+                let delay = retry_delay(email.attempts, &self.config);
+                tracing::warn!(
+                    error = ?e,
+                    email_id = email.id,
+                    attempts = email.attempts,
+                    retry_in_secs = delay.as_secs(),
+                    "Failed to send queued email"
+                );
+                self.metrics.retried.fetch_add(1, Ordering::Relaxed);
+                self.outbox
+                    .reschedule(email.id, &format!("{e:#}"), self.clock.now() + delay)
+                    .await
+            }
+        };
+
+        if let Err(e) = updated {
+            tracing::error!(error = ?e, email_id = email.id, "Failed to update queued email");
+        }
+    }
+
+    async fn report(&self) {
+        let metrics = self.metrics.snapshot();
+        match self.outbox.stats().await {
+            Ok(stats) => tracing::info!(
+                pending = stats.pending,
+                dead = stats.dead,
+                sent = metrics.sent,
+                retried = metrics.retried,
+                dead_lettered = metrics.dead_lettered,
+                "Email outbox metrics"
+            ),
+            Err(e) => tracing::error!(error = ?e, "Failed to read email outbox stats"),
+        }
+    }
+}
+
+// The delay before the next attempt doubles with every failed attempt
+pub fn retry_delay(attempts: u32, config: &EmailOutboxConfig) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(31);
+    config
+        .initial_backoff_in_secs
+        .saturating_mul(1 << exponent)
+        .min(config.max_backoff_in_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+
+    use color_eyre::eyre::{Result, eyre};
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::{
+        domain::{clock::MockClock, email::Email, email_client::EmailMessage},
+        services::data_stores::VecEmailOutbox,
+    };
+
+    // Fails the first `failures` deliveries, then succeeds
+    struct FlakyEmailClient {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl FlakyEmailClient {
+        fn failing(failures: u32) -> Self {
+            Self {
+                failures,
+                calls: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            if self.calls.fetch_add(1, Ordering::Relaxed) < self.failures {
+                return Err(eyre!("connection reset"));
+            }
+            Ok(())
+        }
+    }
+
+    fn config() -> EmailOutboxConfig {
+        EmailOutboxConfig {
+            max_attempts: 3,
+            // Retries are due immediately so the tests do not have to wait
+            initial_backoff_in_secs: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    async fn outbox_with_one_email() -> Arc<VecEmailOutbox> {
+        let outbox = Arc::new(VecEmailOutbox::new());
+        outbox
+            .enqueue(
+                &Email::try_from(Secret::new("user@example.com".to_owned())).unwrap(),
+                &EmailMessage {
+                    subject: "Your sign-in code".to_owned(),
+                    html_body: "<p>123456</p>".to_owned(),
+                    text_body: "123456".to_owned(),
+                    attachments: Vec::new(),
+                    expires_at: None,
+                },
+            )
+            .await
+            .unwrap();
+        outbox
+    }
+
+    #[tokio::test]
+    async fn test_delivered_emails_are_removed() {
+        let outbox = outbox_with_one_email().await;
+        let worker = EmailOutboxWorker::new(outbox.clone(), FlakyEmailClient::failing(0), config());
+
+        assert_eq!(worker.process_due().await.unwrap(), 1);
+
+        assert!(outbox.entries().is_empty());
+        assert_eq!(worker.metrics().snapshot().sent, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_retried() {
+        let outbox = outbox_with_one_email().await;
+        let worker = EmailOutboxWorker::new(outbox.clone(), FlakyEmailClient::failing(1), config());
+
+        worker.process_due().await.unwrap();
+
+        let entries = outbox.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].last_error.as_deref(), Some("connection reset"));
+        assert!(!entries[0].dead);
+
+        worker.process_due().await.unwrap();
+
+        assert!(outbox.entries().is_empty());
+        assert_eq!(
+            worker.metrics().snapshot(),
+            EmailOutboxMetricsSnapshot {
+                sent: 1,
+                retried: 1,
+                dead_lettered: 0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_email_is_dead_lettered_after_max_attempts() {
+        let outbox = outbox_with_one_email().await;
+        let worker = EmailOutboxWorker::new(
+            outbox.clone(),
+            FlakyEmailClient::failing(u32::MAX),
+            config(),
+        );
+
+        for _ in 0..5 {
+            worker.process_due().await.unwrap();
+        }
+
+        let entries = outbox.entries();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].dead);
+        assert_eq!(entries[0].email.attempts, 3);
+        assert!(entries[0].email.message.text_body.is_empty());
+        assert_eq!(worker.metrics().snapshot().dead_lettered, 1);
+        assert_eq!(outbox.stats().await.unwrap().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_waits_out_its_backoff() {
+        let clock = Arc::new(MockClock::default());
+        let outbox = Arc::new(VecEmailOutbox::with_clock(clock.clone()));
+        outbox
+            .enqueue(
+                &Email::try_from(Secret::new("user@example.com".to_owned())).unwrap(),
+                &EmailMessage {
+                    subject: "Your sign-in code".to_owned(),
+                    html_body: "<p>123456</p>".to_owned(),
+                    text_body: "123456".to_owned(),
+                    attachments: Vec::new(),
+                    expires_at: None,
+                },
+            )
+            .await
+            .unwrap();
+        let config = EmailOutboxConfig {
+            initial_backoff_in_secs: Duration::from_secs(60),
+            ..config()
+        };
+        let worker = EmailOutboxWorker::new(outbox.clone(), FlakyEmailClient::failing(1), config)
+            .with_clock(clock.clone());
+
+        assert_eq!(worker.process_due().await.unwrap(), 1);
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(worker.process_due().await.unwrap(), 0);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(worker.process_due().await.unwrap(), 1);
+        assert!(outbox.entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_email_is_dead_lettered_instead_of_sent() {
+        let clock = Arc::new(MockClock::default());
+        let outbox = Arc::new(VecEmailOutbox::with_clock(clock.clone()));
+        outbox
+            .enqueue(
+                &Email::try_from(Secret::new("user@example.com".to_owned())).unwrap(),
+                &EmailMessage {
+                    subject: "Your sign-in code".to_owned(),
+                    html_body: "<p>123456</p>".to_owned(),
+                    text_body: "123456".to_owned(),
+                    attachments: Vec::new(),
+                    expires_at: None,
+                }
+                .expiring_at(clock.now() + Duration::from_secs(600)),
+            )
+            .await
+            .unwrap();
+        let config = EmailOutboxConfig {
+            initial_backoff_in_secs: Duration::from_secs(3600),
+            max_backoff_in_secs: Duration::from_secs(3600),
+            ..config()
+        };
+        let worker = EmailOutboxWorker::new(outbox.clone(), FlakyEmailClient::failing(1), config)
+            .with_clock(clock.clone());
+
+        worker.process_due().await.unwrap();
+        // The retry is due long after the code has run out
+        clock.advance(Duration::from_secs(3600));
+        worker.process_due().await.unwrap();
+
+        let entries = outbox.entries();
+        assert!(entries[0].dead);
+        assert_eq!(entries[0].last_error.as_deref(), Some("Expired"));
+        assert_eq!(
+            worker.metrics().snapshot(),
+            EmailOutboxMetricsSnapshot {
+                sent: 0,
+                retried: 1,
+                dead_lettered: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_the_maximum() {
+        let config = EmailOutboxConfig {
+            initial_backoff_in_secs: Duration::from_secs(5),
+            max_backoff_in_secs: Duration::from_secs(60),
+            ..Default::default()
+        };
+
+        let delays: Vec<_> = (1..=6)
+            .map(|attempts| retry_delay(attempts, &config).as_secs())
+            .collect();
+        assert_eq!(delays, vec![5, 10, 20, 40, 60, 60]);
+        assert_eq!(retry_delay(u32::MAX, &config), Duration::from_secs(60));
+    }
+}
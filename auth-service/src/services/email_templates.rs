@@ -0,0 +1,336 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+
+use crate::domain::{
+    email_client::EmailMessage, locale::Locale, security_notification::SecurityNotification,
+};
+
+// An email that can be rendered to both an HTML and a plaintext body. The
+// bodies live in templates/emails, the subjects are translated here.
+pub trait EmailTemplate {
+    fn subject(&self, locale: Locale) -> &'static str;
+    fn html_body(&self) -> askama::Result<String>;
+    fn text_body(&self) -> askama::Result<String>;
+
+    fn render(&self, locale: Locale) -> Result<EmailMessage> {
+        Ok(EmailMessage {
+            subject: self.subject(locale).to_owned(),
+            html_body: self.html_body()?,
+            text_body: self.text_body()?,
+            attachments: Vec::new(),
+            expires_at: None,
+        })
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.html")]
+pub struct TwoFaCodeHtml<'a> {
+    pub code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.txt")]
+pub struct TwoFaCodeText<'a> {
+    pub code: &'a str,
+}
+
+pub struct TwoFaCodeEmail<'a> {
+    pub code: &'a str,
+}
+
+impl EmailTemplate for TwoFaCodeEmail<'_> {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => "Your sign-in code",
+            Locale::Nb => "Din innloggingskode",
+        }
+    }
+
+    fn html_body(&self) -> askama::Result<String> {
+        TwoFaCodeHtml { code: self.code }.render()
+    }
+
+    fn text_body(&self) -> askama::Result<String> {
+        TwoFaCodeText { code: self.code }.render()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/verification.html")]
+pub struct VerificationHtml<'a> {
+    pub link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/verification.txt")]
+pub struct VerificationText<'a> {
+    pub link: &'a str,
+}
+
+pub struct VerificationEmail<'a> {
+    pub link: &'a str,
+}
+
+impl EmailTemplate for VerificationEmail<'_> {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => "Verify your email address",
+            Locale::Nb => "Bekreft e-postadressen din",
+        }
+    }
+
+    fn html_body(&self) -> askama::Result<String> {
+        VerificationHtml { link: self.link }.render()
+    }
+
+    fn text_body(&self) -> askama::Result<String> {
+        VerificationText { link: self.link }.render()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.html")]
+pub struct PasswordResetHtml<'a> {
+    pub link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.txt")]
+pub struct PasswordResetText<'a> {
+    pub link: &'a str,
+}
+
+pub struct PasswordResetEmail<'a> {
+    pub link: &'a str,
+}
+
+impl EmailTemplate for PasswordResetEmail<'_> {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => "Reset your password",
+            Locale::Nb => "Tilbakestill passordet ditt",
+        }
+    }
+
+    fn html_body(&self) -> askama::Result<String> {
+        PasswordResetHtml { link: self.link }.render()
+    }
+
+    fn text_body(&self) -> askama::Result<String> {
+        PasswordResetText { link: self.link }.render()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/account_export.html")]
+pub struct AccountExportHtml {
+    pub exported_at: DateTime<Utc>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/account_export.txt")]
+pub struct AccountExportText {
+    pub exported_at: DateTime<Utc>,
+}
+
+// The export itself is attached by the sender
+pub struct AccountExportEmail {
+    pub exported_at: DateTime<Utc>,
+}
+
+impl EmailTemplate for AccountExportEmail {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => "Your account data",
+            Locale::Nb => "Kontodataene dine",
+        }
+    }
+
+    fn html_body(&self) -> askama::Result<String> {
+        AccountExportHtml {
+            exported_at: self.exported_at,
+        }
+        .render()
+    }
+
+    fn text_body(&self) -> askama::Result<String> {
+        AccountExportText {
+            exported_at: self.exported_at,
+        }
+        .render()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/account_deletion_scheduled.html")]
+pub struct AccountDeletionScheduledHtml<'a> {
+    pub link: &'a str,
+    pub due_at: DateTime<Utc>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/account_deletion_scheduled.txt")]
+pub struct AccountDeletionScheduledText<'a> {
+    pub link: &'a str,
+    pub due_at: DateTime<Utc>,
+}
+
+pub struct AccountDeletionScheduledEmail<'a> {
+    pub link: &'a str,
+    pub due_at: DateTime<Utc>,
+}
+
+impl EmailTemplate for AccountDeletionScheduledEmail<'_> {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => "Your account will be deleted",
+            Locale::Nb => "Kontoen din blir slettet",
+        }
+    }
+
+    fn html_body(&self) -> askama::Result<String> {
+        AccountDeletionScheduledHtml {
+            link: self.link,
+            due_at: self.due_at,
+        }
+        .render()
+    }
+
+    fn text_body(&self) -> askama::Result<String> {
+        AccountDeletionScheduledText {
+            link: self.link,
+            due_at: self.due_at,
+        }
+        .render()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/security_notification.html")]
+pub struct SecurityNotificationHtml<'a> {
+    pub notification: &'a SecurityNotification,
+}
+
+#[derive(Template)]
+#[template(path = "emails/security_notification.txt")]
+pub struct SecurityNotificationText<'a> {
+    pub notification: &'a SecurityNotification,
+}
+
+impl EmailTemplate for SecurityNotification {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (SecurityNotification::NewSignIn { .. }, Locale::En) => "New sign-in to your account",
+            (SecurityNotification::NewSignIn { .. }, Locale::Nb) => "Ny innlogging på kontoen din",
+            (SecurityNotification::Elevated { .. }, Locale::En) => {
+                "Your account was unlocked for sensitive changes"
+            }
+            (SecurityNotification::Elevated { .. }, Locale::Nb) => {
+                "Kontoen din ble låst opp for sensitive endringer"
+            }
+            (SecurityNotification::PasswordChanged { .. }, Locale::En) => {
+                "Your password was changed"
+            }
+            (SecurityNotification::PasswordChanged { .. }, Locale::Nb) => {
+                "Passordet ditt ble endret"
+            }
+            (SecurityNotification::AccountDeleted { .. }, Locale::En) => "Your account was deleted",
+            (SecurityNotification::AccountDeleted { .. }, Locale::Nb) => "Kontoen din ble slettet",
+        }
+    }
+
+    fn html_body(&self) -> askama::Result<String> {
+        SecurityNotificationHtml { notification: self }.render()
+    }
+
+    fn text_body(&self) -> askama::Result<String> {
+        SecurityNotificationText { notification: self }.render()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_fa_code_email_contains_code_in_both_bodies() {
+        let message = TwoFaCodeEmail { code: "123456" }
+            .render(Locale::En)
+            .unwrap();
+
+        assert_eq!(message.subject, "Your sign-in code");
+        assert!(message.html_body.contains("<html"));
+        assert!(message.html_body.contains("123456"));
+        assert!(message.text_body.contains("123456"));
+        assert!(!message.text_body.contains('<'));
+    }
+
+    #[test]
+    fn test_subject_is_localised() {
+        let email = PasswordResetEmail {
+            link: "https://example.com/reset",
+        };
+
+        assert_eq!(email.subject(Locale::En), "Reset your password");
+        assert_eq!(email.subject(Locale::Nb), "Tilbakestill passordet ditt");
+    }
+
+    #[test]
+    fn test_html_body_is_escaped() {
+        let notification = SecurityNotification::NewSignIn {
+            ip: None,
+            user_agent: Some("<script>alert(1)</script>".to_owned()),
+            at: DateTime::from_timestamp(0, 0).unwrap(),
+        };
+
+        let message = notification.render(Locale::En).unwrap();
+
+        assert!(!message.html_body.contains("<script>"));
+        assert!(
+            message
+                .text_body
+                .contains("Device: <script>alert(1)</script>")
+        );
+        assert!(message.text_body.contains("IP address: unknown"));
+    }
+
+    #[test]
+    fn test_links_are_rendered() {
+        let link = "https://example.com/verify?token=abc&user=1";
+        let message = VerificationEmail { link }.render(Locale::En).unwrap();
+
+        assert!(message.text_body.contains(link));
+        assert!(message.html_body.contains("token=abc&#38;user=1"));
+    }
+
+    #[test]
+    fn test_account_export_email_mentions_when_it_was_made() {
+        let message = AccountExportEmail {
+            exported_at: DateTime::from_timestamp(0, 0).unwrap(),
+        }
+        .render(Locale::Nb)
+        .unwrap();
+
+        assert_eq!(message.subject, "Kontodataene dine");
+        assert!(message.text_body.contains("1970-01-01 00:00:00 UTC"));
+        assert!(message.attachments.is_empty());
+    }
+
+    #[test]
+    fn test_account_deletion_email_has_restore_link_and_due_date() {
+        let link = "https://example.com/restore-account.html?token=abc";
+        let message = AccountDeletionScheduledEmail {
+            link,
+            due_at: DateTime::from_timestamp(0, 0).unwrap(),
+        }
+        .render(Locale::En)
+        .unwrap();
+
+        assert_eq!(message.subject, "Your account will be deleted");
+        assert!(message.text_body.contains(link));
+        assert!(message.text_body.contains("1970-01-01 00:00:00 UTC"));
+        assert!(message.html_body.contains("Restore account"));
+    }
+}
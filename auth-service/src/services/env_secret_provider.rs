@@ -0,0 +1,117 @@
+use std::{path::Path, sync::Arc};
+
+use color_eyre::eyre::Result;
+use dotenvy::dotenv;
+use secrecy::Secret;
+
+use crate::{
+    domain::secret_provider::{SecretProvider, SecretValues},
+    services::file_secret_provider::read_secret_file,
+};
+
+type Lookup = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+// Reads a secret from the environment variable of its name, or from the file
+// named by the same variable with a _FILE suffix, the convention for Docker
+// and Kubernetes secrets. The variable itself takes precedence.
+#[derive(Clone)]
+pub struct EnvSecretProvider {
+    lookup: Arc<Lookup>,
+}
+
+impl Default for EnvSecretProvider {
+    fn default() -> Self {
+        Self::with_lookup(|name| {
+            dotenv().ok();
+            std::env::var(name).ok()
+        })
+    }
+}
+
+impl std::fmt::Debug for EnvSecretProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnvSecretProvider").finish_non_exhaustive()
+    }
+}
+
+impl EnvSecretProvider {
+    // Looks variables up with `lookup` instead of in the process environment
+    pub fn with_lookup(lookup: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self {
+            lookup: Arc::new(lookup),
+        }
+    }
+
+    pub fn read(&self, name: &str) -> Result<Option<Secret<String>>> {
+        let lookup = |name: &str| (self.lookup)(name).filter(|value| !value.is_empty());
+        if let Some(value) = lookup(name) {
+            return Ok(Some(Secret::new(value)));
+        }
+
+        match lookup(&format!("{name}_FILE")) {
+            Some(path) => read_secret_file(Path::new(&path)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // The environment can be read without awaiting, which lets the config be
+    // loaded outside of an async context
+    pub fn read_all(&self, names: &[&str]) -> Result<SecretValues> {
+        let mut secrets = SecretValues::default();
+        for name in names {
+            if let Some(value) = self.read(name)? {
+                secrets.insert(name, value);
+            }
+        }
+        Ok(secrets)
+    }
+}
+
+#[async_trait::async_trait]
+impl SecretProvider for EnvSecretProvider {
+    async fn get_secret(&self, name: &str) -> Result<Option<Secret<String>>> {
+        self.read(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn provider(variables: &[(&str, &str)]) -> EnvSecretProvider {
+        let variables: HashMap<String, String> = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        EnvSecretProvider::with_lookup(move |name| variables.get(name).cloned())
+    }
+
+    #[test]
+    fn test_reads_the_variable_or_its_file() {
+        let path = std::env::temp_dir().join(format!("secret-{}", Uuid::new_v4().simple()));
+        std::fs::write(&path, "from a file\n").unwrap();
+        let file = ("SECRET_FILE", path.to_str().unwrap());
+
+        let secrets = provider(&[file]).read_all(&["SECRET"]).unwrap();
+        assert_eq!(secrets.get("SECRET"), Some("from a file"));
+
+        let secrets = provider(&[file, ("SECRET", "from the variable")])
+            .read_all(&["SECRET"])
+            .unwrap();
+        assert_eq!(secrets.get("SECRET"), Some("from the variable"));
+
+        std::fs::remove_file(path).unwrap();
+        assert!(provider(&[]).read("SECRET").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_empty_variables_count_as_unset() {
+        let provider = provider(&[("SECRET", ""), ("SECRET_FILE", "")]);
+
+        assert!(provider.read("SECRET").unwrap().is_none());
+    }
+}
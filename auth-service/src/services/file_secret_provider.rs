@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Result, WrapErr};
+use secrecy::Secret;
+
+use crate::domain::secret_provider::SecretProvider;
+
+// Reads each secret from a file named after it, as in a mounted Kubernetes
+// secret or the /run/secrets directory of Docker
+pub struct FileSecretProvider {
+    directory: PathBuf,
+}
+
+impl FileSecretProvider {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn read(&self, name: &str) -> Result<Option<Secret<String>>> {
+        let path = self.directory.join(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        read_secret_file(&path).map(Some)
+    }
+}
+
+#[async_trait::async_trait]
+impl SecretProvider for FileSecretProvider {
+    async fn get_secret(&self, name: &str) -> Result<Option<Secret<String>>> {
+        self.read(name)
+    }
+}
+
+// Editors and `echo` leave a trailing newline that is not part of the secret
+pub fn read_secret_file(path: &Path) -> Result<Secret<String>> {
+    let contents = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read secret file {}", path.display()))?;
+    Ok(Secret::new(
+        contents.trim_end_matches(['\r', '\n']).to_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reads_secrets_from_the_directory() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        std::fs::create_dir(&directory).unwrap();
+        std::fs::write(directory.join("JWT_SECRET"), "from a file\n").unwrap();
+
+        let provider = FileSecretProvider::new(&directory);
+        let secret = provider.get_secret("JWT_SECRET").await.unwrap().unwrap();
+        assert_eq!(secret.expose_secret(), "from a file");
+        assert!(provider.get_secret("MISSING").await.unwrap().is_none());
+
+        let secrets = provider
+            .get_secrets(&["JWT_SECRET", "MISSING"])
+            .await
+            .unwrap();
+        assert_eq!(secrets.get("JWT_SECRET"), Some("from a file"));
+        assert_eq!(secrets.get("MISSING"), None);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,2 +1,17 @@
+pub mod account_purge;
+pub mod capturing_email_client;
+pub mod config_reloader;
 pub mod data_stores;
+pub mod email_outbox_worker;
+pub mod email_templates;
+pub mod env_secret_provider;
+pub mod file_secret_provider;
+pub mod outbox_email_client;
 pub mod postmark_email_client;
+pub mod secret_refresher;
+pub mod sms_templates;
+pub mod smtp_email_client;
+pub mod token_store_cleanup;
+pub mod user_admin;
+pub mod vault_secret_provider;
+pub mod webhook_sms_client;
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+
+use crate::domain::{
+    data_stores::EmailOutbox,
+    email::Email,
+    email_client::{EmailClient, EmailMessage},
+};
+
+// Queues emails in the outbox instead of sending them, so requests do not wait
+// on the email provider. The outbox worker does the actual delivery.
+pub struct OutboxEmailClient<O> {
+    outbox: Arc<O>,
+}
+
+impl<O> OutboxEmailClient<O> {
+    pub fn new(outbox: Arc<O>) -> Self {
+        Self { outbox }
+    }
+}
+
+#[async_trait::async_trait]
+impl<O: EmailOutbox> EmailClient for OutboxEmailClient<O> {
+    #[tracing::instrument(name = "Queueing email", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        self.outbox.enqueue(recipient, message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::services::data_stores::VecEmailOutbox;
+
+    #[tokio::test]
+    async fn send_email_queues_the_message() {
+        let outbox = Arc::new(VecEmailOutbox::new());
+        let email_client = OutboxEmailClient::new(outbox.clone());
+        let recipient = Email::try_from(Secret::new("user@example.com".to_owned())).unwrap();
+        let message = EmailMessage {
+            subject: "Your sign-in code".to_owned(),
+            html_body: "<p>123456</p>".to_owned(),
+            text_body: "123456".to_owned(),
+            attachments: Vec::new(),
+            expires_at: None,
+        };
+
+        email_client.send_email(&recipient, &message).await.unwrap();
+
+        let entries = outbox.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].email.message, message);
+        assert_eq!(entries[0].email.attempts, 0);
+    }
+}
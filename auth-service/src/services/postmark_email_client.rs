@@ -1,9 +1,13 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
 use color_eyre::eyre::Result;
 use reqwest::{Client, Url};
 use secrecy::{ExposeSecret, Secret};
 
 use crate::{
-    domain::{email::Email, email_client::EmailClient},
+    domain::{
+        email::Email,
+        email_client::{EmailClient, EmailMessage},
+    },
     settings::AuthServiceSetting,
     utils::constants::prod,
 };
@@ -34,17 +38,26 @@ impl PostmarkEmailClient {
 #[async_trait::async_trait]
 impl EmailClient for PostmarkEmailClient {
     #[tracing::instrument(name = "Sending email", skip_all)]
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
 
         let request_body = SendEmailRequest {
             from: self.sender.as_ref().expose_secret(),
             to: recipient.as_ref().expose_secret(),
-            subject,
-            html_body: content,
-            text_body: content,
+            subject: &message.subject,
+            html_body: &message.html_body,
+            text_body: &message.text_body,
             message_stream: MESSAGE_STREAM,
+            attachments: message
+                .attachments
+                .iter()
+                .map(|attachment| Attachment {
+                    name: &attachment.filename,
+                    content: STANDARD.encode(&attachment.content),
+                    content_type: &attachment.content_type,
+                })
+                .collect(),
         };
 
         let request = self
@@ -74,6 +87,17 @@ struct SendEmailRequest<'a> {
     html_body: &'a str,
     text_body: &'a str,
     message_stream: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<Attachment<'a>>,
+}
+
+// Postmark takes the content of attachments base64 encoded
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Attachment<'a> {
+    name: &'a str,
+    content: String,
+    content_type: &'a str,
 }
 
 pub fn configure_postmark_email_client() -> PostmarkEmailClient {
@@ -88,20 +112,22 @@ pub fn configure_postmark_email_client() -> PostmarkEmailClient {
         AuthServiceSetting::load()
             .email_client
             .auth_token
-            .to_owned(),
+            .clone()
+            .expect("POSTMARK_AUTH_TOKEN must be set."),
         http_client,
     )
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::domain::email_client::EmailAttachment;
     use crate::utils::constants::test;
 
     use super::*;
     use fake::faker::internet::en::SafeEmail;
     use fake::faker::lorem::en::{Paragraph, Sentence};
     use fake::{Fake, Faker};
-    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::matchers::{any, body_partial_json, header, header_exists, method, path};
     use wiremock::{Mock, MockServer, Request, ResponseTemplate};
 
     use super::PostmarkEmailClient;
@@ -111,9 +137,16 @@ mod tests {
         Sentence(1..2).fake()
     }
 
-    // Helper function to generate test content
-    fn content() -> String {
-        Paragraph(1..10).fake()
+    // Helper function to generate a test message
+    fn message() -> EmailMessage {
+        let content: String = Paragraph(1..10).fake();
+        EmailMessage {
+            subject: subject(),
+            html_body: format!("<p>{}</p>", content),
+            text_body: content,
+            attachments: Vec::new(),
+            expires_at: None,
+        }
     }
 
     // Helper function to generate a test email
@@ -167,10 +200,57 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+        let outcome = email_client.send_email(&email(), &message()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_attachments_base64_encoded() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let message = message().with_attachment(EmailAttachment {
+            filename: "export.json".to_owned(),
+            content_type: "application/json".to_owned(),
+            content: b"{}".to_vec(),
+        });
+
+        Mock::given(path("/email"))
+            .and(body_partial_json(serde_json::json!({
+                "Attachments": [{
+                    "Name": "export.json",
+                    "Content": "e30=",
+                    "ContentType": "application/json"
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
             .await;
 
+        assert!(email_client.send_email(&email(), &message).await.is_ok());
+    }
+
+    // Test to ensure the HTML and plaintext bodies are sent separately
+    #[tokio::test]
+    async fn send_email_sends_html_and_text_bodies() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let message = message();
+
+        Mock::given(path("/email"))
+            .and(body_partial_json(serde_json::json!({
+                "Subject": message.subject,
+                "HtmlBody": message.html_body,
+                "TextBody": message.text_body,
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&email(), &message).await;
+
         assert!(outcome.is_ok());
     }
 
@@ -188,9 +268,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_err());
     }
@@ -210,9 +288,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_err());
     }
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{
+    domain::secret_provider::SecretProvider, settings::Settings, utils::constants::env::SECRETS,
+};
+
+// Fetches the secrets from the provider on an interval and rebuilds the
+// config when any of them changed, so rotated secrets are used without a
+// restart. Connections that were opened with an old secret, such as the
+// database pool, keep using it.
+pub struct SecretRefresher {
+    provider: Box<dyn SecretProvider>,
+    settings: Settings,
+    interval: Duration,
+}
+
+impl SecretRefresher {
+    pub fn new(provider: Box<dyn SecretProvider>, settings: Settings, interval: Duration) -> Self {
+        Self {
+            provider,
+            settings,
+            interval,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately, the secrets were just loaded
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            self.refresh().await;
+        }
+    }
+
+    #[tracing::instrument(name = "Refreshing secrets", skip_all)]
+    pub async fn refresh(&self) {
+        let secrets = match self.provider.get_secrets(&SECRETS).await {
+            Ok(secrets) => secrets,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to fetch secrets, keeping the current ones");
+                return;
+            }
+        };
+
+        match self.settings.rotate_secrets(secrets) {
+            Ok(rotated) => {
+                for name in rotated {
+                    tracing::info!(secret = %name, "Secret rotated");
+                }
+            }
+            Err(e) => tracing::error!(error = %e, "Keeping the current secrets"),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use askama::Template;
+
+// Text messages have no subject or HTML part, so unlike emails they are plain templates
+#[derive(Template)]
+#[template(path = "sms/two_fa_code.txt")]
+pub struct TwoFaCodeSms<'a> {
+    pub code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "sms/phone_verification.txt")]
+pub struct PhoneVerificationSms<'a> {
+    pub code: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sms_contains_code() {
+        let two_fa = TwoFaCodeSms { code: "123456" }.render().unwrap();
+        let verification = PhoneVerificationSms { code: "654321" }.render().unwrap();
+
+        assert!(two_fa.starts_with("123456 "));
+        assert!(verification.starts_with("654321 "));
+    }
+}
@@ -0,0 +1,338 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{Result, eyre};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Attachment, Mailbox, MultiPart, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    domain::{
+        email::Email,
+        email_client::{EmailClient, EmailMessage},
+    },
+    settings::{AuthServiceSetting, SmtpConfig, SmtpTls},
+};
+
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Email,
+}
+
+impl SmtpEmailClient {
+    pub fn new(config: &SmtpConfig, sender: Email, timeout: Duration) -> Result<Self> {
+        let mut builder = match config.tls {
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        };
+
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+
+        match (&config.username, &config.password) {
+            (Some(username), Some(password)) => {
+                builder = builder.credentials(Credentials::new(
+                    username.to_owned(),
+                    password.expose_secret().to_owned(),
+                ));
+            }
+            (None, None) => {}
+            _ => return Err(eyre!("SMTP username and password must be set together")),
+        }
+
+        Ok(Self {
+            transport: builder.timeout(Some(timeout)).build(),
+            sender,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let mut body =
+            MultiPart::alternative_plain_html(message.text_body.clone(), message.html_body.clone());
+        if !message.attachments.is_empty() {
+            body = message.attachments.iter().try_fold(
+                MultiPart::mixed().multipart(body),
+                |mixed, attachment| {
+                    let content_type = ContentType::parse(&attachment.content_type)?;
+                    Ok::<_, color_eyre::Report>(
+                        mixed.singlepart(
+                            Attachment::new(attachment.filename.clone())
+                                .body(attachment.content.clone(), content_type),
+                        ),
+                    )
+                },
+            )?;
+        }
+
+        let email = Message::builder()
+            .from(self.sender.as_ref().expose_secret().parse::<Mailbox>()?)
+            .to(recipient.as_ref().expose_secret().parse::<Mailbox>()?)
+            .subject(&message.subject)
+            .multipart(body)?;
+
+        self.transport.send(email).await?;
+
+        Ok(())
+    }
+}
+
+pub fn configure_smtp_email_client() -> SmtpEmailClient {
+    let settings = AuthServiceSetting::load();
+    let config = &settings.email_client;
+
+    let smtp = config
+        .smtp
+        .as_ref()
+        .expect("email_client.smtp must be set when using the smtp email provider");
+    let sender = Email::try_from(Secret::new(config.sender.clone()))
+        .expect("email_client.sender is not a valid email address");
+
+    SmtpEmailClient::new(smtp, sender, config.timeout_in_millis)
+        .expect("Failed to build SMTP email client")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::Mutex,
+    };
+
+    use crate::domain::email_client::EmailAttachment;
+    use crate::utils::constants::test;
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone)]
+    struct ReceivedMail {
+        auth: Option<String>,
+        mail_from: String,
+        rcpt_to: Vec<String>,
+        data: String,
+    }
+
+    // A minimal SMTP server standing in for a local mail sink such as MailHog.
+    // It accepts everything it is sent and records it.
+    struct SmtpSink {
+        port: u16,
+        received: Arc<Mutex<Vec<ReceivedMail>>>,
+    }
+
+    impl SmtpSink {
+        async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let received = Arc::new(Mutex::new(Vec::new()));
+
+            let sink_received = received.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(Self::session(stream, sink_received.clone()));
+                }
+            });
+
+            SmtpSink { port, received }
+        }
+
+        async fn session(stream: tokio::net::TcpStream, received: Arc<Mutex<Vec<ReceivedMail>>>) {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut mail = ReceivedMail::default();
+            let mut auth = None;
+
+            writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250-sink\r\n250 AUTH PLAIN\r\n"
+                } else if command.starts_with("AUTH PLAIN") {
+                    auth = line.split_whitespace().nth(2).map(str::to_owned);
+                    b"235 Authentication succeeded\r\n"
+                } else if command.starts_with("MAIL FROM") {
+                    mail = ReceivedMail {
+                        auth: auth.clone(),
+                        mail_from: line[10..].to_owned(),
+                        ..Default::default()
+                    };
+                    b"250 OK\r\n"
+                } else if command.starts_with("RCPT TO") {
+                    mail.rcpt_to.push(line[8..].to_owned());
+                    b"250 OK\r\n"
+                } else if command == "DATA" {
+                    writer.write_all(b"354 End data with .\r\n").await.unwrap();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line == "." {
+                            break;
+                        }
+                        mail.data.push_str(&line);
+                        mail.data.push('\n');
+                    }
+                    received.lock().await.push(std::mem::take(&mut mail));
+                    b"250 Queued\r\n"
+                } else if command == "QUIT" {
+                    let _ = writer.write_all(b"221 Bye\r\n").await;
+                    return;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+        }
+
+        async fn received(&self) -> Vec<ReceivedMail> {
+            self.received.lock().await.clone()
+        }
+    }
+
+    fn email(address: &str) -> Email {
+        Email::try_from(Secret::new(address.to_owned())).unwrap()
+    }
+
+    fn smtp_config(port: u16, tls: SmtpTls) -> SmtpConfig {
+        SmtpConfig {
+            host: "127.0.0.1".to_owned(),
+            port: Some(port),
+            tls,
+            username: None,
+            password: None,
+        }
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Your sign-in code".to_owned(),
+            html_body: "<p>Your code is 123456</p>".to_owned(),
+            text_body: "Your code is 123456".to_owned(),
+            attachments: Vec::new(),
+            expires_at: None,
+        }
+    }
+
+    fn email_client(config: &SmtpConfig) -> SmtpEmailClient {
+        SmtpEmailClient::new(
+            config,
+            email(test::email_client::SENDER),
+            test::email_client::TIMEOUT,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_html_and_text_parts() {
+        let sink = SmtpSink::start().await;
+        let email_client = email_client(&smtp_config(sink.port, SmtpTls::None));
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message())
+            .await;
+        assert!(outcome.is_ok(), "{:?}", outcome);
+
+        let received = sink.received().await;
+        assert_eq!(received.len(), 1);
+
+        let mail = &received[0];
+        assert_eq!(mail.auth, None);
+        assert!(mail.mail_from.contains(test::email_client::SENDER));
+        assert_eq!(mail.rcpt_to, vec!["<recipient@example.com>"]);
+        assert!(mail.data.contains("Subject: Your sign-in code"));
+        assert!(mail.data.contains("multipart/alternative"));
+        assert!(mail.data.contains("Content-Type: text/plain"));
+        assert!(mail.data.contains("Content-Type: text/html"));
+        assert!(mail.data.contains("<p>Your code is 123456</p>"));
+    }
+
+    #[tokio::test]
+    async fn send_email_adds_attachments_after_the_bodies() {
+        let sink = SmtpSink::start().await;
+        let email_client = email_client(&smtp_config(sink.port, SmtpTls::None));
+        let message = message().with_attachment(EmailAttachment {
+            filename: "export.json".to_owned(),
+            content_type: "application/json".to_owned(),
+            content: b"{}".to_vec(),
+        });
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message)
+            .await;
+        assert!(outcome.is_ok(), "{:?}", outcome);
+
+        let mail = &sink.received().await[0];
+        assert!(mail.data.contains("multipart/mixed"));
+        assert!(mail.data.contains("multipart/alternative"));
+        assert!(mail.data.contains("filename=\"export.json\""));
+        assert!(mail.data.contains("Content-Type: application/json"));
+    }
+
+    #[tokio::test]
+    async fn send_email_authenticates_with_credentials() {
+        let sink = SmtpSink::start().await;
+        let mut config = smtp_config(sink.port, SmtpTls::None);
+        config.username = Some("mailer".to_owned());
+        config.password = Some(Secret::new("hunter2".to_owned()));
+        let email_client = email_client(&config);
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message())
+            .await;
+        assert!(outcome.is_ok(), "{:?}", outcome);
+
+        // AUTH PLAIN sends base64("\0mailer\0hunter2")
+        let received = sink.received().await;
+        assert_eq!(received[0].auth.as_deref(), Some("AG1haWxlcgBodW50ZXIy"));
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_starttls_is_not_offered() {
+        let sink = SmtpSink::start().await;
+        let email_client = email_client(&smtp_config(sink.port, SmtpTls::Starttls));
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message())
+            .await;
+
+        assert!(outcome.is_err());
+        assert!(sink.received().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_server_does_not_speak_tls() {
+        let sink = SmtpSink::start().await;
+        let email_client = email_client(&smtp_config(sink.port, SmtpTls::Implicit));
+
+        let outcome = email_client
+            .send_email(&email("recipient@example.com"), &message())
+            .await;
+
+        assert!(outcome.is_err());
+        assert!(sink.received().await.is_empty());
+    }
+
+    #[test]
+    fn new_rejects_username_without_password() {
+        let mut config = smtp_config(25, SmtpTls::None);
+        config.username = Some("mailer".to_owned());
+
+        assert!(
+            SmtpEmailClient::new(
+                &config,
+                email(test::email_client::SENDER),
+                test::email_client::TIMEOUT
+            )
+            .is_err()
+        );
+    }
+}
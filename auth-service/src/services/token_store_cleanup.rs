@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::time::MissedTickBehavior;
+
+use crate::{
+    services::data_stores::{PostgresBannedTokenStore, PostgresTwoFaCodeStore},
+    settings::Settings,
+};
+
+// Postgres keeps expired 2FA codes and banned tokens until they are deleted,
+// unlike Redis which expires the keys by itself.
+pub struct TokenStoreCleanup {
+    two_fa_code_store: PostgresTwoFaCodeStore,
+    banned_token_store: PostgresBannedTokenStore,
+    interval: Duration,
+}
+
+impl TokenStoreCleanup {
+    pub fn new(pool: PgPool, settings: Settings, interval: Duration) -> Self {
+        Self {
+            two_fa_code_store: PostgresTwoFaCodeStore::new(pool.clone()),
+            banned_token_store: PostgresBannedTokenStore::new(pool, settings),
+            interval,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            self.delete_expired().await;
+        }
+    }
+
+    #[tracing::instrument(name = "Cleaning up expired tokens", skip_all)]
+    pub async fn delete_expired(&self) {
+        match self.two_fa_code_store.delete_expired().await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!(deleted, "Deleted expired 2FA codes"),
+            Err(e) => tracing::error!(error = ?e, "Failed to delete expired 2FA codes"),
+        }
+        match self.banned_token_store.delete_expired().await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!(
+                deleted,
+                "Deleted expired banned tokens and token generations"
+            ),
+            Err(e) => tracing::error!(error = ?e, "Failed to delete expired banned tokens"),
+        }
+    }
+}
@@ -0,0 +1,757 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::{Result, WrapErr, eyre};
+use rand::{Rng, distr::Alphanumeric};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        data_stores::{
+            AuditEvent, AuditEventType, AuditLog, BannedTokenStore, UserQuery, UserStore,
+            UserStoreError,
+        },
+        email::Email,
+        password::Password,
+        password_hash::PasswordHash,
+        role::Role,
+        user::{ImportedUser, UserProfile},
+        user_status::UserStatus,
+    },
+    utils::password_hash::compute_password_hash,
+};
+
+const EXPORT_PAGE_SIZE: u32 = 100;
+const TEMPORARY_PASSWORD_LENGTH: usize = 20;
+
+// A user as exported to and imported from JSON. Password hashes are never
+// exported. An imported user can come with a password, or with the bcrypt,
+// scrypt, pbkdf2 or argon2 hash another system kept for it, and gets a
+// temporary password when it has neither.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserRecord {
+    pub email: String,
+    #[serde(rename = "requires2FA", default)]
+    pub requires_2fa: bool,
+    #[serde(default)]
+    pub status: UserStatus,
+    #[serde(rename = "passwordResetRequired", default)]
+    pub password_reset_required: bool,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    #[serde(default, skip_serializing)]
+    pub password: Option<Secret<String>>,
+    #[serde(rename = "passwordHash", default, skip_serializing)]
+    pub password_hash: Option<Secret<String>>,
+}
+
+impl From<UserProfile> for UserRecord {
+    fn from(profile: UserProfile) -> Self {
+        UserRecord {
+            email: profile.email.as_ref().expose_secret().to_owned(),
+            requires_2fa: profile.requires_2fa,
+            status: profile.status,
+            password_reset_required: profile.password_reset_required,
+            roles: profile.roles,
+            password: None,
+            password_hash: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub created: Vec<CreatedUser>,
+    // Users that already existed and were left untouched
+    pub skipped: Vec<String>,
+    // Users that were not created, nothing of them was kept
+    pub failed: Vec<FailedUser>,
+}
+
+#[derive(Debug)]
+pub struct CreatedUser {
+    pub email: String,
+    // Set when the record had neither a password nor a hash, the user has to
+    // change it on first login
+    pub temporary_password: Option<Password>,
+}
+
+#[derive(Debug)]
+pub struct FailedUser {
+    pub email: String,
+    pub error: UserStoreError,
+}
+
+// User management for operators, used by the auth-admin binary. Every change
+// is written to the audit log with `actor` as the actor.
+pub struct UserAdmin {
+    user_store: Arc<dyn UserStore>,
+    banned_token_store: Arc<dyn BannedTokenStore>,
+    audit_log: Arc<dyn AuditLog>,
+    actor: String,
+}
+
+impl UserAdmin {
+    pub fn new(
+        user_store: Arc<dyn UserStore>,
+        banned_token_store: Arc<dyn BannedTokenStore>,
+        audit_log: Arc<dyn AuditLog>,
+        actor: impl Into<String>,
+    ) -> Self {
+        Self {
+            user_store,
+            banned_token_store,
+            audit_log,
+            actor: actor.into(),
+        }
+    }
+
+    // Creates the user together with its roles, or nothing at all. Returns the
+    // generated password when none was given.
+    pub async fn create_user(
+        &self,
+        email: &Email,
+        password: Option<Password>,
+        requires_2fa: bool,
+        roles: &[Role],
+    ) -> Result<Option<Password>> {
+        let (password, temporary_password) = password_or_temporary(password);
+
+        self.user_store
+            .import_user(ImportedUser {
+                email: email.clone(),
+                password_hash: hash_password(password).await?,
+                requires_2fa,
+                status: UserStatus::Active,
+                password_reset_required: temporary_password.is_some(),
+                roles: roles.to_vec(),
+            })
+            .await?;
+
+        self.record(AuditEventType::AdminCreateUser, email, None)
+            .await?;
+        Ok(temporary_password)
+    }
+
+    // Sets a new password and signs the user out everywhere. Returns the
+    // generated password when none was given, which has to be changed on the
+    // next login.
+    pub async fn reset_password(
+        &self,
+        email: &Email,
+        password: Option<Password>,
+    ) -> Result<Option<Password>> {
+        let (password, temporary_password) = password_or_temporary(password);
+
+        self.user_store.set_new_password(email, password).await?;
+        self.user_store
+            .set_password_reset_required(email, temporary_password.is_some())
+            .await?;
+        self.banned_token_store.revoke_all_tokens(email).await?;
+
+        self.record(AuditEventType::AdminResetPassword, email, None)
+            .await?;
+        Ok(temporary_password)
+    }
+
+    pub async fn set_requires_2fa(&self, email: &Email, requires_2fa: bool) -> Result<()> {
+        self.user_store
+            .set_requires_2fa(email, requires_2fa)
+            .await?;
+
+        self.record(
+            AuditEventType::AdminSetRequires2Fa,
+            email,
+            Some(format!("requires_2fa={requires_2fa}")),
+        )
+        .await
+    }
+
+    // Returns false when the user was not locked
+    pub async fn unlock(&self, email: &Email) -> Result<bool> {
+        if self.user_store.get_status(email).await? != UserStatus::Locked {
+            return Ok(false);
+        }
+
+        self.user_store
+            .set_status(email, UserStatus::Active)
+            .await?;
+
+        self.record(AuditEventType::AdminUnlockUser, email, None)
+            .await?;
+        Ok(true)
+    }
+
+    pub async fn revoke_sessions(&self, email: &Email) -> Result<()> {
+        // Make sure the user exists so typos are reported
+        self.user_store.get_user(email).await?;
+        self.banned_token_store.revoke_all_tokens(email).await?;
+
+        self.record(AuditEventType::AdminRevokeSessions, email, None)
+            .await
+    }
+
+    pub async fn export_users(&self) -> Result<Vec<UserRecord>> {
+        let mut records = Vec::new();
+        let mut query = UserQuery {
+            email_contains: None,
+            page: 1,
+            per_page: EXPORT_PAGE_SIZE,
+        };
+
+        loop {
+            let page = self.user_store.search_users(&query).await?;
+            let last_page = page.users.len() < EXPORT_PAGE_SIZE as usize;
+            records.extend(page.users.into_iter().map(UserRecord::from));
+            if last_page {
+                break;
+            }
+            query.page += 1;
+        }
+
+        let event = AuditEvent::new(AuditEventType::AdminExportUsers)
+            .actor(&self.actor)
+            .details(format!("{} users", records.len()));
+        self.audit_log.record(event).await?;
+
+        Ok(records)
+    }
+
+    // Every user is created together with its roles or not at all. Users that
+    // already exist are skipped, and one that fails doesn't stop the others.
+    pub async fn import_users(&self, users: UserImport) -> ImportReport {
+        let mut report = ImportReport::default();
+        for (record, email, credential) in users.0 {
+            match self.import_user(&record, &email, credential).await {
+                Ok(temporary_password) => {
+                    // The user is created either way, so a failure is only logged
+                    if let Err(e) = self
+                        .record(
+                            AuditEventType::AdminCreateUser,
+                            &email,
+                            Some("imported".to_owned()),
+                        )
+                        .await
+                    {
+                        tracing::error!(error = ?e, "Failed to record an imported user");
+                    }
+                    report.created.push(CreatedUser {
+                        email: record.email,
+                        temporary_password,
+                    });
+                }
+                Err(UserStoreError::UserAlreadyExists) => report.skipped.push(record.email),
+                Err(error) => report.failed.push(FailedUser {
+                    email: record.email,
+                    error,
+                }),
+            }
+        }
+
+        report
+    }
+
+    // Returns the generated password when the record had neither a password
+    // nor a hash
+    async fn import_user(
+        &self,
+        record: &UserRecord,
+        email: &Email,
+        credential: Credential,
+    ) -> Result<Option<Password>, UserStoreError> {
+        let (password_hash, temporary_password) = match credential {
+            Credential::Hash(password_hash) => (password_hash, None),
+            Credential::Password(password) => {
+                let (password, temporary_password) = password_or_temporary(password);
+                (hash_password(password).await?, temporary_password)
+            }
+        };
+
+        self.user_store
+            .import_user(ImportedUser {
+                email: email.clone(),
+                password_hash,
+                requires_2fa: record.requires_2fa,
+                status: record.status,
+                password_reset_required: record.password_reset_required
+                    || temporary_password.is_some(),
+                roles: record.roles.clone(),
+            })
+            .await?;
+        Ok(temporary_password)
+    }
+
+    async fn record(
+        &self,
+        event_type: AuditEventType,
+        target: &Email,
+        details: Option<String>,
+    ) -> Result<()> {
+        let mut event = AuditEvent::new(event_type)
+            .actor(&self.actor)
+            .target(target.as_ref().expose_secret());
+        if let Some(details) = details {
+            event = event.details(details);
+        }
+
+        self.audit_log.record(event).await?;
+        Ok(())
+    }
+}
+
+// Records that were all validated, so a bad file is rejected before any user
+// is created
+#[derive(Debug)]
+pub struct UserImport(Vec<(UserRecord, Email, Credential)>);
+
+impl TryFrom<Vec<UserRecord>> for UserImport {
+    type Error = color_eyre::Report;
+
+    fn try_from(records: Vec<UserRecord>) -> Result<Self> {
+        let users = records
+            .into_iter()
+            .enumerate()
+            .map(|(index, record)| {
+                parse_record(record)
+                    .wrap_err_with(|| format!("Invalid user record at index {index}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(UserImport(users))
+    }
+}
+
+// What an imported user logs in with, a missing password is replaced by a
+// temporary one
+#[derive(Debug)]
+enum Credential {
+    Password(Option<Password>),
+    Hash(PasswordHash),
+}
+
+fn parse_record(record: UserRecord) -> Result<(UserRecord, Email, Credential)> {
+    let email = Email::try_from(Secret::new(record.email.clone()))
+        .map_err(|e| eyre!("{e}: '{}'", record.email))?;
+    // Only the user can schedule the deletion of their account, which also
+    // needs a restore link and a due date that a record doesn't carry
+    if record.status == UserStatus::PendingDeletion {
+        return Err(eyre!("Users can't be imported pending deletion"));
+    }
+    let credential = match (&record.password, &record.password_hash) {
+        (Some(_), Some(_)) => return Err(eyre!("Give either password or passwordHash, not both")),
+        (_, Some(password_hash)) => {
+            Credential::Hash(PasswordHash::try_from(password_hash.clone())?)
+        }
+        (password, None) => {
+            Credential::Password(password.clone().map(Password::try_from).transpose()?)
+        }
+    };
+    Ok((record, email, credential))
+}
+
+async fn hash_password(password: Password) -> Result<PasswordHash, UserStoreError> {
+    compute_password_hash(password)
+        .await
+        .and_then(|hash| Ok(PasswordHash::try_from(hash)?))
+        .map_err(UserStoreError::UnexpectedError)
+}
+
+// Returns the password to store, and the same password again when it was
+// generated so it can be handed to the user
+fn password_or_temporary(password: Option<Password>) -> (Password, Option<Password>) {
+    match password {
+        Some(password) => (password, None),
+        None => {
+            let password = temporary_password();
+            (password.clone(), Some(password))
+        }
+    }
+}
+
+fn temporary_password() -> Password {
+    let password: String = rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(TEMPORARY_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect();
+    Password::try_from(Secret::new(password)).expect("Temporary passwords are long enough")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        services::data_stores::{HashMapUserStore, HashSetBannedTokenStore, VecAuditLog},
+        settings::AuthServiceSetting,
+    };
+
+    use super::*;
+
+    struct Fixture {
+        admin: UserAdmin,
+        user_store: Arc<HashMapUserStore>,
+        banned_token_store: Arc<HashSetBannedTokenStore>,
+        audit_log: Arc<VecAuditLog>,
+    }
+
+    fn fixture() -> Fixture {
+        let user_store = Arc::new(HashMapUserStore::default());
+        let banned_token_store =
+            Arc::new(HashSetBannedTokenStore::new(AuthServiceSetting::handle()));
+        let audit_log = Arc::new(VecAuditLog::new());
+        let admin = UserAdmin::new(
+            user_store.clone(),
+            banned_token_store.clone(),
+            audit_log.clone(),
+            "auth-admin",
+        );
+        Fixture {
+            admin,
+            user_store,
+            banned_token_store,
+            audit_log,
+        }
+    }
+
+    fn email(email: &str) -> Email {
+        Email::try_from(Secret::new(email.to_owned())).unwrap()
+    }
+
+    fn password(password: &str) -> Password {
+        Password::try_from(Secret::new(password.to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_user_generates_a_temporary_password() {
+        let fixture = fixture();
+        let email = email("ops@example.com");
+
+        let temporary_password = fixture
+            .admin
+            .create_user(&email, None, true, &[Role::admin()])
+            .await
+            .unwrap()
+            .expect("A temporary password should be generated");
+
+        assert!(
+            fixture
+                .user_store
+                .authenticate_user(&email, &temporary_password)
+                .await
+                .is_ok()
+        );
+        let profile = fixture.user_store.get_user_profile(&email).await.unwrap();
+        assert!(profile.requires_2fa);
+        assert!(profile.password_reset_required);
+        assert!(profile.roles.contains(&Role::admin()));
+
+        let records = fixture
+            .audit_log
+            .recent_events("ops@example.com", 10)
+            .await
+            .unwrap();
+        assert_eq!(records[0].event.event_type, AuditEventType::AdminCreateUser);
+        assert_eq!(records[0].event.actor.as_deref(), Some("auth-admin"));
+    }
+
+    #[tokio::test]
+    async fn test_create_user_with_an_unknown_role_keeps_nothing_of_it() {
+        let fixture = fixture();
+        let email = email("ops@example.com");
+
+        let error = fixture
+            .admin
+            .create_user(
+                &email,
+                Some(password("long-enough")),
+                false,
+                &[Role::parse("unknown").unwrap()],
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<UserStoreError>(),
+            Some(&UserStoreError::RoleNotFound)
+        );
+        assert!(fixture.user_store.get_user(&email).await.is_err());
+        assert!(
+            fixture
+                .audit_log
+                .recent_events("ops@example.com", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_revokes_sessions() {
+        let fixture = fixture();
+        let email = email("user@example.com");
+        fixture
+            .admin
+            .create_user(&email, Some(password("old-password")), false, &[])
+            .await
+            .unwrap();
+        let generation = fixture
+            .banned_token_store
+            .token_generation(&email)
+            .await
+            .unwrap();
+
+        let temporary_password = fixture
+            .admin
+            .reset_password(&email, Some(password("new-password")))
+            .await
+            .unwrap();
+
+        assert!(temporary_password.is_none());
+        assert!(
+            fixture
+                .user_store
+                .authenticate_user(&email, &password("new-password"))
+                .await
+                .is_ok()
+        );
+        assert!(
+            fixture
+                .banned_token_store
+                .token_generation(&email)
+                .await
+                .unwrap()
+                > generation
+        );
+        let profile = fixture.user_store.get_user_profile(&email).await.unwrap();
+        assert!(!profile.password_reset_required);
+    }
+
+    #[tokio::test]
+    async fn test_unlock_only_changes_locked_users() {
+        let fixture = fixture();
+        let email = email("user@example.com");
+        fixture
+            .admin
+            .create_user(&email, None, false, &[])
+            .await
+            .unwrap();
+
+        assert!(!fixture.admin.unlock(&email).await.unwrap());
+
+        fixture
+            .user_store
+            .set_status(&email, UserStatus::Locked)
+            .await
+            .unwrap();
+        assert!(fixture.admin.unlock(&email).await.unwrap());
+        assert_eq!(
+            fixture.user_store.get_status(&email).await.unwrap(),
+            UserStatus::Active
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_sessions_of_unknown_user_fails() {
+        let fixture = fixture();
+        assert!(
+            fixture
+                .admin
+                .revoke_sessions(&email("missing@example.com"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_users() {
+        let source = fixture();
+        for index in 0..(EXPORT_PAGE_SIZE + 5) {
+            source
+                .admin
+                .create_user(
+                    &email(&format!("user{index}@example.com")),
+                    None,
+                    false,
+                    &[],
+                )
+                .await
+                .unwrap();
+        }
+        source
+            .admin
+            .set_requires_2fa(&email("user1@example.com"), true)
+            .await
+            .unwrap();
+
+        let records = source.admin.export_users().await.unwrap();
+        assert_eq!(records.len(), EXPORT_PAGE_SIZE as usize + 5);
+        let json = serde_json::to_string(&records).unwrap();
+        assert!(!json.contains("password\""));
+
+        let target = fixture();
+        target
+            .admin
+            .create_user(&email("user0@example.com"), None, false, &[])
+            .await
+            .unwrap();
+        let report = target
+            .admin
+            .import_users(
+                UserImport::try_from(serde_json::from_str::<Vec<UserRecord>>(&json).unwrap())
+                    .unwrap(),
+            )
+            .await;
+
+        assert_eq!(report.skipped, vec!["user0@example.com"]);
+        assert_eq!(report.created.len(), EXPORT_PAGE_SIZE as usize + 4);
+        assert!(
+            report
+                .created
+                .iter()
+                .all(|user| user.temporary_password.is_some())
+        );
+        let profile = target
+            .user_store
+            .get_user_profile(&email("user1@example.com"))
+            .await
+            .unwrap();
+        assert!(profile.requires_2fa);
+        assert!(profile.password_reset_required);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_the_whole_file_on_an_invalid_record() {
+        let fixture = fixture();
+        let records: Vec<UserRecord> = serde_json::from_value(serde_json::json!([
+            { "email": "valid@example.com", "password": "long-enough" },
+            { "email": "not-an-email" }
+        ]))
+        .unwrap();
+
+        let error = UserImport::try_from(records).unwrap_err();
+        assert!(error.to_string().contains("index 1"));
+        assert!(
+            fixture
+                .user_store
+                .get_user(&email("valid@example.com"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_a_failed_user_and_keeps_nothing_of_it() {
+        let fixture = fixture();
+        let records: Vec<UserRecord> = serde_json::from_value(serde_json::json!([
+            { "email": "unknown-role@example.com", "password": "long-enough", "roles": ["unknown"] },
+            { "email": "admin@example.com", "password": "long-enough", "roles": ["admin"] }
+        ]))
+        .unwrap();
+
+        let report = fixture
+            .admin
+            .import_users(UserImport::try_from(records).unwrap())
+            .await;
+
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].email, "unknown-role@example.com");
+        assert_eq!(report.failed[0].error, UserStoreError::RoleNotFound);
+        assert!(
+            fixture
+                .user_store
+                .get_user(&email("unknown-role@example.com"))
+                .await
+                .is_err()
+        );
+
+        assert_eq!(report.created.len(), 1);
+        let profile = fixture
+            .user_store
+            .get_user_profile(&email("admin@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(profile.roles, vec![Role::admin(), Role::user()]);
+        assert!(
+            fixture
+                .user_store
+                .authenticate_user(&email("admin@example.com"), &password("long-enough"))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_users_with_password_hashes() {
+        let fixture = fixture();
+        let bcrypt_hash = bcrypt::hash("imported-password", 4).unwrap();
+        let records: Vec<UserRecord> = serde_json::from_value(serde_json::json!([
+            { "email": "bcrypt@example.com", "passwordHash": bcrypt_hash },
+            {
+                "email": "scrypt@example.com",
+                "passwordHash": "$scrypt$ln=4,r=8,p=1$c29tZXNhbHQ$cm0lQ2BRMh0ejqG6V+cBvHpYxXhtHgl/LO7DLE/6t0g"
+            }
+        ]))
+        .unwrap();
+
+        let report = fixture
+            .admin
+            .import_users(UserImport::try_from(records).unwrap())
+            .await;
+        assert_eq!(report.created.len(), 2);
+        assert!(
+            report
+                .created
+                .iter()
+                .all(|user| user.temporary_password.is_none())
+        );
+
+        let bcrypt_user = email("bcrypt@example.com");
+        assert!(
+            fixture
+                .user_store
+                .authenticate_user(&bcrypt_user, &password("wrong-password"))
+                .await
+                .is_err()
+        );
+        // The first login rehashes, the second verifies against our own hash
+        for _ in 0..2 {
+            fixture
+                .user_store
+                .authenticate_user(&bcrypt_user, &password("imported-password"))
+                .await
+                .unwrap();
+        }
+        assert!(
+            !fixture
+                .user_store
+                .get_user_profile(&bcrypt_user)
+                .await
+                .unwrap()
+                .password_reset_required
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_users_pending_deletion() {
+        let records: Vec<UserRecord> = serde_json::from_value(serde_json::json!([
+            { "email": "pending@example.com", "status": "pending_deletion" }
+        ]))
+        .unwrap();
+
+        let error = UserImport::try_from(records).unwrap_err();
+        assert!(format!("{error:#}").contains("pending deletion"));
+    }
+
+    #[test]
+    fn test_import_rejects_a_record_with_password_and_hash() {
+        let records: Vec<UserRecord> = serde_json::from_value(serde_json::json!([
+            {
+                "email": "both@example.com",
+                "password": "long-enough",
+                "passwordHash": bcrypt::hash("long-enough", 4).unwrap()
+            }
+        ]))
+        .unwrap();
+
+        let error = UserImport::try_from(records).unwrap_err();
+        assert!(format!("{error:#}").contains("passwordHash"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::{Result, WrapErr};
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    domain::secret_provider::{SecretProvider, SecretValues},
+    settings::VaultConfig,
+    utils::constants::prod,
+};
+
+// Reads secrets from a single entry of a Vault KV version 2 engine, or any
+// server speaking the same API. Each secret is a key of the entry named like
+// its environment variable.
+pub struct VaultSecretProvider {
+    http_client: Client,
+    address: String,
+    mount: String,
+    path: String,
+    token: Secret<String>,
+}
+
+impl VaultSecretProvider {
+    pub fn new(
+        address: String,
+        mount: String,
+        path: String,
+        token: Secret<String>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            address,
+            mount,
+            path,
+            token,
+        }
+    }
+
+    async fn read_entry(&self) -> Result<HashMap<String, String>> {
+        // Joined onto a base with a trailing slash, otherwise the last segment
+        // of an address like https://host/vault would be replaced
+        let base = format!("{}/", self.address.trim_end_matches('/'));
+        let url = Url::parse(&base)?.join(&format!(
+            "v1/{}/data/{}",
+            self.mount.trim_matches('/'),
+            self.path.trim_matches('/')
+        ))?;
+
+        let response = self
+            .http_client
+            .get(url)
+            .header(VAULT_TOKEN_HEADER, self.token.expose_secret())
+            .send()
+            .await?
+            .error_for_status()
+            .wrap_err("Vault refused to read the secrets")?
+            .json::<ReadSecretResponse>()
+            .await?;
+
+        Ok(response.data.data)
+    }
+}
+
+#[async_trait::async_trait]
+impl SecretProvider for VaultSecretProvider {
+    async fn get_secret(&self, name: &str) -> Result<Option<Secret<String>>> {
+        Ok(self.read_entry().await?.remove(name).map(Secret::new))
+    }
+
+    // All secrets live in one entry, so they are read with a single request
+    async fn get_secrets(&self, names: &[&str]) -> Result<SecretValues> {
+        let mut entry = self.read_entry().await?;
+        let mut secrets = SecretValues::default();
+        for name in names {
+            if let Some(value) = entry.remove(*name) {
+                secrets.insert(name, Secret::new(value));
+            }
+        }
+        Ok(secrets)
+    }
+}
+
+const VAULT_TOKEN_HEADER: &str = "X-Vault-Token";
+
+#[derive(Deserialize)]
+struct ReadSecretResponse {
+    data: SecretData,
+}
+
+#[derive(Deserialize)]
+struct SecretData {
+    data: HashMap<String, String>,
+}
+
+pub fn configure_vault_secret_provider(
+    config: &VaultConfig,
+    token: Secret<String>,
+) -> VaultSecretProvider {
+    let http_client = Client::builder()
+        .timeout(prod::SECRET_PROVIDER_TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    VaultSecretProvider::new(
+        config.address.clone(),
+        config.mount.clone(),
+        config.path.clone(),
+        token,
+        http_client,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn vault_secret_provider(address: String) -> VaultSecretProvider {
+        VaultSecretProvider::new(
+            address,
+            "secret".to_owned(),
+            "auth-service".to_owned(),
+            Secret::new("vault-token".to_owned()),
+            Client::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_reads_the_requested_secrets_with_one_request() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/auth-service"))
+            .and(header(VAULT_TOKEN_HEADER, "vault-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {
+                    "data": {
+                        "JWT_SECRET": "from vault",
+                        "UNRELATED": "not requested"
+                    },
+                    "metadata": { "version": 3 }
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let provider = vault_secret_provider(mock_server.uri());
+        let secrets = provider
+            .get_secrets(&["JWT_SECRET", "DATABASE_URL"])
+            .await
+            .unwrap();
+
+        assert_eq!(secrets.get("JWT_SECRET"), Some("from vault"));
+        assert_eq!(secrets.get("DATABASE_URL"), None);
+        assert_eq!(secrets.get("UNRELATED"), None);
+    }
+
+    #[tokio::test]
+    async fn test_keeps_the_path_of_the_address() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/vault/v1/secret/data/auth-service"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "data": { "JWT_SECRET": "from vault" } }
+            })))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        for address in ["/vault", "/vault/"] {
+            let provider = vault_secret_provider(format!("{}{}", mock_server.uri(), address));
+            assert_eq!(
+                provider
+                    .get_secret("JWT_SECRET")
+                    .await
+                    .unwrap()
+                    .map(|secret| secret.expose_secret().to_owned()),
+                Some("from vault".to_owned())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fails_when_vault_refuses_the_token() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&mock_server)
+            .await;
+
+        let provider = vault_secret_provider(mock_server.uri());
+        assert!(provider.get_secret("JWT_SECRET").await.is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use color_eyre::eyre::Result;
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    domain::{phone_number::PhoneNumber, sms_client::SmsClient},
+    settings::{AuthServiceSetting, SmsClientConfig},
+};
+
+// Sends text messages by posting them to an HTTP endpoint, which can be an SMS
+// provider's API or a small relay in front of one.
+pub struct WebhookSmsClient {
+    http_client: Client,
+    webhook_url: String,
+    sender: Option<String>,
+    auth_token: Option<Secret<String>>,
+}
+
+impl WebhookSmsClient {
+    pub fn new(
+        webhook_url: String,
+        sender: Option<String>,
+        auth_token: Option<Secret<String>>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            webhook_url,
+            sender,
+            auth_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for WebhookSmsClient {
+    #[tracing::instrument(name = "Sending SMS", skip_all)]
+    async fn send_sms(&self, recipient: &PhoneNumber, message: &str) -> Result<()> {
+        let request_body = SendSmsRequest {
+            to: recipient.as_ref().expose_secret(),
+            from: self.sender.as_deref(),
+            body: message,
+        };
+
+        let mut request = self.http_client.post(&self.webhook_url).json(&request_body);
+        if let Some(auth_token) = &self.auth_token {
+            request = request.bearer_auth(auth_token.expose_secret());
+        }
+
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize, Debug)]
+struct SendSmsRequest<'a> {
+    to: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<&'a str>,
+    body: &'a str,
+}
+
+// None when no sms_client section is configured, which leaves SMS unavailable
+pub fn configure_webhook_sms_client() -> Option<WebhookSmsClient> {
+    let settings = AuthServiceSetting::load();
+    let config: &SmsClientConfig = settings.sms_client.as_ref()?;
+
+    let http_client = Client::builder()
+        .timeout(config.timeout_in_millis)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    Some(WebhookSmsClient::new(
+        config.webhook_url.clone(),
+        config.sender.clone(),
+        config.auth_token.clone(),
+        http_client,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wiremock::matchers::{any, body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn phone_number() -> PhoneNumber {
+        PhoneNumber::try_from(Secret::new("+4791234567".to_owned())).unwrap()
+    }
+
+    fn sms_client(mock_server: &MockServer, auth_token: Option<&str>) -> WebhookSmsClient {
+        let http_client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        WebhookSmsClient::new(
+            format!("{}/sms", mock_server.uri()),
+            Some("AuthService".to_owned()),
+            auth_token.map(|token| Secret::new(token.to_owned())),
+            http_client,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_sms_posts_message_to_webhook() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(&mock_server, Some("token"));
+
+        Mock::given(method("POST"))
+            .and(path("/sms"))
+            .and(header("Authorization", "Bearer token"))
+            .and(body_json(serde_json::json!({
+                "to": "+4791234567",
+                "from": "AuthService",
+                "body": "123456 is your sign-in code",
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client
+            .send_sms(&phone_number(), "123456 is your sign-in code")
+            .await;
+
+        assert!(outcome.is_ok(), "{:?}", outcome);
+    }
+
+    #[tokio::test]
+    async fn send_sms_omits_authorization_without_token() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(&mock_server, None);
+
+        Mock::given(path("/sms"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&phone_number(), "hello").await;
+
+        assert!(outcome.is_ok());
+        let requests = mock_server.received_requests().await.unwrap();
+        assert!(!requests[0].headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn send_sms_fails_if_the_webhook_returns_500() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(&mock_server, None);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&phone_number(), "hello").await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_sms_times_out_if_the_webhook_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(&mock_server, None);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(180)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&phone_number(), "hello").await;
+
+        assert!(outcome.is_err());
+    }
+}
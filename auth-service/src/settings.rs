@@ -1,6 +1,8 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
     ops::Deref,
-    sync::{Arc, LazyLock},
+    path::Path,
+    sync::{Arc, OnceLock},
     time::Duration,
 };
 
@@ -10,22 +12,32 @@ use color_eyre::eyre::Result;
 use config::ConfigError;
 use dashmap::DashSet;
 use dotenvy::dotenv;
-use secrecy::Secret;
-use serde::{Deserialize, Deserializer, Serialize};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use thiserror::Error;
 
-pub static CONFIG: LazyLock<ArcSwap<Config>> =
-    LazyLock::new(|| ArcSwap::from_pointee(Config::new().expect("Failed to load config")));
+// Set by `AuthServiceSetting::init`, or loaded from the environment on first
+// use when it was not
+static CONFIG: OnceLock<Settings> = OnceLock::new();
 
+use crate::domain::email_domain_policy::EmailDomainPolicy;
+use crate::domain::locale::Locale;
+use crate::domain::secret_provider::SecretValues;
+use crate::services::env_secret_provider::EnvSecretProvider;
 use crate::utils::constants::env::{
     AUTH_SERVICE_ALLOWED_ORIGINS_ENV_VAR, DATABASE_URL_ENV_VAR, JWT_ELEVATED_SECRET_ENV_VAR,
-    JWT_SECRET_ENV_VAR, POSTMARK_AUTH_TOKEN_ENV_VAR, REDIS_HOST_NAME_ENV_VAR,
+    JWT_SECRET_ENV_VAR, POSTMARK_AUTH_TOKEN_ENV_VAR, REDIS_HOST_NAME_ENV_VAR, SECRETS,
+    SMS_WEBHOOK_AUTH_TOKEN_ENV_VAR, SMTP_PASSWORD_ENV_VAR,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 #[allow(unused)]
 pub struct JWTConfig {
     pub cookie_name: String,
+    #[serde(serialize_with = "redacted")]
     pub secret: Secret<String>,
+    #[serde(rename = "time_to_live_in_seconds")]
     pub time_to_live: i64,
 }
 
@@ -50,7 +62,7 @@ impl<'de> Deserialize<'de> for JWTConfig {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct AuthConfig {
     pub jwt: JWTConfig,
@@ -58,13 +70,51 @@ pub struct AuthConfig {
     pub allowed_origins: AllowedOrigins,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailProvider {
+    #[default]
+    Postmark,
+    Smtp,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    // Plaintext, only meant for local mail sinks
+    None,
+    #[default]
+    Starttls,
+    Implicit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct SmtpConfig {
+    pub host: String,
+    // Defaults to the standard port of the TLS mode
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    #[serde(serialize_with = "redacted_option")]
+    pub password: Option<Secret<String>>,
+}
+
+#[derive(Debug, Serialize)]
 #[allow(unused)]
 pub struct EmailClientConfig {
+    pub provider: EmailProvider,
     pub base_url: String,
     pub sender: String,
+    #[serde(serialize_with = "millis")]
     pub timeout_in_millis: Duration,
-    pub auth_token: Secret<String>,
+    // Only used by the postmark provider
+    #[serde(serialize_with = "redacted_option")]
+    pub auth_token: Option<Secret<String>>,
+    // Only used by the smtp provider
+    pub smtp: Option<SmtpConfig>,
+    pub locale: Locale,
 }
 
 impl<'de> Deserialize<'de> for EmailClientConfig {
@@ -74,127 +124,821 @@ impl<'de> Deserialize<'de> for EmailClientConfig {
     {
         #[derive(Deserialize)]
         struct Helper {
+            #[serde(default)]
+            provider: EmailProvider,
             base_url: String,
             sender: String,
             timeout_in_millis: u64,
-            auth_token: Secret<String>,
+            auth_token: Option<Secret<String>>,
+            smtp: Option<SmtpConfig>,
+            #[serde(default)]
+            locale: Locale,
         }
 
         let helper = Helper::deserialize(deserializer)?;
 
+        match helper.provider {
+            EmailProvider::Postmark if helper.auth_token.is_none() => {
+                return Err(serde::de::Error::custom(
+                    "POSTMARK_AUTH_TOKEN must be set when using the postmark email provider",
+                ));
+            }
+            EmailProvider::Smtp if helper.smtp.is_none() => {
+                return Err(serde::de::Error::custom(
+                    "email_client.smtp must be set when using the smtp email provider",
+                ));
+            }
+            _ => {}
+        }
+
         let config = EmailClientConfig {
+            provider: helper.provider,
             base_url: helper.base_url,
             sender: helper.sender,
             timeout_in_millis: Duration::from_millis(helper.timeout_in_millis),
             auth_token: helper.auth_token,
+            smtp: helper.smtp,
+            locale: helper.locale,
+        };
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[allow(unused)]
+pub struct SmsClientConfig {
+    pub webhook_url: String,
+    pub sender: Option<String>,
+    #[serde(serialize_with = "millis")]
+    pub timeout_in_millis: Duration,
+    #[serde(serialize_with = "redacted_option")]
+    pub auth_token: Option<Secret<String>>,
+}
+
+impl<'de> Deserialize<'de> for SmsClientConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Helper {
+            webhook_url: String,
+            sender: Option<String>,
+            timeout_in_millis: u64,
+            auth_token: Option<Secret<String>>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+
+        let config = SmsClientConfig {
+            webhook_url: helper.webhook_url,
+            sender: helper.sender,
+            timeout_in_millis: Duration::from_millis(helper.timeout_in_millis),
+            auth_token: helper.auth_token,
+        };
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[allow(unused)]
+pub struct EmailOutboxConfig {
+    #[serde(serialize_with = "millis")]
+    pub poll_interval_in_millis: Duration,
+    pub batch_size: u32,
+    // Emails are dead-lettered after this many failed delivery attempts
+    pub max_attempts: u32,
+    #[serde(serialize_with = "secs")]
+    pub initial_backoff_in_secs: Duration,
+    #[serde(serialize_with = "secs")]
+    pub max_backoff_in_secs: Duration,
+}
+
+impl Default for EmailOutboxConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_in_millis: Duration::from_millis(1000),
+            batch_size: 20,
+            max_attempts: 8,
+            initial_backoff_in_secs: Duration::from_secs(5),
+            max_backoff_in_secs: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EmailOutboxConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Helper {
+            poll_interval_in_millis: Option<u64>,
+            batch_size: Option<u32>,
+            max_attempts: Option<u32>,
+            initial_backoff_in_secs: Option<u64>,
+            max_backoff_in_secs: Option<u64>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+        let default = EmailOutboxConfig::default();
+
+        let config = EmailOutboxConfig {
+            poll_interval_in_millis: helper
+                .poll_interval_in_millis
+                .map(Duration::from_millis)
+                .unwrap_or(default.poll_interval_in_millis),
+            batch_size: helper.batch_size.unwrap_or(default.batch_size),
+            max_attempts: helper.max_attempts.unwrap_or(default.max_attempts),
+            initial_backoff_in_secs: helper
+                .initial_backoff_in_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.initial_backoff_in_secs),
+            max_backoff_in_secs: helper
+                .max_backoff_in_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.max_backoff_in_secs),
+        };
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenStoreBackend {
+    #[default]
+    Redis,
+    Postgres,
+    // Kept in process memory, only suitable for a single instance
+    Memory,
+}
+
+// Where 2FA codes and banned tokens are kept
+#[derive(Debug, Clone, Serialize)]
+#[allow(unused)]
+pub struct TokenStoreConfig {
+    pub backend: TokenStoreBackend,
+    // How often the postgres and memory backends remove expired entries,
+    // Redis expires keys by itself
+    #[serde(serialize_with = "secs")]
+    pub cleanup_interval_in_secs: Duration,
+}
+
+impl Default for TokenStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: TokenStoreBackend::default(),
+            cleanup_interval_in_secs: Duration::from_secs(300),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenStoreConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Helper {
+            #[serde(default)]
+            backend: TokenStoreBackend,
+            cleanup_interval_in_secs: Option<u64>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+        let default = TokenStoreConfig::default();
+
+        let config = TokenStoreConfig {
+            backend: helper.backend,
+            cleanup_interval_in_secs: helper
+                .cleanup_interval_in_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.cleanup_interval_in_secs),
+        };
+
+        Ok(config)
+    }
+}
+
+// Accounts deleted by their users are kept for a grace period, during which
+// the emailed link restores them, and are purged once it is over
+#[derive(Debug, Clone, Serialize)]
+#[allow(unused)]
+pub struct AccountDeletionConfig {
+    pub grace_period_in_days: u32,
+    // How often accounts past their grace period are looked for
+    #[serde(serialize_with = "secs")]
+    pub purge_interval_in_secs: Duration,
+    // The page the restore link opens, the token is added as the token query
+    // parameter
+    pub restore_url: String,
+}
+
+impl Default for AccountDeletionConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_in_days: 30,
+            purge_interval_in_secs: Duration::from_secs(3600),
+            restore_url: "http://localhost:3000/restore-account.html".to_owned(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AccountDeletionConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Helper {
+            grace_period_in_days: Option<u32>,
+            purge_interval_in_secs: Option<u64>,
+            restore_url: Option<String>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+        let default = AccountDeletionConfig::default();
+
+        let config = AccountDeletionConfig {
+            grace_period_in_days: helper
+                .grace_period_in_days
+                .unwrap_or(default.grace_period_in_days),
+            purge_interval_in_secs: helper
+                .purge_interval_in_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.purge_interval_in_secs),
+            restore_url: helper.restore_url.unwrap_or(default.restore_url),
         };
 
         Ok(config)
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[default]
+    Postgres,
+    // For single-node installations and local development. The email outbox
+    // and the postgres token store are unavailable with it.
+    Sqlite,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct DatabaseConfig {
+    #[serde(default)]
+    pub backend: DatabaseBackend,
+    #[serde(default = "default_sqlite_url")]
+    pub sqlite_url: String,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            backend: DatabaseBackend::default(),
+            sqlite_url: default_sqlite_url(),
+        }
+    }
+}
+
+fn default_sqlite_url() -> String {
+    "sqlite://auth-service.db".to_owned()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct PostgresConfig {
-    pub url: Secret<String>,
+    // Only needed when database.backend is postgres
+    #[serde(serialize_with = "redacted_option")]
+    pub url: Option<Secret<String>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct RedisConfig {
     pub host_name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct SignupConfig {
+    #[serde(default)]
+    pub domain_policy: EmailDomainPolicy,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct ProxyConfig {
+    // Reverse proxies in front of the service. Each appends the address it was
+    // connected from to X-Forwarded-For, so the client address is the entry
+    // added by the outermost one. Anything further left is set by the client.
+    // With none, the address of the connection is used.
+    #[serde(default)]
+    pub trusted_proxies: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct DevConfig {
+    // Capture emails in memory and serve them at /_dev/mailbox instead of
+    // delivering them. Never enable this in production.
+    #[serde(default)]
+    pub mailbox: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretProviderKind {
+    // Environment variables, or the files named by their _FILE variants
+    #[default]
+    Env,
+    // One file per secret in secrets.directory
+    File,
+    // An entry of a Vault KV version 2 engine
+    Vault,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct VaultConfig {
+    pub address: String,
+    #[serde(default = "default_vault_mount")]
+    pub mount: String,
+    pub path: String,
+}
+
+fn default_vault_mount() -> String {
+    "secret".to_owned()
+}
+
+// Where the secrets listed in `constants::env::SECRETS` come from. Secrets the
+// provider does not hold fall back to the environment.
+#[derive(Debug, Clone, Serialize)]
+#[allow(unused)]
+pub struct SecretsConfig {
+    pub provider: SecretProviderKind,
+    // Only used by the file provider
+    pub directory: Option<String>,
+    // Only used by the vault provider
+    pub vault: Option<VaultConfig>,
+    // How often the secrets are fetched again to pick up rotations
+    #[serde(serialize_with = "secs")]
+    pub refresh_interval_in_secs: Duration,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            provider: SecretProviderKind::default(),
+            directory: None,
+            vault: None,
+            refresh_interval_in_secs: Duration::from_secs(300),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretsConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Helper {
+            #[serde(default)]
+            provider: SecretProviderKind,
+            directory: Option<String>,
+            vault: Option<VaultConfig>,
+            refresh_interval_in_secs: Option<u64>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+        let default = SecretsConfig::default();
+
+        let config = SecretsConfig {
+            provider: helper.provider,
+            directory: helper.directory,
+            vault: helper.vault,
+            refresh_interval_in_secs: helper
+                .refresh_interval_in_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.refresh_interval_in_secs),
+        };
+
+        Ok(config)
+    }
+}
+
+impl SecretsConfig {
+    // Reads only the secrets section, which is needed to fetch the secrets
+    // the rest of the config depends on
+    pub fn new() -> Result<Self, SettingsError> {
+        #[derive(Deserialize)]
+        struct Helper {
+            #[serde(default)]
+            secrets: SecretsConfig,
+        }
+
+        let helper: Helper = config::Config::builder()
+            .add_source(config::File::with_name(CONFIG_FILE_PATH))
+            .build()?
+            .try_deserialize()?;
+        Ok(helper.secrets)
+    }
+}
+
+// Serializes to the layout of config.json with every secret redacted, so it is
+// safe to log
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(unused)]
 pub struct Config {
     pub auth: AuthConfig,
+    #[serde(default)]
+    pub signup: SignupConfig,
     pub email_client: EmailClientConfig,
+    // SMS two-factor authentication is unavailable when this is not set
+    pub sms_client: Option<SmsClientConfig>,
+    #[serde(default)]
+    pub email_outbox: EmailOutboxConfig,
+    #[serde(default)]
+    pub token_store: TokenStoreConfig,
+    #[serde(default)]
+    pub account_deletion: AccountDeletionConfig,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
     pub postgres: PostgresConfig,
-    pub redis: RedisConfig,
+    // Only needed when token_store.backend is redis
+    pub redis: Option<RedisConfig>,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub dev: DevConfig,
+    #[serde(default)]
+    pub secrets: SecretsConfig,
 }
 
+pub const CONFIG_FILE_PATH: &str = "config/config.json";
+// HS256 keys shorter than the 256 bit hash output weaken the signature
+const MIN_JWT_SECRET_LENGTH: usize = 32;
+
 impl Config {
-    pub fn new() -> Result<Self, ConfigError> {
-        config::Config::builder()
-            .add_source(config::File::with_name("config/config"))
+    // Loads the config file and the environment, then validates the result
+    pub fn new() -> Result<Self, SettingsError> {
+        Self::with_secrets(&SecretValues::default())
+    }
+
+    // Like `new`, with secrets fetched from a secret provider taking
+    // precedence over the ones in the environment
+    pub fn with_secrets(provided: &SecretValues) -> Result<Self, SettingsError> {
+        Self::from_file(Path::new(CONFIG_FILE_PATH), provided)
+    }
+
+    // Like `with_secrets`, reading the config file at `path`
+    pub fn from_file(path: &Path, provided: &SecretValues) -> Result<Self, SettingsError> {
+        let mut secrets = EnvSecretProvider::default()
+            .read_all(&SECRETS)
+            .map_err(SettingsError::Secrets)?;
+        secrets.extend(provided.clone());
+        // A missing secret is left empty, validation reports it along with
+        // any other problem
+        let secret = |name: &str| secrets.get(name).filter(|value| !value.is_empty());
+
+        let config: Config = config::Config::builder()
+            .add_source(config::File::from(path))
             .add_source(config::Environment::default())
-            .set_override("auth.jwt.secret", get_jwt_secret())?
-            .set_override("auth.elevated_jwt.secret", get_elevated_jwt_secret())?
-            .set_override("email_client.auth_token", get_email_client_auth_token())?
-            .set_override("postgres.url", get_database_url())?
+            .set_override(
+                "auth.jwt.secret",
+                secret(JWT_SECRET_ENV_VAR).unwrap_or_default(),
+            )?
+            .set_override(
+                "auth.elevated_jwt.secret",
+                secret(JWT_ELEVATED_SECRET_ENV_VAR).unwrap_or_default(),
+            )?
+            .set_override_option(
+                "email_client.auth_token",
+                secret(POSTMARK_AUTH_TOKEN_ENV_VAR),
+            )?
+            .set_override_option("email_client.smtp.password", secret(SMTP_PASSWORD_ENV_VAR))?
+            .set_override_option(
+                "sms_client.auth_token",
+                secret(SMS_WEBHOOK_AUTH_TOKEN_ENV_VAR),
+            )?
+            .set_override_option("postgres.url", secret(DATABASE_URL_ENV_VAR))?
             .set_override_option("redis.host_name", get_redis_host_name())?
             .set_override_option("auth.allowed_origins", get_allowed_origins())?
             .build()?
-            .try_deserialize()
+            .try_deserialize()?;
+
+        config.validate()?;
+        Ok(config)
     }
-}
 
-fn get_jwt_secret() -> String {
-    dotenv().ok(); // Load environment variables
-    let secret = std::env::var(JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
+    // Checks what deserializing alone cannot, returning every problem found
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let mut problems = Vec::new();
+
+        for (name, env_var, jwt) in [
+            ("jwt", JWT_SECRET_ENV_VAR, &self.auth.jwt),
+            (
+                "elevated_jwt",
+                JWT_ELEVATED_SECRET_ENV_VAR,
+                &self.auth.elevated_jwt,
+            ),
+        ] {
+            let secret = jwt.secret.expose_secret();
+            if secret.is_empty() {
+                problems.push(format!("{env_var} must be set"));
+            } else if secret.len() < MIN_JWT_SECRET_LENGTH {
+                problems.push(format!(
+                    "{env_var} must be at least {MIN_JWT_SECRET_LENGTH} characters long"
+                ));
+            }
+            if jwt.cookie_name.is_empty() {
+                problems.push(format!("auth.{name}.cookie_name must not be empty"));
+            }
+            if jwt.time_to_live <= 0 {
+                problems.push(format!(
+                    "auth.{name}.time_to_live_in_seconds must be greater than 0"
+                ));
+            }
+        }
+        if !self.auth.jwt.secret.expose_secret().is_empty()
+            && self.auth.jwt.secret.expose_secret() == self.auth.elevated_jwt.secret.expose_secret()
+        {
+            problems.push(format!(
+                "{JWT_SECRET_ENV_VAR} and {JWT_ELEVATED_SECRET_ENV_VAR} must differ"
+            ));
+        }
+        if self.auth.elevated_jwt.time_to_live >= self.auth.jwt.time_to_live {
+            problems.push(
+                "auth.elevated_jwt.time_to_live_in_seconds must be shorter than auth.jwt.time_to_live_in_seconds"
+                    .to_owned(),
+            );
+        }
+        if self.auth.allowed_origins.is_empty() {
+            problems.push("auth.allowed_origins must contain at least one origin".to_owned());
+        }
+        if self.auth.jwt.cookie_name == self.auth.elevated_jwt.cookie_name {
+            problems.push(
+                "auth.jwt.cookie_name and auth.elevated_jwt.cookie_name must differ".to_owned(),
+            );
+        }
+        if self.email_outbox.batch_size == 0 {
+            problems.push("email_outbox.batch_size must be greater than 0".to_owned());
+        }
+        if self.email_outbox.max_attempts == 0 {
+            problems.push("email_outbox.max_attempts must be greater than 0".to_owned());
+        }
+        if self.email_outbox.initial_backoff_in_secs > self.email_outbox.max_backoff_in_secs {
+            problems.push(
+                "email_outbox.initial_backoff_in_secs must not exceed email_outbox.max_backoff_in_secs"
+                    .to_owned(),
+            );
+        }
+        if self.token_store.cleanup_interval_in_secs.is_zero() {
+            problems.push("token_store.cleanup_interval_in_secs must be greater than 0".to_owned());
+        }
+        if self.account_deletion.grace_period_in_days == 0 {
+            problems
+                .push("account_deletion.grace_period_in_days must be greater than 0".to_owned());
+        }
+        if self.account_deletion.purge_interval_in_secs.is_zero() {
+            problems
+                .push("account_deletion.purge_interval_in_secs must be greater than 0".to_owned());
+        }
+        if reqwest::Url::parse(&self.account_deletion.restore_url).is_err() {
+            problems.push("account_deletion.restore_url must be a valid URL".to_owned());
+        }
+        match self.secrets.provider {
+            SecretProviderKind::File if self.secrets.directory.is_none() => problems
+                .push("secrets.directory must be set when secrets.provider is file".to_owned()),
+            SecretProviderKind::Vault if self.secrets.vault.is_none() => {
+                problems.push("secrets.vault must be set when secrets.provider is vault".to_owned())
+            }
+            _ => {}
+        }
+        if self.secrets.refresh_interval_in_secs.is_zero() {
+            problems.push("secrets.refresh_interval_in_secs must be greater than 0".to_owned());
+        }
+
+        let uses_postgres = self.database.backend == DatabaseBackend::Postgres;
+        if uses_postgres && self.postgres.url.is_none() {
+            problems.push(format!(
+                "{DATABASE_URL_ENV_VAR} must be set when database.backend is postgres"
+            ));
+        }
+        match self.token_store.backend {
+            TokenStoreBackend::Redis if self.redis.is_none() => problems.push(format!(
+                "redis.host_name or {REDIS_HOST_NAME_ENV_VAR} must be set when token_store.backend is redis"
+            )),
+            TokenStoreBackend::Postgres if !uses_postgres => problems.push(
+                "token_store.backend postgres requires database.backend postgres".to_owned(),
+            ),
+            _ => {}
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(SettingsError::Invalid(problems))
+        }
+    }
+
+    // Lists the settings that differ from `other` as `path: old -> new`.
+    // Secrets are redacted on both sides, so changes to them are not listed.
+    pub fn diff(&self, other: &Config) -> Vec<String> {
+        let mut old = Vec::new();
+        let mut new = Vec::new();
+        flatten(
+            "",
+            &serde_json::to_value(self).unwrap_or_default(),
+            &mut old,
+        );
+        flatten(
+            "",
+            &serde_json::to_value(other).unwrap_or_default(),
+            &mut new,
+        );
+
+        let old: BTreeMap<_, _> = old.into_iter().collect();
+        let new: BTreeMap<_, _> = new.into_iter().collect();
+        let null = Value::Null;
+
+        old.keys()
+            .chain(new.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|path| {
+                let before = old.get(path).unwrap_or(&null);
+                let after = new.get(path).unwrap_or(&null);
+                (before != after).then(|| format!("{path}: {before} -> {after}"))
+            })
+            .collect()
     }
-    secret
 }
 
-fn get_elevated_jwt_secret() -> String {
-    dotenv().ok();
-    let secret =
-        std::env::var(JWT_ELEVATED_SECRET_ENV_VAR).expect("JWT_ELEVATED_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_ELEVATED_SECRET must not be empty.");
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("Failed to load config: {0}")]
+    Load(#[from] ConfigError),
+    #[error("Invalid config:\n  {}", .0.join("\n  "))]
+    Invalid(Vec<String>),
+    #[error("Failed to load secrets: {0:#}")]
+    Secrets(color_eyre::Report),
+}
+
+fn flatten(path: &str, value: &Value, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                flatten(&path, value, out);
+            }
+        }
+        _ => out.push((path.to_owned(), value.clone())),
     }
-    secret
 }
 
-fn get_database_url() -> String {
-    dotenv().ok();
-    let url = std::env::var(DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set");
-    if url.is_empty() {
-        panic!("DATABASE_URL must not be empty.");
+const REDACTED: &str = "[redacted]";
+
+fn redacted<S: Serializer>(_: &Secret<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+fn redacted_option<S: Serializer>(
+    secret: &Option<Secret<String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(_) => serializer.serialize_str(REDACTED),
+        None => serializer.serialize_none(),
     }
-    url
 }
 
-fn get_redis_host_name() -> Option<String> {
-    dotenv().ok();
-    std::env::var(REDIS_HOST_NAME_ENV_VAR).ok()
+fn millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
 }
 
-fn get_email_client_auth_token() -> String {
+fn get_redis_host_name() -> Option<String> {
     dotenv().ok();
-    let token =
-        std::env::var(POSTMARK_AUTH_TOKEN_ENV_VAR).expect("POSTMARK_AUTH_TOKEN must be set.");
-    if token.is_empty() {
-        panic!("POSTMARK_AUTH_TOKEN must not be empty.");
-    }
-    token
+    std::env::var(REDIS_HOST_NAME_ENV_VAR).ok()
 }
 
 fn get_allowed_origins() -> Option<Vec<String>> {
     std::env::var(AUTH_SERVICE_ALLOWED_ORIGINS_ENV_VAR)
         .ok()
-        .and_then(|s| {
-            Some(
-                s.split(',')
-                    .map(|origin| origin.trim().to_owned())
-                    .collect(),
-            )
+        .map(|s| {
+            s.split(',')
+                .map(|origin| origin.trim().to_owned())
+                .collect()
         })
 }
 
+// A handle to the current config, cloned into the application state. A new
+// config stored through any clone is seen by all of them, so handlers should
+// load it once per request to work against a consistent snapshot.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    config: Arc<ArcSwap<Config>>,
+    // The secrets the config was built with when they came from a secret
+    // provider, reused when the config file is reloaded
+    secrets: Arc<ArcSwap<SecretValues>>,
+}
+
+impl Settings {
+    pub fn new(config: Config) -> Self {
+        Self::with_secrets(config, SecretValues::default())
+    }
+
+    pub fn with_secrets(config: Config, secrets: SecretValues) -> Self {
+        Settings {
+            config: Arc::new(ArcSwap::from_pointee(config)),
+            secrets: Arc::new(ArcSwap::from_pointee(secrets)),
+        }
+    }
+
+    pub fn load(&self) -> Guard<Arc<Config>> {
+        self.config.load()
+    }
+
+    pub fn store(&self, config: Config) {
+        self.config.store(Arc::new(config));
+    }
+
+    // Rebuilds the config from its sources and swaps it in, returning what
+    // changed. The current config is kept when the new one fails to load or
+    // validate.
+    pub fn reload(&self) -> Result<Vec<String>, SettingsError> {
+        self.reload_from(Path::new(CONFIG_FILE_PATH))
+    }
+
+    // Like `reload`, reading the config file at `path`
+    pub fn reload_from(&self, path: &Path) -> Result<Vec<String>, SettingsError> {
+        self.replace(Config::from_file(path, &self.secrets.load())?)
+    }
+
+    // Rebuilds the config with freshly fetched secrets when any of them
+    // changed, returning the names of the changed secrets
+    pub fn rotate_secrets(&self, secrets: SecretValues) -> Result<Vec<String>, SettingsError> {
+        let rotated = self.secrets.load().changed(&secrets);
+        if rotated.is_empty() {
+            return Ok(rotated);
+        }
+
+        self.replace(Config::with_secrets(&secrets)?)?;
+        self.secrets.store(Arc::new(secrets));
+        Ok(rotated)
+    }
+
+    // Validates `config` and swaps it in, returning what changed. The current
+    // config is kept when it is invalid.
+    pub fn replace(&self, config: Config) -> Result<Vec<String>, SettingsError> {
+        config.validate()?;
+
+        let changes = self.load().diff(&config);
+        self.store(config);
+        Ok(changes)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AuthServiceSetting;
 
 impl AuthServiceSetting {
+    // Makes `settings` the process wide settings. Has to be called before the
+    // config is first used, returns the settings back when it was too late.
+    pub fn init(settings: Settings) -> Result<(), Settings> {
+        CONFIG.set(settings)
+    }
+
     pub fn load() -> Guard<Arc<Config>> {
-        CONFIG.load()
+        Self::settings().load()
     }
 
     pub fn get_config() -> Guard<Arc<Config>> {
-        CONFIG.load()
+        Self::settings().load()
+    }
+
+    // The process wide settings, used by the application state unless it is
+    // given its own
+    pub fn handle() -> Settings {
+        Self::settings().clone()
+    }
+
+    fn settings() -> &'static Settings {
+        CONFIG.get_or_init(|| Settings::new(Config::new().unwrap_or_else(|e| panic!("{e}"))))
     }
 }
 
@@ -210,8 +954,210 @@ mod tests {
         let config = AuthServiceSetting::load();
         assert!(!config.auth.jwt.secret.expose_secret().is_empty());
         assert!(!config.auth.elevated_jwt.secret.expose_secret().is_empty());
-        assert!(!config.postgres.url.expose_secret().is_empty());
-        assert!(!config.email_client.auth_token.expose_secret().is_empty());
+        assert!(
+            !config
+                .postgres
+                .url
+                .as_ref()
+                .unwrap()
+                .expose_secret()
+                .is_empty()
+        );
+        assert!(
+            !config
+                .email_client
+                .auth_token
+                .as_ref()
+                .unwrap()
+                .expose_secret()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_signup_domain_policy_is_loaded() {
+        let config = AuthServiceSetting::load();
+        assert!(matches!(
+            config.signup.domain_policy,
+            EmailDomainPolicy::Denylist {
+                block_disposable: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_settings_clones_see_a_stored_config() {
+        let settings = Settings::new(Config::new().unwrap());
+        let clone = settings.clone();
+
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.cookie_name = "reloaded".to_owned();
+        settings.store(config);
+
+        assert_eq!(clone.load().auth.jwt.cookie_name, "reloaded");
+        assert_ne!(AuthServiceSetting::load().auth.jwt.cookie_name, "reloaded");
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let mut config = Config::new().unwrap();
+        assert!(config.validate().is_ok());
+
+        config.auth.jwt.time_to_live = 0;
+        config.auth.elevated_jwt.cookie_name = config.auth.jwt.cookie_name.clone();
+        config.email_outbox.batch_size = 0;
+
+        let Err(SettingsError::Invalid(problems)) = config.validate() else {
+            panic!("Expected the config to be invalid");
+        };
+        assert_eq!(
+            problems,
+            vec![
+                "auth.jwt.time_to_live_in_seconds must be greater than 0",
+                "auth.elevated_jwt.time_to_live_in_seconds must be shorter than auth.jwt.time_to_live_in_seconds",
+                "auth.jwt.cookie_name and auth.elevated_jwt.cookie_name must differ",
+                "email_outbox.batch_size must be greater than 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_checks_the_jwt_secrets() {
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.secret = Secret::new(String::new());
+        config.auth.elevated_jwt.secret = Secret::new("too short".to_owned());
+
+        let Err(SettingsError::Invalid(problems)) = config.validate() else {
+            panic!("Expected the config to be invalid");
+        };
+        assert_eq!(
+            problems,
+            vec![
+                "JWT_SECRET must be set",
+                "JWT_ELEVATED_SECRET must be at least 32 characters long",
+            ]
+        );
+
+        let secret = "a".repeat(MIN_JWT_SECRET_LENGTH);
+        config.auth.jwt.secret = Secret::new(secret.clone());
+        config.auth.elevated_jwt.secret = Secret::new(secret);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_checks_account_deletion() {
+        let mut config = Config::new().unwrap();
+        config.account_deletion.grace_period_in_days = 0;
+        config.account_deletion.restore_url = "restore-account.html".to_owned();
+
+        let Err(SettingsError::Invalid(problems)) = config.validate() else {
+            panic!("Expected the config to be invalid");
+        };
+        assert_eq!(
+            problems,
+            vec![
+                "account_deletion.grace_period_in_days must be greater than 0",
+                "account_deletion.restore_url must be a valid URL",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_lists_changed_settings_without_secrets() {
+        let old = Config::new().unwrap();
+        let mut new = Config::new().unwrap();
+        new.auth.jwt.time_to_live = old.auth.jwt.time_to_live + 60;
+        new.auth.jwt.secret = Secret::new("a different secret".to_owned());
+
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            vec![format!(
+                "auth.jwt.time_to_live_in_seconds: {} -> {}",
+                old.auth.jwt.time_to_live, new.auth.jwt.time_to_live
+            )]
+        );
+        assert!(!changes.concat().contains("a different secret"));
+    }
+
+    #[test]
+    fn test_replace_keeps_the_current_config_when_invalid() {
+        let settings = Settings::new(Config::new().unwrap());
+        let cookie_name = settings.load().auth.jwt.cookie_name.clone();
+
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.cookie_name = String::new();
+        assert!(settings.replace(config).is_err());
+        assert_eq!(settings.load().auth.jwt.cookie_name, cookie_name);
+
+        let mut config = Config::new().unwrap();
+        config.auth.jwt.cookie_name = "reloaded".to_owned();
+        let changes = settings.replace(config).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(settings.load().auth.jwt.cookie_name, "reloaded");
+    }
+
+    #[test]
+    fn test_rotate_secrets_rebuilds_the_config_when_a_secret_changed() {
+        let settings = Settings::new(Config::new().unwrap());
+        let rotated_secret = "r".repeat(MIN_JWT_SECRET_LENGTH);
+        let mut secrets = SecretValues::default();
+        secrets.insert(JWT_SECRET_ENV_VAR, Secret::new(rotated_secret.clone()));
+
+        let rotated = settings.rotate_secrets(secrets.clone()).unwrap();
+        assert_eq!(rotated, vec![JWT_SECRET_ENV_VAR]);
+        assert_eq!(
+            settings.load().auth.jwt.secret.expose_secret(),
+            &rotated_secret
+        );
+        assert!(settings.rotate_secrets(secrets).unwrap().is_empty());
+
+        // Reloading the config file keeps the rotated secret
+        settings.reload().unwrap();
+        assert_eq!(
+            settings.load().auth.jwt.secret.expose_secret(),
+            &rotated_secret
+        );
+
+        let mut secrets = SecretValues::default();
+        secrets.insert(JWT_SECRET_ENV_VAR, Secret::new("too short".to_owned()));
+        assert!(settings.rotate_secrets(secrets).is_err());
+        assert_eq!(
+            settings.load().auth.jwt.secret.expose_secret(),
+            &rotated_secret
+        );
+    }
+
+    #[test]
+    fn test_email_client_provider_requires_its_settings() {
+        let postmark = serde_json::json!({
+            "base_url": "https://api.postmarkapp.com/",
+            "sender": "sender@example.com",
+            "timeout_in_millis": 1000
+        });
+        assert!(serde_json::from_value::<EmailClientConfig>(postmark).is_err());
+
+        let smtp = serde_json::json!({
+            "provider": "smtp",
+            "base_url": "",
+            "sender": "sender@example.com",
+            "timeout_in_millis": 1000
+        });
+        assert!(serde_json::from_value::<EmailClientConfig>(smtp).is_err());
+
+        let smtp = serde_json::json!({
+            "provider": "smtp",
+            "base_url": "",
+            "sender": "sender@example.com",
+            "timeout_in_millis": 1000,
+            "smtp": { "host": "mail.example.com", "tls": "implicit" }
+        });
+        let config = serde_json::from_value::<EmailClientConfig>(smtp).unwrap();
+        assert_eq!(config.provider, EmailProvider::Smtp);
+        let smtp = config.smtp.unwrap();
+        assert_eq!(smtp.tls, SmtpTls::Implicit);
+        assert_eq!(smtp.port, None);
     }
 }
 
@@ -229,10 +1175,11 @@ impl Serialize for AllowedOrigins {
     where
         S: serde::Serializer,
     {
+        // Sorted so that serializing the same origins always gives the same output
         let headers = self
             .iter()
-            .filter_map(|header_value| header_value.to_str().and_then(|h| Ok(h.to_owned())).ok())
-            .collect::<Vec<_>>();
+            .filter_map(|header_value| header_value.to_str().map(|h| h.to_owned()).ok())
+            .collect::<BTreeSet<_>>();
 
         headers.serialize(serializer)
     }
@@ -0,0 +1,60 @@
+use crate::domain::{
+    auth_api_error::AuthApiError,
+    data_stores::{AuditEvent, AuditEventType, AuditLog, AuditOutcome},
+};
+
+use super::extractors::RequestMetadata;
+
+pub fn request_event(event_type: AuditEventType, metadata: &RequestMetadata) -> AuditEvent {
+    AuditEvent::new(event_type)
+        .ip(metadata.ip.clone())
+        .user_agent(metadata.user_agent.clone())
+}
+
+// Records the outcome of a request. A failure to write the audit log is logged
+// rather than failing a request that has already taken effect.
+pub async fn record_outcome<A, T>(
+    audit_log: &A,
+    event: AuditEvent,
+    result: &Result<T, AuthApiError>,
+) where
+    A: AuditLog + ?Sized,
+{
+    let event = match result {
+        Ok(_) => event.outcome(AuditOutcome::Success),
+        Err(e) => event.outcome(AuditOutcome::Failure).details(e.to_string()),
+    };
+
+    if let Err(e) = audit_log.record(event).await {
+        tracing::error!(error = ?e, "Failed to record audit event");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::data_stores::VecAuditLog;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_outcome_marks_failures() {
+        let audit_log = VecAuditLog::new();
+        let metadata = RequestMetadata {
+            ip: Some("10.0.0.1".to_owned()),
+            user_agent: None,
+        };
+
+        let event = request_event(AuditEventType::Login, &metadata).actor("test@example.com");
+        let result: Result<(), AuthApiError> = Err(AuthApiError::MissingToken);
+        record_outcome(&audit_log, event, &result).await;
+
+        let records = audit_log
+            .recent_events("test@example.com", 1)
+            .await
+            .unwrap();
+        let event = &records[0].event;
+        assert_eq!(event.outcome, AuditOutcome::Failure);
+        assert_eq!(event.ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(event.details.as_deref(), Some("Missing token"));
+    }
+}
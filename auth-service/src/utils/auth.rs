@@ -1,20 +1,19 @@
-use std::sync::Arc;
-
 use axum_extra::extract::{
     CookieJar,
     cookie::{Cookie, SameSite},
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::eyre;
-use jsonwebtoken::{DecodingKey, EncodingKey, Validation, decode, encode};
+use jsonwebtoken::{
+    DecodingKey, EncodingKey, Validation, decode, encode, errors::ErrorKind as JwtErrorKind,
+};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize, ser::SerializeStruct};
 use thiserror::Error;
 
 use crate::{
-    domain::{data_stores::BannedTokenStore, email::Email},
-    settings::{AuthServiceSetting, Config},
-    utils::constants::{JWT_COOKIE_NAME, JWT_ELEVATED_COOKIE_NAME},
+    domain::{clock::Clock, data_stores::BannedTokenStore, email::Email, role::Role},
+    settings::Config,
 };
 
 #[derive(Debug, Error)]
@@ -41,28 +40,34 @@ pub fn extract_token<'a>(jar: &'a CookieJar, cookie_name: &str) -> Result<&'a st
 // Create cookie with a new JWT auth token
 pub fn generate_auth_cookie(
     email: &Email,
-    config: &Arc<Config>,
+    roles: &[Role],
+    generation: u64,
+    config: &Config,
+    clock: &dyn Clock,
 ) -> Result<Cookie<'static>, TokenAuthError> {
     let token_ttl = config.auth.jwt.time_to_live;
     let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
 
-    let token = generate_auth_token(email, token_ttl, jwt_secret)?;
-    Ok(create_auth_cookie(token, *JWT_COOKIE_NAME))
+    let token = generate_auth_token(email, roles, generation, token_ttl, jwt_secret, clock.now())?;
+    Ok(create_auth_cookie(token, &config.auth.jwt.cookie_name).into_owned())
 }
 
 pub fn generate_elevated_auth_cookie(
     email: &Email,
-    config: &Arc<Config>,
+    roles: &[Role],
+    generation: u64,
+    config: &Config,
+    clock: &dyn Clock,
 ) -> Result<Cookie<'static>, TokenAuthError> {
     let token_ttl = config.auth.elevated_jwt.time_to_live;
     let jwt_secret = config.auth.elevated_jwt.secret.expose_secret().as_bytes();
 
-    let token = generate_auth_token(email, token_ttl, jwt_secret)?;
-    Ok(create_auth_cookie(token, *JWT_ELEVATED_COOKIE_NAME))
+    let token = generate_auth_token(email, roles, generation, token_ttl, jwt_secret, clock.now())?;
+    Ok(create_auth_cookie(token, &config.auth.elevated_jwt.cookie_name).into_owned())
 }
 
-pub fn create_removal_cookie(cookie_name: &str) -> Cookie<'_> {
-    let mut cookie = create_auth_cookie(String::new(), cookie_name);
+pub fn create_removal_cookie(cookie_name: &str) -> Cookie<'static> {
+    let mut cookie = create_auth_cookie(String::new(), cookie_name).into_owned();
     cookie.make_removal();
     cookie
 }
@@ -80,15 +85,18 @@ pub fn create_auth_cookie(token: String, cookie_name: &str) -> Cookie<'_> {
 // Create JWT auth token
 fn generate_auth_token(
     email: &Email,
+    roles: &[Role],
+    generation: u64,
     token_ttl_seconds: i64,
     secret: &[u8],
+    issued_at: DateTime<Utc>,
 ) -> Result<String, TokenAuthError> {
     let delta = chrono::Duration::try_seconds(token_ttl_seconds).ok_or(
         TokenAuthError::UnexpectedError(eyre!("Failed to create auth token duration")),
     )?;
 
     // Create JWT expiration time
-    let exp = Utc::now()
+    let exp = issued_at
         .checked_add_signed(delta)
         .ok_or(TokenAuthError::UnexpectedError(eyre!(
             "Duration out of range"
@@ -102,7 +110,12 @@ fn generate_auth_token(
 
     let sub = Clone::clone(email.as_ref());
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub,
+        exp,
+        roles: roles.to_vec(),
+        generation,
+    };
 
     create_token(&claims, secret)
 }
@@ -111,38 +124,47 @@ fn generate_auth_token(
 pub async fn validate_auth_token(
     token: &str,
     banned_token_store: &dyn BannedTokenStore,
+    config: &Config,
+    clock: &dyn Clock,
 ) -> Result<Claims, TokenAuthError> {
-    let config = AuthServiceSetting::load();
     let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
-    validate_token(token, banned_token_store, jwt_secret).await
+    validate_token(token, banned_token_store, jwt_secret, clock).await
 }
 
 pub async fn validate_elevated_auth_token(
     token: &str,
     banned_token_store: &dyn BannedTokenStore,
+    config: &Config,
+    clock: &dyn Clock,
 ) -> Result<Claims, TokenAuthError> {
-    let config = AuthServiceSetting::load();
     let jwt_secret = config.auth.elevated_jwt.secret.expose_secret().as_bytes();
-    validate_token(token, banned_token_store, jwt_secret).await
+    validate_token(token, banned_token_store, jwt_secret, clock).await
 }
 
 async fn validate_token(
     token: &str,
     banned_token_store: &dyn BannedTokenStore,
     secret: &[u8],
+    clock: &dyn Clock,
 ) -> Result<Claims, TokenAuthError> {
-    let claims = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
-    .map_err(TokenAuthError::TokenError)?;
-
-    let token = create_token(&claims, secret)?;
+    // jsonwebtoken checks exp against the system clock, so expiry is checked
+    // here against the injected clock instead
+    let mut validation = Validation::default();
+    validation.validate_exp = false;
+
+    let claims = decode::<Claims>(token, &DecodingKey::from_secret(secret), &validation)
+        .map(|data| data.claims)
+        .map_err(TokenAuthError::TokenError)?;
+
+    let exp = i64::try_from(claims.exp).map_err(|_| TokenAuthError::InvalidToken)?;
+    if exp.saturating_add(validation.leeway as i64) < clock.now().timestamp() {
+        return Err(TokenAuthError::TokenError(
+            JwtErrorKind::ExpiredSignature.into(),
+        ));
+    }
 
     let is_banned = banned_token_store
-        .contains_token(&token)
+        .contains_token(token)
         .await
         .map_err(|e| TokenAuthError::UnexpectedError(eyre!(e)))?;
 
@@ -150,6 +172,16 @@ async fn validate_token(
         return Err(TokenAuthError::TokenIsBanned);
     }
 
+    let email = Email::try_from(claims.sub.clone()).map_err(|_| TokenAuthError::InvalidToken)?;
+    let generation = banned_token_store
+        .token_generation(&email)
+        .await
+        .map_err(|e| TokenAuthError::UnexpectedError(eyre!(e)))?;
+
+    if claims.generation < generation {
+        return Err(TokenAuthError::TokenIsBanned);
+    }
+
     Ok(claims)
 }
 
@@ -167,6 +199,17 @@ fn create_token(claims: &Claims, secret: &[u8]) -> Result<String, TokenAuthError
 pub struct Claims {
     pub sub: Secret<String>,
     pub exp: usize,
+    // Tokens issued before roles were introduced carry no roles claim
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    #[serde(default)]
+    pub generation: u64,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r.as_str() == role)
+    }
 }
 
 impl Serialize for Claims {
@@ -174,9 +217,11 @@ impl Serialize for Claims {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("Claims", 2)?;
+        let mut state = serializer.serialize_struct("Claims", 4)?;
         state.serialize_field("sub", &self.sub.expose_secret())?;
         state.serialize_field("exp", &self.exp)?;
+        state.serialize_field("roles", &self.roles)?;
+        state.serialize_field("generation", &self.generation)?;
         state.end()
     }
 }
@@ -185,7 +230,11 @@ impl Serialize for Claims {
 mod tests {
     use secrecy::{ExposeSecret, Secret};
 
-    use crate::services::data_stores::hashset_banned_token_store::HashSetBannedTokenStore;
+    use crate::{
+        domain::clock::{MockClock, SystemClock},
+        services::data_stores::hashset_banned_token_store::HashSetBannedTokenStore,
+        settings::AuthServiceSetting,
+    };
 
     use super::*;
 
@@ -193,7 +242,8 @@ mod tests {
     async fn test_generate_auth_cookie() {
         let config = AuthServiceSetting::load();
         let email = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email, &config).unwrap();
+        let cookie =
+            generate_auth_cookie(&email, &[Role::user()], 0, &config, &SystemClock).unwrap();
         assert_eq!(cookie.name(), config.auth.jwt.cookie_name);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -220,7 +270,15 @@ mod tests {
         let token_ttl = config.auth.jwt.time_to_live;
         let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
         let email = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email, token_ttl, jwt_secret).unwrap();
+        let result = generate_auth_token(
+            &email,
+            &[Role::user()],
+            0,
+            token_ttl,
+            jwt_secret,
+            Utc::now(),
+        )
+        .unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
@@ -230,9 +288,17 @@ mod tests {
         let token_ttl = config.auth.jwt.time_to_live;
         let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
         let email = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let banned_token_store = HashSetBannedTokenStore::default();
-        let token = generate_auth_token(&email, token_ttl, jwt_secret).unwrap();
-        let result = validate_auth_token(&token, &banned_token_store)
+        let banned_token_store = HashSetBannedTokenStore::new(AuthServiceSetting::handle());
+        let token = generate_auth_token(
+            &email,
+            &[Role::user()],
+            0,
+            token_ttl,
+            jwt_secret,
+            Utc::now(),
+        )
+        .unwrap();
+        let result = validate_auth_token(&token, &banned_token_store, &config, &SystemClock)
             .await
             .unwrap();
         assert_eq!(result.sub.expose_secret(), "test@example.com");
@@ -245,11 +311,54 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_contains_roles() {
+        let config = AuthServiceSetting::load();
+        let token_ttl = config.auth.jwt.time_to_live;
+        let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
+        let email = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
+        let banned_token_store = HashSetBannedTokenStore::new(AuthServiceSetting::handle());
+        let token = generate_auth_token(
+            &email,
+            &[Role::admin(), Role::user()],
+            0,
+            token_ttl,
+            jwt_secret,
+            Utc::now(),
+        )
+        .unwrap();
+        let claims = validate_auth_token(&token, &banned_token_store, &config, &SystemClock)
+            .await
+            .unwrap();
+        assert!(claims.has_role(Role::ADMIN));
+        assert!(claims.has_role(Role::USER));
+        assert!(!claims.has_role("support"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_without_roles_claim() {
+        let config = AuthServiceSetting::load();
+        let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
+        let exp = Utc::now().timestamp() as usize + 60;
+        let token = encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "test@example.com", "exp": exp }),
+            &EncodingKey::from_secret(jwt_secret),
+        )
+        .unwrap();
+        let banned_token_store = HashSetBannedTokenStore::new(AuthServiceSetting::handle());
+        let claims = validate_auth_token(&token, &banned_token_store, &config, &SystemClock)
+            .await
+            .unwrap();
+        assert!(claims.roles.is_empty());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
+        let config = AuthServiceSetting::load();
         let token = "invalid_token".to_owned();
-        let banned_token_store = HashSetBannedTokenStore::default();
-        let result = validate_auth_token(&token, &banned_token_store).await;
+        let banned_token_store = HashSetBannedTokenStore::new(AuthServiceSetting::handle());
+        let result = validate_auth_token(&token, &banned_token_store, &config, &SystemClock).await;
         assert!(result.is_err());
     }
 
@@ -259,11 +368,91 @@ mod tests {
         let token_ttl = config.auth.jwt.time_to_live;
         let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
         let email = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
-        let mut banned_token_store = HashSetBannedTokenStore::default();
-        let token = generate_auth_token(&email, token_ttl, jwt_secret).unwrap();
+        let banned_token_store = HashSetBannedTokenStore::new(AuthServiceSetting::handle());
+        let token = generate_auth_token(
+            &email,
+            &[Role::user()],
+            0,
+            token_ttl,
+            jwt_secret,
+            Utc::now(),
+        )
+        .unwrap();
 
         banned_token_store.ban_token(token.clone()).await.unwrap();
-        let result = validate_auth_token(&token, &banned_token_store).await;
+        let result = validate_auth_token(&token, &banned_token_store, &config, &SystemClock).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_revoke_all_tokens() {
+        let config = AuthServiceSetting::load();
+        let token_ttl = config.auth.jwt.time_to_live;
+        let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
+        let email = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
+        let banned_token_store = HashSetBannedTokenStore::new(AuthServiceSetting::handle());
+        let token = generate_auth_token(
+            &email,
+            &[Role::user()],
+            0,
+            token_ttl,
+            jwt_secret,
+            Utc::now(),
+        )
+        .unwrap();
+
+        banned_token_store.revoke_all_tokens(&email).await.unwrap();
+        let result = validate_auth_token(&token, &banned_token_store, &config, &SystemClock).await;
+        assert!(matches!(result, Err(TokenAuthError::TokenIsBanned)));
+
+        let generation = banned_token_store.token_generation(&email).await.unwrap();
+        let token = generate_auth_token(
+            &email,
+            &[Role::user()],
+            generation,
+            token_ttl,
+            jwt_secret,
+            Utc::now(),
+        )
+        .unwrap();
+        assert!(
+            validate_auth_token(&token, &banned_token_store, &config, &SystemClock)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_expires_with_the_clock() {
+        let config = AuthServiceSetting::load();
+        let token_ttl = config.auth.jwt.time_to_live;
+        let jwt_secret = config.auth.jwt.secret.expose_secret().as_bytes();
+        let email = Email::try_from(Secret::from("test@example.com".to_owned())).unwrap();
+        let banned_token_store = HashSetBannedTokenStore::new(AuthServiceSetting::handle());
+        let clock = MockClock::default();
+        let token = generate_auth_token(
+            &email,
+            &[Role::user()],
+            0,
+            token_ttl,
+            jwt_secret,
+            clock.now(),
+        )
+        .unwrap();
+
+        clock.advance(std::time::Duration::from_secs(token_ttl as u64));
+        assert!(
+            validate_auth_token(&token, &banned_token_store, &config, &clock)
+                .await
+                .is_ok()
+        );
+
+        // Past the expiry and the leeway allowed by jsonwebtoken
+        clock.advance(std::time::Duration::from_secs(61));
+        let result = validate_auth_token(&token, &banned_token_store, &config, &clock).await;
+        assert!(matches!(
+            result,
+            Err(TokenAuthError::TokenError(e)) if *e.kind() == JwtErrorKind::ExpiredSignature
+        ));
+    }
 }
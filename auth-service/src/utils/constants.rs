@@ -51,10 +51,6 @@
 //     Secret::new(token)
 // }
 
-use std::sync::LazyLock;
-
-use crate::settings::AuthServiceSetting;
-
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const JWT_ELEVATED_SECRET_ENV_VAR: &str = "JWT_ELEVATED_SECRET";
@@ -62,24 +58,31 @@ pub mod env {
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const SMS_WEBHOOK_AUTH_TOKEN_ENV_VAR: &str = "SMS_WEBHOOK_AUTH_TOKEN";
+    pub const VAULT_TOKEN_ENV_VAR: &str = "VAULT_TOKEN";
+
+    // Loaded through the configured secret provider. Each can also be given as
+    // a file path in the same variable with a _FILE suffix.
+    pub const SECRETS: [&str; 6] = [
+        JWT_SECRET_ENV_VAR,
+        JWT_ELEVATED_SECRET_ENV_VAR,
+        DATABASE_URL_ENV_VAR,
+        POSTMARK_AUTH_TOKEN_ENV_VAR,
+        SMTP_PASSWORD_ENV_VAR,
+        SMS_WEBHOOK_AUTH_TOKEN_ENV_VAR,
+    ];
 }
 
-pub const JWT_COOKIE_NAME: LazyLock<&'static str> = LazyLock::new(|| {
-    let cookie_name = AuthServiceSetting::load().auth.jwt.cookie_name.clone();
-    Box::leak(cookie_name.into_boxed_str())
-});
-pub static JWT_ELEVATED_COOKIE_NAME: LazyLock<&'static str> = LazyLock::new(|| {
-    let cookie_name = AuthServiceSetting::load()
-        .auth
-        .elevated_jwt
-        .cookie_name
-        .clone();
-    Box::leak(cookie_name.into_boxed_str())
-});
 // pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
 
 pub mod prod {
+    use std::time::Duration;
+
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+    // How often the config file is checked for changes
+    pub const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+    pub const SECRET_PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
     pub mod email_client {
         use std::time::Duration;
 
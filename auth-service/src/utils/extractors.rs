@@ -0,0 +1,209 @@
+use std::{convert::Infallible, marker::PhantomData, net::SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{HeaderMap, header::USER_AGENT, request::Parts},
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+
+use crate::{
+    auth_service_state::{SharedBannedTokenStore, SharedClock, SharedUserStore},
+    domain::{auth_api_error::AuthApiError, email::Email, role::Role},
+    settings::Settings,
+    utils::auth::{self, Claims},
+};
+
+pub trait RequiredRole: Send + Sync + 'static {
+    const ROLE: &'static str;
+}
+
+pub struct Admin;
+
+impl RequiredRole for Admin {
+    const ROLE: &'static str = Role::ADMIN;
+}
+
+// Extracts the claims of the logged in user, rejecting the request unless they
+// currently hold the role `R`. The roles claim is only as fresh as the token, so
+// the user's roles are read from the store to make revocations take effect
+// immediately. Usable from any router whose state can provide the stores, the
+// clock and the settings.
+pub struct RequireRole<R: RequiredRole> {
+    pub claims: Claims,
+    _role: PhantomData<R>,
+}
+
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    R: RequiredRole,
+    SharedBannedTokenStore: FromRef<S>,
+    SharedClock: FromRef<S>,
+    SharedUserStore: FromRef<S>,
+    Settings: FromRef<S>,
+{
+    type Rejection = RoleRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = authenticate(parts, state).await?;
+
+        // From here on the caller is known, so a refusal can be pinned on them
+        let refused = |error: AuthApiError| RoleRejection {
+            subject: Some(claims.sub.expose_secret().to_owned()),
+            error,
+        };
+        let email =
+            Email::try_from(claims.sub.clone()).map_err(|_| refused(AuthApiError::Forbidden))?;
+        let roles = SharedUserStore::from_ref(state)
+            .get_roles(&email)
+            .await
+            .map_err(|e| refused(e.into()))?;
+        if !roles.iter().any(|role| role.as_str() == R::ROLE) {
+            return Err(refused(AuthApiError::Forbidden));
+        }
+
+        Ok(RequireRole {
+            claims,
+            _role: PhantomData,
+        })
+    }
+}
+
+async fn authenticate<S>(parts: &Parts, state: &S) -> Result<Claims, AuthApiError>
+where
+    SharedBannedTokenStore: FromRef<S>,
+    SharedClock: FromRef<S>,
+    Settings: FromRef<S>,
+{
+    let jar = CookieJar::from_headers(&parts.headers);
+    let config = Settings::from_ref(state).load();
+    let token = auth::extract_token(&jar, &config.auth.jwt.cookie_name)?;
+
+    let banned_token_store = SharedBannedTokenStore::from_ref(state);
+    let clock = SharedClock::from_ref(state);
+    let claims = auth::validate_auth_token(token, &*banned_token_store, &config, &*clock).await?;
+
+    Ok(claims)
+}
+
+// Why a `RequireRole` was refused. Names the user when they are logged in but
+// lack the role, so handlers can audit the attempt against them.
+#[derive(Debug)]
+pub struct RoleRejection {
+    pub subject: Option<String>,
+    pub error: AuthApiError,
+}
+
+impl From<AuthApiError> for RoleRejection {
+    fn from(error: AuthApiError) -> Self {
+        RoleRejection {
+            subject: None,
+            error,
+        }
+    }
+}
+
+impl IntoResponse for RoleRejection {
+    fn into_response(self) -> Response {
+        self.error.into_response()
+    }
+}
+
+// Client details recorded alongside audit events. Forwarded addresses are
+// only taken from the configured number of trusted proxies, the rest of the
+// header is up to the client.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMetadata {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for RequestMetadata
+where
+    S: Send + Sync,
+    Settings: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let trusted_proxies = Settings::from_ref(state).load().proxy.trusted_proxies;
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        let ip = forwarded_client_ip(&parts.headers, trusted_proxies).or(peer_ip);
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        Ok(RequestMetadata { ip, user_agent })
+    }
+}
+
+// The X-Forwarded-For entry added by the outermost of `trusted_proxies`
+// proxies. None without trusted proxies or when the header is short of that.
+fn forwarded_client_ip(headers: &HeaderMap, trusted_proxies: usize) -> Option<String> {
+    let hop = trusted_proxies.checked_sub(1)?;
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    forwarded
+        .into_iter()
+        .rev()
+        .nth(hop)
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn forwarded_for(values: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_forwarded_client_ip_ignores_entries_set_by_the_client() {
+        let headers = forwarded_for(&["6.6.6.6, 203.0.113.5"]);
+        assert_eq!(
+            forwarded_client_ip(&headers, 1).as_deref(),
+            Some("203.0.113.5")
+        );
+        assert_eq!(forwarded_client_ip(&headers, 2).as_deref(), Some("6.6.6.6"));
+    }
+
+    #[test]
+    fn test_forwarded_client_ip_spans_repeated_headers() {
+        let headers = forwarded_for(&["6.6.6.6", "203.0.113.5, 10.0.0.2"]);
+        assert_eq!(
+            forwarded_client_ip(&headers, 2).as_deref(),
+            Some("203.0.113.5")
+        );
+    }
+
+    #[test]
+    fn test_forwarded_client_ip_needs_trusted_proxies() {
+        let headers = forwarded_for(&["203.0.113.5"]);
+        assert_eq!(forwarded_client_ip(&headers, 0), None);
+        assert_eq!(forwarded_client_ip(&headers, 2), None);
+        assert_eq!(forwarded_client_ip(&HeaderMap::new(), 1), None);
+    }
+}
@@ -1,4 +1,8 @@
+pub mod audit;
 pub mod auth;
 pub mod config;
 pub mod constants;
+pub mod extractors;
+pub mod notifications;
+pub mod password_hash;
 pub mod tracing;
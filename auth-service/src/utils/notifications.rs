@@ -0,0 +1,90 @@
+use crate::{
+    auth_service_state::AuthServiceState,
+    domain::{
+        device_fingerprint::DeviceFingerprint, email::Email, email_client::EmailClient,
+        locale::Locale, security_notification::SecurityNotification,
+    },
+    services::email_templates::EmailTemplate,
+};
+
+use super::extractors::RequestMetadata;
+
+// Notifications are best effort: failing to send one is logged and never fails
+// the request that triggered it.
+pub async fn notify_security_event(
+    app_state: &AuthServiceState,
+    email: &Email,
+    notification: SecurityNotification,
+) {
+    if security_notifications_enabled(app_state, email).await {
+        let locale = app_state.settings.load().email_client.locale;
+        send_security_notification(&*app_state.email_client, email, &notification, locale).await;
+    }
+}
+
+pub async fn security_notifications_enabled(app_state: &AuthServiceState, email: &Email) -> bool {
+    let enabled = app_state
+        .user_store
+        .security_notifications_enabled(email)
+        .await;
+
+    enabled.unwrap_or_else(|e| {
+        tracing::error!(error = ?e, "Failed to read notification preference");
+        false
+    })
+}
+
+pub async fn send_security_notification<E>(
+    email_client: &E,
+    email: &Email,
+    notification: &SecurityNotification,
+    locale: Locale,
+) where
+    E: EmailClient + ?Sized,
+{
+    let result = match notification.render(locale) {
+        Ok(message) => email_client.send_email(email, &message).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+        tracing::error!(error = ?e, "Failed to send security notification");
+    }
+}
+
+// Remembers the device the request came from, telling the user when it is one
+// they have not signed in from before.
+pub async fn notify_if_new_device(
+    app_state: &AuthServiceState,
+    email: &Email,
+    metadata: &RequestMetadata,
+) {
+    if remember_device(app_state, email, metadata).await {
+        let notification = SecurityNotification::NewSignIn {
+            ip: metadata.ip.clone(),
+            user_agent: metadata.user_agent.clone(),
+            at: app_state.clock.now(),
+        };
+        notify_security_event(app_state, email, notification).await;
+    }
+}
+
+// Returns true if the device had not been seen for this user before
+pub async fn remember_device(
+    app_state: &AuthServiceState,
+    email: &Email,
+    metadata: &RequestMetadata,
+) -> bool {
+    let fingerprint =
+        DeviceFingerprint::new(metadata.user_agent.as_deref(), metadata.ip.as_deref());
+
+    let is_new = app_state
+        .user_store
+        .remember_device(email, &fingerprint)
+        .await;
+
+    is_new.unwrap_or_else(|e| {
+        tracing::error!(error = ?e, "Failed to remember device");
+        false
+    })
+}
@@ -0,0 +1,219 @@
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, Version,
+    password_hash::{PasswordHasher, SaltString, rand_core},
+};
+use color_eyre::eyre::{Result, eyre};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{password::Password, password_hash::PasswordHashScheme};
+
+// The parameters new hashes are computed with. Hashes made with anything else
+// are replaced on the next successful login.
+const ARGON2_MEMORY_COST: u32 = 15000;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+fn hasher() -> Result<Argon2<'static>> {
+    Ok(Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(
+            ARGON2_MEMORY_COST,
+            ARGON2_ITERATIONS,
+            ARGON2_PARALLELISM,
+            None,
+        )?,
+    ))
+}
+
+// Verifies against our own hashes as well as the bcrypt, scrypt and pbkdf2
+// hashes of imported users
+#[tracing::instrument(name = "Verify password hash", skip_all)]
+pub async fn verify_password_hash(
+    expected_password_hash: Secret<String>,
+    password_candidate: Password,
+) -> Result<()> {
+    let current_span: tracing::Span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let expected_password_hash = expected_password_hash.expose_secret();
+            let password = password_candidate.as_ref().expose_secret().as_bytes();
+
+            match PasswordHashScheme::detect(expected_password_hash) {
+                Some(PasswordHashScheme::Bcrypt) => {
+                    if bcrypt::verify(password, expected_password_hash)? {
+                        Ok(())
+                    } else {
+                        Err(eyre!("Password does not match"))
+                    }
+                }
+                _ => {
+                    let expected_password_hash: PasswordHash<'_> =
+                        PasswordHash::new(expected_password_hash)?;
+                    expected_password_hash
+                        .verify_password(&[&hasher()?, &Scrypt, &Pbkdf2], password)
+                        .map_err(|e| e.into())
+                }
+            }
+        })
+    })
+    .await?
+}
+
+// Whether the hash was computed by another scheme or with other parameters
+// than new hashes are
+pub fn needs_rehash(password_hash: &Secret<String>) -> bool {
+    let Ok(hash) = PasswordHash::new(password_hash.expose_secret()) else {
+        return true;
+    };
+    let Ok(params) = Params::try_from(&hash) else {
+        return true;
+    };
+
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || hash.version != Some(Version::V0x13.into())
+        || params.m_cost() != ARGON2_MEMORY_COST
+        || params.t_cost() != ARGON2_ITERATIONS
+        || params.p_cost() != ARGON2_PARALLELISM
+}
+
+#[tracing::instrument(name = "Computing password hash", skip_all)]
+pub async fn compute_password_hash(password: Password) -> Result<Secret<String>> {
+    let current_span: tracing::Span = tracing::Span::current();
+
+    tokio::task::spawn_blocking(move || {
+        current_span.in_scope(move || {
+            let salt: SaltString = SaltString::generate(rand_core::OsRng);
+            hasher()?
+                .hash_password(password.as_ref().expose_secret().as_bytes(), &salt)
+                .map(|h| Secret::from(h.to_string()))
+                .map_err(Into::into)
+        })
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compute_password_hash() {
+        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+        let hash_result = compute_password_hash(password.clone()).await;
+
+        assert!(hash_result.is_ok());
+        let hash = hash_result.unwrap();
+        assert!(!hash.expose_secret().is_empty());
+        assert_ne!(hash.expose_secret(), password.as_ref().expose_secret());
+    }
+
+    #[tokio::test]
+    async fn test_verify_password_hash_success() {
+        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+        let hash = compute_password_hash(password.clone()).await.unwrap();
+
+        let result = verify_password_hash(hash, password).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verify_password_hash_failure() {
+        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+        let wrong_password = Password::try_from(Secret::from("wrongpassword".to_owned())).unwrap();
+        let hash = compute_password_hash(password).await.unwrap();
+
+        let result = verify_password_hash(hash, wrong_password).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_password_hash_invalid_hash() {
+        let invalid_hash = Secret::from("invalid_hash_format".to_owned());
+        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+
+        let result = verify_password_hash(invalid_hash, password).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compute_password_hash_deterministic_salt() {
+        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+        let hash1 = compute_password_hash(password.clone()).await.unwrap();
+        let hash2 = compute_password_hash(password.clone()).await.unwrap();
+
+        // Hashes should be different due to random salt
+        assert_ne!(hash1.expose_secret(), hash2.expose_secret());
+
+        // But both should verify successfully
+        assert!(verify_password_hash(hash1, password.clone()).await.is_ok());
+        assert!(verify_password_hash(hash2, password.clone()).await.is_ok());
+    }
+
+    // Hashes as produced by other systems, with cheap parameters to keep the
+    // tests fast
+    fn foreign_hashes(password: &str) -> Vec<Secret<String>> {
+        let salt = SaltString::generate(rand_core::OsRng);
+        let scrypt_params = scrypt::Params::new(4, 8, 1, 32).unwrap();
+        let pbkdf2_params = pbkdf2::Params {
+            rounds: 1000,
+            output_length: 32,
+        };
+
+        vec![
+            bcrypt::hash(password, 4).unwrap(),
+            Scrypt
+                .hash_password_customized(password.as_bytes(), None, None, scrypt_params, &salt)
+                .unwrap()
+                .to_string(),
+            Pbkdf2
+                .hash_password_customized(
+                    password.as_bytes(),
+                    Some(pbkdf2::Algorithm::Pbkdf2Sha512.ident()),
+                    None,
+                    pbkdf2_params,
+                    &salt,
+                )
+                .unwrap()
+                .to_string(),
+            Argon2::default()
+                .hash_password(password.as_bytes(), &salt)
+                .unwrap()
+                .to_string(),
+        ]
+        .into_iter()
+        .map(Secret::new)
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn test_verify_foreign_password_hashes() {
+        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+        let wrong_password = Password::try_from(Secret::from("wrongpassword".to_owned())).unwrap();
+
+        for hash in foreign_hashes("testpassword123") {
+            assert!(
+                verify_password_hash(hash.clone(), password.clone())
+                    .await
+                    .is_ok(),
+                "{}",
+                hash.expose_secret()
+            );
+            assert!(
+                verify_password_hash(hash.clone(), wrong_password.clone())
+                    .await
+                    .is_err()
+            );
+            assert!(needs_rehash(&hash), "{}", hash.expose_secret());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_own_hashes_do_not_need_rehashing() {
+        let password = Password::try_from(Secret::from("testpassword123".to_owned())).unwrap();
+        let hash = compute_password_hash(password).await.unwrap();
+        assert!(!needs_rehash(&hash));
+    }
+}
@@ -0,0 +1,429 @@
+use auth_service::{
+    domain::{
+        auth_api_error::ErrorResponse, clock::Clock, data_stores::ERASED_SUBJECT, email::Email,
+        restore_token::RestoreToken, role::Role, user_status::UserStatus,
+    },
+    routes::{
+        AdminImportResponse, AdminUserListResponse, AdminUserResponse,
+        PasswordResetRequiredResponse,
+    },
+};
+use secrecy::Secret;
+use sqlx::Row;
+
+use crate::helpers::{TestApp, get_random_email};
+
+async fn signup_user(app: &TestApp, requires_2fa: bool) -> serde_json::Value {
+    let body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    body
+}
+
+async fn audit_events(app: &TestApp, target: &str) -> Vec<String> {
+    sqlx::query("SELECT event_type FROM audit_log WHERE target = $1 ORDER BY id")
+        .bind(target)
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to read audit log")
+        .into_iter()
+        .map(|row| row.get("event_type"))
+        .collect()
+}
+
+async fn audit_outcomes(app: &TestApp, target: &str) -> Vec<(String, String)> {
+    sqlx::query("SELECT event_type, outcome FROM audit_log WHERE target = $1 ORDER BY id")
+        .bind(target)
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to read audit log")
+        .into_iter()
+        .map(|row| (row.get("event_type"), row.get("outcome")))
+        .collect()
+}
+
+#[tokio::test]
+async fn should_return_400_without_token() {
+    let app = TestApp::new().await;
+
+    let response = app.admin_get("/users", &()).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_return_403_for_non_admin() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+
+    let response = app.admin_get("/users", &()).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let error = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(error.error, "Insufficient permissions");
+}
+
+#[tokio::test]
+async fn should_return_403_once_admin_role_is_revoked() {
+    let app = TestApp::new().await;
+    let admin = app.login_as_admin().await;
+    assert_eq!(app.admin_get("/users", &()).await.status().as_u16(), 200);
+
+    let email = Email::try_from(Secret::new(admin)).unwrap();
+    app.user_store
+        .revoke_role(&email, &Role::admin())
+        .await
+        .unwrap();
+
+    // Same token, which still carries the admin role
+    let response = app.admin_get("/users", &()).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_search_users_with_pagination() {
+    let app = TestApp::new().await;
+    for _ in 0..3 {
+        signup_user(&app, false).await;
+    }
+    app.login_as_admin().await;
+
+    let response = app
+        .admin_get("/users", &[("email", "example.com"), ("perPage", "2")])
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let list = response.json::<AdminUserListResponse>().await.unwrap();
+    assert_eq!(list.total, 4);
+    assert_eq!(list.page, 1);
+    assert_eq!(list.per_page, 2);
+    assert_eq!(list.users.len(), 2);
+
+    let response = app
+        .admin_get(
+            "/users",
+            &[("email", "example.com"), ("perPage", "2"), ("page", "2")],
+        )
+        .await;
+    let list = response.json::<AdminUserListResponse>().await.unwrap();
+    assert_eq!(list.users.len(), 2);
+}
+
+#[tokio::test]
+async fn should_view_user_and_record_audit_event() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, true).await;
+    let email = user["email"].as_str().unwrap();
+    app.login_as_admin().await;
+
+    let response = app.admin_get(&format!("/users/{}", email), &()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let profile = response.json::<AdminUserResponse>().await.unwrap();
+    assert_eq!(profile.email, email);
+    assert!(profile.requires_2fa);
+    assert_eq!(profile.status, UserStatus::Active);
+    assert_eq!(profile.roles, vec![Role::user()]);
+
+    assert_eq!(audit_events(&app, email).await, vec!["admin_view_user"]);
+}
+
+#[tokio::test]
+async fn should_return_401_when_viewing_missing_user() {
+    let app = TestApp::new().await;
+    app.login_as_admin().await;
+
+    let response = app
+        .admin_get(&format!("/users/{}", get_random_email()), &())
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn suspended_user_should_not_be_able_to_login() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+    app.login_as_admin().await;
+
+    let response = app.admin_post(&format!("/users/{}/disable", email)).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.login(&user).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let error = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(error.error, "Account is suspended");
+
+    let response = app.admin_post(&format!("/users/{}/enable", email)).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+    assert_eq!(
+        audit_events(&app, email).await,
+        vec!["admin_disable_user", "admin_enable_user"]
+    );
+}
+
+#[tokio::test]
+async fn revoke_sessions_should_invalidate_existing_tokens() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+    let user_token = app.get_jwt_token().unwrap();
+
+    app.login_as_admin().await;
+    let response = app
+        .admin_post(&format!("/users/{}/revoke-sessions", email))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .verify_token(&serde_json::json!({ "token": user_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Tokens issued after the revocation are valid again
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+    let response = app
+        .verify_token(&serde_json::json!({ "token": app.get_jwt_token().unwrap() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn force_password_reset_should_be_reported_on_login() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+    app.login_as_admin().await;
+
+    let response = app
+        .admin_post(&format!("/users/{}/force-password-reset", email))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.login(&user).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["passwordResetRequired"], true);
+}
+
+#[tokio::test]
+async fn should_toggle_requires_2fa() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+    app.login_as_admin().await;
+
+    let response = app
+        .admin_put(
+            &format!("/users/{}/requires-2fa", email),
+            &serde_json::json!({ "requires2FA": true }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let profile = app
+        .admin_get(&format!("/users/{}", email), &())
+        .await
+        .json::<AdminUserResponse>()
+        .await
+        .unwrap();
+    assert!(profile.requires_2fa);
+}
+
+#[tokio::test]
+async fn should_delete_user() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+    app.login_as_admin().await;
+
+    let response = app.admin_delete(&format!("/users/{}", email)).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.admin_delete(&format!("/users/{}", email)).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The deleted user is erased from the audit log, the event included. Only
+    // the failed second attempt names them.
+    assert_eq!(
+        audit_outcomes(&app, email).await,
+        vec![("admin_delete_user".to_owned(), "failure".to_owned())]
+    );
+    assert_eq!(
+        audit_events(&app, ERASED_SUBJECT).await,
+        vec!["admin_delete_user"]
+    );
+}
+
+#[tokio::test]
+async fn should_delete_user_during_their_grace_period() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+    let parsed = Email::try_from(Secret::new(email.to_owned())).unwrap();
+    app.user_store
+        .schedule_deletion(
+            &parsed,
+            app.clock.now() + chrono::Duration::days(30),
+            &RestoreToken::generate(),
+        )
+        .await
+        .unwrap();
+    app.login_as_admin().await;
+
+    let response = app.admin_delete(&format!("/users/{}", email)).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert!(app.user_store.get_user(&parsed).await.is_err());
+    assert_eq!(app.purge_due_accounts().await, 0);
+}
+
+#[tokio::test]
+async fn should_not_enable_user_during_their_grace_period() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+    let parsed = Email::try_from(Secret::new(email.to_owned())).unwrap();
+    app.user_store
+        .schedule_deletion(
+            &parsed,
+            app.clock.now() + chrono::Duration::days(30),
+            &RestoreToken::generate(),
+        )
+        .await
+        .unwrap();
+    app.login_as_admin().await;
+
+    let response = app.admin_post(&format!("/users/{}/enable", email)).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    // Still due once the grace period is over
+    assert_eq!(
+        app.user_store.get_status(&parsed).await.unwrap(),
+        UserStatus::PendingDeletion
+    );
+    assert_eq!(
+        audit_outcomes(&app, email).await,
+        vec![("admin_enable_user".to_owned(), "failure".to_owned())]
+    );
+}
+
+#[tokio::test]
+async fn should_audit_refused_admin_actions() {
+    let app = TestApp::new().await;
+    let user = signup_user(&app, false).await;
+    let email = user["email"].as_str().unwrap();
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+
+    let response = app.admin_post(&format!("/users/{}/disable", email)).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert_eq!(
+        audit_outcomes(&app, email).await,
+        vec![("admin_disable_user".to_owned(), "failure".to_owned())]
+    );
+}
+
+#[tokio::test]
+async fn should_import_users_and_rehash_their_passwords_on_login() {
+    let app = TestApp::new().await;
+    app.login_as_admin().await;
+    let hashed = get_random_email();
+    let without_password = get_random_email();
+
+    let records = serde_json::json!([
+        { "email": hashed, "passwordHash": bcrypt::hash("imported-password", 4).unwrap() },
+        { "email": without_password }
+    ]);
+    let response = app.admin_post_json("/users/import", &records).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let report = response.json::<AdminImportResponse>().await.unwrap();
+    assert!(report.skipped.is_empty());
+    assert_eq!(report.created.len(), 2);
+    assert!(report.created[0].temporary_password.is_none());
+    assert!(report.created[1].temporary_password.is_some());
+
+    let response = app.admin_post_json("/users/import", &records).await;
+    let report = response.json::<AdminImportResponse>().await.unwrap();
+    assert_eq!(report.skipped, vec![hashed.clone(), without_password]);
+
+    let login = serde_json::json!({ "email": hashed, "password": "imported-password" });
+    assert_eq!(app.login(&login).await.status().as_u16(), 200);
+
+    let password_hash: String = sqlx::query("SELECT password_hash FROM users WHERE email = $1")
+        .bind(&hashed)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to read user")
+        .get("password_hash");
+    assert!(password_hash.starts_with("$argon2id$"));
+    assert_eq!(app.login(&login).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_let_imported_users_with_a_short_password_log_in_and_reset_it() {
+    let app = TestApp::new().await;
+    app.login_as_admin().await;
+    let email = get_random_email();
+
+    let records = serde_json::json!([
+        { "email": email, "passwordHash": bcrypt::hash("legacy", 4).unwrap() }
+    ]);
+    let response = app.admin_post_json("/users/import", &records).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // A wrong password shorter than the policy allows is rejected as before
+    let wrong = serde_json::json!({ "email": email, "password": "wrong" });
+    assert_eq!(app.login(&wrong).await.status().as_u16(), 400);
+
+    let login = serde_json::json!({ "email": email, "password": "legacy" });
+    let response = app.login(&login).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<PasswordResetRequiredResponse>()
+        .await
+        .unwrap();
+    assert!(body.password_reset_required);
+
+    assert_eq!(app.post_elevate(&login).await.status().as_u16(), 200);
+    let new_password = serde_json::json!({ "new_password": "long-enough-password" });
+    assert_eq!(
+        app.post_change_password(&new_password)
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+
+    app.logout().await;
+    assert_eq!(app.login(&login).await.status().as_u16(), 400);
+    let login = serde_json::json!({ "email": email, "password": "long-enough-password" });
+    let response = app.login(&login).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert!(body.get("passwordResetRequired").is_none());
+}
+
+#[tokio::test]
+async fn should_reject_import_with_unsupported_hash() {
+    let app = TestApp::new().await;
+    app.login_as_admin().await;
+    let email = get_random_email();
+
+    let records = serde_json::json!([
+        { "email": email, "passwordHash": "5f4dcc3b5aa765d61d8327deb882cf99" }
+    ]);
+    let response = app.admin_post_json("/users/import", &records).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.admin_get(&format!("/users/{}", email), &()).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
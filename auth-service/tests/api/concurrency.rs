@@ -0,0 +1,69 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::task::JoinSet;
+
+use crate::helpers::{TestApp, get_random_email};
+
+const SIGNUPS: usize = 8;
+
+// Generous, a signup that isn't blocked finishes well within it
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+async fn signup(app: &TestApp, email: String) -> u16 {
+    let body = serde_json::json!({
+        "email": email,
+        "password": "passwordpassword",
+        "requires2FA": false,
+    });
+    app.post_signup(&body).await.status().as_u16()
+}
+
+async fn sessions_waiting_on_a_lock(app: &TestApp) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM pg_stat_activity WHERE wait_event_type = 'Lock'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to read pg_stat_activity")
+}
+
+// Signups used to take a store-wide write lock, so a single signup waiting on
+// the database held up every other one
+#[tokio::test(flavor = "multi_thread")]
+async fn signup_waiting_on_the_database_should_not_block_other_signups() {
+    let app = Arc::new(TestApp::new().await);
+    let blocked_email = get_random_email();
+
+    // Inserting the same email waits on this row until the transaction ends
+    let mut transaction = app.db_pool.begin().await.unwrap();
+    sqlx::query("INSERT INTO users (email, password_hash) VALUES ($1, 'hash')")
+        .bind(&blocked_email)
+        .execute(&mut *transaction)
+        .await
+        .unwrap();
+
+    let blocked = tokio::spawn({
+        let app = app.clone();
+        async move { signup(&app, blocked_email).await }
+    });
+    tokio::time::timeout(TIMEOUT, async {
+        while sessions_waiting_on_a_lock(&app).await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Signup never reached the database");
+
+    let mut signups = JoinSet::new();
+    for _ in 0..SIGNUPS {
+        let app = app.clone();
+        signups.spawn(async move { signup(&app, get_random_email()).await });
+    }
+    let statuses = tokio::time::timeout(TIMEOUT, signups.join_all())
+        .await
+        .expect("Signups were held up by the blocked one");
+    assert_eq!(statuses, vec![201; SIGNUPS]);
+    assert!(!blocked.is_finished());
+
+    // The email was taken by the time the blocked signup got to it
+    transaction.commit().await.unwrap();
+    assert_eq!(blocked.await.unwrap(), 409);
+}
@@ -1,21 +1,31 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use auth_service::{
     AuthService,
     auth_service::get_postgres_pool,
     auth_service_state::AuthServiceState,
     domain::{
-        data_stores::{BannedTokenStore, TwoFaCodeStore},
+        clock::MockClock,
+        data_stores::{BannedTokenStore, TwoFaCodeStore, UserStore},
         email::Email,
+        email_client::EmailClient,
+        role::Role,
         two_fa_attempt_id::TwoFaAttemptId,
     },
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFaCodeStore},
+        account_purge::AccountPurge,
+        capturing_email_client::CapturingEmailClient,
+        data_stores::{
+            PostgresAuditLog, PostgresUserStore, RedisBannedTokenStore, RedisTwoFaCodeStore,
+        },
         postmark_email_client::PostmarkEmailClient,
+        webhook_sms_client::WebhookSmsClient,
     },
+    settings::{Config, Settings},
     utils::constants::test,
 };
 
+use redis::aio::MultiplexedConnection;
 use reqwest::{
     Client, Url,
     cookie::{CookieStore, Jar},
@@ -29,10 +39,7 @@ use testcontainers_modules::{
     redis::Redis,
     testcontainers::{ContainerAsync, runners::AsyncRunner},
 };
-use tokio::{
-    net::TcpListener,
-    sync::{Mutex, RwLock},
-};
+use tokio::net::TcpListener;
 use uuid::Uuid;
 use wiremock::MockServer;
 
@@ -40,9 +47,17 @@ pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
-    pub two_fa_code_store: Arc<RwLock<dyn TwoFaCodeStore>>,
-    pub banned_token_store: Arc<RwLock<dyn BannedTokenStore>>,
+    pub user_store: Arc<dyn UserStore>,
+    pub two_fa_code_store: Arc<dyn TwoFaCodeStore>,
+    pub banned_token_store: Arc<dyn BannedTokenStore>,
+    // Tokens are issued and checked against this clock
+    pub clock: Arc<MockClock>,
+    // The app's own config, not shared with other test apps
+    pub settings: Settings,
     pub email_server: MockServer,
+    pub sms_server: MockServer,
+    pub db_pool: PgPool,
+    app_state: AuthServiceState,
     #[allow(unused)]
     user_store_container: ContainerAsync<postgres::Postgres>,
     #[allow(unused)]
@@ -55,21 +70,46 @@ pub struct TestApp {
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::build(None, load_config()).await
+    }
+
+    // Emails go to the dev mailbox instead of the mock Postmark server
+    pub async fn with_dev_mailbox() -> Self {
+        Self::build(Some(CapturingEmailClient::new()), load_config()).await
+    }
+
+    // Runs the app with a modified copy of the config
+    pub async fn with_config(configure: impl FnOnce(&mut Config)) -> Self {
+        let mut config = load_config();
+        configure(&mut config);
+        Self::build(None, config).await
+    }
+
+    async fn build(dev_mailbox: Option<CapturingEmailClient>, config: Config) -> Self {
         // TEST_APP_COUNTER.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
 
+        let settings = Settings::new(config);
         let (redis_container, redis_connection) = setup_and_connect_redis_container().await;
-        let redis_connection = Arc::new(Mutex::new(redis_connection));
-        let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
+        let banned_token_store = Arc::new(RedisBannedTokenStore::new(
             redis_connection.clone(),
-        )));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFaCodeStore::new(redis_connection)));
+            settings.clone(),
+        ));
+        let two_fa_code_store = Arc::new(RedisTwoFaCodeStore::new(redis_connection));
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
-        let email_client = Arc::new(configure_postmark_email_client(base_url));
+        let email_client: Arc<Box<dyn EmailClient>> = match &dev_mailbox {
+            Some(mailbox) => Arc::new(Box::new(mailbox.clone())),
+            None => Arc::new(Box::new(configure_postmark_email_client(base_url))),
+        };
+
+        let sms_server = MockServer::start().await;
+        let sms_client = Arc::new(configure_webhook_sms_client(&sms_server));
 
         let (user_store_container, pool) = setup_and_connect_user_store_container().await;
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pool)));
+        let clock = Arc::new(MockClock::default());
+        let audit_log = Arc::new(PostgresAuditLog::new(pool.clone()));
+        let user_store = Arc::new(PostgresUserStore::with_clock(pool.clone(), clock.clone()));
 
         let listener = TcpListener::bind(test::APP_ADDRESS)
             .await
@@ -78,15 +118,22 @@ impl TestApp {
         let address = format!("http://{}", listener.local_addr().unwrap());
 
         let app_state = AuthServiceState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
             email_client,
-        );
-
-        let app = AuthService::with_state(app_state);
+            audit_log,
+        )
+        .with_sms_client(sms_client)
+        .with_clock(clock.clone())
+        .with_settings(settings.clone());
+
+        let mut app = AuthService::with_state(app_state.clone());
+        if let Some(mailbox) = dev_mailbox {
+            app = app.with_dev_mailbox(mailbox);
+        }
 
-        let _ = tokio::spawn(app.as_standalone(listener, None));
+        tokio::spawn(app.as_standalone(listener, None));
 
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
@@ -98,9 +145,15 @@ impl TestApp {
             address,
             cookie_jar,
             http_client,
+            user_store,
             two_fa_code_store,
             banned_token_store,
+            clock,
+            settings,
             email_server,
+            sms_server,
+            db_pool: pool,
+            app_state,
             user_store_container,
             redis_container,
         }
@@ -135,6 +188,14 @@ impl TestApp {
         );
     }
 
+    pub fn jwt_cookie_name(&self) -> String {
+        self.settings.load().auth.jwt.cookie_name.clone()
+    }
+
+    pub fn elevated_jwt_cookie_name(&self) -> String {
+        self.settings.load().auth.elevated_jwt.cookie_name.clone()
+    }
+
     pub fn get_jwt_token(&self) -> Option<String> {
         let cookie = self
             .cookie_jar
@@ -153,15 +214,29 @@ impl TestApp {
             .split(';')
             .map(|c| c.trim())
             .find(|c| c.starts_with(cookie_name))
-            .and_then(|c| {
-                c.split_once('=')
-                    .and_then(|(_, token)| Some(token.to_owned()))
-            })
+            .and_then(|c| c.split_once('=').map(|(_, token)| token.to_owned()))
+    }
+
+    pub async fn get_dev_mailbox(&self, accept: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/_dev/mailbox", &self.address))
+            .header("Accept", accept)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn clear_dev_mailbox(&self) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/_dev/mailbox", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -172,7 +247,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -181,7 +256,7 @@ impl TestApp {
 
     pub async fn login<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
@@ -190,7 +265,7 @@ impl TestApp {
 
     pub async fn logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -198,7 +273,7 @@ impl TestApp {
 
     pub async fn verify_2fa<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
             .json(body)
             .send()
             .await
@@ -207,7 +282,7 @@ impl TestApp {
 
     pub async fn verify_token<Body: Serialize>(&self, token: &Body) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/verify-token", &self.address))
+            .post(format!("{}/verify-token", &self.address))
             .json(token)
             .send()
             .await
@@ -216,7 +291,7 @@ impl TestApp {
 
     pub async fn verify_elevated_token<Body: Serialize>(&self, token: &Body) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/verify-elevated-token", &self.address))
+            .post(format!("{}/verify-elevated-token", &self.address))
             .json(token)
             .send()
             .await
@@ -225,15 +300,31 @@ impl TestApp {
 
     pub async fn delete_account(&self) -> reqwest::Response {
         self.http_client
-            .delete(&format!("{}/delete-account", &self.address))
+            .delete(format!("{}/delete-account", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
     }
 
+    pub async fn post_restore_account<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/account/restore", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Runs the purge job once, against the app's clock
+    pub async fn purge_due_accounts(&self) -> usize {
+        AccountPurge::new(self.app_state.clone(), Duration::from_secs(60))
+            .purge_due()
+            .await
+    }
+
     pub async fn post_elevate<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/elevate", &self.address))
+            .post(format!("{}/elevate", &self.address))
             .json(body)
             .send()
             .await
@@ -242,13 +333,192 @@ impl TestApp {
 
     pub async fn post_change_password<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/change-password", &self.address))
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_security_events<Query: Serialize>(&self, query: &Query) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/account/security-events", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_account_export(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/account/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_account_export(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/account/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_notification_preferences(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/account/notification-preferences",
+                &self.address
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn put_notification_preferences<Body: Serialize>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.http_client
+            .put(format!(
+                "{}/account/notification-preferences",
+                &self.address
+            ))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Subjects of all emails the app has sent so far, in order
+    pub async fn sent_email_subjects(&self) -> Vec<String> {
+        self.email_server
+            .received_requests()
+            .await
+            .expect("Request recording disabled")
+            .iter()
+            .filter_map(|request| serde_json::from_slice::<Value>(&request.body).ok())
+            .filter_map(|body| body["Subject"].as_str().map(str::to_owned))
+            .collect()
+    }
+
+    pub async fn post_phone_number<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/account/phone", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_verify_phone_number<Body: Serialize>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/account/phone/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn put_two_fa_channel<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        self.http_client
+            .put(format!("{}/account/two-fa-channel", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Bodies of all text messages the app has sent so far, in order
+    pub async fn sent_sms_bodies(&self) -> Vec<String> {
+        self.sms_server
+            .received_requests()
+            .await
+            .expect("Request recording disabled")
+            .iter()
+            .map(|request| {
+                let body: Value =
+                    serde_json::from_slice(&request.body).expect("Failed to parse SMS JSON");
+                body["body"].as_str().expect("Missing body").to_owned()
+            })
+            .collect()
+    }
+
+    pub async fn admin_get<Query: Serialize>(
+        &self,
+        path: &str,
+        query: &Query,
+    ) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin{}", &self.address, path))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn admin_post(&self, path: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn admin_post_json<Body: Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin{}", &self.address, path))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn admin_put<Body: Serialize>(&self, path: &str, body: &Body) -> reqwest::Response {
+        self.http_client
+            .put(format!("{}/admin{}", &self.address, path))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request")
     }
 
+    pub async fn admin_delete(&self, path: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    // Signs up a user with the admin role and logs them in on this app's client
+    pub async fn login_as_admin(&self) -> String {
+        let email = get_random_email();
+        let body = serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        });
+        assert_eq!(self.post_signup(&body).await.status().as_u16(), 201);
+
+        let parsed = Email::try_from(Secret::new(email.clone())).unwrap();
+        self.user_store
+            .assign_role(&parsed, &Role::admin())
+            .await
+            .expect("Failed to assign admin role");
+
+        assert_eq!(self.login(&body).await.status().as_u16(), 200);
+        email
+    }
+
     pub async fn get_verify_two_fa_request(
         &self,
         email: &str,
@@ -259,14 +529,14 @@ impl TestApp {
             .received_requests()
             .await
             .expect("Request recording disabled")
-            .get(0)
+            .first()
             .expect("No email received")
             .body
             .clone();
 
         let email_json: serde_json::Value =
             serde_json::from_slice(email_body).expect("Failed to parse email JSON");
-        let code = email_json["TextBody"].as_str().expect("Missing content");
+        let code = extract_two_fa_code(email_json["TextBody"].as_str().expect("Missing content"));
 
         serde_json::json!({
             "email": email,
@@ -276,6 +546,19 @@ impl TestApp {
     }
 }
 
+// Finds the 2FA code in the plaintext body of the email sent on login
+pub fn extract_two_fa_code(text_body: &str) -> String {
+    text_body
+        .split_whitespace()
+        .find(|word| word.len() == 6 && word.chars().all(|c| c.is_ascii_digit()))
+        .expect("No 2FA code in email")
+        .to_owned()
+}
+
+fn load_config() -> Config {
+    Config::new().expect("Failed to load config")
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
@@ -301,6 +584,15 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
 
+fn configure_webhook_sms_client(sms_server: &MockServer) -> WebhookSmsClient {
+    let http_client = Client::builder()
+        .timeout(test::email_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    WebhookSmsClient::new(format!("{}/sms", sms_server.uri()), None, None, http_client)
+}
+
 // static POSTGRES_CONTAINER: OnceCell<RwLock<ContainerAsync<postgres::Postgres>>> =
 //     OnceCell::const_new();
 
@@ -414,7 +706,7 @@ async fn setup_and_connect_user_store_container() -> (ContainerAsync<postgres::P
     (container, connection)
 }
 
-async fn setup_and_connect_redis_container() -> (ContainerAsync<Redis>, redis::Connection) {
+async fn setup_and_connect_redis_container() -> (ContainerAsync<Redis>, MultiplexedConnection) {
     let container = Redis::default()
         .start()
         .await
@@ -434,7 +726,8 @@ async fn setup_and_connect_redis_container() -> (ContainerAsync<Redis>, redis::C
 
     let connection = redis::Client::open(db_url)
         .expect("Failed to open redis client")
-        .get_connection()
+        .get_multiplexed_async_connection()
+        .await
         .expect("Failed to connect redis client");
 
     (container, connection)
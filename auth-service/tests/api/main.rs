@@ -1,2 +1,4 @@
+mod admin_api;
+mod concurrency;
 mod helpers;
 mod user_api;
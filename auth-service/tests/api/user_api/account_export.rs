@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use auth_service::{domain::auth_api_error::ErrorResponse, routes::AccountExport};
+use serde_json::Value;
+
+use crate::helpers::{TestApp, get_standard_test_user};
+
+#[tokio::test]
+async fn should_return_400_without_elevated_token() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(error.error, "Missing token");
+
+    assert_eq!(app.post_account_export().await.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_export_everything_held_on_the_user() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+    assert_eq!(app.post_elevate(&user).await.status().as_u16(), 200);
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "attachment; filename=\"account-export.json\""
+    );
+
+    let export = response.json::<AccountExport>().await.unwrap();
+    assert_eq!(export.profile.email, user["email"].as_str().unwrap());
+    assert!(export.profile.security_notifications);
+    assert!(!export.two_factor.required);
+    assert_eq!(export.two_factor.phone_number, None);
+    assert_eq!(export.devices.len(), 1);
+
+    let event_types: Vec<_> = export
+        .security_events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert!(event_types.contains(&"signup"));
+    assert!(event_types.contains(&"login"));
+    assert!(event_types.contains(&"elevate"));
+}
+
+#[tokio::test]
+async fn should_not_export_who_acted_on_the_user() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+
+    app.login_as_admin().await;
+    let email = user["email"].as_str().unwrap();
+    let response = app
+        .admin_put(
+            &format!("/users/{}/requires-2fa", email),
+            &serde_json::json!({ "requires2FA": false }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+    assert_eq!(app.post_elevate(&user).await.status().as_u16(), 200);
+    let export = app
+        .get_account_export()
+        .await
+        .json::<AccountExport>()
+        .await
+        .unwrap();
+
+    let by_admin = export
+        .security_events
+        .iter()
+        .find(|event| event.event_type == "admin_set_requires_2fa")
+        .expect("Missing admin event");
+    assert_eq!(by_admin.actor, None);
+    assert_eq!(by_admin.ip, None);
+    assert_eq!(by_admin.user_agent, None);
+}
+
+#[tokio::test]
+async fn should_email_the_export_as_an_attachment() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+    assert_eq!(app.post_elevate(&user).await.status().as_u16(), 200);
+
+    assert_eq!(app.post_account_export().await.status().as_u16(), 202);
+
+    // The export is put together after the response has been sent
+    let mut attachments = Vec::new();
+    for _ in 0..50 {
+        attachments = export_attachments(&app).await;
+        if !attachments.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0]["Name"], "account-export.json");
+    assert_eq!(attachments[0]["ContentType"], "application/json");
+}
+
+async fn export_attachments(app: &TestApp) -> Vec<Value> {
+    app.email_server
+        .received_requests()
+        .await
+        .expect("Request recording disabled")
+        .iter()
+        .filter_map(|request| serde_json::from_slice::<Value>(&request.body).ok())
+        .filter(|body| body["Subject"] == "Your account data")
+        .filter_map(|body| body["Attachments"].as_array().cloned())
+        .flatten()
+        .collect()
+}
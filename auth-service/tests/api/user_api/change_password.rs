@@ -1,5 +1,3 @@
-use auth_service::utils::constants::JWT_ELEVATED_COOKIE_NAME;
-
 use crate::helpers::{TestApp, get_standard_test_user};
 
 #[tokio::test]
@@ -55,7 +53,7 @@ async fn should_return_401_with_invalid_token() {
     let app = TestApp::new().await;
 
     // Add an invalid elevated token
-    app.add_invalid_cookie(*JWT_ELEVATED_COOKIE_NAME);
+    app.add_invalid_cookie(&app.elevated_jwt_cookie_name());
 
     let body = serde_json::json!({
         "new_password": "newpassword123"
@@ -1,4 +1,13 @@
-use auth_service::domain::auth_api_error::{AuthApiError, ErrorResponse};
+use std::time::Duration;
+
+use auth_service::domain::{
+    auth_api_error::{AuthApiError, ErrorResponse},
+    email::Email,
+    restore_token::RestoreToken,
+};
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::Value;
 
 use crate::helpers::{TestApp, get_standard_test_user};
 
@@ -70,3 +79,122 @@ pub async fn should_return_400_after_logout() {
         AuthApiError::MissingToken.to_string()
     );
 }
+
+#[tokio::test]
+async fn should_refuse_login_until_restored() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+    app.login(&user).await;
+    app.post_elevate(&user).await;
+
+    assert_eq!(app.delete_account().await.status().as_u16(), 204);
+
+    let response = app.login(&user).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        AuthApiError::AccountPendingDeletion.to_string()
+    );
+
+    let body = serde_json::json!({ "token": restore_token(&app).await });
+    assert_eq!(app.post_restore_account(&body).await.status().as_u16(), 204);
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+
+    // The link only works once
+    assert_eq!(app.post_restore_account(&body).await.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_revoke_sessions_on_deletion() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+    app.login(&user).await;
+    app.post_elevate(&user).await;
+    let token = app.get_jwt_token().expect("No auth token after login");
+
+    assert_eq!(app.delete_account().await.status().as_u16(), 204);
+
+    let response = app
+        .verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_reject_invalid_restore_tokens() {
+    let app = TestApp::new().await;
+
+    for token in [
+        "not a token",
+        RestoreToken::generate().as_ref().expose_secret(),
+    ] {
+        let response = app
+            .post_restore_account(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(
+            response.json::<ErrorResponse>().await.unwrap().error,
+            AuthApiError::InvalidRestoreToken.to_string()
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_purge_once_the_grace_period_is_over() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+    app.login(&user).await;
+    app.post_elevate(&user).await;
+    assert_eq!(app.delete_account().await.status().as_u16(), 204);
+
+    assert_eq!(app.purge_due_accounts().await, 0);
+
+    let grace_period = app.settings.load().account_deletion.grace_period_in_days;
+    app.clock
+        .advance(Duration::from_secs(u64::from(grace_period) * 24 * 60 * 60));
+    assert_eq!(app.purge_due_accounts().await, 1);
+
+    let email = Email::try_from(Secret::new("test@example.com".to_owned())).unwrap();
+    assert!(app.user_store.get_user(&email).await.is_err());
+
+    // Too late to restore
+    let body = serde_json::json!({ "token": restore_token(&app).await });
+    assert_eq!(app.post_restore_account(&body).await.status().as_u16(), 400);
+
+    let remaining: i64 =
+        sqlx::query_scalar("SELECT count(*) FROM audit_log WHERE actor = $1 OR target = $1")
+            .bind("test@example.com")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to read audit log");
+    assert_eq!(remaining, 0);
+}
+
+// The token in the restore link of the last deletion email
+async fn restore_token(app: &TestApp) -> String {
+    let text_body = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording disabled")
+        .iter()
+        .filter_map(|request| serde_json::from_slice::<Value>(&request.body).ok())
+        .filter(|body| body["Subject"] == "Your account will be deleted")
+        .filter_map(|body| body["TextBody"].as_str().map(str::to_owned))
+        .next_back()
+        .expect("No deletion email sent");
+
+    let link = text_body
+        .split_whitespace()
+        .find(|word| word.contains("token="))
+        .expect("No restore link in email");
+    Url::parse(link)
+        .expect("Invalid restore link")
+        .query_pairs()
+        .find(|(name, _)| name == "token")
+        .map(|(_, token)| token.into_owned())
+        .expect("No token in restore link")
+}
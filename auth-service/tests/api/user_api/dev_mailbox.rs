@@ -0,0 +1,80 @@
+use auth_service::routes::{MailboxResponse, TwoFactorAuthResponse};
+
+use crate::helpers::{TestApp, extract_two_fa_code, get_standard_test_user};
+
+async fn mailbox(app: &TestApp) -> MailboxResponse {
+    let response = app.get_dev_mailbox("application/json").await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<MailboxResponse>()
+        .await
+        .expect("Failed to parse response")
+}
+
+#[tokio::test]
+async fn should_complete_2fa_login_with_code_from_mailbox() {
+    let app = TestApp::with_dev_mailbox().await;
+    let body = get_standard_test_user(true);
+    app.post_signup(&body).await;
+
+    let response = app.login(&body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Failed to parse response")
+        .attempt_id;
+
+    let messages = mailbox(&app).await.messages;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].recipient, "test@example.com");
+    assert_eq!(messages[0].subject, "Your sign-in code");
+
+    let body = serde_json::json!({
+        "email": "test@example.com",
+        "2FACode": extract_two_fa_code(&messages[0].text_body),
+        "loginAttemptId": attempt_id,
+    });
+    assert_eq!(app.verify_2fa(&body).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_render_mailbox_as_html() {
+    let app = TestApp::with_dev_mailbox().await;
+    let body = get_standard_test_user(true);
+    app.post_signup(&body).await;
+    app.login(&body).await;
+
+    let response = app.get_dev_mailbox("text/html").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Your sign-in code"));
+    assert!(page.contains("test@example.com"));
+}
+
+#[tokio::test]
+async fn should_clear_mailbox() {
+    let app = TestApp::with_dev_mailbox().await;
+    let body = get_standard_test_user(true);
+    app.post_signup(&body).await;
+    app.login(&body).await;
+
+    assert_eq!(app.clear_dev_mailbox().await.status().as_u16(), 204);
+    assert!(mailbox(&app).await.messages.is_empty());
+}
+
+#[tokio::test]
+async fn should_not_serve_mailbox_unless_enabled() {
+    let app = TestApp::new().await;
+
+    let response = app.get_dev_mailbox("application/json").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
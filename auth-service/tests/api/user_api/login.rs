@@ -3,18 +3,21 @@ use auth_service::{
         auth_api_error::{AuthApiError, ErrorResponse},
         data_stores::UserStoreError,
         email::Email,
+        password::Password,
         two_fa_attempt_id::TwoFaAttemptId,
         user::UserError,
     },
     routes::TwoFactorAuthResponse,
+    settings::Config,
+    utils::password_hash::compute_password_hash,
 };
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use wiremock::{
     Mock, ResponseTemplate,
     matchers::{method, path},
 };
 
-use crate::helpers::{TestApp, get_standard_test_user};
+use crate::helpers::{TestApp, extract_two_fa_code, get_standard_test_user};
 
 #[tokio::test]
 async fn login_returns_200() {
@@ -38,6 +41,32 @@ async fn login_returns_200() {
     assert_eq!(response.status().as_u16(), 200);
 }
 
+#[tokio::test]
+async fn should_set_the_configured_cookie_name() {
+    let app = TestApp::with_config(|config| config.auth.jwt.cookie_name = "session".into()).await;
+
+    let body = get_standard_test_user(false);
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+
+    assert!(app.get_token("session").is_some());
+}
+
+#[tokio::test]
+async fn should_use_the_cookie_name_of_a_reloaded_config() {
+    let app = TestApp::new().await;
+
+    let body = get_standard_test_user(false);
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let mut config = Config::new().expect("Failed to load config");
+    config.auth.jwt.cookie_name = "reloaded".into();
+    app.settings.store(config);
+
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+    assert!(app.get_token("reloaded").is_some());
+}
+
 #[tokio::test]
 async fn should_return_206_when_2fa_enabled() {
     let app = TestApp::new().await;
@@ -65,7 +94,7 @@ async fn should_return_206_when_2fa_enabled() {
 
     let login_id = TwoFaAttemptId::parse(&response.attempt_id).expect("Invalid code");
 
-    let two_fa_code_store = app.two_fa_code_store.read().await;
+    let two_fa_code_store = &app.two_fa_code_store;
     let email = Email::try_from(Secret::new(body["email"].as_str().unwrap().to_owned())).unwrap();
     let (login_attempt_id, _) = two_fa_code_store
         .get_login_attempt_id_and_two_fa_code(&email)
@@ -102,14 +131,14 @@ async fn should_return_200_if_valid_credentials_and_2fa_enabled() {
         .received_requests()
         .await
         .expect("Request recording disabled")
-        .get(0)
+        .first()
         .expect("No email received")
         .body
         .clone();
 
     let email_json: serde_json::Value =
         serde_json::from_slice(&email_body).expect("Failed to parse email JSON");
-    let code = email_json["TextBody"].as_str().expect("Missing content");
+    let code = extract_two_fa_code(email_json["TextBody"].as_str().expect("Missing content"));
 
     let body = serde_json::json!({
         "email": body["email"].as_str().expect("Email was not a string"),
@@ -179,6 +208,34 @@ async fn should_return_400_whith_invalid_password() {
     )
 }
 
+#[tokio::test]
+async fn should_return_400_for_a_password_against_the_policy_even_if_it_matches() {
+    let app = TestApp::new().await;
+    let user = get_standard_test_user(false);
+    assert!(app.post_signup(&user).await.status().is_success());
+
+    // Hashed by us, unlike the hash of an imported user
+    let short_password = Password::unchecked(Secret::new("pass".to_owned()));
+    let password_hash = compute_password_hash(short_password).await.unwrap();
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE email = $2")
+        .bind(password_hash.expose_secret())
+        .bind("test@example.com")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let body = serde_json::json!({
+        "email": "test@example.com",
+        "password": "pass"
+    });
+    let response = app.login(&body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let email = Email::try_from(Secret::new("test@example.com".to_owned())).unwrap();
+    let profile = app.user_store.get_user_profile(&email).await.unwrap();
+    assert!(!profile.password_reset_required);
+}
+
 #[tokio::test]
 async fn should_return_401_with_wrong_password() {
     let app = TestApp::new().await;
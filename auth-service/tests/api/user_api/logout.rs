@@ -1,5 +1,3 @@
-use auth_service::utils::constants::JWT_COOKIE_NAME;
-
 use crate::helpers::{TestApp, get_standard_test_user};
 
 #[tokio::test]
@@ -54,7 +52,7 @@ async fn should_return_400_if_logout_is_called_twice() {
 async fn logout_returns_401_if_invalid_token() {
     let app = TestApp::new().await;
 
-    app.add_invalid_cookie(*JWT_COOKIE_NAME);
+    app.add_invalid_cookie(&app.jwt_cookie_name());
 
     let response = app.logout().await;
 
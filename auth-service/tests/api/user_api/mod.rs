@@ -1,9 +1,14 @@
+mod account_export;
 mod change_password;
 mod delete_account;
+mod dev_mailbox;
 mod elevate;
 mod login;
 mod logout;
+mod notifications;
+mod phone;
 mod root;
+mod security_events;
 mod signup;
 mod verify_2fa;
 mod verify_elevated_token;
@@ -0,0 +1,140 @@
+use auth_service::routes::NotificationPreferences;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, get_standard_test_user};
+
+const NEW_SIGN_IN: &str = "New sign-in to your account";
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn login_from(app: &TestApp, body: &serde_json::Value, ip: &str) -> reqwest::Response {
+    app.http_client
+        .post(format!("{}/login", &app.address))
+        .header("x-forwarded-for", ip)
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn should_not_notify_login_from_signup_device() {
+    let app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let body = get_standard_test_user(false);
+    app.post_signup(&body).await;
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+
+    assert!(app.sent_email_subjects().await.is_empty());
+}
+
+#[tokio::test]
+async fn should_notify_login_from_new_device_once() {
+    let app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let body = get_standard_test_user(false);
+    app.post_signup(&body).await;
+
+    assert_eq!(
+        login_from(&app, &body, "198.51.100.7")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    // Same network, so the same device
+    assert_eq!(
+        login_from(&app, &body, "198.51.100.8")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+
+    assert_eq!(app.sent_email_subjects().await, vec![NEW_SIGN_IN]);
+}
+
+#[tokio::test]
+async fn should_notify_password_change_and_account_deletion() {
+    let app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let body = get_standard_test_user(false);
+    app.post_signup(&body).await;
+    app.login(&body).await;
+    assert_eq!(app.post_elevate(&body).await.status().as_u16(), 200);
+
+    let change = serde_json::json!({ "new_password": "new_password" });
+    assert_eq!(
+        app.post_change_password(&change).await.status().as_u16(),
+        200
+    );
+    assert_eq!(app.delete_account().await.status().as_u16(), 204);
+
+    assert_eq!(
+        app.sent_email_subjects().await,
+        vec![
+            "Your account was unlocked for sensitive changes",
+            "Your password was changed",
+            "Your account will be deleted"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn should_not_notify_after_opting_out() {
+    let app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let body = get_standard_test_user(false);
+    app.post_signup(&body).await;
+    app.login(&body).await;
+
+    let preferences = app
+        .get_notification_preferences()
+        .await
+        .json::<NotificationPreferences>()
+        .await
+        .expect("Could not deserialize response body to NotificationPreferences");
+    assert!(preferences.security_notifications);
+
+    let opt_out = serde_json::json!({ "securityNotifications": false });
+    assert_eq!(
+        app.put_notification_preferences(&opt_out)
+            .await
+            .status()
+            .as_u16(),
+        204
+    );
+
+    assert_eq!(
+        login_from(&app, &body, "198.51.100.7")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    assert_eq!(app.post_elevate(&body).await.status().as_u16(), 200);
+
+    assert!(app.sent_email_subjects().await.is_empty());
+}
+
+#[tokio::test]
+async fn should_return_400_for_preferences_without_token() {
+    let app = TestApp::new().await;
+
+    let response = app.get_notification_preferences().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
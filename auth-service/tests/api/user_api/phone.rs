@@ -0,0 +1,152 @@
+use auth_service::{domain::email::Email, routes::TwoFactorAuthResponse};
+use secrecy::Secret;
+use serde_json::Value;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, extract_two_fa_code, get_standard_test_user};
+
+const PHONE_NUMBER: &str = "+44 7700 900123";
+
+async fn mount_sms_webhook(app: &TestApp) {
+    Mock::given(path("/sms"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.sms_server)
+        .await;
+}
+
+// Signs up a user without 2FA and leaves the session elevated
+async fn signup_and_elevate(app: &TestApp) -> Value {
+    let body = get_standard_test_user(false);
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+    assert_eq!(app.post_elevate(&body).await.status().as_u16(), 200);
+    body
+}
+
+async fn verify_phone_number(app: &TestApp) {
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let sms = app.sent_sms_bodies().await;
+    let code = extract_two_fa_code(sms.last().expect("No SMS sent"));
+
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+}
+
+#[tokio::test]
+async fn should_send_login_codes_by_sms_once_channel_is_set() {
+    let app = TestApp::new().await;
+    mount_sms_webhook(&app).await;
+    let body = signup_and_elevate(&app).await;
+
+    verify_phone_number(&app).await;
+    let response = app
+        .put_two_fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let email = body["email"].as_str().unwrap();
+    app.user_store
+        .set_requires_2fa(
+            &Email::try_from(Secret::new(email.to_owned())).unwrap(),
+            true,
+        )
+        .await
+        .unwrap();
+    app.logout().await;
+
+    let response = app.login(&body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let two_fa_response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Failed to get two factor response");
+
+    let sms = app.sent_sms_bodies().await;
+    assert_eq!(sms.len(), 2);
+    assert!(
+        app.sent_email_subjects()
+            .await
+            .iter()
+            .all(|subject| subject != "Your sign-in code"),
+        "The login code should not be emailed"
+    );
+
+    let response = app
+        .verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": two_fa_response.attempt_id,
+            "2FACode": extract_two_fa_code(&sms[1]),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_400_with_invalid_phone_number() {
+    let app = TestApp::new().await;
+    mount_sms_webhook(&app).await;
+    signup_and_elevate(&app).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "0123 456" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(app.sent_sms_bodies().await.is_empty());
+}
+
+#[tokio::test]
+async fn should_return_400_without_elevated_token() {
+    let app = TestApp::new().await;
+    mount_sms_webhook(&app).await;
+
+    let body = get_standard_test_user(false);
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_return_400_with_wrong_verification_code() {
+    let app = TestApp::new().await;
+    mount_sms_webhook(&app).await;
+    signup_and_elevate(&app).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let code = extract_two_fa_code(&app.sent_sms_bodies().await[0]);
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": wrong_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_return_409_when_choosing_sms_without_verified_phone_number() {
+    let app = TestApp::new().await;
+    mount_sms_webhook(&app).await;
+    signup_and_elevate(&app).await;
+
+    let response = app
+        .put_two_fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+}
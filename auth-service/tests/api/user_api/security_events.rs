@@ -0,0 +1,129 @@
+use auth_service::routes::SecurityEventsResponse;
+
+use crate::helpers::{TestApp, get_random_email, get_standard_test_user};
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_is_missing() {
+    let app = TestApp::new().await;
+
+    let response = app.get_security_events(&()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let app = TestApp::new().await;
+    app.add_invalid_cookie(&app.jwt_cookie_name());
+
+    let response = app.get_security_events(&()).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_return_own_events_most_recent_first() {
+    let app = TestApp::new().await;
+
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+
+    let wrong_password = serde_json::json!({
+        "email": user["email"],
+        "password": "wrong-password"
+    });
+    assert_eq!(app.login(&wrong_password).await.status().as_u16(), 401);
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+
+    // Events of other users must not show up
+    let other_user = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password",
+        "requires2FA": false
+    });
+    app.post_signup(&other_user).await;
+
+    let response = app.get_security_events(&()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<SecurityEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to SecurityEventsResponse")
+        .events;
+
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| (event.event_type.as_str(), event.outcome.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("login", "success"),
+            ("login", "failure"),
+            ("signup", "success")
+        ]
+    );
+    assert!(events.iter().all(|event| event.ip.is_some()));
+}
+
+#[tokio::test]
+async fn should_respect_limit() {
+    let app = TestApp::new().await;
+
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+    app.login(&user).await;
+
+    let response = app
+        .get_security_events(&[("limit", "1")])
+        .await
+        .json::<SecurityEventsResponse>()
+        .await
+        .unwrap();
+
+    assert_eq!(response.events.len(), 1);
+    assert_eq!(response.events[0].event_type, "login");
+}
+
+#[tokio::test]
+async fn should_not_show_who_acted_on_the_user() {
+    let app = TestApp::new().await;
+
+    let user = get_standard_test_user(false);
+    app.post_signup(&user).await;
+
+    app.login_as_admin().await;
+    let email = user["email"].as_str().unwrap();
+    let response = app
+        .admin_put(
+            &format!("/users/{}/requires-2fa", email),
+            &serde_json::json!({ "requires2FA": false }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert_eq!(app.login(&user).await.status().as_u16(), 200);
+    let events = app
+        .get_security_events(&())
+        .await
+        .json::<SecurityEventsResponse>()
+        .await
+        .unwrap()
+        .events;
+
+    let by_admin = events
+        .iter()
+        .find(|event| event.event_type == "admin_set_requires_2fa")
+        .expect("Missing admin event");
+    assert_eq!(by_admin.actor, None);
+    assert_eq!(by_admin.ip, None);
+    assert_eq!(by_admin.user_agent, None);
+
+    let login = events
+        .iter()
+        .find(|event| event.event_type == "login")
+        .unwrap();
+    assert_eq!(login.actor.as_deref(), Some(email));
+    assert!(login.ip.is_some());
+}
@@ -116,6 +116,29 @@ async fn signup_should_return_409_if_email_already_exists() {
     );
 }
 
+#[tokio::test]
+async fn signup_should_return_403_if_email_domain_is_disposable() {
+    let app = TestApp::new().await;
+
+    let body = serde_json::json!({
+        "email": "someone@mailinator.com",
+        "password": "passwordpassword",
+        "requires2FA": false,
+    });
+
+    let response = app.post_signup(&body).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        AuthApiError::EmailDomainNotAllowed.to_string()
+    );
+}
+
 #[tokio::test]
 async fn signup_returns_422_if_malformed_input() {
     let app = TestApp::new().await;
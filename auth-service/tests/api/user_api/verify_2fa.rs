@@ -48,10 +48,15 @@ async fn should_return_200_if_correct_code() {
 
     let response = app.verify_2fa(&verify_2fa_request).await;
     let token = app.get_jwt_token().expect("No jwt token stored");
-    let banned_token_store = app.banned_token_store.read().await;
-    auth::validate_auth_token(&token, &*banned_token_store)
-        .await
-        .expect("Invalid auth token");
+    let banned_token_store = &*app.banned_token_store;
+    auth::validate_auth_token(
+        &token,
+        banned_token_store,
+        &app.settings.load(),
+        &*app.clock,
+    )
+    .await
+    .expect("Invalid auth token");
 
     assert_eq!(response.status().as_u16(), 200);
 }
@@ -1,6 +1,6 @@
 use auth_service::{
     domain::auth_api_error::{AuthApiError, ErrorResponse},
-    utils::{auth::TokenAuthError, constants::JWT_ELEVATED_COOKIE_NAME},
+    utils::auth::TokenAuthError,
 };
 
 use crate::helpers::{TestApp, get_standard_test_user};
@@ -19,7 +19,7 @@ async fn should_return_200_with_valid_elevated_token() {
     assert_eq!(response.status().as_u16(), 200);
 
     let token = app
-        .get_token(*JWT_ELEVATED_COOKIE_NAME)
+        .get_token(&app.elevated_jwt_cookie_name())
         .expect("Missing elevated token in response");
 
     let body = serde_json::json!({
@@ -82,7 +82,7 @@ async fn should_return_401_if_elevated_token_is_banned() {
     assert_eq!(response.status().as_u16(), 200);
 
     let elevated_token = app
-        .get_token(*JWT_ELEVATED_COOKIE_NAME)
+        .get_token(&app.elevated_jwt_cookie_name())
         .expect("Elevated token not found");
 
     assert!(app.logout().await.status().is_success());
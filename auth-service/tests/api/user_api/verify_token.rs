@@ -1,8 +1,17 @@
+use std::time::Duration;
+
 use auth_service::{
-    domain::auth_api_error::{AuthApiError, ErrorResponse},
+    domain::{
+        auth_api_error::{AuthApiError, ErrorResponse},
+        email::Email,
+        role::Role,
+        user_status::UserStatus,
+    },
+    routes::VerifyTokenResponse,
     utils::auth::TokenAuthError,
 };
 use reqwest::{Url, cookie::CookieStore};
+use secrecy::Secret;
 
 use crate::helpers::{TestApp, get_standard_test_user};
 
@@ -32,6 +41,28 @@ async fn should_return_200_with_valid_token() {
     assert_eq!(response.status().as_u16(), 200);
 }
 
+#[tokio::test]
+async fn should_return_roles_of_token() {
+    let app = TestApp::new().await;
+
+    let body = get_standard_test_user(false);
+    assert!(app.post_signup(&body).await.status().is_success());
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+
+    let body = serde_json::json!({
+        "token": app.get_jwt_token()
+    });
+
+    let response = app.verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Failed to parse verify token response");
+    assert_eq!(response.roles, vec![Role::user()]);
+}
+
 #[tokio::test]
 async fn should_return_401_if_token_is_invalid() {
     let app = TestApp::new().await;
@@ -45,6 +76,27 @@ async fn should_return_401_if_token_is_invalid() {
     assert_eq!(response.status().as_u16(), 401);
 }
 
+#[tokio::test]
+async fn should_return_401_once_token_has_expired() {
+    let app = TestApp::new().await;
+
+    let body = get_standard_test_user(false);
+    assert!(app.post_signup(&body).await.status().is_success());
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+
+    let body = serde_json::json!({
+        "token": app.get_jwt_token()
+    });
+
+    // Past the token lifetime and the leeway allowed when validating it
+    app.clock.advance(Duration::from_secs(
+        app.settings.load().auth.jwt.time_to_live as u64 + 61,
+    ));
+
+    let response = app.verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
 #[tokio::test]
 async fn should_return_401_if_token_is_banned() {
     let app = TestApp::new().await;
@@ -76,6 +128,37 @@ async fn should_return_401_if_token_is_banned() {
     )
 }
 
+#[tokio::test]
+async fn should_return_403_if_user_is_suspended() {
+    let app = TestApp::new().await;
+
+    let body = get_standard_test_user(false);
+    assert!(app.post_signup(&body).await.status().is_success());
+    assert_eq!(app.login(&body).await.status().as_u16(), 200);
+
+    let token = serde_json::json!({
+        "token": app.get_jwt_token()
+    });
+    assert_eq!(app.verify_token(&token).await.status().as_u16(), 200);
+
+    let email = Email::try_from(Secret::new("test@example.com".to_owned())).unwrap();
+    app.user_store
+        .set_status(&email, UserStatus::Suspended)
+        .await
+        .unwrap();
+
+    let response = app.verify_token(&token).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("failed to parse error response")
+            .error,
+        AuthApiError::AccountSuspended.to_string()
+    );
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
     let app = TestApp::new().await;